@@ -0,0 +1,334 @@
+//! Agents
+//!
+//! An agent is a stored system prompt and LLM configuration, optionally
+//! grounded in a knowledge collection. [`Agent`] is the API view of an
+//! `agents` row with its JSON columns parsed; [`Agent::chat_request`] turns a
+//! conversation into the request routed through the hub, and [`threads`]
+//! keeps conversations server-side.
+
+pub mod threads;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::cache::Embedder;
+use crate::db::AgentRow;
+use crate::error::{Result, SynapseError};
+use crate::vector::{VectorFilter, VectorStore};
+use crate::{ChatRequest, EmbeddingRequest, Message};
+
+/// Chunks retrieved per question unless the agent sets `topK`
+pub const DEFAULT_TOP_K: usize = 4;
+
+/// Model settings applied to every conversation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmConfig {
+    /// Explicit provider; the router picks one when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Token budget for thread history; see [`threads::DEFAULT_HISTORY_TOKENS`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_tokens: Option<u32>,
+    /// Fold thread messages that fall out of the history window into a
+    /// summary instead of dropping them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summarize_history: bool,
+}
+
+/// Model used to embed questions for knowledge retrieval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub model: String,
+}
+
+/// Where the agent's knowledge collection lives and how much to retrieve
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VectorDbSettings {
+    /// `VectorDb` provider account; the in-process store when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+    /// Chunks scoring below this cosine similarity are ignored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Agent {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub system_prompt: Option<String>,
+    pub llm_config: LlmConfig,
+    pub embedding_config: Option<EmbeddingConfig>,
+    pub vector_db_config: Option<VectorDbSettings>,
+    pub knowledge_collection: Option<String>,
+    pub enabled: bool,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+/// A knowledge chunk the answer was grounded in
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnowledgeSource {
+    pub id: String,
+    pub score: f32,
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Parse an optional JSON column, treating `{}` and `null` as unset
+fn optional_config<T: DeserializeOwned>(value: Option<JsonValue>) -> Result<Option<T>> {
+    match value {
+        None | Some(JsonValue::Null) => Ok(None),
+        Some(JsonValue::Object(ref map)) if map.is_empty() => Ok(None),
+        Some(value) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(|e| SynapseError::Internal(format!("Invalid agent config: {}", e))),
+    }
+}
+
+impl Agent {
+    pub fn from_row(row: AgentRow) -> Result<Self> {
+        let llm_config = serde_json::from_value(row.llm_config)
+            .map_err(|e| SynapseError::Internal(format!("Invalid agent LLM config: {}", e)))?;
+
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            system_prompt: row.system_prompt,
+            llm_config,
+            embedding_config: optional_config(row.embedding_config)?,
+            vector_db_config: optional_config(row.vector_db_config)?,
+            knowledge_collection: row.knowledge_collection,
+            enabled: row.enabled,
+            status: row.status,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            created_by: row.created_by,
+        })
+    }
+
+    /// Whether the agent accepts conversations
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.status == "active"
+    }
+
+    /// Check settings that the database cannot
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(SynapseError::Validation("Agent name is required".to_string()));
+        }
+        if self.llm_config.model.trim().is_empty() {
+            return Err(SynapseError::Validation("llmConfig.model is required".to_string()));
+        }
+        if self.knowledge_collection.is_some() && self.embedding_config.is_none() {
+            return Err(SynapseError::Validation(
+                "An agent with a knowledge collection needs an embeddingConfig".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Build the routed request: the agent's system prompt and retrieved
+    /// knowledge first, then the caller's conversation, with the agent's
+    /// model settings
+    pub fn chat_request(&self, messages: Vec<Message>, sources: &[KnowledgeSource], user_id: Option<String>) -> ChatRequest {
+        let mut system = self.system_prompt.clone().unwrap_or_default();
+        if !sources.is_empty() {
+            if !system.is_empty() {
+                system.push_str("\n\n");
+            }
+            system.push_str("Answer using the following context where it is relevant.\n");
+            for (i, source) in sources.iter().enumerate() {
+                system.push_str(&format!("\n[{}] {}\n", i + 1, source.content));
+            }
+        }
+
+        let mut conversation = Vec::with_capacity(messages.len() + 1);
+        if !system.is_empty() {
+            conversation.push(Message::system(system));
+        }
+        conversation.extend(messages);
+
+        let defaults = ChatRequest::default();
+        let mut metadata = std::collections::HashMap::new();
+        metadata.insert("agent_id".to_string(), self.id.clone());
+
+        ChatRequest {
+            model: self.llm_config.model.clone(),
+            provider: self.llm_config.provider.clone(),
+            messages: conversation,
+            temperature: self.llm_config.temperature.unwrap_or(defaults.temperature),
+            max_tokens: self.llm_config.max_tokens.unwrap_or(defaults.max_tokens),
+            top_p: self.llm_config.top_p,
+            user_id,
+            metadata,
+            ..defaults
+        }
+    }
+
+    /// Retrieve the chunks of the agent's knowledge collection closest to
+    /// `question`, returning them with the embedding cost
+    ///
+    /// Returns nothing when the agent has no knowledge collection.
+    pub async fn retrieve(
+        &self,
+        question: &str,
+        embedder: &dyn Embedder,
+        store: &dyn VectorStore,
+        user_id: Option<String>,
+    ) -> Result<(Vec<KnowledgeSource>, f64)> {
+        let (Some(collection), Some(embedding)) = (&self.knowledge_collection, &self.embedding_config) else {
+            return Ok((Vec::new(), 0.0));
+        };
+        if question.trim().is_empty() {
+            return Ok((Vec::new(), 0.0));
+        }
+
+        let response = embedder.embed(&EmbeddingRequest {
+            model: embedding.model.clone(),
+            input: vec![question.to_string()],
+            provider: embedding.provider.clone(),
+            dimensions: None,
+            user_id,
+        }).await?;
+        let Some(vector) = response.data.first() else {
+            return Ok((Vec::new(), response.cost));
+        };
+
+        let settings = self.vector_db_config.clone().unwrap_or_default();
+        let min_score = settings.min_score.unwrap_or(0.0);
+        let sources = store
+            .query(collection, vector, settings.top_k.unwrap_or(DEFAULT_TOP_K), &VectorFilter::new())
+            .await?
+            .into_iter()
+            .filter(|m| m.score >= min_score)
+            .filter_map(|m| {
+                Some(KnowledgeSource {
+                    content: m.payload["content"].as_str()?.to_string(),
+                    document_id: m.payload["document_id"].as_str().map(str::to_string),
+                    title: m.payload["title"].as_str().map(str::to_string),
+                    id: m.id,
+                    score: m.score,
+                })
+            })
+            .collect();
+
+        Ok((sources, response.cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{MemoryVectorStore, VectorRecord};
+    use crate::{EmbeddingResponse, TokenUsage};
+
+    struct FakeEmbedder;
+
+    #[async_trait::async_trait]
+    impl Embedder for FakeEmbedder {
+        async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+            Ok(EmbeddingResponse {
+                provider: "fake".to_string(),
+                model: request.model.clone(),
+                data: vec![vec![1.0, 0.0]],
+                usage: TokenUsage::default(),
+                latency_ms: 0,
+                cost: 0.0002,
+            })
+        }
+    }
+
+    fn agent() -> Agent {
+        Agent::from_row(AgentRow {
+            id: "agent-1".to_string(),
+            name: "Support".to_string(),
+            description: None,
+            system_prompt: Some("You are a support agent.".to_string()),
+            llm_config: serde_json::json!({"provider": "openai", "model": "gpt-4o-mini", "temperature": 0.2}),
+            embedding_config: Some(serde_json::json!({"model": "text-embedding-3-small"})),
+            vector_db_config: Some(serde_json::json!({})),
+            knowledge_collection: Some("support-docs".to_string()),
+            enabled: true,
+            status: "active".to_string(),
+            metadata: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_row() {
+        let agent = agent();
+        assert_eq!(agent.llm_config.model, "gpt-4o-mini");
+        assert!(agent.vector_db_config.is_none());
+        assert!(agent.is_active());
+        assert!(agent.validate().is_ok());
+    }
+
+    #[test]
+    fn test_chat_request_applies_agent_settings() {
+        let sources = vec![KnowledgeSource {
+            id: "c1".to_string(),
+            score: 0.9,
+            content: "Refunds take 5 days.".to_string(),
+            document_id: None,
+            title: None,
+        }];
+        let request = agent().chat_request(vec![Message::user("How long do refunds take?")], &sources, None);
+
+        assert_eq!(request.model, "gpt-4o-mini");
+        assert_eq!(request.provider.as_deref(), Some("openai"));
+        assert_eq!(request.temperature, 0.2);
+        assert_eq!(request.messages.len(), 2);
+        assert_eq!(request.messages[0].role, "system");
+        let system = request.messages[0].content.text();
+        assert!(system.starts_with("You are a support agent."));
+        assert!(system.contains("[1] Refunds take 5 days."));
+        assert_eq!(request.metadata["agent_id"], "agent-1");
+    }
+
+    #[tokio::test]
+    async fn test_retrieve() {
+        let store = MemoryVectorStore::new();
+        store.upsert("support-docs", vec![
+            VectorRecord { id: "near".to_string(), vector: vec![1.0, 0.1], payload: serde_json::json!({"content": "Refunds take 5 days."}) },
+            VectorRecord { id: "far".to_string(), vector: vec![0.0, 1.0], payload: serde_json::json!({"content": "We are in Berlin."}) },
+        ]).await.unwrap();
+
+        let mut agent = agent();
+        agent.vector_db_config = Some(VectorDbSettings { account_id: None, top_k: Some(2), min_score: Some(0.5) });
+        let (sources, cost) = agent.retrieve("refunds?", &FakeEmbedder, &store, None).await.unwrap();
+
+        assert_eq!(cost, 0.0002);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].id, "near");
+    }
+}
@@ -0,0 +1,210 @@
+//! Conversation threads
+//!
+//! A thread stores an agent conversation so clients send only the new turn.
+//! Each turn replays the newest stored messages that fit the agent's history
+//! token budget; with `summarizeHistory` the messages that fall out of the
+//! window are folded into a rolling summary instead of being dropped.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::Agent;
+use crate::db::{NewThreadMessage, ThreadMessageRow, ThreadRow};
+use crate::error::{Result, SynapseError};
+use crate::{ChatRequest, Message};
+
+/// History budget when the agent does not set `historyTokens`
+pub const DEFAULT_HISTORY_TOKENS: u32 = 4000;
+
+/// Rough per-message overhead for role and formatting tokens
+const MESSAGE_OVERHEAD_TOKENS: u32 = 4;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Thread {
+    pub id: String,
+    pub agent_id: String,
+    pub user_id: String,
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub summarized_count: usize,
+    pub message_count: usize,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Included when a single thread is fetched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub messages: Option<Vec<ThreadMessage>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThreadMessage {
+    pub id: String,
+    pub position: usize,
+    #[serde(flatten)]
+    pub message: Message,
+    pub token_count: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Thread {
+    pub fn from_row(row: ThreadRow) -> Self {
+        Self {
+            id: row.id,
+            agent_id: row.agent_id,
+            user_id: row.user_id,
+            title: row.title,
+            summary: row.summary,
+            summarized_count: row.summarized_count.max(0) as usize,
+            message_count: row.message_count.max(0) as usize,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            messages: None,
+        }
+    }
+
+    pub fn with_messages(mut self, messages: Vec<ThreadMessage>) -> Self {
+        self.messages = Some(messages);
+        self
+    }
+
+    /// Threads are only visible to the user who created them, and only
+    /// through their agent
+    pub fn check_owner(&self, agent_id: &str, user_id: &str) -> Result<()> {
+        if self.agent_id != agent_id || self.user_id != user_id {
+            return Err(SynapseError::NotFound(format!("Thread {}", self.id)));
+        }
+        Ok(())
+    }
+}
+
+impl ThreadMessage {
+    pub fn from_row(row: ThreadMessageRow) -> Result<Self> {
+        let message = serde_json::from_value(row.message)
+            .map_err(|e| SynapseError::Internal(format!("Invalid thread message {}: {}", row.id, e)))?;
+        Ok(Self {
+            id: row.id,
+            position: row.position.max(0) as usize,
+            message,
+            token_count: row.token_count.max(0) as u32,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Estimated tokens for a message, at about four characters per token
+pub fn estimate_tokens(message: &Message) -> u32 {
+    let chars = message.content.text().chars().count() as u32;
+    chars.div_ceil(4) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// A message ready to append to a thread
+pub fn new_message(message: &Message) -> NewThreadMessage {
+    NewThreadMessage {
+        role: message.role.clone(),
+        message: serde_json::to_value(message).unwrap_or_default(),
+        token_count: estimate_tokens(message) as i32,
+    }
+}
+
+/// Index of the oldest message to replay: the newest messages whose
+/// combined tokens fit `budget`
+pub fn window_start(history: &[ThreadMessage], budget: u32) -> usize {
+    let mut used = 0u32;
+    for (i, message) in history.iter().enumerate().rev() {
+        used = used.saturating_add(message.token_count);
+        if used > budget {
+            return i + 1;
+        }
+    }
+    0
+}
+
+/// Request asking the agent's model to fold `messages` into the previous
+/// summary
+pub fn summary_request(agent: &Agent, previous: Option<&str>, messages: &[Message], user_id: Option<String>) -> ChatRequest {
+    let mut transcript = String::new();
+    if let Some(previous) = previous {
+        transcript.push_str(&format!("Summary so far:\n{}\n\n", previous));
+    }
+    transcript.push_str("Conversation:\n");
+    for message in messages {
+        transcript.push_str(&format!("{}: {}\n", message.role, message.content.text()));
+    }
+
+    let defaults = ChatRequest::default();
+    ChatRequest {
+        model: agent.llm_config.model.clone(),
+        provider: agent.llm_config.provider.clone(),
+        messages: vec![
+            Message::system(
+                "Summarize the conversation for an assistant continuing it. Keep facts, decisions, names and open \
+                 questions; be concise.",
+            ),
+            Message::user(transcript),
+        ],
+        temperature: 0.0,
+        max_tokens: 512,
+        user_id,
+        ..defaults
+    }
+}
+
+/// System message carrying the summary of earlier turns
+pub fn summary_message(summary: &str) -> Message {
+    Message::system(format!("Summary of the earlier conversation:\n{}", summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(token_counts: &[u32]) -> Vec<ThreadMessage> {
+        token_counts
+            .iter()
+            .enumerate()
+            .map(|(position, &token_count)| ThreadMessage {
+                id: position.to_string(),
+                position,
+                message: Message::user("hi"),
+                token_count,
+                created_at: Utc::now(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_window_start() {
+        let history = history(&[100, 200, 300, 400]);
+        assert_eq!(window_start(&history, 10_000), 0);
+        assert_eq!(window_start(&history, 700), 2);
+        assert_eq!(window_start(&history, 699), 3);
+        assert_eq!(window_start(&history, 0), 4);
+        assert_eq!(window_start(&[], 100), 0);
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(&Message::user("")), MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(estimate_tokens(&Message::user("abcdefghi")), 3 + MESSAGE_OVERHEAD_TOKENS);
+    }
+
+    #[test]
+    fn test_check_owner() {
+        let thread = Thread::from_row(ThreadRow {
+            id: "t1".to_string(),
+            agent_id: "agent-1".to_string(),
+            user_id: "alice".to_string(),
+            title: None,
+            summary: None,
+            summarized_count: 0,
+            message_count: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        assert!(thread.check_owner("agent-1", "alice").is_ok());
+        assert!(thread.check_owner("agent-1", "bob").is_err());
+        assert!(thread.check_owner("agent-2", "alice").is_err());
+    }
+}
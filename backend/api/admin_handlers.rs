@@ -1,11 +1,15 @@
 //! Admin API handlers for users, roles, and system health
 
-use axum::{extract::{Path, State}, http::StatusCode, Json};
+use axum::{extract::{Path, Query, State}, http::StatusCode, Json};
 use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use crate::api::state::AppState;
-use crate::governance::{User, ApiKey, UserUpdate};
+use crate::cache::CacheStats;
+use crate::health::{ComponentKind, ComponentStatus};
+use crate::db::{RequestLogFilter, RequestLogRepository, RequestLogRow};
+use crate::request_log;
+use crate::governance::UserUpdate;
 use crate::error::Result;
 
 // ============== USER MANAGEMENT ==============
@@ -277,66 +281,50 @@ pub async fn get_system_health(State(state): State<Arc<AppState>>) -> Json<Healt
         last_check: now,
     });
     
-    // Check PostgreSQL connection
-    let (pg_status, pg_details, pg_latency) = if let Some(ref pool) = state.db_pool {
-        let start = std::time::Instant::now();
-        match sqlx::query("SELECT 1").fetch_one(pool).await {
-            Ok(_) => {
-                let latency = start.elapsed().as_millis() as u64;
-                ("healthy".to_string(), "Connected".to_string(), latency)
+    // PostgreSQL and Redis from the background prober
+    let components = state.health_monitor.components().await;
+    for (key, name) in [("database", "PostgreSQL"), ("redis", "Redis")] {
+        services.push(match components.iter().find(|c| c.key == key) {
+            Some(component) => component_service(component),
+            None => ServiceHealth {
+                name: name.to_string(),
+                status: "down".to_string(),
+                latency_ms: 0,
+                details: "Not configured".to_string(),
+                last_check: now,
             },
-            Err(e) => ("down".to_string(), format!("Error: {}", e), 0),
-        }
-    } else {
-        ("down".to_string(), "Not configured".to_string(), 0)
-    };
-    
-    services.push(ServiceHealth {
-        name: "PostgreSQL".to_string(),
-        status: pg_status.clone(),
-        latency_ms: pg_latency,
-        details: pg_details.clone(),
-        last_check: now,
-    });
-    
-    // Check Redis connection (we don't have direct access, assume healthy if DB is healthy)
-    let (redis_status, redis_details) = if pg_status == "healthy" {
-        ("healthy".to_string(), "Assumed connected".to_string())
-    } else {
-        ("unknown".to_string(), "Cannot verify".to_string())
-    };
-    
-    services.push(ServiceHealth {
-        name: "Redis".to_string(),
-        status: redis_status,
-        latency_ms: 0,
-        details: redis_details,
-        last_check: now,
-    });
+        });
+    }
     
-    // Check LLM Providers
-    let provider_count = state.account_manager.list_providers().await.len();
-    let (provider_status, provider_details) = if provider_count > 0 {
-        ("healthy".to_string(), format!("{} providers loaded", provider_count))
-    } else {
+    // LLM providers and accounts, summarised
+    let probed: Vec<_> = components
+        .iter()
+        .filter(|c| matches!(c.kind, ComponentKind::Provider | ComponentKind::Account))
+        .collect();
+    let healthy = probed.iter().filter(|c| c.health.healthy).count();
+    let (provider_status, provider_details) = if probed.is_empty() {
         ("degraded".to_string(), "No providers configured".to_string())
+    } else if healthy == probed.len() {
+        ("healthy".to_string(), format!("{} providers and accounts healthy", healthy))
+    } else if healthy > 0 {
+        ("degraded".to_string(), format!("{} of {} providers and accounts healthy", healthy, probed.len()))
+    } else {
+        ("down".to_string(), "No provider or account is reachable".to_string())
     };
+    let latencies: Vec<f64> = probed.iter().filter_map(|c| c.health.avg_latency_ms).collect();
     
     services.push(ServiceHealth {
         name: "LLM Providers".to_string(),
         status: provider_status,
-        latency_ms: 0,
+        latency_ms: if latencies.is_empty() { 0 } else { (latencies.iter().sum::<f64>() / latencies.len() as f64) as u64 },
         details: provider_details,
-        last_check: now,
+        last_check: probed.iter().filter_map(|c| c.latest()).map(|r| r.checked_at).max().unwrap_or(now),
     });
     
     let all_healthy = services.iter().all(|s| s.status == "healthy");
-    let any_down = services.iter().any(|s| s.status == "down");
     
     let overall_status = if all_healthy {
         "healthy"
-    } else if any_down {
-        "degraded"
     } else {
         "degraded"
     };
@@ -346,13 +334,275 @@ pub async fn get_system_health(State(state): State<Arc<AppState>>) -> Json<Healt
         services,
         metrics: SystemMetrics {
             uptime_seconds: state.uptime_seconds(),
-            total_requests: 0, // Would come from metrics counter
+            total_requests: crate::metrics::metrics().total_requests(),
             active_sessions: 0,
-            active_accounts: provider_count,
+            active_accounts: probed.len(),
         },
     })
 }
 
+fn component_service(component: &ComponentStatus) -> ServiceHealth {
+    let latest = component.latest();
+    ServiceHealth {
+        name: component.name.clone(),
+        status: if component.health.healthy { "healthy" } else { "down" }.to_string(),
+        latency_ms: latest.map(|r| r.latency_ms).unwrap_or(0),
+        details: match latest.and_then(|r| r.error.as_deref()) {
+            Some(error) => format!("Error: {}", error),
+            None => "Connected".to_string(),
+        },
+        last_check: latest.map(|r| r.checked_at).unwrap_or_else(Utc::now),
+    }
+}
+
+/// Latest probe results and recent history for every provider, account,
+/// Redis and the database
+pub async fn get_component_health(State(state): State<Arc<AppState>>) -> Json<Vec<ComponentStatus>> {
+    Json(state.health_monitor.components().await)
+}
+
+// ============== RESPONSE CACHE ==============
+
+/// Hit/miss counters for the response cache since startup
+pub async fn get_cache_stats(State(state): State<Arc<AppState>>) -> Json<CacheStats> {
+    Json(state.response_cache.stats())
+}
+
+// ============== REQUEST LOGS ==============
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestLogQuery {
+    pub application_id: Option<String>,
+    pub user_id: Option<String>,
+    pub model: Option<String>,
+    /// "success" or "error"
+    pub status: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestLogSummary {
+    pub id: String,
+    pub application_id: String,
+    pub user_id: Option<String>,
+    pub request_id: String,
+    pub model: String,
+    pub provider: Option<String>,
+    pub account: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost: f64,
+    pub latency_ms: u64,
+    pub cache_status: Option<String>,
+    pub streamed: bool,
+    pub redacted: bool,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct RequestLogDetail {
+    #[serde(flatten)]
+    pub summary: RequestLogSummary,
+    pub request: serde_json::Value,
+    pub response: Option<serde_json::Value>,
+    pub attempts: serde_json::Value,
+}
+
+impl From<&RequestLogRow> for RequestLogSummary {
+    fn from(row: &RequestLogRow) -> Self {
+        Self {
+            id: row.id.clone(),
+            application_id: row.application_id.clone(),
+            user_id: row.user_id.clone(),
+            request_id: row.request_id.clone(),
+            model: row.model.clone(),
+            provider: row.provider.clone(),
+            account: row.account.clone(),
+            status: row.status.clone(),
+            error: row.error.clone(),
+            prompt_tokens: row.prompt_tokens.max(0) as u32,
+            completion_tokens: row.completion_tokens.max(0) as u32,
+            cost: row.cost,
+            latency_ms: row.latency_ms.max(0) as u64,
+            cache_status: row.cache_status.clone(),
+            streamed: row.streamed,
+            redacted: row.redacted,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        }
+    }
+}
+
+fn request_log_repo(state: &AppState) -> Result<RequestLogRepository> {
+    state.db_pool
+        .clone()
+        .map(RequestLogRepository::new)
+        .ok_or_else(|| crate::error::SynapseError::Internal("Database not available".into()))
+}
+
+async fn find_request_log(state: &AppState, log_id: &str) -> Result<RequestLogRow> {
+    request_log_repo(state)?
+        .find_by_id(log_id)
+        .await
+        .map_err(|e| crate::error::SynapseError::DatabaseError(e.to_string()))?
+        .ok_or_else(|| crate::error::SynapseError::NotFound("Request log not found".into()))
+}
+
+/// Search request logs, newest first
+pub async fn search_request_logs(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<RequestLogQuery>,
+) -> Result<Json<Vec<RequestLogSummary>>> {
+    let filter = RequestLogFilter {
+        application_id: query.application_id,
+        user_id: query.user_id,
+        model: query.model,
+        status: query.status,
+        from: query.from,
+        to: query.to,
+        limit: query.limit.unwrap_or(50).clamp(1, 500),
+        offset: query.offset.unwrap_or(0).max(0),
+    };
+
+    let rows = request_log_repo(&state)?
+        .search(&filter)
+        .await
+        .map_err(|e| crate::error::SynapseError::DatabaseError(e.to_string()))?;
+    Ok(Json(rows.iter().map(RequestLogSummary::from).collect()))
+}
+
+pub async fn get_request_log(
+    State(state): State<Arc<AppState>>,
+    Path(log_id): Path<String>,
+) -> Result<Json<RequestLogDetail>> {
+    let row = find_request_log(&state, &log_id).await?;
+    Ok(Json(RequestLogDetail {
+        summary: RequestLogSummary::from(&row),
+        request: row.request,
+        response: row.response,
+        attempts: row.attempts,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ReplayRequest {
+    /// Model to run the logged request against
+    pub model: String,
+    /// Provider to use; the router picks when absent
+    pub provider: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResult {
+    pub model: String,
+    pub provider: Option<String>,
+    pub content: Option<String>,
+    pub error: Option<String>,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost: f64,
+    pub latency_ms: u64,
+}
+
+impl ReplayResult {
+    fn from_response(response: &crate::ChatResponse) -> Self {
+        Self {
+            model: response.model.clone(),
+            provider: Some(response.provider.clone()),
+            content: response.choices.first().map(|c| c.message.content.text()),
+            error: None,
+            prompt_tokens: response.usage.prompt_tokens,
+            completion_tokens: response.usage.completion_tokens,
+            cost: response.cost,
+            latency_ms: response.latency_ms,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayResponse {
+    pub log_id: String,
+    /// The replayed prompt had personal data masked
+    pub redacted: bool,
+    pub original: ReplayResult,
+    pub replay: ReplayResult,
+}
+
+/// Re-run a logged request against another model and return both results
+///
+/// The replay bypasses the response cache. A provider failure is reported
+/// in the replay result rather than failing the comparison.
+pub async fn replay_request_log(
+    State(state): State<Arc<AppState>>,
+    Path(log_id): Path<String>,
+    Json(req): Json<ReplayRequest>,
+) -> Result<Json<ReplayResponse>> {
+    let row = find_request_log(&state, &log_id).await?;
+    let mut request: crate::ChatRequest = serde_json::from_value(row.request.clone())
+        .map_err(|e| crate::error::SynapseError::Internal(format!("Logged request is unreadable: {}", e)))?;
+    request.model = req.model.clone();
+    request.provider = req.provider.clone();
+    request.cache = None;
+
+    let original = match row.response.clone().map(serde_json::from_value::<crate::ChatResponse>) {
+        Some(Ok(response)) => ReplayResult::from_response(&response),
+        _ => ReplayResult {
+            model: row.model.clone(),
+            provider: row.provider.clone(),
+            content: None,
+            error: row.error.clone(),
+            prompt_tokens: row.prompt_tokens.max(0) as u32,
+            completion_tokens: row.completion_tokens.max(0) as u32,
+            cost: row.cost,
+            latency_ms: row.latency_ms.max(0) as u64,
+        },
+    };
+
+    let start = std::time::Instant::now();
+    let replay = match state.route_chat(&request).await {
+        Ok(response) => {
+            if let Err(e) = state.cost_manager.record_cost(
+                &response.provider,
+                &response.model,
+                &response.usage,
+                response.cost,
+                "admin:replay",
+                &response.id,
+            ).await {
+                tracing::warn!(log = %log_id, error = %e, "Failed to record replay cost");
+            }
+            ReplayResult::from_response(&response)
+        }
+        Err(e) => ReplayResult {
+            model: req.model,
+            provider: req.provider,
+            content: None,
+            error: Some(e.to_string()),
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cost: 0.0,
+            latency_ms: start.elapsed().as_millis() as u64,
+        },
+    };
+
+    Ok(Json(ReplayResponse {
+        log_id: row.id,
+        redacted: row.redacted,
+        original,
+        replay,
+    }))
+}
+
 // ============== ROLES MANAGEMENT ==============
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -430,6 +680,8 @@ pub async fn list_permissions() -> Json<Vec<Permission>> {
         Permission { id: "agents:delete".to_string(), name: "Delete Agents".to_string(), description: "Delete agent configurations".to_string(), category: "Agents".to_string() },
         Permission { id: "knowledge:read".to_string(), name: "View Knowledge".to_string(), description: "Search knowledge base".to_string(), category: "Knowledge".to_string() },
         Permission { id: "knowledge:write".to_string(), name: "Manage Knowledge".to_string(), description: "Upload and modify documents".to_string(), category: "Knowledge".to_string() },
+        Permission { id: "workflows:read".to_string(), name: "View Workflows".to_string(), description: "View workflows and their executions".to_string(), category: "Workflows".to_string() },
+        Permission { id: "workflows:approve".to_string(), name: "Approve Workflow Steps".to_string(), description: "Approve or reject workflow approval steps".to_string(), category: "Workflows".to_string() },
         Permission { id: "providers:read".to_string(), name: "View Providers".to_string(), description: "View provider configurations".to_string(), category: "Admin".to_string() },
         Permission { id: "providers:write".to_string(), name: "Manage Providers".to_string(), description: "Configure LLM providers".to_string(), category: "Admin".to_string() },
         Permission { id: "billing:read".to_string(), name: "View Billing".to_string(), description: "View usage and costs".to_string(), category: "Billing".to_string() },
@@ -441,6 +693,7 @@ pub async fn list_permissions() -> Json<Vec<Permission>> {
 
 // ============== APPLICATION MANAGEMENT ==============
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Application {
     pub id: String,
@@ -470,6 +723,11 @@ pub struct ApplicationResponse {
     pub last_used: Option<DateTime<Utc>>,
     pub expires_at: Option<DateTime<Utc>>,
     pub requests_today: u64,
+    pub cache_ttl_seconds: u32,
+    pub semantic_cache_threshold: f32,
+    pub request_logging: bool,
+    pub log_retention_days: u32,
+    pub log_redaction: bool,
 }
 
 #[derive(Deserialize)]
@@ -480,6 +738,15 @@ pub struct CreateApplicationRequest {
     pub scopes: Vec<String>,
     pub rate_limit: Option<u32>,
     pub expires_in_days: Option<i64>,
+    /// Cache responses for this many seconds by default; 0 or absent disables
+    pub cache_ttl_seconds: Option<u32>,
+    /// Serve prompts at least this similar (0..1) from the semantic cache
+    pub semantic_cache_threshold: Option<f32>,
+    /// Record requests and responses; off unless set
+    pub request_logging: Option<bool>,
+    pub log_retention_days: Option<u32>,
+    /// Mask personal data in logs; on unless set to false
+    pub log_redaction: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -506,6 +773,11 @@ pub async fn list_applications(State(state): State<Arc<AppState>>) -> Json<Vec<A
                         last_used: app.last_used,
                         expires_at: app.expires_at,
                         requests_today: app.requests_today as u64,
+                        cache_ttl_seconds: app.cache_ttl_seconds.max(0) as u32,
+                        semantic_cache_threshold: app.semantic_cache_threshold,
+                        request_logging: app.request_logging,
+                        log_retention_days: app.log_retention_days.max(0) as u32,
+                        log_redaction: app.log_redaction,
                     }
                 }).collect();
                 Json(responses)
@@ -521,12 +793,24 @@ pub async fn list_applications(State(state): State<Arc<AppState>>) -> Json<Vec<A
     }
 }
 
+/// Similarity thresholds are cosine similarities, so only 0..=1 is meaningful
+fn check_semantic_threshold(threshold: Option<f32>) -> Result<()> {
+    match threshold {
+        Some(t) if !(0.0..=1.0).contains(&t) => Err(crate::error::SynapseError::Validation(
+            "semanticCacheThreshold must be between 0 and 1".into(),
+        )),
+        _ => Ok(()),
+    }
+}
+
 pub async fn create_application(
     State(state): State<Arc<AppState>>,
     Json(req): Json<CreateApplicationRequest>,
 ) -> Result<(StatusCode, Json<CreateApplicationResponse>)> {
     use uuid::Uuid;
     use md5::{Md5, Digest};
+
+    check_semantic_threshold(req.semantic_cache_threshold)?;
     
     // Generate API key
     let raw_key = format!("sk-synapse-{}", Uuid::new_v4().to_string().replace("-", ""));
@@ -551,7 +835,12 @@ pub async fn create_application(
             &prefix,
             &scopes_json,
             req.rate_limit.unwrap_or(100) as i32,
-            expires_at
+            expires_at,
+            req.cache_ttl_seconds.unwrap_or(0) as i32,
+            req.semantic_cache_threshold.unwrap_or(0.0),
+            req.request_logging.unwrap_or(false),
+            req.log_retention_days.map_or(request_log::DEFAULT_RETENTION_DAYS, |d| d as i32),
+            req.log_redaction.unwrap_or(true),
         ).await {
             Ok(row) => {
                 let scopes: Vec<String> = serde_json::from_value(row.scopes).unwrap_or_default();
@@ -568,6 +857,11 @@ pub async fn create_application(
                         last_used: row.last_used,
                         expires_at: row.expires_at,
                         requests_today: row.requests_today as u64,
+                        cache_ttl_seconds: row.cache_ttl_seconds.max(0) as u32,
+                        semantic_cache_threshold: row.semantic_cache_threshold,
+                        request_logging: row.request_logging,
+                        log_retention_days: row.log_retention_days.max(0) as u32,
+                        log_redaction: row.log_redaction,
                     },
                     api_key: raw_key,
                 })))
@@ -589,6 +883,11 @@ pub struct UpdateApplicationRequest {
     pub description: Option<String>,
     pub scopes: Option<Vec<String>>,
     pub rate_limit: Option<u32>,
+    pub cache_ttl_seconds: Option<u32>,
+    pub semantic_cache_threshold: Option<f32>,
+    pub request_logging: Option<bool>,
+    pub log_retention_days: Option<u32>,
+    pub log_redaction: Option<bool>,
 }
 
 pub async fn update_application(
@@ -596,6 +895,8 @@ pub async fn update_application(
     Path(app_id): Path<String>,
     Json(req): Json<UpdateApplicationRequest>,
 ) -> Result<Json<ApplicationResponse>> {
+    check_semantic_threshold(req.semantic_cache_threshold)?;
+
     if let Some(repo) = &state.application_repo {
         let scopes_json = req.scopes.map(|s| serde_json::to_value(s).unwrap_or(serde_json::json!([])));
         
@@ -605,7 +906,12 @@ pub async fn update_application(
             req.description.as_deref(),
             scopes_json.as_ref(),
             req.rate_limit.map(|r| r as i32),
-            None // Not updating expires_at through this endpoint for now
+            None, // Not updating expires_at through this endpoint for now
+            req.cache_ttl_seconds.map(|t| t as i32),
+            req.semantic_cache_threshold,
+            req.request_logging,
+            req.log_retention_days.map(|d| d as i32),
+            req.log_redaction,
         ).await {
             Ok(Some(row)) => {
                 let scopes: Vec<String> = serde_json::from_value(row.scopes).unwrap_or_default();
@@ -621,6 +927,11 @@ pub async fn update_application(
                     last_used: row.last_used,
                     expires_at: row.expires_at,
                     requests_today: row.requests_today as u64,
+                    cache_ttl_seconds: row.cache_ttl_seconds.max(0) as u32,
+                    semantic_cache_threshold: row.semantic_cache_threshold,
+                    request_logging: row.request_logging,
+                    log_retention_days: row.log_retention_days.max(0) as u32,
+                    log_redaction: row.log_redaction,
                 }))
             },
             Ok(None) => Err(crate::error::SynapseError::NotFound("Application not found".into())),
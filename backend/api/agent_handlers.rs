@@ -0,0 +1,518 @@
+//! Agent API handlers
+
+use axum::{extract::{Path, State}, http::{header, HeaderMap, StatusCode}, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::agents::threads::{self, Thread, ThreadMessage};
+use crate::agents::{Agent, EmbeddingConfig, KnowledgeSource, LlmConfig, VectorDbSettings};
+use crate::api::handlers::complete_chat;
+use crate::api::state::AppState;
+use crate::db::{AgentRepository, CostRepository, DbPool, ThreadRepository};
+use crate::error::{Result, SynapseError};
+use crate::{ChatResponse, Message, TokenUsage};
+
+fn repository(state: &AppState) -> Result<(AgentRepository, &DbPool)> {
+    let pool = state.db_pool.as_ref()
+        .ok_or_else(|| SynapseError::DatabaseError("Database not connected".to_string()))?;
+    Ok((AgentRepository::new(pool.clone()), pool))
+}
+
+async fn find_agent(repo: &AgentRepository, agent_id: &str) -> Result<Agent> {
+    let row = repo.find_by_id(agent_id).await?
+        .ok_or_else(|| SynapseError::NotFound(format!("Agent {}", agent_id)))?;
+    Agent::from_row(row)
+}
+
+fn to_json<T: Serialize>(value: &Option<T>) -> Option<serde_json::Value> {
+    value.as_ref().map(|v| serde_json::to_value(v).unwrap_or_default())
+}
+
+/// List all agents
+pub async fn list_agents(State(state): State<Arc<AppState>>) -> Result<Json<Vec<Agent>>> {
+    let (repo, _) = repository(&state)?;
+    let agents = repo.list_all().await?
+        .into_iter()
+        .map(Agent::from_row)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Json(agents))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAgentRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub system_prompt: Option<String>,
+    pub llm_config: LlmConfig,
+    pub embedding_config: Option<EmbeddingConfig>,
+    pub vector_db_config: Option<VectorDbSettings>,
+    pub knowledge_collection: Option<String>,
+    pub created_by: Option<String>,
+}
+
+/// Create an agent
+pub async fn create_agent(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateAgentRequest>,
+) -> Result<(StatusCode, Json<Agent>)> {
+    let (repo, _) = repository(&state)?;
+
+    // Validate before touching the database
+    let now = chrono::Utc::now();
+    let draft = Agent {
+        id: Uuid::new_v4().to_string(),
+        name: req.name,
+        description: req.description,
+        system_prompt: req.system_prompt,
+        llm_config: req.llm_config,
+        embedding_config: req.embedding_config,
+        vector_db_config: req.vector_db_config,
+        knowledge_collection: req.knowledge_collection,
+        enabled: true,
+        status: "active".to_string(),
+        created_at: now,
+        updated_at: now,
+        created_by: req.created_by,
+    };
+    draft.validate()?;
+
+    let llm_config = serde_json::to_value(&draft.llm_config).unwrap_or_default();
+    repo.create(
+        &draft.id,
+        &draft.name,
+        draft.description.as_deref(),
+        draft.system_prompt.as_deref(),
+        &llm_config,
+        to_json(&draft.embedding_config).as_ref(),
+        to_json(&draft.vector_db_config).as_ref(),
+        draft.created_by.as_deref(),
+    ).await?;
+    if let Some(ref collection) = draft.knowledge_collection {
+        repo.update_knowledge_collection(&draft.id, collection).await?;
+    }
+
+    tracing::info!(agent = %draft.id, "Created agent {}", draft.name);
+    Ok((StatusCode::CREATED, Json(find_agent(&repo, &draft.id).await?)))
+}
+
+/// Get an agent
+pub async fn get_agent(
+    State(state): State<Arc<AppState>>,
+    Path(agent_id): Path<String>,
+) -> Result<Json<Agent>> {
+    let (repo, _) = repository(&state)?;
+    Ok(Json(find_agent(&repo, &agent_id).await?))
+}
+
+/// Fields to change; `null` clears an optional setting
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateAgentRequest {
+    pub name: Option<String>,
+    #[serde(default, with = "serde_with_null")]
+    pub description: Option<Option<String>>,
+    #[serde(default, with = "serde_with_null")]
+    pub system_prompt: Option<Option<String>>,
+    pub llm_config: Option<LlmConfig>,
+    #[serde(default, with = "serde_with_null")]
+    pub embedding_config: Option<Option<EmbeddingConfig>>,
+    #[serde(default, with = "serde_with_null")]
+    pub vector_db_config: Option<Option<VectorDbSettings>>,
+    #[serde(default, with = "serde_with_null")]
+    pub knowledge_collection: Option<Option<String>>,
+    pub enabled: Option<bool>,
+    pub status: Option<String>,
+}
+
+/// Tell a missing field (`None`) apart from an explicit `null` (`Some(None)`)
+mod serde_with_null {
+    use serde::{Deserialize, Deserializer};
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Option::<T>::deserialize(deserializer).map(Some)
+    }
+}
+
+/// Update an agent
+pub async fn update_agent(
+    State(state): State<Arc<AppState>>,
+    Path(agent_id): Path<String>,
+    Json(req): Json<UpdateAgentRequest>,
+) -> Result<Json<Agent>> {
+    let (repo, _) = repository(&state)?;
+    let mut agent = find_agent(&repo, &agent_id).await?;
+
+    if let Some(name) = req.name {
+        agent.name = name;
+    }
+    if let Some(description) = req.description {
+        agent.description = description;
+    }
+    if let Some(system_prompt) = req.system_prompt {
+        agent.system_prompt = system_prompt;
+    }
+    if let Some(llm_config) = req.llm_config {
+        agent.llm_config = llm_config;
+    }
+    if let Some(embedding_config) = req.embedding_config {
+        agent.embedding_config = embedding_config;
+    }
+    if let Some(vector_db_config) = req.vector_db_config {
+        agent.vector_db_config = vector_db_config;
+    }
+    if let Some(knowledge_collection) = req.knowledge_collection {
+        agent.knowledge_collection = knowledge_collection;
+    }
+    if let Some(enabled) = req.enabled {
+        agent.enabled = enabled;
+    }
+    if let Some(status) = req.status {
+        agent.status = status;
+    }
+    agent.validate()?;
+
+    let llm_config = serde_json::to_value(&agent.llm_config).unwrap_or_default();
+    let row = repo.update(
+        &agent.id,
+        &agent.name,
+        agent.description.as_deref(),
+        agent.system_prompt.as_deref(),
+        &llm_config,
+        to_json(&agent.embedding_config).as_ref(),
+        to_json(&agent.vector_db_config).as_ref(),
+        agent.knowledge_collection.as_deref(),
+        agent.enabled,
+        &agent.status,
+    ).await?
+        .ok_or_else(|| SynapseError::NotFound(format!("Agent {}", agent_id)))?;
+
+    Ok(Json(Agent::from_row(row)?))
+}
+
+/// Delete an agent
+pub async fn delete_agent(
+    State(state): State<Arc<AppState>>,
+    Path(agent_id): Path<String>,
+) -> Result<StatusCode> {
+    let (repo, _) = repository(&state)?;
+    if repo.delete(&agent_id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(SynapseError::NotFound(format!("Agent {}", agent_id)))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AgentChatRequest {
+    /// The conversation, or only the new turn when `thread_id` is set
+    pub messages: Vec<Message>,
+    /// Continue a stored thread; requires its owner's session
+    pub thread_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AgentChatResponse {
+    pub agent_id: String,
+    #[serde(flatten)]
+    pub response: ChatResponse,
+    /// Knowledge chunks added to the prompt, best first
+    pub sources: Vec<KnowledgeSource>,
+    /// The thread with this turn appended
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread: Option<Thread>,
+}
+
+/// POST /v1/agents/:agent_id/chat
+///
+/// Answers with the agent's system prompt and model settings, grounded in
+/// its knowledge collection when it has one. The user is that of the
+/// request's session, if it sends one, and anonymous otherwise. Spend counts
+/// against both the user's and the agent's budget.
+pub async fn agent_chat(
+    State(state): State<Arc<AppState>>,
+    Path(agent_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<AgentChatRequest>,
+) -> Result<Json<AgentChatResponse>> {
+    let user_id = match headers.contains_key(header::AUTHORIZATION) {
+        true => Some(state.session_user(&headers).await?.id),
+        false => None,
+    };
+    let (repo, pool) = repository(&state)?;
+    let agent = find_agent(&repo, &agent_id).await?;
+    if !agent.is_active() {
+        return Err(SynapseError::Validation(format!("Agent {} is disabled", agent_id)));
+    }
+    if req.messages.is_empty() {
+        return Err(SynapseError::Validation("messages must not be empty".to_string()));
+    }
+
+    state.cost_manager.can_request(user_id.as_deref().unwrap_or("anonymous"), 0.01).await?;
+    state.cost_manager.can_request(&agent.id, 0.01).await?;
+
+    let costs = CostRepository::new(pool.clone());
+    let thread_repo = ThreadRepository::new(pool.clone());
+    let thread = match req.thread_id {
+        Some(ref thread_id) => {
+            let owner = user_id.as_deref()
+                .ok_or_else(|| SynapseError::Unauthorized("A session is required to continue a thread".to_string()))?;
+            Some(find_thread(&thread_repo, &agent.id, owner, thread_id).await?)
+        }
+        None => None,
+    };
+
+    let question = req.messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.text())
+        .unwrap_or_default();
+    let (sources, embedding_cost) = if agent.knowledge_collection.is_some() {
+        let account_id = agent.vector_db_config.as_ref().and_then(|c| c.account_id.as_deref());
+        let store = state.vector_store(account_id).await?;
+        agent.retrieve(&question, state.as_ref(), store.as_ref(), user_id.clone()).await?
+    } else {
+        (Vec::new(), 0.0)
+    };
+
+    let conversation = match thread {
+        Some(ref thread) => {
+            let mut history = thread_history(&state, &thread_repo, &costs, &agent, thread, &req.messages, user_id.clone()).await?;
+            history.extend(req.messages.iter().cloned());
+            history
+        }
+        None => req.messages.clone(),
+    };
+
+    let request = agent.chat_request(conversation, &sources, user_id.clone());
+    let (response, _) = complete_chat(&state, &request).await?;
+
+    if let Some(ref embedding) = agent.embedding_config {
+        if embedding_cost > 0.0 {
+            let provider = embedding.provider.as_deref().unwrap_or("embedding");
+            record_agent_cost(&state, &costs, &agent.id, user_id.as_deref(), provider, &embedding.model, &TokenUsage::default(), embedding_cost, &response.id).await;
+        }
+    }
+    record_agent_cost(&state, &costs, &agent.id, user_id.as_deref(), &response.provider, &response.model, &response.usage, response.cost, &response.id).await;
+
+    let thread = match thread {
+        Some(thread) => {
+            let mut turn: Vec<_> = req.messages.iter().map(threads::new_message).collect();
+            turn.extend(response.choices.first().map(|c| threads::new_message(&c.message)));
+            let updated = thread_repo.append_messages(&thread.id, &turn).await?;
+            Some(with_messages(&thread_repo, Thread::from_row(updated)).await?)
+        }
+        None => None,
+    };
+
+    Ok(Json(AgentChatResponse { agent_id: agent.id, response, sources, thread }))
+}
+
+/// Stored messages to replay before the new turn: the newest that fit the
+/// agent's history budget, preceded by the rolling summary when the agent
+/// summarizes history
+async fn thread_history(
+    state: &AppState,
+    thread_repo: &ThreadRepository,
+    costs: &CostRepository,
+    agent: &Agent,
+    thread: &Thread,
+    turn: &[Message],
+    user_id: Option<String>,
+) -> Result<Vec<Message>> {
+    let history = thread_repo.list_messages(&thread.id).await?
+        .into_iter()
+        .map(ThreadMessage::from_row)
+        .collect::<Result<Vec<_>>>()?;
+
+    let turn_tokens: u32 = turn.iter().map(threads::estimate_tokens).sum();
+    let budget = agent.llm_config.history_tokens
+        .unwrap_or(threads::DEFAULT_HISTORY_TOKENS)
+        .saturating_sub(turn_tokens);
+    let start = threads::window_start(&history, budget);
+
+    let mut summary = thread.summary.clone();
+    if agent.llm_config.summarize_history && start > thread.summarized_count {
+        let dropped: Vec<Message> = history[thread.summarized_count..start].iter().map(|m| m.message.clone()).collect();
+        let request = threads::summary_request(agent, summary.as_deref(), &dropped, user_id.clone());
+        match complete_chat(state, &request).await {
+            Ok((response, _)) => {
+                record_agent_cost(state, costs, &agent.id, user_id.as_deref(), &response.provider, &response.model, &response.usage, response.cost, &response.id).await;
+                let text = response.choices.first().map(|c| c.message.content.text()).unwrap_or_default();
+                thread_repo.update_summary(&thread.id, &text, start as i32).await?;
+                summary = Some(text);
+            }
+            // Answer without the newest summary rather than failing the turn
+            Err(e) => tracing::warn!(thread = %thread.id, error = %e, "Failed to summarize thread history"),
+        }
+    }
+
+    let mut messages = Vec::with_capacity(history.len() - start + 1);
+    if agent.llm_config.summarize_history {
+        messages.extend(summary.as_deref().filter(|s| !s.is_empty()).map(threads::summary_message));
+    }
+    messages.extend(history.into_iter().skip(start).map(|m| m.message));
+    Ok(messages)
+}
+
+async fn find_thread(thread_repo: &ThreadRepository, agent_id: &str, user_id: &str, thread_id: &str) -> Result<Thread> {
+    let row = thread_repo.find_by_id(thread_id).await?
+        .ok_or_else(|| SynapseError::NotFound(format!("Thread {}", thread_id)))?;
+    let thread = Thread::from_row(row);
+    thread.check_owner(agent_id, user_id)?;
+    Ok(thread)
+}
+
+async fn with_messages(thread_repo: &ThreadRepository, thread: Thread) -> Result<Thread> {
+    let messages = thread_repo.list_messages(&thread.id).await?
+        .into_iter()
+        .map(ThreadMessage::from_row)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(thread.with_messages(messages))
+}
+
+#[derive(Deserialize)]
+pub struct CreateThreadRequest {
+    pub title: Option<String>,
+}
+
+/// Start a conversation thread with an agent, owned by the user of the
+/// request's session
+pub async fn create_thread(
+    State(state): State<Arc<AppState>>,
+    Path(agent_id): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<CreateThreadRequest>,
+) -> Result<(StatusCode, Json<Thread>)> {
+    let user = state.session_user(&headers).await?;
+    let (repo, pool) = repository(&state)?;
+    let agent = find_agent(&repo, &agent_id).await?;
+
+    let row = ThreadRepository::new(pool.clone())
+        .create(&Uuid::new_v4().to_string(), &agent.id, &user.id, req.title.as_deref())
+        .await?;
+    Ok((StatusCode::CREATED, Json(Thread::from_row(row))))
+}
+
+/// List the session user's threads with an agent
+pub async fn list_threads(
+    State(state): State<Arc<AppState>>,
+    Path(agent_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<Thread>>> {
+    let user = state.session_user(&headers).await?;
+    let (_, pool) = repository(&state)?;
+    let threads = ThreadRepository::new(pool.clone())
+        .list_by_owner(&agent_id, &user.id)
+        .await?
+        .into_iter()
+        .map(Thread::from_row)
+        .collect();
+    Ok(Json(threads))
+}
+
+/// Get a thread with its messages
+pub async fn get_thread(
+    State(state): State<Arc<AppState>>,
+    Path((agent_id, thread_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Json<Thread>> {
+    let user = state.session_user(&headers).await?;
+    let (_, pool) = repository(&state)?;
+    let thread_repo = ThreadRepository::new(pool.clone());
+    let thread = find_thread(&thread_repo, &agent_id, &user.id, &thread_id).await?;
+    Ok(Json(with_messages(&thread_repo, thread).await?))
+}
+
+/// Delete a thread and its messages
+pub async fn delete_thread(
+    State(state): State<Arc<AppState>>,
+    Path((agent_id, thread_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode> {
+    let user = state.session_user(&headers).await?;
+    let (_, pool) = repository(&state)?;
+    let thread_repo = ThreadRepository::new(pool.clone());
+    find_thread(&thread_repo, &agent_id, &user.id, &thread_id).await?;
+    thread_repo.delete(&thread_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Record spend in the cost ledger and `cost_entries`, charging the user's
+/// and the agent's budgets
+#[allow(clippy::too_many_arguments)]
+async fn record_agent_cost(
+    state: &AppState,
+    costs: &CostRepository,
+    agent_id: &str,
+    user_id: Option<&str>,
+    provider: &str,
+    model: &str,
+    usage: &TokenUsage,
+    cost: f64,
+    request_id: &str,
+) {
+    let ledger_user = user_id.unwrap_or("anonymous");
+    if let Err(e) = state.cost_manager.record_cost(provider, model, usage, cost, ledger_user, request_id).await {
+        tracing::warn!(agent = %agent_id, error = %e, "Failed to record cost");
+    }
+    state.cost_manager.charge(agent_id, cost).await;
+
+    if let Err(e) = costs.create_entry(
+        &Uuid::new_v4().to_string(),
+        user_id,
+        Some(agent_id),
+        None,
+        provider,
+        model,
+        usage.prompt_tokens as i32,
+        usage.completion_tokens as i32,
+        cost,
+        Some(request_id),
+    ).await {
+        tracing::warn!(agent = %agent_id, error = %e, "Failed to persist cost entry");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token)).unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_threads_require_session() {
+        let state = Arc::new(AppState::new(Vec::new()));
+        state.auth_service.create_user("owner@example.com", "Owner", "secret").await.unwrap();
+        let path = || Path(("agent".to_string(), "thread".to_string()));
+
+        let result = get_thread(State(state.clone()), path(), HeaderMap::new()).await;
+        assert!(matches!(result, Err(SynapseError::Unauthorized(_))));
+        let result = delete_thread(State(state.clone()), path(), bearer("not-a-session")).await;
+        assert!(matches!(result, Err(SynapseError::Unauthorized(_))));
+
+        // Sending a bad session is rejected even where one is optional
+        let body = serde_json::from_value(serde_json::json!({
+            "messages": [{"role": "user", "content": "hi"}],
+            "thread_id": "thread",
+        })).unwrap();
+        let result = agent_chat(State(state.clone()), Path("agent".to_string()), bearer("not-a-session"), Json(body)).await;
+        assert!(matches!(result, Err(SynapseError::Unauthorized(_))));
+
+        // A valid session gets past authentication to the thread lookup
+        let session = state.auth_service.authenticate("owner@example.com", "secret").await.unwrap();
+        let result = list_threads(State(state.clone()), Path("agent".to_string()), bearer(&session.token)).await;
+        assert!(matches!(result, Err(SynapseError::DatabaseError(_))));
+    }
+}
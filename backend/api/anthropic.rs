@@ -0,0 +1,722 @@
+//! Anthropic wire format for `/v1/messages`
+//!
+//! The inverse of `AnthropicAdapter`: requests in the Messages API shape
+//! (top-level `system`, content blocks, `tool_use`/`tool_result`) become an
+//! internal `ChatRequest` that can be routed to any provider, and the result
+//! is rendered back as a `message` object or Messages streaming events.
+
+use axum::{
+    extract::rejection::JsonRejection,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CacheOptions, ChatRequest, ChatResponse, ChatStreamChunk, ContentPart, FunctionCall, FunctionDefinition,
+    MediaSource, Message, MessageContent, ProviderPreference, SynapseError, TokenUsage, Tool, ToolCall,
+};
+use crate::providers::StreamSummary;
+use super::openai;
+
+// ============================================================================
+// Request
+// ============================================================================
+
+/// `POST /v1/messages` body
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessagesRequest {
+    pub model: String,
+    pub messages: Vec<AnthropicMessage>,
+    pub system: Option<SystemPrompt>,
+    pub max_tokens: u32,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default)]
+    pub stream: bool,
+    pub tools: Option<Vec<AnthropicTool>>,
+    pub tool_choice: Option<serde_json::Value>,
+    pub metadata: Option<RequestMetadata>,
+    /// Hub extension: route to a specific provider
+    pub provider: Option<String>,
+    /// Hub extension: routing strategy when no provider is given
+    pub provider_preference: Option<ProviderPreference>,
+    /// Hub extension: response cache control
+    pub cache: Option<CacheOptions>,
+}
+
+/// `system` may be a plain string or a list of text blocks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum SystemPrompt {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RequestMetadata {
+    pub user_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicMessage {
+    pub role: String,
+    pub content: AnthropicContent,
+}
+
+/// Message content: a plain string or a list of content blocks
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum AnthropicContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: Option<AnthropicContent>,
+        #[serde(default)]
+        is_error: bool,
+    },
+    Image {
+        source: BlockSource,
+    },
+    Document {
+        source: BlockSource,
+        #[serde(default)]
+        title: Option<String>,
+    },
+    /// Anything else (thinking, search results); rejected on conversion
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Source of an image or document block
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BlockSource {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+impl From<BlockSource> for MediaSource {
+    fn from(source: BlockSource) -> Self {
+        match source {
+            BlockSource::Base64 { media_type, data } => MediaSource::Base64 { media_type, data },
+            BlockSource::Url { url } => MediaSource::Url { url },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnthropicTool {
+    pub name: String,
+    pub description: Option<String>,
+    pub input_schema: serde_json::Value,
+}
+
+impl MessagesRequest {
+    /// Convert to the internal request type
+    pub fn into_chat_request(self) -> Result<ChatRequest, SynapseError> {
+        if self.messages.is_empty() {
+            return Err(SynapseError::Validation("messages must not be empty".to_string()));
+        }
+
+        let mut messages = Vec::with_capacity(self.messages.len() + 1);
+        match self.system {
+            Some(SystemPrompt::Text(text)) if !text.is_empty() => messages.push(Message::system(text)),
+            Some(SystemPrompt::Blocks(blocks)) => messages.push(Message::system(blocks_text(blocks)?)),
+            _ => {}
+        }
+        for message in self.messages {
+            messages.extend(messages_from_wire(message)?);
+        }
+
+        let tools = self.tools.map(|tools| {
+            tools
+                .into_iter()
+                .map(|tool| Tool {
+                    tool_type: "function".to_string(),
+                    function: FunctionDefinition {
+                        name: tool.name,
+                        description: tool.description,
+                        parameters: Some(tool.input_schema),
+                    },
+                })
+                .collect()
+        });
+
+        let defaults = ChatRequest::default();
+        Ok(ChatRequest {
+            model: self.model,
+            provider: self.provider,
+            messages,
+            temperature: self.temperature.unwrap_or(defaults.temperature),
+            max_tokens: self.max_tokens,
+            top_p: self.top_p,
+            stop: self.stop_sequences,
+            tools,
+            tool_choice: self.tool_choice.as_ref().and_then(tool_choice_from_wire),
+            response_format: None,
+            retry_invalid_output: false,
+            cache: self.cache,
+            n: None,
+            seed: None,
+            logprobs: None,
+            top_logprobs: None,
+            provider_preference: self.provider_preference,
+            user_id: self.metadata.and_then(|m| m.user_id),
+            metadata: Default::default(),
+        })
+    }
+}
+
+/// Split one Anthropic message into internal messages
+///
+/// `tool_use` blocks become assistant tool calls and each `tool_result`
+/// becomes its own "tool" message, as the OpenAI-style internal format
+/// expects.
+fn messages_from_wire(message: AnthropicMessage) -> Result<Vec<Message>, SynapseError> {
+    let blocks = match message.content {
+        AnthropicContent::Text(text) => return Ok(vec![plain_message(message.role, text.into())]),
+        AnthropicContent::Blocks(blocks) => blocks,
+    };
+
+    let mut messages = Vec::new();
+    let mut parts = Vec::new();
+    let mut tool_calls = Vec::new();
+
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text } => parts.push(ContentPart::Text { text }),
+            ContentBlock::Image { source } => parts.push(ContentPart::Image {
+                source: source.into(),
+                detail: None,
+            }),
+            ContentBlock::Document { source, title } => parts.push(ContentPart::Document {
+                source: source.into(),
+                name: title,
+            }),
+            ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                id,
+                call_type: "function".to_string(),
+                function: FunctionCall { name, arguments: input.to_string() },
+            }),
+            ContentBlock::ToolResult { tool_use_id, content, is_error } => {
+                let mut result = match content {
+                    None => String::new(),
+                    Some(AnthropicContent::Text(t)) => t,
+                    Some(AnthropicContent::Blocks(blocks)) => blocks_text(blocks)?,
+                };
+                if is_error {
+                    result = format!("Error: {}", result);
+                }
+                messages.push(Message {
+                    role: "tool".to_string(),
+                    content: result.into(),
+                    function_call: None,
+                    tool_calls: None,
+                    tool_call_id: Some(tool_use_id),
+                });
+            }
+            ContentBlock::Unsupported => {
+                return Err(SynapseError::Validation("Unsupported content block type".to_string()));
+            }
+        }
+    }
+
+    if !parts.is_empty() || !tool_calls.is_empty() {
+        let content = MessageContent::Parts(parts);
+        // Text-only blocks become plain content for text-only providers
+        let content = if content.media().next().is_none() {
+            MessageContent::Text(content.text())
+        } else {
+            content
+        };
+        let mut msg = plain_message(message.role, content);
+        if !tool_calls.is_empty() {
+            msg.tool_calls = Some(tool_calls);
+        }
+        messages.push(msg);
+    }
+
+    Ok(messages)
+}
+
+fn plain_message(role: String, content: MessageContent) -> Message {
+    Message {
+        role,
+        content,
+        function_call: None,
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+/// Join text blocks; other block types are not valid where only text is
+fn blocks_text(blocks: Vec<ContentBlock>) -> Result<String, SynapseError> {
+    blocks
+        .into_iter()
+        .map(|block| match block {
+            ContentBlock::Text { text } => Ok(text),
+            _ => Err(SynapseError::Validation("Expected text content blocks".to_string())),
+        })
+        .collect()
+}
+
+/// Map Anthropic's `tool_choice` object to the OpenAI-style value
+fn tool_choice_from_wire(choice: &serde_json::Value) -> Option<serde_json::Value> {
+    match choice["type"].as_str()? {
+        "auto" => Some(serde_json::json!("auto")),
+        "any" => Some(serde_json::json!("required")),
+        "none" => Some(serde_json::json!("none")),
+        "tool" => Some(serde_json::json!({
+            "type": "function",
+            "function": {"name": choice["name"]},
+        })),
+        _ => None,
+    }
+}
+
+// ============================================================================
+// Response
+// ============================================================================
+
+/// `message` object
+#[derive(Debug, Serialize)]
+pub struct MessagesResponse {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub object: &'static str,
+    pub role: &'static str,
+    pub content: Vec<serde_json::Value>,
+    pub model: String,
+    pub stop_reason: String,
+    pub stop_sequence: Option<String>,
+    pub usage: MessagesUsage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MessagesUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+impl From<&TokenUsage> for MessagesUsage {
+    fn from(usage: &TokenUsage) -> Self {
+        Self {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }
+    }
+}
+
+impl From<ChatResponse> for MessagesResponse {
+    fn from(response: ChatResponse) -> Self {
+        let (content, stop_reason) = match response.choices.into_iter().next() {
+            Some(choice) => {
+                let tool_calls = choice.message.tool_calls.unwrap_or_default();
+                let mut content = Vec::with_capacity(tool_calls.len() + 1);
+                if !choice.message.content.is_empty() || tool_calls.is_empty() {
+                    content.push(serde_json::json!({"type": "text", "text": choice.message.content.text()}));
+                }
+                content.extend(tool_calls.iter().map(tool_use_block));
+                (content, stop_reason(&choice.finish_reason))
+            }
+            None => (Vec::new(), "end_turn"),
+        };
+
+        Self {
+            id: message_id(&response.id),
+            object: "message",
+            role: "assistant",
+            content,
+            model: response.model,
+            stop_reason: stop_reason.to_string(),
+            stop_sequence: None,
+            usage: MessagesUsage::from(&response.usage),
+        }
+    }
+}
+
+fn tool_use_block(call: &ToolCall) -> serde_json::Value {
+    serde_json::json!({
+        "type": "tool_use",
+        "id": call.id,
+        "name": call.function.name,
+        "input": tool_input(&call.function.arguments),
+    })
+}
+
+/// Tool arguments as a JSON object; Anthropic clients expect `input` to be one
+fn tool_input(arguments: &str) -> serde_json::Value {
+    serde_json::from_str::<serde_json::Value>(arguments)
+        .ok()
+        .filter(|v| v.is_object())
+        .unwrap_or_else(|| serde_json::json!({}))
+}
+
+/// Anthropic message IDs are `msg_`-prefixed; keep provider IDs that already are
+pub fn message_id(id: &str) -> String {
+    if id.starts_with("msg_") {
+        id.to_string()
+    } else if id.is_empty() {
+        format!("msg_{}", uuid::Uuid::new_v4().simple())
+    } else {
+        format!("msg_{}", id)
+    }
+}
+
+/// Normalise provider stop reasons to Anthropic's vocabulary
+pub fn stop_reason(reason: &str) -> &'static str {
+    match openai::finish_reason(reason) {
+        "length" => "max_tokens",
+        "tool_calls" => "tool_use",
+        "content_filter" => "refusal",
+        _ => "end_turn",
+    }
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Content block currently open in a stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OpenBlock {
+    Text,
+    /// Tool call by its index in the provider's delta stream
+    Tool(usize),
+}
+
+/// Turns provider chunks into Messages streaming events
+///
+/// Each event is a `(type, data)` pair; the type doubles as the SSE event
+/// name. Text and tool calls are emitted as separate content blocks, opened
+/// and closed in the order the provider produces them.
+pub struct StreamEncoder {
+    id: String,
+    model: String,
+    block: Option<OpenBlock>,
+    index: usize,
+}
+
+pub type StreamEvent = (&'static str, serde_json::Value);
+
+impl StreamEncoder {
+    pub fn new(model: &str) -> Self {
+        Self {
+            id: message_id(""),
+            model: model.to_string(),
+            block: None,
+            index: 0,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// `message_start`, sent before any provider output
+    pub fn start(&self) -> StreamEvent {
+        ("message_start", serde_json::json!({
+            "type": "message_start",
+            "message": {
+                "id": self.id,
+                "type": "message",
+                "role": "assistant",
+                "content": [],
+                "model": self.model,
+                "stop_reason": null,
+                "stop_sequence": null,
+                "usage": {"input_tokens": 0, "output_tokens": 0},
+            }
+        }))
+    }
+
+    /// Events for one provider chunk
+    pub fn chunk(&mut self, chunk: &ChatStreamChunk) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+
+        if !chunk.delta.is_empty() {
+            if self.block != Some(OpenBlock::Text) {
+                self.open(&mut events, OpenBlock::Text, serde_json::json!({"type": "text", "text": ""}));
+            }
+            events.push(self.delta(serde_json::json!({"type": "text_delta", "text": chunk.delta})));
+        }
+
+        for call in &chunk.tool_calls {
+            if self.block != Some(OpenBlock::Tool(call.index)) {
+                let block = serde_json::json!({
+                    "type": "tool_use",
+                    "id": call.id.clone().unwrap_or_else(|| format!("toolu_{}", uuid::Uuid::new_v4().simple())),
+                    "name": call.name.clone().unwrap_or_default(),
+                    "input": {},
+                });
+                self.open(&mut events, OpenBlock::Tool(call.index), block);
+            }
+            if !call.arguments.is_empty() {
+                events.push(self.delta(serde_json::json!({
+                    "type": "input_json_delta",
+                    "partial_json": call.arguments,
+                })));
+            }
+        }
+
+        events
+    }
+
+    /// Closing events once the provider stream has ended
+    pub fn finish(&mut self, summary: &StreamSummary) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        self.close(&mut events);
+        events.push(("message_delta", serde_json::json!({
+            "type": "message_delta",
+            "delta": {"stop_reason": stop_reason(&summary.finish_reason), "stop_sequence": null},
+            "usage": MessagesUsage::from(&summary.usage),
+        })));
+        events.push(("message_stop", serde_json::json!({"type": "message_stop"})));
+        events
+    }
+
+    fn open(&mut self, events: &mut Vec<StreamEvent>, block: OpenBlock, content_block: serde_json::Value) {
+        self.close(events);
+        events.push(("content_block_start", serde_json::json!({
+            "type": "content_block_start",
+            "index": self.index,
+            "content_block": content_block,
+        })));
+        self.block = Some(block);
+    }
+
+    fn close(&mut self, events: &mut Vec<StreamEvent>) {
+        if self.block.take().is_some() {
+            events.push(("content_block_stop", serde_json::json!({
+                "type": "content_block_stop",
+                "index": self.index,
+            })));
+            self.index += 1;
+        }
+    }
+
+    fn delta(&self, delta: serde_json::Value) -> StreamEvent {
+        ("content_block_delta", serde_json::json!({
+            "type": "content_block_delta",
+            "index": self.index,
+            "delta": delta,
+        }))
+    }
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Error rendered as Anthropic's `{"type": "error", "error": {"type", "message"}}`
+#[derive(Debug)]
+pub struct AnthropicError(pub SynapseError);
+
+impl AnthropicError {
+    /// Error body, also used for in-stream `error` events
+    pub fn body(&self) -> serde_json::Value {
+        let (status, _) = self.0.status_and_code();
+        serde_json::json!({
+            "type": "error",
+            "error": {
+                "type": error_type(status),
+                "message": self.0.to_string(),
+            }
+        })
+    }
+}
+
+fn error_type(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::PAYMENT_REQUIRED => "billing_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::PAYLOAD_TOO_LARGE => "request_too_large",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        StatusCode::SERVICE_UNAVAILABLE => "overloaded_error",
+        s if s.is_client_error() => "invalid_request_error",
+        _ => "api_error",
+    }
+}
+
+impl From<SynapseError> for AnthropicError {
+    fn from(err: SynapseError) -> Self {
+        Self(err)
+    }
+}
+
+impl From<JsonRejection> for AnthropicError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self(SynapseError::Validation(rejection.body_text()))
+    }
+}
+
+impl IntoResponse for AnthropicError {
+    fn into_response(self) -> Response {
+        let (status, _) = self.0.status_and_code();
+        (status, Json(self.body())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Choice, ToolCallDelta};
+
+    #[test]
+    fn test_request_conversion() {
+        let request: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-5-sonnet-latest",
+            "max_tokens": 256,
+            "system": [{"type": "text", "text": "Be brief"}],
+            "messages": [
+                {"role": "user", "content": "What's the weather?"},
+                {"role": "assistant", "content": [
+                    {"type": "text", "text": "Checking."},
+                    {"type": "tool_use", "id": "toolu_1", "name": "weather", "input": {"city": "Paris"}}
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "Sunny"},
+                    {"type": "text", "text": "Thanks"}
+                ]}
+            ],
+            "tools": [{"name": "weather", "input_schema": {"type": "object"}}],
+            "tool_choice": {"type": "any"},
+            "metadata": {"user_id": "user-1"}
+        }))
+        .unwrap();
+
+        let chat = request.into_chat_request().unwrap();
+        let roles: Vec<&str> = chat.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "tool", "user"]);
+        assert_eq!(chat.messages[0].content, "Be brief");
+
+        let call = &chat.messages[2].tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.function.name, "weather");
+        assert_eq!(call.function.arguments, r#"{"city":"Paris"}"#);
+        assert_eq!(chat.messages[3].tool_call_id.as_deref(), Some("toolu_1"));
+        assert_eq!(chat.messages[3].content, "Sunny");
+
+        assert_eq!(chat.max_tokens, 256);
+        assert_eq!(chat.tools.unwrap()[0].function.name, "weather");
+        assert_eq!(chat.tool_choice, Some(serde_json::json!("required")));
+        assert_eq!(chat.user_id.as_deref(), Some("user-1"));
+    }
+
+    #[test]
+    fn test_unsupported_block_rejected() {
+        let request: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "m",
+            "max_tokens": 10,
+            "messages": [{"role": "user", "content": [{"type": "thinking", "thinking": "hmm"}]}]
+        }))
+        .unwrap();
+        assert!(matches!(request.into_chat_request(), Err(SynapseError::Validation(_))));
+    }
+
+    #[test]
+    fn test_image_blocks_become_parts() {
+        let request: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-3-5-sonnet-latest",
+            "max_tokens": 10,
+            "messages": [{"role": "user", "content": [
+                {"type": "image", "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0"}},
+                {"type": "text", "text": "Describe this"}
+            ]}]
+        }))
+        .unwrap();
+
+        let chat = request.into_chat_request().unwrap();
+        assert_eq!(chat.messages[0].content.text(), "Describe this");
+        assert_eq!(chat.input_capabilities(), vec![crate::ModelCapability::Vision]);
+    }
+
+    #[test]
+    fn test_response_shape() {
+        let mut message = Message::assistant("");
+        message.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall { name: "weather".to_string(), arguments: r#"{"city":"Paris"}"#.to_string() },
+        }]);
+        let response = ChatResponse {
+            id: "chatcmpl-1".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            choices: vec![Choice { index: 0, logprobs: None, message, finish_reason: "tool_calls".to_string() }],
+            usage: TokenUsage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 },
+            created: chrono::Utc::now(),
+            latency_ms: 20,
+            cost: 0.0,
+            system_fingerprint: None,
+        };
+
+        let json = serde_json::to_value(MessagesResponse::from(response)).unwrap();
+        assert_eq!(json["type"], "message");
+        assert_eq!(json["id"], "msg_chatcmpl-1");
+        assert_eq!(json["stop_reason"], "tool_use");
+        assert_eq!(json["content"].as_array().unwrap().len(), 1);
+        assert_eq!(json["content"][0]["type"], "tool_use");
+        assert_eq!(json["content"][0]["input"]["city"], "Paris");
+        assert_eq!(json["usage"]["input_tokens"], 10);
+    }
+
+    #[test]
+    fn test_stream_encoder_blocks() {
+        let mut encoder = StreamEncoder::new("gpt-4o");
+        let mut events = vec![encoder.start()];
+        events.extend(encoder.chunk(&ChatStreamChunk { delta: "Hi".to_string(), ..Default::default() }));
+        events.extend(encoder.chunk(&ChatStreamChunk {
+            tool_calls: vec![ToolCallDelta {
+                index: 0,
+                id: Some("call_1".to_string()),
+                name: Some("weather".to_string()),
+                arguments: "{\"city\"".to_string(),
+            }],
+            ..Default::default()
+        }));
+        events.extend(encoder.chunk(&ChatStreamChunk {
+            tool_calls: vec![ToolCallDelta { index: 0, id: None, name: None, arguments: ":\"Paris\"}".to_string() }],
+            ..Default::default()
+        }));
+        events.extend(encoder.finish(&StreamSummary {
+            content: "Hi".to_string(),
+            tool_calls: Vec::new(),
+            finish_reason: "tool_calls".to_string(),
+            usage: TokenUsage { prompt_tokens: 3, completion_tokens: 4, total_tokens: 7 },
+            usage_estimated: false,
+        }));
+
+        let types: Vec<&str> = events.iter().map(|(t, _)| *t).collect();
+        assert_eq!(types, vec![
+            "message_start",
+            "content_block_start", "content_block_delta", "content_block_stop",
+            "content_block_start", "content_block_delta", "content_block_delta", "content_block_stop",
+            "message_delta", "message_stop",
+        ]);
+        assert_eq!(events[4].1["index"], 1);
+        assert_eq!(events[4].1["content_block"]["name"], "weather");
+        assert_eq!(events[8].1["delta"]["stop_reason"], "tool_use");
+        assert_eq!(events[8].1["usage"]["output_tokens"], 4);
+    }
+}
@@ -3,7 +3,7 @@
 use axum::{extract::{Path, State, Query}, http::StatusCode, Json};
 use std::sync::Arc;
 use crate::api::state::AppState;
-use crate::governance::{User, Session, Role, AuditEvent, AuditQuery};
+use crate::governance::{User, Role, AuditEvent, AuditQuery};
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
 
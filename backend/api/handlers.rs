@@ -1,10 +1,14 @@
 //! API route handlers
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{rejection::JsonRejection, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response},
     Json,
 };
+use futures_util::StreamExt;
+use tracing::Instrument;
+use tokio_stream::wrappers::ReceiverStream;
 use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -14,101 +18,232 @@ use crate::{
     CostEntry, Budget,
     error::Result,
 };
+use super::anthropic::{self, AnthropicError, MessagesRequest, MessagesResponse, StreamEncoder};
+use super::openai::{self, ChatCompletion, ChatCompletionChunk, ChatCompletionRequest, OpenAIError};
 use super::state::AppState;
-use crate::db::{ProviderAccountRepository, ProviderAccountRow};
-use serde_json::Value as JsonValue;
+use crate::cache::{CacheSettings, CacheStatus};
+use crate::providers::{ChatStream, ProviderAdapter, StreamAccumulator};
+use crate::metrics::{self, metrics};
+use crate::request_log::{self, LogEntry, Outcome};
+use crate::error::SynapseError;
+use crate::knowledge::rag::{self, Citation, RagOptions};
 
 // ============================================================================
 // Chat Completions
 // ============================================================================
 
 /// POST /v1/chat/completions
+///
+/// Speaks the OpenAI Chat Completions wire format, including streaming
 pub async fn chat_completions(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<ChatRequest>,
-) -> Result<Json<ChatResponse>> {
+    headers: HeaderMap,
+    body: std::result::Result<Json<ChatCompletionRequest>, JsonRejection>,
+) -> std::result::Result<Response, OpenAIError> {
+    let Json(mut body) = body?;
+    let stream = body.stream;
+    let include_usage = body.stream_options.as_ref().is_some_and(|o| o.include_usage);
+    let rag = body.rag.take();
+    let mut request = body.into_chat_request()?;
+    request.cache = request.cache.or_else(|| openai::cache_options(&headers));
     let user_id = request.user_id.clone().unwrap_or_else(|| "anonymous".to_string());
 
     // Check budget (estimate ~1000 tokens)
     state.cost_manager.can_request(&user_id, 0.01).await?;
 
-    // If explicit provider is specified, query database directly for account
-    if let Some(ref provider_id) = request.provider {
-        if let Some(ref pool) = state.db_pool {
-            let repo = ProviderAccountRepository::new(pool.clone());
-            
-            // Try to get default account first, then any enabled account
-            let target_account = if let Ok(Some(account)) = repo.get_default(provider_id).await {
-                Some(account)
-            } else if let Ok(accounts) = repo.list_by_provider(provider_id).await {
-                accounts.into_iter().find(|a| a.enabled && !a.api_key_encrypted.clone().unwrap_or_default().is_empty())
-            } else {
-                None
-            };
-
-            if let Some(account) = target_account {
-                let api_key = account.api_key_encrypted.clone().unwrap_or_default();
-                
-                if !api_key.is_empty() {
-                    let provider_type = match provider_id.as_str() {
-                        "openai" => crate::ProviderType::OpenAI,
-                        "anthropic" => crate::ProviderType::Anthropic,
-                        "mistral" => crate::ProviderType::Mistral,
-                        "cohere" => crate::ProviderType::Cohere,
-                        "groq" => crate::ProviderType::Groq,
-                        "together" => crate::ProviderType::Together,
-                        "gemini" => crate::ProviderType::Gemini,
-                        "azure" | "azure-openai" => crate::ProviderType::AzureOpenAI,
-                        "bedrock" => crate::ProviderType::Bedrock,
-                        _ => crate::ProviderType::OpenAI,
-                    };
-
-                    let base_url = get_provider_base_url(provider_id, account.endpoint.as_deref());
-
-                    let provider = crate::Provider {
-                        id: provider_id.clone(),
-                        name: account.name.clone(),
-                        provider_type,
-                        api_key,
-                        models: Vec::new(),
-                        base_url,
-                        pricing: crate::ProviderPricing {
-                            input_token_cost: 0.0,
-                            output_token_cost: 0.0,
-                        },
-                        enabled: true,
-                        health: crate::ProviderHealth::default(),
-                        headers: std::collections::HashMap::new(),
-                    };
-
-                    let adapter = crate::providers::create_adapter(provider, state.http_client.clone());
-                    match adapter.chat(&request).await {
-                        Ok(response) => {
-                            // Record cost to database
-                            state.cost_manager.record_cost(
-                                &response.provider,
-                                &response.model,
-                                &response.usage,
-                                response.cost,
-                                &user_id,
-                                &response.id,
-                            ).await?;
-
-                            return Ok(Json(response));
-                        }
-                        Err(e) => {
-                            tracing::warn!(provider = %provider_id, error = %e, "Provider failed, trying router");
-                        }
+    let citations = match rag {
+        // Citations are returned with the completion body, which streams lack
+        Some(_) if stream => {
+            return Err(SynapseError::Validation("rag is not supported with stream".to_string()).into());
+        }
+        Some(rag) => retrieve_context(&state, &mut request, &rag).await?,
+        None => Vec::new(),
+    };
+
+    if stream {
+        return stream_chat_completion(state, request, user_id, include_usage).await;
+    }
+
+    let (response, cache_status) = complete_chat(&state, &request).await?;
+
+    // Record cost (zero for cache hits)
+    state.cost_manager.record_cost(
+        &response.provider,
+        &response.model,
+        &response.usage,
+        response.cost,
+        &user_id,
+        &response.id,
+    ).await?;
+
+    let headers = openai::barq_headers(&response.provider, Some(response.cost), Some(response.latency_ms));
+    let headers = openai::with_cache_status(headers, cache_status);
+    let completion = ChatCompletion { citations, ..ChatCompletion::from(response) };
+    Ok((headers, Json(completion)).into_response())
+}
+
+/// Search the `rag` collection for the last user message and add the
+/// results to the request's system message
+async fn retrieve_context(state: &AppState, request: &mut ChatRequest, rag: &RagOptions) -> Result<Vec<Citation>> {
+    let question = rag::question(&request.messages)?;
+    let outcome = state.search_knowledge(&rag.collection_id, &question, &rag.search, request.user_id.clone()).await?;
+    rag::inject(&mut request.messages, rag.template.as_deref(), &question, &outcome.results);
+    Ok(rag::citations(&outcome.results))
+}
+
+/// Run a completion through the response cache
+///
+/// HTTP callers are not tied to an application, so they share one cache
+/// scope and only cache when the request asks for it.
+pub(super) async fn complete_chat(state: &AppState, request: &ChatRequest) -> Result<(ChatResponse, CacheStatus)> {
+    let start = std::time::Instant::now();
+    let (result, attempts) = request_log::track_attempts(
+        state.response_cache.complete(request, "http", CacheSettings::default(), state, || state.route_chat(request)),
+    ).await;
+
+    let (request_id, outcome, cache_status) = match result {
+        Ok((ref response, cache_status)) => (response.id.as_str(), Outcome::Success(response), Some(cache_status)),
+        Err(ref e) => ("", Outcome::Failure(e.to_string()), None),
+    };
+    metrics().observe_chat(metrics::HTTP_APPLICATION, &LogEntry {
+        request_id,
+        request,
+        outcome,
+        attempts,
+        latency_ms: start.elapsed().as_millis() as u64,
+        cache_status,
+        streamed: false,
+    });
+    result
+}
+
+/// Open a provider stream, counting the attempts and any failure
+async fn open_stream(state: &AppState, request: &ChatRequest) -> Result<(Arc<dyn ProviderAdapter>, ChatStream, Vec<request_log::Attempt>)> {
+    let (opened, attempts) = request_log::track_attempts(state.open_chat_stream(request)).await;
+    metrics().observe_attempts(&attempts);
+
+    match opened {
+        Ok((adapter, stream)) => Ok((adapter, stream, attempts)),
+        Err(e) => {
+            metrics().observe_failure(request.provider.as_deref(), &request.model, metrics::HTTP_APPLICATION);
+            Err(e)
+        }
+    }
+}
+
+/// Stream `chat.completion.chunk` events, then record the final usage
+async fn stream_chat_completion(
+    state: Arc<AppState>,
+    request: ChatRequest,
+    user_id: String,
+    include_usage: bool,
+) -> std::result::Result<Response, OpenAIError> {
+    let start = std::time::Instant::now();
+    let (adapter, mut stream, attempts) = open_stream(&state, &request).await?;
+    let headers = openai::barq_headers(&adapter.provider().id, None, None);
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Event>(32);
+    tokio::spawn(async move {
+        let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+        let created = Utc::now().timestamp();
+        let chunk = |choices| {
+            let chunk = ChatCompletionChunk::new(&id, created, &request.model, choices);
+            Event::default().json_data(chunk).unwrap_or_default()
+        };
+
+        let labels = metrics::Labels {
+            provider: &adapter.provider().id,
+            model: &request.model,
+            account: metrics::routed_account(&attempts),
+            application: metrics::HTTP_APPLICATION,
+        };
+        let mut accumulator = StreamAccumulator::new();
+        let mut failed = false;
+        let mut first_token = true;
+        let mut open = tx.send(chunk(vec![openai::role_delta()])).await.is_ok();
+
+        while open {
+            match stream.next().await {
+                Some(Ok(provider_chunk)) => {
+                    if !provider_chunk.delta.is_empty() && std::mem::take(&mut first_token) {
+                        metrics().observe_ttft(&labels, start.elapsed());
+                    }
+                    accumulator.push(&provider_chunk);
+                    if let Some(delta) = openai::chunk_delta(&provider_chunk) {
+                        open = tx.send(chunk(vec![delta])).await.is_ok();
                     }
                 }
+                Some(Err(e)) => {
+                    tracing::warn!(provider = %adapter.name(), error = %e, "Provider stream failed");
+                    failed = true;
+                    let body = OpenAIError(e).body();
+                    let _ = tx.send(Event::default().json_data(body).unwrap_or_default()).await;
+                    open = false;
+                }
+                None => break,
             }
         }
+
+        let summary = accumulator.finish(&request);
+        let cost = adapter.estimate_cost(&summary.usage);
+        if failed {
+            metrics().observe_failure(Some(labels.provider), &request.model, labels.application);
+        } else {
+            metrics().observe_completion(&labels, &summary.usage, cost, start.elapsed(), false);
+        }
+
+        if open {
+            let _ = tx.send(chunk(vec![openai::finish_delta(&summary.finish_reason)])).await;
+            if include_usage {
+                let mut usage_chunk = ChatCompletionChunk::new(&id, created, &request.model, Vec::new());
+                usage_chunk.usage = Some(summary.usage.clone());
+                let _ = tx.send(Event::default().json_data(usage_chunk).unwrap_or_default()).await;
+            }
+            let _ = tx.send(Event::default().data("[DONE]")).await;
+        }
+
+        if let Err(e) = state.cost_manager.record_cost(
+            &adapter.provider().id,
+            &request.model,
+            &summary.usage,
+            cost,
+            &user_id,
+            &id,
+        ).await {
+            tracing::warn!(error = %e, "Failed to record streamed cost");
+        }
+    }.in_current_span());
+
+    let events = ReceiverStream::new(rx).map(Ok::<_, std::convert::Infallible>);
+    Ok((headers, Sse::new(events).keep_alive(KeepAlive::default())).into_response())
+}
+
+// ============================================================================
+// Anthropic Messages
+// ============================================================================
+
+/// POST /v1/messages
+///
+/// Speaks the Anthropic Messages wire format and routes to any provider
+pub async fn messages(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: std::result::Result<Json<MessagesRequest>, JsonRejection>,
+) -> std::result::Result<Response, AnthropicError> {
+    let Json(body) = body?;
+    let stream = body.stream;
+    let mut request = body.into_chat_request()?;
+    request.cache = request.cache.or_else(|| openai::cache_options(&headers));
+    let user_id = request.user_id.clone().unwrap_or_else(|| "anonymous".to_string());
+
+    state.cost_manager.can_request(&user_id, 0.01).await?;
+
+    if stream {
+        return stream_messages(state, request, user_id).await;
     }
 
-    // Fall back to router for providers not in database
-    let response = state.router.route_with_fallback(&request).await?;
+    let (response, cache_status) = complete_chat(&state, &request).await?;
 
-    // Record cost
     state.cost_manager.record_cost(
         &response.provider,
         &response.model,
@@ -118,27 +253,106 @@ pub async fn chat_completions(
         &response.id,
     ).await?;
 
-    Ok(Json(response))
+    let headers = openai::barq_headers(&response.provider, Some(response.cost), Some(response.latency_ms));
+    let headers = openai::with_cache_status(headers, cache_status);
+    Ok((headers, Json(MessagesResponse::from(response))).into_response())
 }
 
-/// Get the base URL for a provider (database-backed version)
-fn get_provider_base_url(provider_id: &str, custom_endpoint: Option<&str>) -> String {
-    if let Some(endpoint) = custom_endpoint {
-        if !endpoint.is_empty() {
-            return endpoint.to_string();
+/// Stream Messages events (`message_start` … `message_stop`), then record usage
+async fn stream_messages(
+    state: Arc<AppState>,
+    request: ChatRequest,
+    user_id: String,
+) -> std::result::Result<Response, AnthropicError> {
+    let start = std::time::Instant::now();
+    let (adapter, mut stream, attempts) = open_stream(&state, &request).await?;
+    let headers = openai::barq_headers(&adapter.provider().id, None, None);
+
+    let (tx, rx) = tokio::sync::mpsc::channel::<Event>(32);
+    tokio::spawn(async move {
+        let event = |(name, data): anthropic::StreamEvent| {
+            Event::default().event(name).json_data(data).unwrap_or_default()
+        };
+
+        let labels = metrics::Labels {
+            provider: &adapter.provider().id,
+            model: &request.model,
+            account: metrics::routed_account(&attempts),
+            application: metrics::HTTP_APPLICATION,
+        };
+        let mut encoder = StreamEncoder::new(&request.model);
+        let mut accumulator = StreamAccumulator::new();
+        let mut failed = false;
+        let mut first_token = true;
+        let mut open = tx.send(event(encoder.start())).await.is_ok();
+
+        'relay: while open {
+            match stream.next().await {
+                Some(Ok(provider_chunk)) => {
+                    if !provider_chunk.delta.is_empty() && std::mem::take(&mut first_token) {
+                        metrics().observe_ttft(&labels, start.elapsed());
+                    }
+                    accumulator.push(&provider_chunk);
+                    for e in encoder.chunk(&provider_chunk) {
+                        if tx.send(event(e)).await.is_err() {
+                            open = false;
+                            continue 'relay;
+                        }
+                    }
+                }
+                Some(Err(e)) => {
+                    tracing::warn!(provider = %adapter.name(), error = %e, "Provider stream failed");
+                    failed = true;
+                    let _ = tx.send(event(("error", AnthropicError(e).body()))).await;
+                    open = false;
+                }
+                None => break,
+            }
         }
-    }
 
-    match provider_id {
-        "openai" => "https://api.openai.com/v1".to_string(),
-        "anthropic" => "https://api.anthropic.com/v1".to_string(),
-        "mistral" => "https://api.mistral.ai/v1".to_string(),
-        "cohere" => "https://api.cohere.ai/v1".to_string(),
-        "groq" => "https://api.groq.com/openai/v1".to_string(),
-        "together" => "https://api.together.xyz/v1".to_string(),
-        "gemini" => "https://generativelanguage.googleapis.com/v1".to_string(),
-        _ => format!("https://api.{}.com/v1", provider_id),
-    }
+        let summary = accumulator.finish(&request);
+        let cost = adapter.estimate_cost(&summary.usage);
+        if failed {
+            metrics().observe_failure(Some(labels.provider), &request.model, labels.application);
+        } else {
+            metrics().observe_completion(&labels, &summary.usage, cost, start.elapsed(), false);
+        }
+
+        if open {
+            for e in encoder.finish(&summary) {
+                let _ = tx.send(event(e)).await;
+            }
+        }
+
+        if let Err(e) = state.cost_manager.record_cost(
+            &adapter.provider().id,
+            &request.model,
+            &summary.usage,
+            cost,
+            &user_id,
+            encoder.id(),
+        ).await {
+            tracing::warn!(error = %e, "Failed to record streamed cost");
+        }
+    }.in_current_span());
+
+    let events = ReceiverStream::new(rx).map(Ok::<_, std::convert::Infallible>);
+    Ok((headers, Sse::new(events).keep_alive(KeepAlive::default())).into_response())
+}
+
+// ============================================================================
+// Metrics
+// ============================================================================
+
+/// GET /metrics
+///
+/// Prometheus text exposition of every hub metric
+pub async fn metrics_export(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    state.refresh_metrics().await;
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        metrics().encode(),
+    )
 }
 
 // ============================================================================
@@ -208,19 +422,20 @@ pub async fn delete_provider(
 // ============================================================================
 
 /// GET /health
+///
+/// Answers from the background prober's latest results
 pub async fn health_check(
     State(state): State<Arc<AppState>>,
 ) -> Json<HealthStatus> {
     let mut components = std::collections::HashMap::new();
     
-    // Check providers
-    let provider_health = state.router.health_check_all().await;
-    for (id, healthy) in provider_health {
-        components.insert(id.clone(), ComponentHealth {
-            name: id,
-            healthy,
-            message: None,
-            latency_ms: None,
+    for component in state.health_monitor.components().await {
+        let latest = component.latest();
+        components.insert(component.key.clone(), ComponentHealth {
+            name: component.name.clone(),
+            healthy: component.health.healthy,
+            message: latest.and_then(|r| r.error.clone()),
+            latency_ms: latest.map(|r| r.latency_ms),
         });
     }
     
@@ -238,7 +453,7 @@ pub async fn health_check(
 pub async fn status(
     State(state): State<Arc<AppState>>,
 ) -> Json<StatusResponse> {
-    let providers = state.router.list_providers();
+    let providers = state.router().list_providers();
     
     Json(StatusResponse {
         status: "running".to_string(),
@@ -497,35 +712,45 @@ pub struct SetBudgetRequest {
 // ============================================================================
 
 /// GET /v1/models
+///
+/// OpenAI-compatible model list backed by the discovered model catalog
 pub async fn list_models(
-    State(_state): State<Arc<AppState>>,
+    State(state): State<Arc<AppState>>,
 ) -> Json<ModelsResponse> {
-    // Return static list of commonly used models
-    let models = vec![
-        ModelInfo { id: "gpt-4".to_string(), provider: "openai".to_string() },
-        ModelInfo { id: "gpt-4-turbo".to_string(), provider: "openai".to_string() },
-        ModelInfo { id: "gpt-4o".to_string(), provider: "openai".to_string() },
-        ModelInfo { id: "gpt-4o-mini".to_string(), provider: "openai".to_string() },
-        ModelInfo { id: "gpt-3.5-turbo".to_string(), provider: "openai".to_string() },
-        ModelInfo { id: "claude-3-opus-20240229".to_string(), provider: "anthropic".to_string() },
-        ModelInfo { id: "claude-3-sonnet-20240229".to_string(), provider: "anthropic".to_string() },
-        ModelInfo { id: "claude-3-5-sonnet-20241022".to_string(), provider: "anthropic".to_string() },
-        ModelInfo { id: "mistral-large-latest".to_string(), provider: "mistral".to_string() },
-        ModelInfo { id: "mistral-medium-latest".to_string(), provider: "mistral".to_string() },
-    ];
-    
-    Json(ModelsResponse { data: models })
+    let models = state
+        .list_models()
+        .await
+        .into_iter()
+        .map(|m| ModelInfo {
+            id: m.id,
+            object: "model",
+            created: 0,
+            owned_by: m.provider.clone(),
+            provider: m.provider,
+            name: m.name,
+            capabilities: m.capabilities,
+        })
+        .collect();
+
+    Json(ModelsResponse { object: "list", data: models })
 }
 
 #[derive(Debug, Serialize)]
 pub struct ModelsResponse {
+    pub object: &'static str,
     pub data: Vec<ModelInfo>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ModelInfo {
     pub id: String,
+    pub object: &'static str,
+    /// Unix timestamp; providers rarely report one, so this is usually 0
+    pub created: i64,
+    pub owned_by: String,
     pub provider: String,
+    pub name: String,
+    pub capabilities: Vec<crate::ModelCapability>,
 }
 
 // ============================================================================
@@ -0,0 +1,244 @@
+//! Knowledge base API handlers
+
+use axum::{extract::{Path, State}, http::StatusCode, Json};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::state::AppState;
+use crate::db::KnowledgeRepository;
+use crate::error::{Result, SynapseError};
+use crate::knowledge::chunking::ChunkingConfig;
+use crate::knowledge::extract::{self, DocumentFormat};
+use crate::knowledge::search::{SearchOptions, SearchResult};
+use crate::knowledge::{self, Collection, Document, MAX_DOCUMENT_BYTES};
+
+fn repository(state: &AppState) -> Result<KnowledgeRepository> {
+    let pool = state.db_pool.as_ref()
+        .ok_or_else(|| SynapseError::DatabaseError("Database not connected".to_string()))?;
+    Ok(KnowledgeRepository::new(pool.clone()))
+}
+
+async fn find_collection(repo: &KnowledgeRepository, collection_id: &str) -> Result<Collection> {
+    let row = repo.find_collection(collection_id).await?
+        .ok_or_else(|| SynapseError::NotFound(format!("Collection {}", collection_id)))?;
+    Collection::from_row(row)
+}
+
+/// List all collections
+pub async fn list_collections(State(state): State<Arc<AppState>>) -> Result<Json<Vec<Collection>>> {
+    let repo = repository(&state)?;
+    let collections = repo.list_collections().await?
+        .into_iter()
+        .map(Collection::from_row)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Json(collections))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCollectionRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub agent_id: Option<String>,
+    pub embedding_model: String,
+    /// Embedding provider; routed like any embedding request when absent
+    pub embedding_provider: Option<String>,
+    /// `VectorDb` provider account holding the vectors; the hub's own
+    /// database when absent
+    pub vector_account_id: Option<String>,
+    #[serde(default)]
+    pub chunking: ChunkingConfig,
+}
+
+/// Create a collection
+pub async fn create_collection(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateCollectionRequest>,
+) -> Result<(StatusCode, Json<Collection>)> {
+    let repo = repository(&state)?;
+
+    if req.name.trim().is_empty() {
+        return Err(SynapseError::Validation("name is required".to_string()));
+    }
+    if req.embedding_model.trim().is_empty() {
+        return Err(SynapseError::Validation("embeddingModel is required".to_string()));
+    }
+    req.chunking.validate()?;
+    // Fail now rather than in the first ingestion job
+    state.vector_store(req.vector_account_id.as_deref()).await?;
+
+    let id = Uuid::new_v4().to_string();
+    let row = repo.create_collection(
+        &id,
+        req.name.trim(),
+        req.description.as_deref(),
+        req.agent_id.as_deref(),
+        &req.embedding_model,
+        req.embedding_provider.as_deref(),
+        req.vector_account_id.as_deref(),
+        &serde_json::to_value(&req.chunking).unwrap_or_default(),
+    ).await?;
+
+    tracing::info!(collection = %id, "Created knowledge collection {}", row.name);
+    Ok((StatusCode::CREATED, Json(Collection::from_row(row)?)))
+}
+
+/// Get a collection
+pub async fn get_collection(
+    State(state): State<Arc<AppState>>,
+    Path(collection_id): Path<String>,
+) -> Result<Json<Collection>> {
+    let repo = repository(&state)?;
+    Ok(Json(find_collection(&repo, &collection_id).await?))
+}
+
+/// Delete a collection with its documents, chunks and vectors
+pub async fn delete_collection(
+    State(state): State<Arc<AppState>>,
+    Path(collection_id): Path<String>,
+) -> Result<StatusCode> {
+    let repo = repository(&state)?;
+    let collection = find_collection(&repo, &collection_id).await?;
+    let store = state.vector_store(collection.vector_account_id.as_deref()).await?;
+    store.drop_collection(&collection.id).await?;
+    repo.delete_collection(&collection_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List a collection's documents with their ingestion progress
+pub async fn list_documents(
+    State(state): State<Arc<AppState>>,
+    Path(collection_id): Path<String>,
+) -> Result<Json<Vec<Document>>> {
+    let repo = repository(&state)?;
+    find_collection(&repo, &collection_id).await?;
+    let documents = repo.list_documents(&collection_id).await?
+        .into_iter()
+        .map(Document::from)
+        .collect();
+    Ok(Json(documents))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadDocumentRequest {
+    /// Title, usually the file name; its extension picks the format when
+    /// `content_type` is absent
+    pub title: String,
+    pub content_type: Option<String>,
+    /// Text of a plain text, Markdown or HTML document
+    pub content: Option<String>,
+    /// Raw file bytes, required for PDF
+    pub content_base64: Option<String>,
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+    pub created_by: Option<String>,
+}
+
+/// Upload a document and queue it for ingestion
+///
+/// Responds once the text is extracted; chunking and embedding continue in
+/// the background and are tracked on the returned document.
+pub async fn upload_document(
+    State(state): State<Arc<AppState>>,
+    Path(collection_id): Path<String>,
+    Json(req): Json<UploadDocumentRequest>,
+) -> Result<(StatusCode, Json<Document>)> {
+    let repo = repository(&state)?;
+    find_collection(&repo, &collection_id).await?;
+
+    let bytes = match (req.content, req.content_base64) {
+        (Some(content), None) => content.into_bytes(),
+        (None, Some(encoded)) => base64::engine::general_purpose::STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| SynapseError::Validation(format!("Invalid contentBase64: {}", e)))?,
+        _ => return Err(SynapseError::Validation("Provide exactly one of content or contentBase64".to_string())),
+    };
+    if bytes.len() > MAX_DOCUMENT_BYTES {
+        return Err(SynapseError::Validation(format!("Documents are limited to {} bytes", MAX_DOCUMENT_BYTES)));
+    }
+
+    let format = DocumentFormat::detect(req.content_type.as_deref(), &req.title)?;
+    let text = extract::extract_text(format, &bytes)?;
+    if text.trim().is_empty() {
+        return Err(SynapseError::Validation("Document contains no extractable text".to_string()));
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let metadata = if req.metadata.is_null() { serde_json::json!({}) } else { req.metadata };
+    let row = repo.create_document(
+        &id,
+        &collection_id,
+        &req.title,
+        &text,
+        format.mime_type(),
+        bytes.len() as i64,
+        &metadata,
+        req.created_by.as_deref(),
+    ).await?;
+    repo.refresh_collection_counts(&collection_id).await?;
+
+    if let Some(ref ingestion) = state.ingestion {
+        ingestion.wake();
+    }
+    tracing::info!(collection = %collection_id, document = %id, "Queued knowledge document {}", row.title);
+    Ok((StatusCode::ACCEPTED, Json(Document::from(row))))
+}
+
+async fn find_document(repo: &KnowledgeRepository, collection_id: &str, document_id: &str) -> Result<Document> {
+    repo.find_document(document_id).await?
+        .filter(|row| row.collection_id == collection_id)
+        .map(Document::from)
+        .ok_or_else(|| SynapseError::NotFound(format!("Document {}", document_id)))
+}
+
+/// Get a document and its ingestion progress
+pub async fn get_document(
+    State(state): State<Arc<AppState>>,
+    Path((collection_id, document_id)): Path<(String, String)>,
+) -> Result<Json<Document>> {
+    let repo = repository(&state)?;
+    Ok(Json(find_document(&repo, &collection_id, &document_id).await?))
+}
+
+/// Delete a document with its chunks and vectors
+pub async fn delete_document(
+    State(state): State<Arc<AppState>>,
+    Path((collection_id, document_id)): Path<(String, String)>,
+) -> Result<StatusCode> {
+    let repo = repository(&state)?;
+    let collection = find_collection(&repo, &collection_id).await?;
+    find_document(&repo, &collection_id, &document_id).await?;
+    let store = state.vector_store(collection.vector_account_id.as_deref()).await?;
+    store.delete_matching(&collection.id, &knowledge::document_filter(&document_id)).await?;
+    repo.delete_document(&document_id).await?;
+    repo.refresh_collection_counts(&collection_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchRequest {
+    pub query: String,
+    #[serde(flatten)]
+    pub options: SearchOptions,
+    pub user_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SearchResponse {
+    pub results: Vec<SearchResult>,
+}
+
+/// Search a collection: vector, keyword (BM25) or hybrid, with optional
+/// metadata filter, reranking and MMR
+pub async fn search_collection(
+    State(state): State<Arc<AppState>>,
+    Path(collection_id): Path<String>,
+    Json(req): Json<SearchRequest>,
+) -> Result<Json<SearchResponse>> {
+    let outcome = state.search_knowledge(&collection_id, &req.query, &req.options, req.user_id).await?;
+    Ok(Json(SearchResponse { results: outcome.results }))
+}
@@ -6,9 +6,29 @@ use axum::{
     response::Response,
     body::Body,
 };
-use tracing::{info, warn};
+use tracing::{info, warn, Instrument};
 use std::time::Instant;
 
+use crate::telemetry;
+
+/// Run the request in a server span that continues the caller's
+/// `traceparent`, if any
+pub async fn trace_middleware(request: Request<Body>, next: Next) -> Response {
+    let span = tracing::info_span!(
+        "http.request",
+        otel.name = %format!("{} {}", request.method(), request.uri().path()),
+        otel.kind = "server",
+        http.method = %request.method(),
+        http.target = %request.uri().path(),
+        http.status_code = tracing::field::Empty,
+    );
+    telemetry::continue_trace(&span, |name| request.headers().get(name).and_then(|v| v.to_str().ok()));
+
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("http.status_code", response.status().as_u16());
+    response
+}
+
 /// Request logging middleware
 pub async fn logging_middleware(request: Request<Body>, next: Next) -> Response {
     let start = Instant::now();
@@ -47,12 +67,14 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+#[allow(dead_code)]
 pub struct RateLimiter {
     requests: Arc<RwLock<HashMap<String, Vec<Instant>>>>,
     max_requests: usize,
     window_secs: u64,
 }
 
+#[allow(dead_code)]
 impl RateLimiter {
     pub fn new(max_requests: usize, window_secs: u64) -> Self {
         Self {
@@ -82,6 +104,7 @@ impl RateLimiter {
 }
 
 /// Optional: API key validation middleware
+#[allow(dead_code)]
 pub async fn api_key_middleware(
     request: Request<Body>,
     next: Next,
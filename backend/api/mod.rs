@@ -1,6 +1,8 @@
 //! REST API implementation
 
+mod anthropic;
 mod handlers;
+mod openai;
 mod routes;
 mod state;
 mod middleware;
@@ -9,6 +11,10 @@ mod governance_handlers;
 mod provider_handlers;
 mod admin_handlers;
 mod settings_handlers;
+mod agent_handlers;
+mod knowledge_handlers;
+mod workflow_handlers;
+mod voice_handlers;
 
 pub use routes::create_router;
 pub use state::AppState;
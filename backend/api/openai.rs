@@ -0,0 +1,583 @@
+//! OpenAI wire format for `/v1/chat/completions`
+//!
+//! Requests and responses follow the OpenAI Chat Completions schema exactly so
+//! the official SDKs can point at the hub. Hub-specific data (serving
+//! provider, cost, latency) travels in `x-barq-*` response headers instead of
+//! the body.
+
+use axum::{
+    extract::{multipart::MultipartRejection, rejection::JsonRejection},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CacheOptions, ChatRequest, ChatResponse, ChatStreamChunk, ContentPart, MediaSource, Message, MessageContent,
+    ProviderPreference, ResponseFormat, SynapseError, TokenUsage, Tool, ToolCall,
+};
+use crate::cache::CacheStatus;
+use crate::knowledge::rag::{Citation, RagOptions};
+
+pub const PROVIDER_HEADER: &str = "x-barq-provider";
+pub const COST_HEADER: &str = "x-barq-cost";
+pub const LATENCY_HEADER: &str = "x-barq-latency-ms";
+/// Response header reporting `hit`, `miss` or `bypass` for cacheable requests
+pub const CACHE_HEADER: &str = "x-barq-cache";
+/// Request header carrying cache directives; plain `Cache-Control` also works
+pub const CACHE_CONTROL_HEADER: &str = "x-barq-cache-control";
+
+// ============================================================================
+// Request
+// ============================================================================
+
+/// `POST /v1/chat/completions` body
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatCompletionMessage>,
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub n: Option<u32>,
+    #[serde(default)]
+    pub stream: bool,
+    pub stream_options: Option<StreamOptions>,
+    pub stop: Option<StopSequences>,
+    pub max_tokens: Option<u32>,
+    /// Newer name for `max_tokens`; takes precedence when both are sent
+    pub max_completion_tokens: Option<u32>,
+    pub seed: Option<i64>,
+    pub logprobs: Option<bool>,
+    pub top_logprobs: Option<u32>,
+    pub tools: Option<Vec<Tool>>,
+    pub tool_choice: Option<serde_json::Value>,
+    pub response_format: Option<ResponseFormat>,
+    /// End-user identifier, also accepted as the hub's `user_id`
+    #[serde(alias = "user_id")]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub metadata: std::collections::HashMap<String, String>,
+    /// Hub extension: route to a specific provider
+    pub provider: Option<String>,
+    /// Hub extension: routing strategy when no provider is given
+    pub provider_preference: Option<ProviderPreference>,
+    /// Hub extension: retry once when output fails `response_format` validation
+    #[serde(default)]
+    pub retry_invalid_output: bool,
+    /// Hub extension: response cache control (`{"ttl": 300, "force": false}`)
+    pub cache: Option<CacheOptions>,
+    /// Hub extension: ground the answer in a knowledge collection
+    /// (`{"collection_id": "...", "top_k": 4, "template": "..."}`)
+    pub rag: Option<RagOptions>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+/// `stop` may be a single string or a list
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum StopSequences {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Chat message as sent by OpenAI clients; `content` may be null or a list of parts
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChatCompletionMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<serde_json::Value>,
+    pub tool_calls: Option<Vec<ToolCall>>,
+    pub tool_call_id: Option<String>,
+}
+
+impl ChatCompletionRequest {
+    /// Convert to the internal request type
+    pub fn into_chat_request(self) -> Result<ChatRequest, SynapseError> {
+        if self.messages.is_empty() {
+            return Err(SynapseError::Validation("messages must not be empty".to_string()));
+        }
+
+        let messages = self
+            .messages
+            .into_iter()
+            .map(message_from_wire)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let defaults = ChatRequest::default();
+        Ok(ChatRequest {
+            model: self.model,
+            provider: self.provider,
+            messages,
+            temperature: self.temperature.unwrap_or(defaults.temperature),
+            max_tokens: self.max_completion_tokens.or(self.max_tokens).unwrap_or(defaults.max_tokens),
+            top_p: self.top_p,
+            stop: self.stop.map(|stop| match stop {
+                StopSequences::One(s) => vec![s],
+                StopSequences::Many(v) => v,
+            }),
+            tools: self.tools,
+            tool_choice: self.tool_choice,
+            response_format: self.response_format,
+            retry_invalid_output: self.retry_invalid_output,
+            cache: self.cache,
+            n: self.n,
+            seed: self.seed,
+            logprobs: self.logprobs,
+            top_logprobs: self.top_logprobs,
+            provider_preference: self.provider_preference,
+            user_id: self.user,
+            metadata: self.metadata,
+        })
+    }
+}
+
+fn message_from_wire(message: ChatCompletionMessage) -> Result<Message, SynapseError> {
+    let content = match message.content {
+        None | Some(serde_json::Value::Null) => MessageContent::default(),
+        Some(serde_json::Value::String(text)) => MessageContent::Text(text),
+        Some(serde_json::Value::Array(parts)) => {
+            let parts = parts.iter().map(part_from_wire).collect::<Result<Vec<_>, _>>()?;
+            // Collapse text-only arrays so text-only providers see plain content
+            if parts.iter().all(|p| matches!(p, ContentPart::Text { .. })) {
+                MessageContent::Text(MessageContent::Parts(parts).text())
+            } else {
+                MessageContent::Parts(parts)
+            }
+        }
+        Some(_) => {
+            return Err(SynapseError::Validation("message content must be a string or an array".to_string()));
+        }
+    };
+
+    Ok(Message {
+        role: message.role,
+        content,
+        function_call: None,
+        tool_calls: message.tool_calls,
+        tool_call_id: message.tool_call_id,
+    })
+}
+
+/// Convert one OpenAI content part (`text`, `image_url`, `input_audio`, `file`)
+fn part_from_wire(part: &serde_json::Value) -> Result<ContentPart, SynapseError> {
+    let missing = |field: &str| SynapseError::Validation(format!("content part is missing {}", field));
+
+    match part["type"].as_str() {
+        Some("text") => Ok(ContentPart::Text {
+            text: part["text"].as_str().unwrap_or("").to_string(),
+        }),
+        Some("image_url") => {
+            // Accept both {"url": ...} and the bare-string form some clients send
+            let image = &part["image_url"];
+            let url = image["url"].as_str().or(image.as_str()).ok_or_else(|| missing("image_url.url"))?;
+            Ok(ContentPart::Image {
+                source: MediaSource::from_url(url),
+                detail: image["detail"].as_str().map(String::from),
+            })
+        }
+        Some("input_audio") => {
+            let audio = &part["input_audio"];
+            Ok(ContentPart::Audio {
+                data: audio["data"].as_str().ok_or_else(|| missing("input_audio.data"))?.to_string(),
+                format: audio["format"].as_str().ok_or_else(|| missing("input_audio.format"))?.to_string(),
+            })
+        }
+        Some("file") => {
+            let file = &part["file"];
+            if file.get("file_id").is_some_and(|id| !id.is_null()) {
+                return Err(SynapseError::Validation(
+                    "file_id references are not supported; send file_data instead".to_string(),
+                ));
+            }
+            let data = file["file_data"].as_str().ok_or_else(|| missing("file.file_data"))?;
+            Ok(ContentPart::Document {
+                source: MediaSource::from_url(data),
+                name: file["filename"].as_str().map(String::from),
+            })
+        }
+        other => Err(SynapseError::Validation(format!(
+            "Unsupported content part type: {}",
+            other.unwrap_or("missing")
+        ))),
+    }
+}
+
+// ============================================================================
+// Response
+// ============================================================================
+
+/// `chat.completion` object
+#[derive(Debug, Serialize)]
+pub struct ChatCompletion {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChatCompletionChoice>,
+    pub usage: TokenUsage,
+    pub system_fingerprint: Option<String>,
+    /// Hub extension: sources retrieved for a `rag` request
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<Citation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatCompletionChoice {
+    pub index: usize,
+    pub message: AssistantMessage,
+    pub logprobs: Option<serde_json::Value>,
+    pub finish_reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AssistantMessage {
+    pub role: String,
+    /// Null when the model only produced tool calls
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+}
+
+impl From<ChatResponse> for ChatCompletion {
+    fn from(response: ChatResponse) -> Self {
+        let choices = response
+            .choices
+            .into_iter()
+            .map(|choice| {
+                let tool_calls = choice.message.tool_calls.filter(|calls| !calls.is_empty());
+                let content = if choice.message.content.is_empty() && tool_calls.is_some() {
+                    None
+                } else {
+                    Some(choice.message.content.text())
+                };
+                ChatCompletionChoice {
+                    index: choice.index,
+                    message: AssistantMessage {
+                        role: "assistant".to_string(),
+                        content,
+                        tool_calls,
+                    },
+                    logprobs: choice.logprobs,
+                    finish_reason: finish_reason(&choice.finish_reason).to_string(),
+                }
+            })
+            .collect();
+
+        Self {
+            id: response.id,
+            object: "chat.completion",
+            created: response.created.timestamp(),
+            model: response.model,
+            choices,
+            usage: response.usage,
+            system_fingerprint: response.system_fingerprint,
+            citations: Vec::new(),
+        }
+    }
+}
+
+/// `chat.completion.chunk` object
+#[derive(Debug, Serialize)]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<ChunkChoice>,
+    /// Only present on the trailing usage chunk when `include_usage` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+    pub system_fingerprint: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkChoice {
+    pub index: usize,
+    pub delta: serde_json::Value,
+    pub logprobs: Option<serde_json::Value>,
+    pub finish_reason: Option<String>,
+}
+
+impl ChatCompletionChunk {
+    pub fn new(id: &str, created: i64, model: &str, choices: Vec<ChunkChoice>) -> Self {
+        Self {
+            id: id.to_string(),
+            object: "chat.completion.chunk",
+            created,
+            model: model.to_string(),
+            choices,
+            usage: None,
+            system_fingerprint: None,
+        }
+    }
+}
+
+/// Opening delta announcing the assistant role
+pub fn role_delta() -> ChunkChoice {
+    ChunkChoice {
+        index: 0,
+        delta: serde_json::json!({"role": "assistant", "content": ""}),
+        logprobs: None,
+        finish_reason: None,
+    }
+}
+
+/// Delta for one provider chunk; `None` when it carries nothing to relay
+pub fn chunk_delta(chunk: &ChatStreamChunk) -> Option<ChunkChoice> {
+    let mut delta = serde_json::Map::new();
+    if !chunk.delta.is_empty() {
+        delta.insert("content".to_string(), serde_json::json!(chunk.delta));
+    }
+    if !chunk.tool_calls.is_empty() {
+        let calls: Vec<serde_json::Value> = chunk
+            .tool_calls
+            .iter()
+            .map(|call| {
+                let mut value = serde_json::json!({
+                    "index": call.index,
+                    "function": {"arguments": call.arguments},
+                });
+                if let Some(ref id) = call.id {
+                    value["id"] = serde_json::json!(id);
+                    value["type"] = serde_json::json!("function");
+                }
+                if let Some(ref name) = call.name {
+                    value["function"]["name"] = serde_json::json!(name);
+                }
+                value
+            })
+            .collect();
+        delta.insert("tool_calls".to_string(), serde_json::json!(calls));
+    }
+
+    if delta.is_empty() {
+        return None;
+    }
+    Some(ChunkChoice {
+        index: 0,
+        delta: serde_json::Value::Object(delta),
+        logprobs: None,
+        finish_reason: None,
+    })
+}
+
+/// Closing delta carrying the finish reason
+pub fn finish_delta(reason: &str) -> ChunkChoice {
+    ChunkChoice {
+        index: 0,
+        delta: serde_json::json!({}),
+        logprobs: None,
+        finish_reason: Some(finish_reason(reason).to_string()),
+    }
+}
+
+/// Normalise provider stop reasons to OpenAI's vocabulary
+pub fn finish_reason(reason: &str) -> &str {
+    match reason.to_lowercase().as_str() {
+        "end_turn" | "stop_sequence" | "complete" | "stop" => "stop",
+        "max_tokens" | "length" => "length",
+        "tool_use" | "tool_calls" | "tool_call" => "tool_calls",
+        "content_filter" | "safety" | "recitation" | "error_toxic" => "content_filter",
+        _ => reason,
+    }
+}
+
+/// Cache directives from request headers, preferring the hub's own header
+pub fn cache_options(headers: &HeaderMap) -> Option<CacheOptions> {
+    [CACHE_CONTROL_HEADER, "cache-control"]
+        .iter()
+        .filter_map(|name| headers.get(*name)?.to_str().ok())
+        .find_map(CacheOptions::from_directives)
+}
+
+/// Add the cache outcome to response headers
+pub fn with_cache_status(mut headers: HeaderMap, status: CacheStatus) -> HeaderMap {
+    if let Some(value) = status.header_value() {
+        headers.insert(CACHE_HEADER, HeaderValue::from_static(value));
+    }
+    headers
+}
+
+/// Hub metadata headers for a completed request; `provider` is the provider
+/// id, not an account name
+pub fn barq_headers(provider: &str, cost: Option<f64>, latency_ms: Option<u64>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(provider) {
+        headers.insert(PROVIDER_HEADER, value);
+    }
+    if let Some(cost) = cost {
+        if let Ok(value) = HeaderValue::from_str(&format!("{:.8}", cost)) {
+            headers.insert(COST_HEADER, value);
+        }
+    }
+    if let Some(latency_ms) = latency_ms {
+        headers.insert(LATENCY_HEADER, HeaderValue::from(latency_ms));
+    }
+    headers
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+/// Error rendered as OpenAI's `{"error": {"message", "type", "code"}}`
+#[derive(Debug)]
+pub struct OpenAIError(pub SynapseError);
+
+impl OpenAIError {
+    /// Error body, also used for in-stream errors
+    pub fn body(&self) -> serde_json::Value {
+        let (status, code) = self.0.status_and_code();
+        serde_json::json!({
+            "error": {
+                "message": self.0.to_string(),
+                "type": error_type(status),
+                "param": null,
+                "code": code.to_lowercase(),
+            }
+        })
+    }
+}
+
+fn error_type(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::PAYMENT_REQUIRED => "insufficient_quota",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        s if s.is_client_error() => "invalid_request_error",
+        _ => "api_error",
+    }
+}
+
+impl From<SynapseError> for OpenAIError {
+    fn from(err: SynapseError) -> Self {
+        Self(err)
+    }
+}
+
+impl From<JsonRejection> for OpenAIError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self(SynapseError::Validation(rejection.body_text()))
+    }
+}
+
+impl From<MultipartRejection> for OpenAIError {
+    fn from(rejection: MultipartRejection) -> Self {
+        Self(SynapseError::Validation(rejection.body_text()))
+    }
+}
+
+impl IntoResponse for OpenAIError {
+    fn into_response(self) -> Response {
+        let (status, _) = self.0.status_and_code();
+        (status, Json(self.body())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Choice;
+
+    #[test]
+    fn test_request_conversion() {
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": [{"type": "text", "text": "Hi"}]},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function", "function": {"name": "f", "arguments": "{}"}}
+                ]},
+                {"role": "tool", "content": "42", "tool_call_id": "call_1"}
+            ],
+            "stop": "END",
+            "max_tokens": 100,
+            "max_completion_tokens": 50,
+            "seed": 7,
+            "n": 2,
+            "stream": true,
+            "stream_options": {"include_usage": true},
+            "user": "user-1"
+        }))
+        .unwrap();
+
+        assert!(request.stream);
+        assert!(request.stream_options.as_ref().unwrap().include_usage);
+
+        let chat = request.into_chat_request().unwrap();
+        assert_eq!(chat.messages[1].content, "Hi");
+        assert_eq!(chat.messages[2].content, "");
+        assert_eq!(chat.messages[3].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(chat.stop, Some(vec!["END".to_string()]));
+        assert_eq!(chat.max_tokens, 50);
+        assert_eq!(chat.seed, Some(7));
+        assert_eq!(chat.n, Some(2));
+        assert_eq!(chat.user_id.as_deref(), Some("user-1"));
+    }
+
+    #[test]
+    fn test_multimodal_parts() {
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "Compare"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0", "detail": "high"}},
+                {"type": "file", "file": {"filename": "a.pdf", "file_data": "data:application/pdf;base64,JVBER"}}
+            ]}]
+        }))
+        .unwrap();
+
+        let chat = request.into_chat_request().unwrap();
+        let MessageContent::Parts(ref parts) = chat.messages[0].content else {
+            panic!("expected parts");
+        };
+        assert!(matches!(&parts[1], ContentPart::Image { source: MediaSource::Base64 { .. }, detail: Some(_) }));
+        assert!(matches!(&parts[2], ContentPart::Document { name: Some(n), .. } if n == "a.pdf"));
+        assert_eq!(chat.input_capabilities().len(), 2);
+    }
+
+    #[test]
+    fn test_response_shape() {
+        let response = ChatResponse {
+            id: "chatcmpl-1".to_string(),
+            provider: "anthropic".to_string(),
+            model: "claude-3-5-sonnet".to_string(),
+            choices: vec![Choice {
+                index: 0,
+                logprobs: None,
+                message: Message::assistant("Hello"),
+                finish_reason: "end_turn".to_string(),
+            }],
+            usage: TokenUsage { prompt_tokens: 3, completion_tokens: 1, total_tokens: 4 },
+            created: chrono::Utc::now(),
+            latency_ms: 12,
+            cost: 0.001,
+            system_fingerprint: None,
+        };
+        let created = response.created.timestamp();
+
+        let json = serde_json::to_value(ChatCompletion::from(response)).unwrap();
+        assert_eq!(json["object"], "chat.completion");
+        assert_eq!(json["created"], created);
+        assert_eq!(json["choices"][0]["finish_reason"], "stop");
+        assert_eq!(json["choices"][0]["message"]["content"], "Hello");
+        assert!(json.get("provider").is_none());
+        assert!(json.get("cost").is_none());
+    }
+
+    #[test]
+    fn test_error_body() {
+        let err = OpenAIError(SynapseError::Validation("bad".to_string()));
+        let body = err.body();
+        assert_eq!(body["error"]["type"], "invalid_request_error");
+        assert_eq!(body["error"]["code"], "validation_error");
+    }
+}
@@ -2,12 +2,12 @@
 
 use axum::{extract::{Path, State}, http::StatusCode, Json};
 use std::sync::Arc;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use crate::api::state::AppState;
 use crate::providers::account_manager::{
-    ProviderAccount, ProviderDefinition, ProviderCategory, QuotaPeriod,
+    ProviderAccount, ProviderDefinition, QuotaPeriod,
     AccountConfig, ApiKeyConfig, AzureConfig, AwsConfig, VectorDbConfig,
-    AccountUpdate, ProviderUsageSummary, AccountDetailedStatus, QuotaUpdate,
+    ProviderUsageSummary, AccountDetailedStatus,
 };
 use crate::types::ProviderModel;
 use crate::error::Result;
@@ -197,6 +197,7 @@ pub async fn create_account(
         ).await.map_err(|e| crate::error::SynapseError::DatabaseError(e.to_string()))?;
 
         tracing::info!("Saved provider account {} to database", account.id);
+        state.rebuild_router().await;
         Ok((StatusCode::CREATED, Json(account)))
     } else {
         Err(crate::error::SynapseError::DatabaseError("Database not connected".to_string()))
@@ -302,6 +303,7 @@ pub async fn update_account(
             &quota_json,
             &config_json,
         ).await.map_err(|e| crate::error::SynapseError::DatabaseError(e.to_string()))?;
+        state.rebuild_router().await;
         
         Ok(Json(account))
     } else {
@@ -327,6 +329,7 @@ pub async fn delete_account(
     if let Some(ref pool) = state.db_pool {
         let repo = ProviderAccountRepository::new(pool.clone());
         if let Ok(true) = repo.delete(&account_id).await {
+            state.rebuild_router().await;
             StatusCode::NO_CONTENT
         } else {
              StatusCode::NOT_FOUND
@@ -343,7 +346,8 @@ pub async fn set_default_account(
 ) -> StatusCode {
     if let Some(ref pool) = state.db_pool {
          let repo = ProviderAccountRepository::new(pool.clone());
-         if let Ok(_) = repo.set_default(&provider_id, &account_id).await {
+         if repo.set_default(&provider_id, &account_id).await.is_ok() {
+             state.rebuild_router().await;
              StatusCode::OK
          } else {
              StatusCode::INTERNAL_SERVER_ERROR
@@ -355,7 +359,7 @@ pub async fn set_default_account(
 
 /// Get usage summary for a provider
 pub async fn get_provider_usage(
-    State(state): State<Arc<AppState>>,
+    State(_state): State<Arc<AppState>>,
     Path(provider_id): Path<String>,
 ) -> Json<ProviderUsageSummary> {
     // Usage stats might still need to aggregate from DB logs/metrics
@@ -381,6 +385,7 @@ pub async fn record_usage(
     StatusCode::OK
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct RecordUsageRequest {
     pub tokens: u64,
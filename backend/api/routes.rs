@@ -1,22 +1,28 @@
 //! API routes configuration
 
-use axum::{middleware, routing::{get, post, delete, put}, Router};
+use axum::{extract::DefaultBodyLimit, middleware, routing::{get, post, delete, put}, Router};
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tower_http::compression::CompressionLayer;
 
-use super::{handlers, state::AppState, middleware::logging_middleware};
-use super::{governance_handlers, provider_handlers, admin_handlers, settings_handlers};
+use super::{handlers, state::AppState, middleware::{logging_middleware, trace_middleware}};
+use super::{governance_handlers, provider_handlers, admin_handlers, settings_handlers, agent_handlers, knowledge_handlers, workflow_handlers, voice_handlers};
 
 /// Create the API router with all routes
 pub fn create_router(state: Arc<AppState>) -> Router {
-    let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any);
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any)
+        // Let browser clients read the x-barq-* metadata headers
+        .expose_headers(Any);
 
     // API v1 routes
     let api_v1 = Router::new()
         // Chat completions
         .route("/chat/completions", post(handlers::chat_completions))
+        .route("/messages", post(handlers::messages))
         // Old providers (legacy)
         .route("/providers", get(handlers::list_providers))
         .route("/providers", post(handlers::create_provider))
@@ -56,6 +62,72 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/provider-accounts/accounts/:account_id", put(provider_handlers::update_account))
         .route("/provider-accounts/accounts/:account_id", delete(provider_handlers::delete_account))
         .route("/provider-accounts/accounts/:account_id/usage", post(provider_handlers::record_usage))
+        // Agents
+        .route("/agents", get(agent_handlers::list_agents))
+        .route("/agents", post(agent_handlers::create_agent))
+        .route("/agents/:agent_id", get(agent_handlers::get_agent))
+        .route("/agents/:agent_id", put(agent_handlers::update_agent))
+        .route("/agents/:agent_id", delete(agent_handlers::delete_agent))
+        .route("/agents/:agent_id/chat", post(agent_handlers::agent_chat))
+        .route("/agents/:agent_id/threads", get(agent_handlers::list_threads))
+        .route("/agents/:agent_id/threads", post(agent_handlers::create_thread))
+        .route("/agents/:agent_id/threads/:thread_id", get(agent_handlers::get_thread))
+        .route("/agents/:agent_id/threads/:thread_id", delete(agent_handlers::delete_thread))
+        // Knowledge bases
+        .route("/knowledge/collections", get(knowledge_handlers::list_collections))
+        .route("/knowledge/collections", post(knowledge_handlers::create_collection))
+        .route("/knowledge/collections/:collection_id", get(knowledge_handlers::get_collection))
+        .route("/knowledge/collections/:collection_id", delete(knowledge_handlers::delete_collection))
+        .route("/knowledge/collections/:collection_id/documents", get(knowledge_handlers::list_documents))
+        .route(
+            "/knowledge/collections/:collection_id/documents",
+            // Base64 uploads are a third larger than the document
+            post(knowledge_handlers::upload_document)
+                .layer(DefaultBodyLimit::max(crate::knowledge::MAX_DOCUMENT_BYTES / 3 * 4 + 64 * 1024)),
+        )
+        .route("/knowledge/collections/:collection_id/documents/:document_id", get(knowledge_handlers::get_document))
+        .route("/knowledge/collections/:collection_id/documents/:document_id", delete(knowledge_handlers::delete_document))
+        .route("/knowledge/:collection_id/search", post(knowledge_handlers::search_collection))
+        // Workflows
+        .route("/workflows", get(workflow_handlers::list_workflows))
+        .route("/workflows", post(workflow_handlers::create_workflow))
+        .route("/workflows/:workflow_id", get(workflow_handlers::get_workflow))
+        .route("/workflows/:workflow_id", put(workflow_handlers::update_workflow))
+        .route("/workflows/:workflow_id", delete(workflow_handlers::delete_workflow))
+        .route("/workflows/:workflow_id/versions", get(workflow_handlers::list_versions))
+        .route("/workflows/:workflow_id/run", post(workflow_handlers::run_workflow))
+        .route("/workflows/:workflow_id/executions", get(workflow_handlers::list_executions))
+        .route("/workflows/:workflow_id/executions/:execution_id", get(workflow_handlers::get_execution))
+        .route("/workflows/:workflow_id/executions/:execution_id/cancel", post(workflow_handlers::cancel_execution))
+        .route("/workflows/:workflow_id/executions/:execution_id/pause", post(workflow_handlers::pause_execution))
+        .route("/workflows/:workflow_id/executions/:execution_id/resume", post(workflow_handlers::resume_execution))
+        .route(
+            "/workflows/:workflow_id/executions/:execution_id/approvals/:node_id",
+            post(workflow_handlers::decide_approval),
+        )
+        // Speech
+        .route("/audio/speech", post(voice_handlers::create_speech))
+        .route("/audio/voices", get(voice_handlers::list_voices))
+        .route("/audio/voices/sync", post(voice_handlers::sync_voices))
+        .route("/audio/tts/providers", get(voice_handlers::list_tts_providers))
+        .route("/audio/tts/accounts", get(voice_handlers::list_tts_accounts))
+        .route("/audio/tts/accounts", post(voice_handlers::create_tts_account))
+        .route("/audio/tts/accounts/:account_id", delete(voice_handlers::delete_tts_account))
+        .route(
+            "/audio/transcriptions",
+            post(voice_handlers::create_transcription)
+                .layer(DefaultBodyLimit::max(crate::voice::stt::MAX_AUDIO_BYTES + 64 * 1024)),
+        )
+        .route(
+            "/audio/translations",
+            post(voice_handlers::create_translation)
+                .layer(DefaultBodyLimit::max(crate::voice::stt::MAX_AUDIO_BYTES + 64 * 1024)),
+        )
+        .route("/audio/stt/providers", get(voice_handlers::list_stt_providers))
+        .route("/audio/stt/models", get(voice_handlers::list_stt_models))
+        .route("/audio/stt/accounts", get(voice_handlers::list_stt_accounts))
+        .route("/audio/stt/accounts", post(voice_handlers::create_stt_account))
+        .route("/audio/stt/accounts/:account_id", delete(voice_handlers::delete_stt_account))
         // Admin: User Management
         .route("/admin/users", get(admin_handlers::list_users))
         .route("/admin/users", post(admin_handlers::create_user))
@@ -68,6 +140,13 @@ pub fn create_router(state: Arc<AppState>) -> Router {
         .route("/admin/api-keys/:key_id", delete(admin_handlers::delete_api_key))
         // Admin: System Health
         .route("/admin/health", get(admin_handlers::get_system_health))
+        .route("/admin/health/components", get(admin_handlers::get_component_health))
+        // Admin: Response Cache
+        .route("/admin/cache/stats", get(admin_handlers::get_cache_stats))
+        // Admin: Request Logs
+        .route("/admin/logs", get(admin_handlers::search_request_logs))
+        .route("/admin/logs/:log_id", get(admin_handlers::get_request_log))
+        .route("/admin/logs/:log_id/replay", post(admin_handlers::replay_request_log))
         // Admin: Roles & Permissions
         .route("/admin/roles/definitions", get(admin_handlers::list_role_definitions))
         .route("/admin/permissions", get(admin_handlers::list_permissions))
@@ -81,11 +160,13 @@ pub fn create_router(state: Arc<AppState>) -> Router {
 
     Router::new()
         .route("/health", get(handlers::health_check))
+        .route("/metrics", get(handlers::metrics_export))
         .nest("/v1", api_v1)
         .fallback(handlers::not_found)
         .layer(middleware::from_fn(logging_middleware))
         .layer(TraceLayer::new_for_http())
         .layer(CompressionLayer::new())
         .layer(cors)
+        .layer(middleware::from_fn(trace_middleware))
         .with_state(state)
 }
@@ -5,6 +5,7 @@ use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::api::state::AppState;
+use crate::mail::SmtpSettings;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -16,17 +17,6 @@ pub struct AppSettings {
     pub default_model: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct SmtpSettings {
-    pub host: String,
-    pub port: i32,
-    pub username: String,
-    pub password: Option<String>,
-    pub from_email: String,
-    pub encryption: String, // "tls", "ssl", "none"
-}
-
 // GET /settings
 pub async fn get_settings(State(state): State<Arc<AppState>>) -> Json<AppSettings> {
     // Try to fetch from DB
@@ -2,16 +2,28 @@
 
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use crate::{Provider, router::SmartRouter, cost::CostManager};
+use crate::{ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, Provider, router::SmartRouter, cost::CostManager};
+use crate::catalog::{CatalogModel, CatalogSource, ModelCatalog};
+use crate::cache::{Embedder, ResponseCache};
+use crate::health::{self, ComponentKind, HealthMonitor, ProbeTarget};
+use crate::vector::{MemoryVectorStore, PostgresVectorStore, VectorStore};
+use crate::knowledge::rerank::{self, RerankOptions, Reranker};
+use crate::knowledge::search::{self, SearchChunk, SearchMode, SearchOptions, SearchOutcome};
+use crate::knowledge::worker::{IngestServices, IngestionWorker};
+use crate::knowledge::Collection;
+use crate::workflows::engine::WorkflowServices;
+use crate::workflows::runner::WorkflowRunner;
 
-use crate::governance::{AuthService, RBACService, AuditService};
-use crate::providers::ProviderAccountManager;
+use crate::governance::{AuthService, RBACService, AuditService, User};
+use crate::providers::{ChatStream, ProviderAccountManager, ProviderAdapter};
 use crate::db::DbPool;
 use crate::db::ApplicationRepository;
 
 /// Shared application state
 pub struct AppState {
-    pub router: Arc<SmartRouter>,
+    // Swapped whole when providers or accounts change; see `rebuild_router`
+    router: std::sync::RwLock<Arc<SmartRouter>>,
+    router_rebuild: tokio::sync::Mutex<()>,
     pub cost_manager: Arc<CostManager>,
     pub providers: Arc<RwLock<Vec<Provider>>>,
     pub http_client: reqwest::Client,
@@ -27,6 +39,19 @@ pub struct AppState {
     pub db_pool: Option<DbPool>,
     // Repositories
     pub application_repo: Option<Arc<ApplicationRepository>>,
+    // Discovered models across providers and accounts
+    pub model_catalog: Arc<ModelCatalog>,
+    // Exact-match response cache (disabled until Redis is connected)
+    pub response_cache: Arc<ResponseCache>,
+    // Latest background probe results
+    pub health_monitor: Arc<HealthMonitor>,
+    // Vector store for collections without a vector database account:
+    // Postgres when connected, in-memory otherwise
+    pub local_vectors: Arc<dyn VectorStore>,
+    // Background knowledge ingestion, when a database is connected
+    pub ingestion: Option<Arc<IngestionWorker>>,
+    // Durable workflow executions, when a database is connected
+    pub workflow_runner: Option<Arc<WorkflowRunner>>,
 }
 
 impl AppState {
@@ -62,23 +87,34 @@ impl AppState {
             tracing::info!("Loaded provider accounts from database");
         }
         
-        Ok(Self {
-            router,
-            cost_manager: Arc::new(CostManager::new()),
+        let instance_id = uuid::Uuid::new_v4().to_string();
+        let cost_manager = Arc::new(CostManager::new());
+        let ingestion = IngestionWorker::new(pool.clone(), instance_id.clone(), cost_manager.clone());
+        let workflow_runner = WorkflowRunner::new(pool.clone(), instance_id, http_client.clone(), audit_service.clone());
+
+        let state = Self {
+            router: std::sync::RwLock::new(router),
+            router_rebuild: tokio::sync::Mutex::new(()),
+            cost_manager,
             providers: Arc::new(RwLock::new(providers.clone())),
-            http_client: reqwest::Client::builder()
-                .timeout(std::time::Duration::from_secs(120))
-                .build()
-                .expect("Failed to create HTTP client"),
+            http_client,
             start_time: std::time::Instant::now(),
             version: env!("CARGO_PKG_VERSION").to_string(),
             auth_service,
             rbac_service,
             audit_service,
             account_manager,
-            db_pool: Some(pool),
+            db_pool: Some(pool.clone()),
             application_repo: Some(application_repo),
-        })
+            model_catalog: Arc::new(ModelCatalog::default()),
+            response_cache: Arc::new(ResponseCache::disabled()),
+            health_monitor: Arc::new(HealthMonitor::new()),
+            local_vectors: Arc::new(PostgresVectorStore::new(pool)),
+            ingestion: Some(Arc::new(ingestion)),
+            workflow_runner: Some(Arc::new(workflow_runner)),
+        };
+        state.rebuild_router().await;
+        Ok(state)
     }
     
     /// Create new state without database (in-memory only)
@@ -98,7 +134,8 @@ impl AppState {
         let account_manager = ProviderAccountManager::new();
         
         Self {
-            router,
+            router: std::sync::RwLock::new(router),
+            router_rebuild: tokio::sync::Mutex::new(()),
             cost_manager: Arc::new(CostManager::new()),
             providers: Arc::new(RwLock::new(providers.clone())),
             http_client: reqwest::Client::builder()
@@ -113,12 +150,400 @@ impl AppState {
             account_manager: Arc::new(account_manager),
             db_pool: None,
             application_repo: None,
+            model_catalog: Arc::new(ModelCatalog::default()),
+            response_cache: Arc::new(ResponseCache::disabled()),
+            health_monitor: Arc::new(HealthMonitor::new()),
+            local_vectors: Arc::new(MemoryVectorStore::new()),
+            ingestion: None,
+            workflow_runner: None,
+        }
+    }
+
+    /// Current router; callers keep the returned handle for the whole
+    /// request, so a rebuild never drops requests in flight
+    pub fn router(&self) -> Arc<SmartRouter> {
+        self.router.read().expect("router lock poisoned").clone()
+    }
+
+    pub async fn reload_providers(&self, providers: Vec<Provider>) {
+        *self.providers.write().await = providers;
+        self.rebuild_router().await;
+    }
+
+    /// Rebuild the router from enabled database accounts and environment
+    /// providers and swap it in, keeping learned health scores
+    ///
+    /// Accounts come first, default accounts ahead of the rest, so they win
+    /// ties in fallback order. Only chat accounts with listed prices are
+    /// routed, each under its own route id. If accounts cannot be loaded the
+    /// current router is kept.
+    pub async fn rebuild_router(&self) {
+        let _guard = self.router_rebuild.lock().await;
+
+        let mut routes = Vec::new();
+        if let Some(ref pool) = self.db_pool {
+            match crate::db::ProviderAccountRepository::new(pool.clone()).list_all().await {
+                Ok(mut accounts) => {
+                    accounts.retain(|a| a.enabled);
+                    accounts.sort_by_key(|a| !a.is_default);
+                    routes.extend(accounts.iter().filter_map(|a| Some((a.route_id(), a.to_routed_provider()?))));
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to load provider accounts, keeping current router");
+                    return;
+                }
+            }
+        }
+        routes.extend(self.providers.read().await.iter().map(|p| (p.id.clone(), p.clone())));
+
+        let router = SmartRouter::from_routes(routes, self.http_client.clone());
+        router.inherit_health(&self.router()).await;
+        let adapters = router.list_providers().len();
+        *self.router.write().expect("router lock poisoned") = Arc::new(router);
+        tracing::info!(adapters, "Router rebuilt");
+    }
+
+    /// Vector store behind a `VectorDb` provider account, or the local store
+    /// when no account is given
+    pub async fn vector_store(&self, account_id: Option<&str>) -> crate::Result<Arc<dyn VectorStore>> {
+        let Some(account_id) = account_id else {
+            return Ok(self.local_vectors.clone());
+        };
+        let pool = self.db_pool.as_ref()
+            .ok_or_else(|| crate::SynapseError::DatabaseError("Database not connected".to_string()))?;
+        let account = crate::db::ProviderAccountRepository::new(pool.clone())
+            .find_by_id(account_id)
+            .await?
+            .ok_or_else(|| crate::SynapseError::NotFound(format!("Vector account {}", account_id)))?;
+        let config = account.vector_db_config().ok_or_else(|| {
+            crate::SynapseError::Validation(format!("Account {} has no vector database settings", account_id))
+        })?;
+        crate::vector::create_store(&account.provider_id, &config, self.http_client.clone())
+    }
+
+    /// Rebuild the router whenever any replica changes provider accounts
+    ///
+    /// Listens on the channel notified by the database trigger and runs until
+    /// the process exits. A dropped listener connection triggers a rebuild
+    /// too, since notifications sent while disconnected are lost.
+    pub async fn watch_provider_accounts(&self) {
+        let Some(ref pool) = self.db_pool else {
+            return;
+        };
+        let channel = crate::db::ACCOUNTS_CHANGED_CHANNEL;
+
+        loop {
+            let mut listener = match sqlx::postgres::PgListener::connect_with(pool).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to connect account change listener");
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+            if let Err(e) = listener.listen(channel).await {
+                tracing::warn!(error = %e, "Failed to listen for account changes");
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                continue;
+            }
+
+            loop {
+                match listener.try_recv().await {
+                    Ok(Some(_)) => self.rebuild_router().await,
+                    Ok(None) => {
+                        tracing::warn!("Account change listener reconnecting");
+                        self.rebuild_router().await;
+                    }
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Account change listener failed");
+                        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adapters for a provider's enabled database accounts, default account
+    /// first, priced for `model`
+    pub async fn account_adapters(&self, provider_id: &str, model: &str) -> Vec<Arc<dyn ProviderAdapter>> {
+        let Some(ref pool) = self.db_pool else {
+            return Vec::new();
+        };
+        let repo = crate::db::ProviderAccountRepository::new(pool.clone());
+
+        let accounts = match repo.get_default(provider_id).await {
+            Ok(Some(account)) => vec![account],
+            _ => repo.list_by_provider(provider_id).await
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|a| a.enabled)
+                .collect(),
+        };
+
+        accounts
+            .iter()
+            .filter(|row| row.serves_chat())
+            .filter_map(|row| row.to_provider(model))
+            .map(|provider| crate::providers::create_adapter(provider, self.http_client.clone()))
+            .collect()
+    }
+
+    /// Run a completion, preferring database accounts for an explicit
+    /// provider and otherwise letting the router pick with fallback
+    #[tracing::instrument(name = "route", skip_all, fields(model = %request.model, provider = ?request.provider))]
+    pub async fn route_chat(&self, request: &ChatRequest) -> crate::Result<ChatResponse> {
+        if let Some(ref provider_id) = request.provider {
+            for adapter in self.account_adapters(provider_id, &request.model).await {
+                adapter.check_input(request)?;
+                let call = crate::providers::structured::chat(adapter.as_ref(), request);
+                match crate::telemetry::attempt(provider_id, Some(adapter.name()), &request.model, call).await {
+                    Ok(response) => {
+                        crate::request_log::record_attempt(provider_id, Some(adapter.name()), None);
+                        return Ok(response);
+                    }
+                    Err(e) => {
+                        crate::request_log::record_attempt(provider_id, Some(adapter.name()), Some(&e));
+                        tracing::warn!(provider = %provider_id, account = %adapter.name(), error = %e, "Account failed, trying next");
+                    }
+                }
+            }
+        }
+
+        self.router().route_with_fallback(request).await
+    }
+
+    /// Open a provider stream, preferring database accounts for an explicit
+    /// provider and otherwise letting the router pick with fallback
+    #[tracing::instrument(name = "route", skip_all, fields(model = %request.model, provider = ?request.provider, stream = true))]
+    pub async fn open_chat_stream(&self, request: &ChatRequest) -> crate::Result<(Arc<dyn ProviderAdapter>, ChatStream)> {
+        if let Some(ref provider_id) = request.provider {
+            for adapter in self.account_adapters(provider_id, &request.model).await {
+                adapter.check_input(request)?;
+                let call = adapter.chat_stream(request);
+                match crate::telemetry::attempt(provider_id, Some(adapter.name()), &request.model, call).await {
+                    Ok(stream) => {
+                        crate::request_log::record_attempt(provider_id, Some(adapter.name()), None);
+                        return Ok((adapter, stream));
+                    }
+                    Err(e) => {
+                        crate::request_log::record_attempt(provider_id, Some(adapter.name()), Some(&e));
+                        tracing::warn!(provider = %provider_id, account = %adapter.name(), error = %e, "Account failed to stream, trying next");
+                    }
+                }
+            }
+        }
+
+        self.router().route_stream(request).await
+    }
+
+    /// Provider built from the default (or first enabled) database account
+    /// for `provider_id`, priced for `model`
+    async fn account_provider(&self, provider_id: &str, model: &str) -> Option<Provider> {
+        let repo = crate::db::ProviderAccountRepository::new(self.db_pool.clone()?);
+        let account = match repo.get_default(provider_id).await {
+            Ok(Some(account)) => Some(account),
+            _ => repo.list_by_provider(provider_id).await
+                .unwrap_or_default()
+                .into_iter()
+                .find(|a| a.enabled),
+        };
+        account.and_then(|a| a.to_provider(model))
+    }
+
+    /// Create embeddings, preferring a database account for an explicit
+    /// provider as for chat
+    pub async fn embed(&self, request: &EmbeddingRequest) -> crate::Result<EmbeddingResponse> {
+        if let Some(ref provider_id) = request.provider {
+            if let Some(provider) = self.account_provider(provider_id, &request.model).await {
+                let adapter = crate::providers::create_adapter(provider, self.http_client.clone());
+                return adapter.embed(request).await;
+            }
+        }
+
+        self.router().embed(request).await
+    }
+
+    /// Search a knowledge collection, recording the query embedding's cost
+    pub async fn search_knowledge(
+        &self,
+        collection_id: &str,
+        query: &str,
+        options: &SearchOptions,
+        user_id: Option<String>,
+    ) -> crate::Result<SearchOutcome> {
+        let pool = self.db_pool.as_ref()
+            .ok_or_else(|| crate::SynapseError::DatabaseError("Database not connected".to_string()))?;
+        let repo = crate::db::KnowledgeRepository::new(pool.clone());
+        let collection = repo.find_collection(collection_id).await?
+            .ok_or_else(|| crate::SynapseError::NotFound(format!("Collection {}", collection_id)))
+            .and_then(Collection::from_row)?;
+        let store = self.vector_store(collection.vector_account_id.as_deref()).await?;
+        let chunks: Vec<SearchChunk> = if options.mode == SearchMode::Vector {
+            Vec::new()
+        } else {
+            repo.list_searchable_chunks(collection_id).await?.into_iter().map(SearchChunk::from).collect()
+        };
+
+        let outcome = search::search(self, store.as_ref(), self, &collection, &chunks, query, options, user_id.clone()).await?;
+        if let Some(ref embedding) = outcome.embedding {
+            if let Err(e) = self.cost_manager.record_cost(
+                &embedding.provider,
+                &embedding.model,
+                &embedding.usage,
+                embedding.cost,
+                user_id.as_deref().unwrap_or("anonymous"),
+                &uuid::Uuid::new_v4().to_string(),
+            ).await {
+                tracing::warn!(collection = %collection_id, error = %e, "Failed to record cost");
+            }
+        }
+        Ok(outcome)
+    }
+
+    /// Probe every enabled provider and database account, Redis and the
+    /// database, and record the results in the health monitor
+    ///
+    /// Environment providers also feed the router's health scores and their
+    /// `Provider::health`.
+    pub async fn probe_health(&self) {
+        let mut targets: Vec<ProbeTarget> = self.providers
+            .read()
+            .await
+            .iter()
+            .filter(|p| p.enabled)
+            .map(|p| ProbeTarget {
+                key: format!("provider:{}", p.id),
+                kind: ComponentKind::Provider,
+                name: p.name.clone(),
+                provider_id: p.id.clone(),
+                adapter: crate::providers::create_adapter(p.clone(), self.http_client.clone()),
+            })
+            .collect();
+
+        if let Some(ref pool) = self.db_pool {
+            let accounts = crate::db::ProviderAccountRepository::new(pool.clone()).list_all().await.unwrap_or_default();
+            for account in accounts.iter().filter(|a| a.enabled && a.serves_chat()) {
+                if let Some(provider) = account.to_provider("") {
+                    targets.push(ProbeTarget {
+                        key: format!("account:{}", account.id),
+                        kind: ComponentKind::Account,
+                        name: account.name.clone(),
+                        provider_id: account.provider_id.clone(),
+                        adapter: crate::providers::create_adapter(provider, self.http_client.clone()),
+                    });
+                }
+            }
+        }
+
+        let probes = targets.iter().map(|t| health::probe(t.adapter.health_check()));
+        let results = futures_util::future::join_all(probes).await;
+
+        let mut keys = std::collections::HashSet::new();
+        for (target, result) in targets.into_iter().zip(results) {
+            let healthy = result.healthy;
+            let summary = self.health_monitor
+                .record(&target.key, target.kind, &target.name, Some(&target.provider_id), result)
+                .await;
+            if target.kind == ComponentKind::Account {
+                // Account probe keys double as their router ids
+                self.router().update_health_score(&target.key, healthy).await;
+            }
+            if target.kind == ComponentKind::Provider {
+                self.router().update_health_score(&target.provider_id, healthy).await;
+                if let Some(provider) = self.providers.write().await.iter_mut().find(|p| p.id == target.provider_id) {
+                    provider.health = summary;
+                }
+            }
+            keys.insert(target.key);
+        }
+
+        if self.response_cache.has_redis() {
+            let redis = health::probe(async {
+                self.response_cache.ping().await.map(|_| true)
+            }).await;
+            self.health_monitor.record("redis", ComponentKind::Redis, "Redis", None, redis).await;
+            keys.insert("redis".to_string());
+        }
+
+        if let Some(ref pool) = self.db_pool {
+            let database = health::probe(async {
+                sqlx::query("SELECT 1").execute(pool).await.map(|_| true)
+            }).await;
+            self.health_monitor.record("database", ComponentKind::Database, "PostgreSQL", None, database).await;
+            keys.insert("database".to_string());
+        }
+
+        self.health_monitor.retain(&keys).await;
+    }
+
+    /// Refresh gauges that mirror live state: provider health, account
+    /// quotas and database pool usage
+    pub async fn refresh_metrics(&self) {
+        let metrics = crate::metrics::metrics();
+
+        for (provider, score) in self.router().health_scores().await {
+            metrics.set_provider_health(&provider, score);
+        }
+
+        for provider in self.account_manager.list_providers().await {
+            for account in self.account_manager.get_accounts(&provider.id).await {
+                for tier in account.quota_statuses() {
+                    metrics.set_quota_remaining(&provider.id, &account.name, tier.period.as_str(), tier.remaining_tokens);
+                }
+            }
+        }
+
+        if let Some(ref pool) = self.db_pool {
+            let idle = pool.num_idle() as u32;
+            metrics.set_db_pool(pool.size().saturating_sub(idle), idle);
         }
     }
 
-    pub async fn reload_providers(&mut self, providers: Vec<Provider>) {
-        *self.providers.write().await = providers.clone();
-        self.router = Arc::new(SmartRouter::new(providers, self.http_client.clone()));
+    /// Models from the catalog, refreshing it first if the cache expired
+    pub async fn list_models(&self) -> Vec<CatalogModel> {
+        if self.model_catalog.is_stale().await {
+            self.refresh_model_catalog().await;
+        }
+        self.model_catalog.models().await
+    }
+
+    /// Rediscover models from every configured provider and account
+    pub async fn refresh_model_catalog(&self) {
+        let mut sources: Vec<CatalogSource> = self.providers.read().await
+            .iter()
+            .filter(|p| p.enabled)
+            .map(|p| CatalogSource {
+                key: format!("provider:{}", p.id),
+                provider_id: p.id.clone(),
+                account_id: None,
+                adapter: crate::providers::create_adapter(p.clone(), self.http_client.clone()),
+                overrides: p.models.clone(),
+            })
+            .collect();
+
+        if let Some(ref pool) = self.db_pool {
+            let repo = crate::db::ProviderAccountRepository::new(pool.clone());
+            match repo.list_all().await {
+                Ok(rows) => {
+                    for row in rows.into_iter().filter(|r| r.enabled && r.serves_chat()) {
+                        let Some(provider) = row.to_provider("") else {
+                            continue;
+                        };
+                        sources.push(CatalogSource {
+                            key: format!("account:{}", row.id),
+                            provider_id: row.provider_id.clone(),
+                            account_id: Some(row.id.clone()),
+                            overrides: provider.models.clone(),
+                            adapter: crate::providers::create_adapter(provider, self.http_client.clone()),
+                        });
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to load provider accounts for model catalog: {}", e),
+            }
+        }
+
+        self.model_catalog.refresh(sources).await;
     }
 
     pub fn uptime_seconds(&self) -> u64 {
@@ -128,6 +553,88 @@ impl AppState {
     pub fn is_database_connected(&self) -> bool {
         self.db_pool.is_some()
     }
+
+    /// The user of the session whose token is sent as `Authorization: Bearer`
+    pub async fn session_user(&self, headers: &axum::http::HeaderMap) -> crate::Result<User> {
+        let token = headers
+            .get(axum::http::header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| crate::SynapseError::Unauthorized("Missing session token".to_string()))?;
+        self.auth_service.validate_session(token).await
+            .ok_or_else(|| crate::SynapseError::Unauthorized("Invalid or expired session".to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl Embedder for AppState {
+    async fn embed(&self, request: &EmbeddingRequest) -> crate::Result<EmbeddingResponse> {
+        AppState::embed(self, request).await
+    }
+}
+
+#[async_trait::async_trait]
+impl IngestServices for AppState {
+    async fn vector_store(&self, account_id: Option<&str>) -> crate::Result<Arc<dyn VectorStore>> {
+        AppState::vector_store(self, account_id).await
+    }
+}
+
+#[async_trait::async_trait]
+impl Reranker for AppState {
+    /// Rerank with a database account for the provider, falling back to a
+    /// configured provider of the same id
+    async fn rerank(&self, options: &RerankOptions, query: &str, documents: &[String]) -> crate::Result<Vec<f32>> {
+        if !rerank::RERANK_PROVIDERS.contains(&options.provider.as_str()) {
+            return Err(crate::SynapseError::Validation(format!(
+                "Reranking is supported with {}",
+                rerank::RERANK_PROVIDERS.join(", ")
+            )));
+        }
+        let provider = match self.account_provider(&options.provider, &options.model).await {
+            Some(provider) => provider,
+            None => self.providers.read().await
+                .iter()
+                .find(|p| p.id == options.provider && p.enabled)
+                .cloned()
+                .ok_or_else(|| crate::SynapseError::NotFound(format!("Provider {}", options.provider)))?,
+        };
+        rerank::rerank(&self.http_client, &provider, &options.model, query, documents).await
+    }
+}
+
+#[async_trait::async_trait]
+impl WorkflowServices for AppState {
+    async fn chat(&self, request: &ChatRequest) -> crate::Result<ChatResponse> {
+        let (response, _) = super::handlers::complete_chat(self, request).await?;
+        if let Err(e) = self.cost_manager.record_cost(
+            &response.provider,
+            &response.model,
+            &response.usage,
+            response.cost,
+            request.user_id.as_deref().unwrap_or("anonymous"),
+            &response.id,
+        ).await {
+            tracing::warn!(error = %e, "Failed to record workflow cost");
+        }
+        Ok(response)
+    }
+
+    async fn search_knowledge(
+        &self,
+        collection_id: &str,
+        query: &str,
+        options: &SearchOptions,
+        user_id: Option<String>,
+    ) -> crate::Result<Vec<search::SearchResult>> {
+        Ok(AppState::search_knowledge(self, collection_id, query, options, user_id).await?.results)
+    }
+
+    fn http_client(&self) -> reqwest::Client {
+        self.http_client.clone()
+    }
 }
 
 /// Load provider accounts from database
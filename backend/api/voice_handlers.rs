@@ -0,0 +1,603 @@
+//! Speech API handlers: OpenAI-compatible synthesis and transcription plus
+//! the voice and model catalogs, providers and accounts
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{multipart::{MultipartError, MultipartRejection}, rejection::JsonRejection, Multipart, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use super::openai::{self, OpenAIError};
+use super::state::AppState;
+use crate::db::{NewVoiceUsage, SttModelRow, VoiceAccountRow, VoiceProviderRow, VoiceRepository};
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::account_manager::QuotaPeriod;
+use crate::voice::stt::{self, AudioFile, SttAdapter, SttModel, Task, TranscriptionRequest};
+use crate::voice::tts::{self, SpeechRequest, TtsAdapter, Voice};
+use crate::voice::{self, VoiceAccount, VoiceEndpoint, VoiceKind, VoiceProvider, VoiceQuota, VoiceQuotas};
+use crate::TokenUsage;
+
+fn repository(state: &AppState) -> Result<VoiceRepository> {
+    let pool = state.db_pool.as_ref()
+        .ok_or_else(|| SynapseError::DatabaseError("Database not connected".to_string()))?;
+    Ok(VoiceRepository::new(pool.clone()))
+}
+
+/// Take `amount` from an account of `provider`, telling apart a provider
+/// without accounts from one whose accounts are out of quota
+async fn reserve_account(repo: &VoiceRepository, kind: VoiceKind, provider: &VoiceProviderRow, amount: u64) -> Result<VoiceAccountRow> {
+    if let Some(account) = repo.reserve_account(kind, &provider.id, amount).await? {
+        return Ok(account);
+    }
+    let accounts = repo.list_accounts(kind, Some(&provider.id)).await?;
+    if !accounts.iter().any(|account| account.enabled) {
+        return Err(SynapseError::Config(format!("{} has no enabled accounts", provider.name)));
+    }
+    Err(SynapseError::Provider(ProviderError::QuotaExceeded(format!(
+        "no {} account has {} {} left",
+        provider.name,
+        amount,
+        match kind {
+            VoiceKind::Tts => "characters",
+            VoiceKind::Stt => "seconds",
+        },
+    ))))
+}
+
+/// Give a reservation back after the provider call failed
+async fn refund(repo: &VoiceRepository, kind: VoiceKind, account: &VoiceAccountRow, amount: u64) {
+    let delta = -i64::try_from(amount).unwrap_or(i64::MAX);
+    if let Err(e) = repo.adjust_quota(kind, &account.id, delta).await {
+        tracing::warn!(account = %account.id, error = %e, "Failed to refund voice quota");
+    }
+}
+
+// ============================================================================
+// Speech
+// ============================================================================
+
+/// The provider and provider voice id for a speech request
+async fn resolve_voice(repo: &VoiceRepository, request: &SpeechRequest) -> Result<(VoiceProviderRow, String, Option<String>)> {
+    let provider = match request.provider {
+        Some(ref provider) => Some(
+            repo.find_provider(VoiceKind::Tts, provider).await?
+                .ok_or_else(|| SynapseError::NotFound(format!("TTS provider {}", provider)))?,
+        ),
+        None => None,
+    };
+    let voice = repo.find_voice(&request.voice, provider.as_ref().map(|p| p.id.as_str())).await?;
+    let provider = match (provider, &voice) {
+        (Some(provider), _) => provider,
+        (None, Some(voice)) => repo.get_provider(VoiceKind::Tts, &voice.provider_id).await?
+            .ok_or_else(|| SynapseError::NotFound(format!("TTS provider {}", voice.provider_id)))?,
+        (None, None) => repo.default_provider(VoiceKind::Tts).await?
+            .ok_or_else(|| SynapseError::NotFound("No TTS provider is enabled".to_string()))?,
+    };
+    // Voices missing from the catalog are passed through as provider ids
+    Ok(match voice {
+        Some(voice) => (provider, voice.voice_id, Some(voice.language)),
+        None => (provider, request.voice.clone(), None),
+    })
+}
+
+/// Accounting for a speech request, settled once its audio has been
+/// streamed: usage and cost are recorded once the provider produced audio,
+/// the reservation is given back when the stream broke off before any
+struct SpeechUsage {
+    state: Arc<AppState>,
+    repo: VoiceRepository,
+    account: VoiceAccountRow,
+    provider_id: String,
+    voice_id: String,
+    model: String,
+    language: Option<String>,
+    user_id: String,
+    characters: u64,
+    cost: f64,
+    request_id: String,
+}
+
+impl SpeechUsage {
+    async fn record(self) {
+        if let Err(e) = self.repo.record_usage(&NewVoiceUsage {
+            kind: VoiceKind::Tts,
+            user_id: &self.user_id,
+            provider_id: &self.provider_id,
+            model_or_voice_id: Some(&self.voice_id),
+            amount: self.characters,
+            cost: self.cost,
+            request_id: &self.request_id,
+            language: self.language.as_deref(),
+        }).await {
+            tracing::warn!(request = %self.request_id, error = %e, "Failed to record speech usage");
+        }
+        if let Err(e) = self.state.cost_manager.record_cost(
+            &self.provider_id,
+            &self.model,
+            &TokenUsage::default(),
+            self.cost,
+            &self.user_id,
+            &self.request_id,
+        ).await {
+            tracing::warn!(request = %self.request_id, error = %e, "Failed to record speech cost");
+        }
+        if let Err(e) = self.repo.increment_voice_usage(&self.provider_id, &self.voice_id).await {
+            tracing::warn!(voice = %self.voice_id, error = %e, "Failed to count voice usage");
+        }
+    }
+
+    async fn refund(self) {
+        refund(&self.repo, VoiceKind::Tts, &self.account, self.characters).await;
+    }
+}
+
+/// POST /v1/audio/speech
+///
+/// Speaks OpenAI's speech API and streams the audio back as the provider
+/// produces it
+pub async fn create_speech(
+    State(state): State<Arc<AppState>>,
+    body: std::result::Result<Json<SpeechRequest>, JsonRejection>,
+) -> std::result::Result<Response, OpenAIError> {
+    let Json(request) = body?;
+    request.validate()?;
+    let repo = repository(&state)?;
+    let user_id = request.user.clone().unwrap_or_else(|| "anonymous".to_string());
+
+    let (provider, voice_id, language) = resolve_voice(&repo, &request).await?;
+    let adapter = TtsAdapter::for_type(&provider.provider_type).ok_or_else(|| {
+        SynapseError::Provider(ProviderError::Unsupported(format!("text-to-speech through {}", provider.provider_type)))
+    })?;
+    let characters = request.characters();
+    let cost = voice::cost(characters, provider.price, tts::PRICE_UNIT);
+    state.cost_manager.can_request(&user_id, cost).await?;
+
+    let account = reserve_account(&repo, VoiceKind::Tts, &provider, characters).await?;
+    let start = std::time::Instant::now();
+    let endpoint = VoiceEndpoint::new(&provider, &account);
+    let response = match adapter.synthesize(&state.http_client, &endpoint, &voice_id, &request).await {
+        Ok(response) => response,
+        Err(e) => {
+            tracing::warn!(provider = %provider.id, account = %account.id, error = %e, "Speech synthesis failed");
+            refund(&repo, VoiceKind::Tts, &account, characters).await;
+            return Err(e.into());
+        }
+    };
+
+    let headers = openai::barq_headers(&provider.id, Some(cost), Some(start.elapsed().as_millis() as u64));
+    let content_type = [(header::CONTENT_TYPE, request.response_format.content_type())];
+    let usage = SpeechUsage {
+        state: state.clone(),
+        repo,
+        account,
+        provider_id: provider.id,
+        model: request.model.as_deref().or(adapter.default_model()).unwrap_or(&voice_id).to_string(),
+        voice_id,
+        language,
+        user_id,
+        characters,
+        cost,
+        request_id: Uuid::new_v4().to_string(),
+    };
+
+    // The provider bills the whole text once it starts producing audio, even
+    // if the client goes away or the stream breaks off later, so the audio is
+    // drained to the end and only a stream that failed before its first
+    // chunk is refunded
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(16);
+    tokio::spawn(async move {
+        let mut audio = response.bytes_stream();
+        let mut produced = false;
+        while let Some(chunk) = audio.next().await {
+            match chunk {
+                Ok(bytes) => {
+                    produced = true;
+                    let _ = tx.send(Ok(bytes)).await;
+                }
+                Err(e) => {
+                    tracing::warn!(provider = %usage.provider_id, account = %usage.account.id, error = %e, "Speech stream failed");
+                    let _ = tx.send(Err(std::io::Error::other(e))).await;
+                    break;
+                }
+            }
+        }
+        if produced {
+            usage.record().await;
+        } else {
+            usage.refund().await;
+        }
+    });
+    Ok((content_type, headers, Body::from_stream(ReceiverStream::new(rx))).into_response())
+}
+
+// ============================================================================
+// Transcription
+// ============================================================================
+
+async fn read_transcription(mut multipart: Multipart) -> Result<TranscriptionRequest> {
+    let invalid = |e: MultipartError| SynapseError::Validation(e.body_text());
+    let mut request = TranscriptionRequest::default();
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        let name = field.name().unwrap_or_default().to_string();
+        if name == "file" {
+            request.file = AudioFile {
+                name: field.file_name().unwrap_or("audio").to_string(),
+                content_type: field.content_type().map(str::to_string),
+                bytes: field.bytes().await.map_err(invalid)?.to_vec(),
+            };
+        } else {
+            let value = field.text().await.map_err(invalid)?;
+            request.set_field(&name, value)?;
+        }
+    }
+    Ok(request)
+}
+
+/// The provider and catalog model for a transcription request
+async fn resolve_model(repo: &VoiceRepository, request: &TranscriptionRequest) -> Result<(VoiceProviderRow, Option<SttModelRow>)> {
+    let provider = match request.provider {
+        Some(ref provider) => Some(
+            repo.find_provider(VoiceKind::Stt, provider).await?
+                .ok_or_else(|| SynapseError::NotFound(format!("STT provider {}", provider)))?,
+        ),
+        None => None,
+    };
+    let model = match request.model {
+        Some(ref model) => repo.find_model(model, provider.as_ref().map(|p| p.id.as_str())).await?,
+        None => None,
+    };
+    let provider = match (provider, &model) {
+        (Some(provider), _) => provider,
+        (None, Some(model)) => repo.get_provider(VoiceKind::Stt, &model.provider_id).await?
+            .ok_or_else(|| SynapseError::NotFound(format!("STT provider {}", model.provider_id)))?,
+        (None, None) => repo.default_provider(VoiceKind::Stt).await?
+            .ok_or_else(|| SynapseError::NotFound("No STT provider is enabled".to_string()))?,
+    };
+    Ok((provider, model))
+}
+
+/// POST /v1/audio/transcriptions
+pub async fn create_transcription(
+    State(state): State<Arc<AppState>>,
+    multipart: std::result::Result<Multipart, MultipartRejection>,
+) -> std::result::Result<Response, OpenAIError> {
+    transcribe(&state, multipart?, Task::Transcribe).await
+}
+
+/// POST /v1/audio/translations
+pub async fn create_translation(
+    State(state): State<Arc<AppState>>,
+    multipart: std::result::Result<Multipart, MultipartRejection>,
+) -> std::result::Result<Response, OpenAIError> {
+    transcribe(&state, multipart?, Task::Translate).await
+}
+
+/// Speaks OpenAI's multipart transcription API. Quota is reserved for the
+/// length estimated from the upload and corrected to the length the
+/// provider reports.
+async fn transcribe(state: &AppState, multipart: Multipart, task: Task) -> std::result::Result<Response, OpenAIError> {
+    let request = read_transcription(multipart).await?;
+    request.validate()?;
+    let repo = repository(state)?;
+    let user_id = request.user.clone().unwrap_or_else(|| "anonymous".to_string());
+
+    let (provider, model) = resolve_model(&repo, &request).await?;
+    if let Some(ref model) = model {
+        stt::check_model(model, &request)?;
+    }
+    let adapter = SttAdapter::for_type(&provider.provider_type).ok_or_else(|| {
+        SynapseError::Provider(ProviderError::Unsupported(format!("speech-to-text through {}", provider.provider_type)))
+    })?;
+    // Models outside the catalog are passed through as provider ids
+    let model_id = model.map(|model| model.model_id).or_else(|| request.model.clone());
+    let estimate = stt::estimate_seconds(&request.file.bytes);
+    state.cost_manager.can_request(&user_id, voice::cost(estimate, provider.price, stt::PRICE_UNIT)).await?;
+
+    let account = reserve_account(&repo, VoiceKind::Stt, &provider, estimate).await?;
+    let start = std::time::Instant::now();
+    let endpoint = VoiceEndpoint::new(&provider, &account);
+    let transcript = match adapter.transcribe(&state.http_client, &endpoint, task, model_id.as_deref(), &request).await {
+        Ok(transcript) => transcript,
+        Err(e) => {
+            tracing::warn!(provider = %provider.id, account = %account.id, error = %e, "Transcription failed");
+            refund(&repo, VoiceKind::Stt, &account, estimate).await;
+            return Err(e.into());
+        }
+    };
+
+    let seconds = transcript.duration.map(|duration| (duration.ceil() as u64).max(1)).unwrap_or(estimate);
+    if seconds != estimate {
+        if let Err(e) = repo.adjust_quota(VoiceKind::Stt, &account.id, seconds as i64 - estimate as i64).await {
+            tracing::warn!(account = %account.id, error = %e, "Failed to correct voice quota");
+        }
+    }
+    let cost = voice::cost(seconds, provider.price, stt::PRICE_UNIT);
+    let request_id = Uuid::new_v4().to_string();
+    // voice_usage.language holds at most 10 characters
+    let language: Option<String> = transcript.language.as_ref().or(request.language.as_ref()).map(|l| l.chars().take(10).collect());
+    repo.record_usage(&NewVoiceUsage {
+        kind: VoiceKind::Stt,
+        user_id: &user_id,
+        provider_id: &provider.id,
+        model_or_voice_id: model_id.as_deref(),
+        amount: seconds,
+        cost,
+        request_id: &request_id,
+        language: language.as_deref(),
+    }).await.map_err(SynapseError::from)?;
+    let model = model_id.as_deref().unwrap_or(&provider.id);
+    state.cost_manager.record_cost(&provider.id, model, &TokenUsage::default(), cost, &user_id, &request_id).await?;
+
+    let headers = openai::barq_headers(&provider.id, Some(cost), Some(start.elapsed().as_millis() as u64));
+    let content_type = [(header::CONTENT_TYPE, request.response_format.content_type())];
+    Ok((content_type, headers, transcript.render(request.response_format, task)).into_response())
+}
+
+// ============================================================================
+// Voices
+// ============================================================================
+
+#[derive(Deserialize)]
+pub struct VoiceQuery {
+    pub provider: Option<String>,
+    /// Language or locale prefix, e.g. `en` or `en-GB`
+    pub language: Option<String>,
+}
+
+/// GET /v1/audio/voices
+pub async fn list_voices(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<VoiceQuery>,
+) -> Result<Json<Vec<Voice>>> {
+    let repo = repository(&state)?;
+    let voices = repo.list_voices(query.provider.as_deref(), query.language.as_deref()).await?;
+    Ok(Json(voices.into_iter().map(Voice::from).collect()))
+}
+
+#[derive(Deserialize, Default)]
+pub struct SyncVoicesRequest {
+    /// Provider id; every supported provider when absent
+    pub provider: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceSync {
+    pub provider_id: String,
+    /// Voices the provider listed
+    pub synced: usize,
+    /// Catalog voices it no longer lists
+    pub disabled: u64,
+    pub error: Option<String>,
+}
+
+/// POST /v1/audio/voices/sync
+///
+/// Refresh the catalog from each provider's voice list, using its first
+/// enabled account
+pub async fn sync_voices(
+    State(state): State<Arc<AppState>>,
+    body: Option<Json<SyncVoicesRequest>>,
+) -> Result<Json<Vec<VoiceSync>>> {
+    let repo = repository(&state)?;
+    let Json(req) = body.unwrap_or_default();
+    let providers = match req.provider {
+        Some(ref id) => vec![repo.get_provider(VoiceKind::Tts, id).await?
+            .ok_or_else(|| SynapseError::NotFound(format!("TTS provider {}", id)))?],
+        None => repo.list_providers(VoiceKind::Tts).await?
+            .into_iter()
+            .filter(|provider| provider.enabled && TtsAdapter::for_type(&provider.provider_type).is_some())
+            .collect(),
+    };
+
+    let mut results = Vec::new();
+    for provider in providers {
+        let result = sync_provider(&state, &repo, &provider).await;
+        if let Err(ref e) = result {
+            tracing::warn!(provider = %provider.id, error = %e, "Voice sync failed");
+        }
+        results.push(match result {
+            Ok((synced, disabled)) => VoiceSync { provider_id: provider.id, synced, disabled, error: None },
+            Err(e) => VoiceSync { provider_id: provider.id, synced: 0, disabled: 0, error: Some(e.to_string()) },
+        });
+    }
+    Ok(Json(results))
+}
+
+async fn sync_provider(state: &AppState, repo: &VoiceRepository, provider: &VoiceProviderRow) -> Result<(usize, u64)> {
+    let adapter = TtsAdapter::for_type(&provider.provider_type).ok_or_else(|| {
+        SynapseError::Provider(ProviderError::Unsupported(format!("voice sync for {}", provider.provider_type)))
+    })?;
+    let account = repo.list_accounts(VoiceKind::Tts, Some(&provider.id)).await?
+        .into_iter()
+        .find(|account| account.enabled)
+        .ok_or_else(|| SynapseError::Config(format!("{} has no enabled accounts", provider.name)))?;
+    let voices = adapter.voices(&state.http_client, &VoiceEndpoint::new(provider, &account)).await?;
+    // An empty list is more likely a provider fault than a catalog to wipe
+    if voices.is_empty() {
+        return Err(SynapseError::Provider(ProviderError::InvalidResponse("provider listed no voices".to_string())));
+    }
+    let disabled = repo.sync_voices(&provider.id, &voices).await?;
+    Ok((voices.len(), disabled))
+}
+
+// ============================================================================
+// Providers and accounts
+// ============================================================================
+
+async fn list_providers(state: &AppState, kind: VoiceKind, supported: &[&str]) -> Result<Json<Vec<VoiceProvider>>> {
+    let repo = repository(state)?;
+    let providers = repo.list_providers(kind).await?
+        .into_iter()
+        .map(|row| VoiceProvider {
+            supported: supported.contains(&row.provider_type.as_str()),
+            id: row.id,
+            name: row.name,
+            provider_type: row.provider_type,
+            description: row.description,
+            supports_streaming: row.supports_streaming,
+            supports_diarization: row.supports_diarization,
+            price: row.price,
+            enabled: row.enabled,
+            is_default: row.is_default,
+        })
+        .collect();
+    Ok(Json(providers))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountQuery {
+    pub provider_id: Option<String>,
+}
+
+async fn list_accounts(state: &AppState, kind: VoiceKind, query: AccountQuery) -> Result<Json<Vec<VoiceAccount>>> {
+    let repo = repository(state)?;
+    let accounts = repo.list_accounts(kind, query.provider_id.as_deref()).await?;
+    Ok(Json(accounts.into_iter().map(VoiceAccount::from).collect()))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateVoiceAccountRequest {
+    pub provider_id: String,
+    pub name: String,
+    /// Optional for a local whisper.cpp server
+    #[serde(default)]
+    pub api_key: String,
+    /// Replaces the provider's base URL
+    pub endpoint: Option<String>,
+    /// Fills `{region}` in the provider's base URL, e.g. for Azure
+    pub region: Option<String>,
+    /// Limits in characters (TTS) or seconds of audio (STT)
+    #[serde(default)]
+    pub quotas: Vec<VoiceQuotaRequest>,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[derive(Deserialize)]
+pub struct VoiceQuotaRequest {
+    pub period: QuotaPeriod,
+    pub limit: u64,
+}
+
+async fn create_account(state: &AppState, kind: VoiceKind, req: CreateVoiceAccountRequest) -> Result<(StatusCode, Json<VoiceAccount>)> {
+    let repo = repository(state)?;
+    if req.name.trim().is_empty() {
+        return Err(SynapseError::Validation("name is required".to_string()));
+    }
+    let provider = repo.get_provider(kind, &req.provider_id).await?
+        .ok_or_else(|| SynapseError::NotFound(format!("{} provider {}", kind.as_str().to_uppercase(), req.provider_id)))?;
+    if req.api_key.trim().is_empty() && kind.needs_api_key(&provider.provider_type) {
+        return Err(SynapseError::Validation("apiKey is required".to_string()));
+    }
+    let quotas: VoiceQuotas = req.quotas
+        .iter()
+        .map(|quota| (quota.period, VoiceQuota { limit: quota.limit, used: 0, reset_at: None }))
+        .collect();
+
+    let row = repo.create_account(
+        kind,
+        &Uuid::new_v4().to_string(),
+        &req.provider_id,
+        req.name.trim(),
+        &req.api_key,
+        req.endpoint.as_deref(),
+        req.region.as_deref(),
+        &serde_json::to_value(&quotas).unwrap_or_default(),
+        req.is_default,
+        req.priority,
+    ).await?;
+    Ok((StatusCode::CREATED, Json(VoiceAccount::from(row))))
+}
+
+async fn delete_account(state: &AppState, kind: VoiceKind, account_id: &str) -> Result<StatusCode> {
+    let repo = repository(state)?;
+    if !repo.delete_account(kind, account_id).await? {
+        return Err(SynapseError::NotFound(format!("Account {}", account_id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /v1/audio/tts/providers
+pub async fn list_tts_providers(State(state): State<Arc<AppState>>) -> Result<Json<Vec<VoiceProvider>>> {
+    list_providers(&state, VoiceKind::Tts, tts::TTS_PROVIDERS).await
+}
+
+/// GET /v1/audio/tts/accounts
+pub async fn list_tts_accounts(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<Vec<VoiceAccount>>> {
+    list_accounts(&state, VoiceKind::Tts, query).await
+}
+
+/// POST /v1/audio/tts/accounts
+pub async fn create_tts_account(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateVoiceAccountRequest>,
+) -> Result<(StatusCode, Json<VoiceAccount>)> {
+    create_account(&state, VoiceKind::Tts, req).await
+}
+
+/// DELETE /v1/audio/tts/accounts/:account_id
+pub async fn delete_tts_account(
+    State(state): State<Arc<AppState>>,
+    Path(account_id): Path<String>,
+) -> Result<StatusCode> {
+    delete_account(&state, VoiceKind::Tts, &account_id).await
+}
+
+/// GET /v1/audio/stt/providers
+pub async fn list_stt_providers(State(state): State<Arc<AppState>>) -> Result<Json<Vec<VoiceProvider>>> {
+    list_providers(&state, VoiceKind::Stt, stt::STT_PROVIDERS).await
+}
+
+#[derive(Deserialize)]
+pub struct ModelQuery {
+    pub provider: Option<String>,
+}
+
+/// GET /v1/audio/stt/models
+pub async fn list_stt_models(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ModelQuery>,
+) -> Result<Json<Vec<SttModel>>> {
+    let repo = repository(&state)?;
+    let models = repo.list_models(query.provider.as_deref()).await?;
+    Ok(Json(models.into_iter().map(SttModel::from).collect()))
+}
+
+/// GET /v1/audio/stt/accounts
+pub async fn list_stt_accounts(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<AccountQuery>,
+) -> Result<Json<Vec<VoiceAccount>>> {
+    list_accounts(&state, VoiceKind::Stt, query).await
+}
+
+/// POST /v1/audio/stt/accounts
+pub async fn create_stt_account(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateVoiceAccountRequest>,
+) -> Result<(StatusCode, Json<VoiceAccount>)> {
+    create_account(&state, VoiceKind::Stt, req).await
+}
+
+/// DELETE /v1/audio/stt/accounts/:account_id
+pub async fn delete_stt_account(
+    State(state): State<Arc<AppState>>,
+    Path(account_id): Path<String>,
+) -> Result<StatusCode> {
+    delete_account(&state, VoiceKind::Stt, &account_id).await
+}
@@ -0,0 +1,402 @@
+//! Workflow API handlers
+
+use axum::{extract::{Path, Query, State}, http::{HeaderMap, StatusCode}, Json};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::api::state::AppState;
+use crate::db::{UserRepository, WorkflowExecutionRow, WorkflowRepository, WorkflowRow};
+use crate::error::{Result, SynapseError};
+use crate::governance::grants;
+use crate::workflows::{
+    execution_definition, Edge, Execution, Node, NodeExecution, NodeKind, Workflow, WorkflowDefinition, WorkflowVersion,
+    WORKFLOW_STATUSES,
+};
+
+/// Executions listed unless `limit` is given
+const DEFAULT_EXECUTION_LIMIT: i64 = 50;
+
+fn repository(state: &AppState) -> Result<WorkflowRepository> {
+    let pool = state.db_pool.as_ref()
+        .ok_or_else(|| SynapseError::DatabaseError("Database not connected".to_string()))?;
+    Ok(WorkflowRepository::new(pool.clone()))
+}
+
+async fn find_workflow(repo: &WorkflowRepository, workflow_id: &str) -> Result<WorkflowRow> {
+    repo.find_workflow(workflow_id).await?
+        .ok_or_else(|| SynapseError::NotFound(format!("Workflow {}", workflow_id)))
+}
+
+fn validate_status(status: &str) -> Result<()> {
+    if !WORKFLOW_STATUSES.contains(&status) {
+        return Err(SynapseError::Validation(format!("status must be one of {}", WORKFLOW_STATUSES.join(", "))));
+    }
+    Ok(())
+}
+
+/// The definition as stored: nodes and edges serialized back from their
+/// parsed form, so defaults are explicit in every version
+fn definition_json(nodes: Vec<Node>, edges: Vec<Edge>) -> Result<(JsonValue, JsonValue)> {
+    let definition = WorkflowDefinition { nodes, edges };
+    definition.validate()?;
+    Ok((
+        serde_json::to_value(&definition.nodes).unwrap_or_default(),
+        serde_json::to_value(&definition.edges).unwrap_or_default(),
+    ))
+}
+
+/// List all workflows
+pub async fn list_workflows(State(state): State<Arc<AppState>>) -> Result<Json<Vec<Workflow>>> {
+    let repo = repository(&state)?;
+    let workflows = repo.list_workflows().await?.into_iter().map(Workflow::from).collect();
+    Ok(Json(workflows))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWorkflowRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+    pub status: Option<String>,
+    pub created_by: Option<String>,
+}
+
+/// Create a workflow
+pub async fn create_workflow(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateWorkflowRequest>,
+) -> Result<(StatusCode, Json<Workflow>)> {
+    let repo = repository(&state)?;
+
+    if req.name.trim().is_empty() {
+        return Err(SynapseError::Validation("name is required".to_string()));
+    }
+    let status = req.status.as_deref().unwrap_or("draft");
+    validate_status(status)?;
+    let (nodes, edges) = definition_json(req.nodes, req.edges)?;
+
+    let id = Uuid::new_v4().to_string();
+    let row = repo.create_workflow(
+        &id,
+        req.name.trim(),
+        req.description.as_deref(),
+        &nodes,
+        &edges,
+        status,
+        req.created_by.as_deref(),
+    ).await?;
+
+    tracing::info!(workflow = %id, "Created workflow {}", row.name);
+    Ok((StatusCode::CREATED, Json(Workflow::from(row))))
+}
+
+/// Get a workflow with its latest definition
+pub async fn get_workflow(
+    State(state): State<Arc<AppState>>,
+    Path(workflow_id): Path<String>,
+) -> Result<Json<Workflow>> {
+    let repo = repository(&state)?;
+    Ok(Json(Workflow::from(find_workflow(&repo, &workflow_id).await?)))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWorkflowRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    /// New definition; saved as the next version when it differs from the
+    /// current one
+    pub nodes: Option<Vec<Node>>,
+    pub edges: Option<Vec<Edge>>,
+    pub updated_by: Option<String>,
+}
+
+/// Update a workflow
+pub async fn update_workflow(
+    State(state): State<Arc<AppState>>,
+    Path(workflow_id): Path<String>,
+    Json(req): Json<UpdateWorkflowRequest>,
+) -> Result<Json<Workflow>> {
+    let repo = repository(&state)?;
+    let current = find_workflow(&repo, &workflow_id).await?;
+
+    let name = req.name.as_deref().map(str::trim).unwrap_or(&current.name);
+    if name.is_empty() {
+        return Err(SynapseError::Validation("name must not be empty".to_string()));
+    }
+    let status = req.status.as_deref().unwrap_or(&current.status);
+    validate_status(status)?;
+
+    let definition = match (req.nodes, req.edges) {
+        (None, None) => None,
+        (nodes, edges) => {
+            let current_definition = WorkflowDefinition::from_json(current.nodes.clone(), current.edges.clone())?;
+            let (nodes, edges) = definition_json(
+                nodes.unwrap_or(current_definition.nodes),
+                edges.unwrap_or(current_definition.edges),
+            )?;
+            (nodes != current.nodes || edges != current.edges).then_some((nodes, edges))
+        }
+    };
+
+    let row = repo.update_workflow(
+        &workflow_id,
+        name,
+        req.description.as_deref().or(current.description.as_deref()),
+        status,
+        definition.as_ref().map(|(nodes, edges)| (nodes, edges)),
+        req.updated_by.as_deref(),
+    ).await?
+    .ok_or_else(|| SynapseError::NotFound(format!("Workflow {}", workflow_id)))?;
+    Ok(Json(Workflow::from(row)))
+}
+
+/// Delete a workflow with its versions and executions
+pub async fn delete_workflow(
+    State(state): State<Arc<AppState>>,
+    Path(workflow_id): Path<String>,
+) -> Result<StatusCode> {
+    let repo = repository(&state)?;
+    if !repo.delete_workflow(&workflow_id).await? {
+        return Err(SynapseError::NotFound(format!("Workflow {}", workflow_id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// List a workflow's saved definitions, newest first
+pub async fn list_versions(
+    State(state): State<Arc<AppState>>,
+    Path(workflow_id): Path<String>,
+) -> Result<Json<Vec<WorkflowVersion>>> {
+    let repo = repository(&state)?;
+    find_workflow(&repo, &workflow_id).await?;
+    let versions = repo.list_versions(&workflow_id).await?.into_iter().map(WorkflowVersion::from).collect();
+    Ok(Json(versions))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunWorkflowRequest {
+    /// Available to nodes as `input.*`
+    #[serde(default)]
+    pub variables: JsonValue,
+    /// Version to run; the latest when absent
+    pub version: Option<i32>,
+    pub user_id: Option<String>,
+}
+
+/// Start an execution
+///
+/// Responds once the execution is queued for the workflow worker; its
+/// progress is read from [`get_execution`].
+pub async fn run_workflow(
+    State(state): State<Arc<AppState>>,
+    Path(workflow_id): Path<String>,
+    Json(req): Json<RunWorkflowRequest>,
+) -> Result<(StatusCode, Json<Execution>)> {
+    let repo = repository(&state)?;
+    let workflow = find_workflow(&repo, &workflow_id).await?;
+    if workflow.status == "archived" {
+        return Err(SynapseError::Validation(format!("Workflow {} is archived", workflow_id)));
+    }
+    let version = req.version.unwrap_or(workflow.version);
+    if repo.find_version(&workflow_id, version).await?.is_none() {
+        return Err(SynapseError::NotFound(format!("Workflow {} version {}", workflow_id, version)));
+    }
+    let variables = match req.variables {
+        JsonValue::Null => serde_json::json!({}),
+        variables @ JsonValue::Object(_) => variables,
+        _ => return Err(SynapseError::Validation("variables must be an object".to_string())),
+    };
+
+    let id = Uuid::new_v4().to_string();
+    let row = repo.create_execution(&id, &workflow_id, version, &variables, req.user_id.as_deref()).await?;
+    state.audit_service
+        .log_workflow_execution(req.user_id.as_deref().unwrap_or("anonymous"), &workflow_id, &id)
+        .await;
+
+    if let Some(ref runner) = state.workflow_runner {
+        runner.wake();
+    }
+    tracing::info!(workflow = %workflow_id, execution = %id, version, "Started workflow execution");
+    Ok((StatusCode::ACCEPTED, Json(Execution::from(row))))
+}
+
+#[derive(Deserialize)]
+pub struct ListExecutionsQuery {
+    pub limit: Option<i64>,
+}
+
+/// List a workflow's executions, newest first
+pub async fn list_executions(
+    State(state): State<Arc<AppState>>,
+    Path(workflow_id): Path<String>,
+    Query(query): Query<ListExecutionsQuery>,
+) -> Result<Json<Vec<Execution>>> {
+    let repo = repository(&state)?;
+    find_workflow(&repo, &workflow_id).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_EXECUTION_LIMIT).clamp(1, 500);
+    let executions = repo.list_executions(&workflow_id, limit).await?.into_iter().map(Execution::from).collect();
+    Ok(Json(executions))
+}
+
+async fn find_execution(repo: &WorkflowRepository, workflow_id: &str, execution_id: &str) -> Result<WorkflowExecutionRow> {
+    repo.find_execution(execution_id).await?
+        .filter(|row| row.workflow_id == workflow_id)
+        .ok_or_else(|| SynapseError::NotFound(format!("Execution {}", execution_id)))
+}
+
+/// Get an execution with the state of each node
+pub async fn get_execution(
+    State(state): State<Arc<AppState>>,
+    Path((workflow_id, execution_id)): Path<(String, String)>,
+) -> Result<Json<Execution>> {
+    let repo = repository(&state)?;
+    let row = find_execution(&repo, &workflow_id, &execution_id).await?;
+    let mut execution = Execution::from(row);
+    execution.nodes = repo.list_node_executions(&execution_id).await?.into_iter().map(Into::into).collect();
+    Ok(Json(execution))
+}
+
+/// Change an execution's status, if it is in one of `from`
+async fn transition(
+    repo: &WorkflowRepository,
+    workflow_id: &str,
+    execution_id: &str,
+    from: &[&str],
+    to: &str,
+) -> Result<Execution> {
+    let current = find_execution(repo, workflow_id, execution_id).await?;
+    let row = repo.transition_execution(execution_id, from, to).await?.ok_or_else(|| {
+        SynapseError::Validation(format!("Execution {} is {}; only {} executions can be {}", execution_id, current.status, from.join(" or "), to))
+    })?;
+    tracing::info!(workflow = %workflow_id, execution = %execution_id, "Workflow execution {} -> {}", current.status, to);
+    Ok(Execution::from(row))
+}
+
+/// Cancel an execution; nodes still running or waiting for approval are
+/// abandoned
+pub async fn cancel_execution(
+    State(state): State<Arc<AppState>>,
+    Path((workflow_id, execution_id)): Path<(String, String)>,
+) -> Result<Json<Execution>> {
+    let repo = repository(&state)?;
+    let from = ["pending", "running", "waiting", "paused"];
+    let execution = transition(&repo, &workflow_id, &execution_id, &from, "cancelled").await?;
+    repo.cancel_running_nodes(&execution_id).await?;
+    Ok(Json(execution))
+}
+
+/// Pause an execution once its running nodes finish
+pub async fn pause_execution(
+    State(state): State<Arc<AppState>>,
+    Path((workflow_id, execution_id)): Path<(String, String)>,
+) -> Result<Json<Execution>> {
+    let repo = repository(&state)?;
+    Ok(Json(transition(&repo, &workflow_id, &execution_id, &["pending", "running"], "paused").await?))
+}
+
+/// Continue a paused execution, or retry a failed one from its failed nodes
+pub async fn resume_execution(
+    State(state): State<Arc<AppState>>,
+    Path((workflow_id, execution_id)): Path<(String, String)>,
+) -> Result<Json<Execution>> {
+    let repo = repository(&state)?;
+    let execution = transition(&repo, &workflow_id, &execution_id, &["paused", "failed"], "pending").await?;
+    if let Some(ref runner) = state.workflow_runner {
+        runner.wake();
+    }
+    Ok(Json(execution))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecideApprovalRequest {
+    /// `approve` or `reject`
+    pub decision: String,
+    pub comment: Option<String>,
+}
+
+/// Approve or reject an approval node waiting for a decision
+///
+/// The approver is the user of the request's session and needs the node's
+/// permission, granted by their role. Approving, or rejecting when the node
+/// has a `"rejected"` branch, queues the execution to continue; any other
+/// rejection fails it.
+pub async fn decide_approval(
+    State(state): State<Arc<AppState>>,
+    Path((workflow_id, execution_id, node_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+    Json(req): Json<DecideApprovalRequest>,
+) -> Result<Json<NodeExecution>> {
+    let user = state.session_user(&headers).await?;
+    let repo = repository(&state)?;
+    let approved = match req.decision.as_str() {
+        "approve" => true,
+        "reject" => false,
+        _ => return Err(SynapseError::Validation("decision must be approve or reject".to_string())),
+    };
+    let execution = find_execution(&repo, &workflow_id, &execution_id).await?;
+    let definition = execution_definition(&repo, &execution).await?;
+    let node = definition.node(&node_id)
+        .ok_or_else(|| SynapseError::NotFound(format!("Node {}", node_id)))?;
+    let NodeKind::Approval(ref approval) = node.kind else {
+        return Err(SynapseError::Validation(format!("Node {} is not an approval node", node_id)));
+    };
+
+    let pool = state.db_pool.as_ref()
+        .ok_or_else(|| SynapseError::DatabaseError("Database not connected".to_string()))?;
+    let granted = UserRepository::new(pool.clone()).permissions(&user.id).await?;
+    if !grants(granted.iter().map(String::as_str), &approval.permission)
+        && !state.rbac_service.check_permission(&user.id, &approval.permission).await
+    {
+        return Err(SynapseError::Forbidden(format!("User {} lacks the {} permission", user.id, approval.permission)));
+    }
+
+    let comment = req.comment.as_deref().map(str::trim).filter(|c| !c.is_empty());
+    let runner = state.workflow_runner.as_ref()
+        .ok_or_else(|| SynapseError::DatabaseError("Database not connected".to_string()))?;
+    let row = runner.decide_approval(&execution, &definition, &node_id, approved, Some(&user.id), comment).await?;
+    Ok(Json(NodeExecution::from(row)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{header, HeaderValue};
+
+    async fn decide(state: &Arc<AppState>, headers: HeaderMap, body: JsonValue) -> Result<Json<NodeExecution>> {
+        let path = ("wf".to_string(), "exec".to_string(), "approve".to_string());
+        let body = serde_json::from_value(body).unwrap();
+        decide_approval(State(state.clone()), Path(path), headers, Json(body)).await
+    }
+
+    #[tokio::test]
+    async fn test_decide_approval_requires_session() {
+        let state = Arc::new(AppState::new(Vec::new()));
+        let approver = state.auth_service.create_user("approver@example.com", "Approver", "secret").await.unwrap();
+
+        // Naming the approver in the body is not enough
+        let body = serde_json::json!({"decision": "approve", "userId": approver.id});
+        let result = decide(&state, HeaderMap::new(), body.clone()).await;
+        assert!(matches!(result, Err(SynapseError::Unauthorized(_))));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Bearer not-a-session"));
+        let result = decide(&state, headers, body.clone()).await;
+        assert_eq!(result.err().unwrap().status_and_code().0, StatusCode::UNAUTHORIZED);
+
+        // A valid session gets past authentication to the execution lookup
+        let session = state.auth_service.authenticate("approver@example.com", "secret").await.unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", session.token)).unwrap());
+        let result = decide(&state, headers, body).await;
+        assert!(matches!(result, Err(SynapseError::DatabaseError(_))));
+    }
+}
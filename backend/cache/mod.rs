@@ -0,0 +1,452 @@
+//! Response cache
+//!
+//! Sits in front of the router for non-streaming completions. Responses are
+//! stored in Redis under a hash of everything that shapes the output, so only
+//! byte-for-byte identical requests hit. Caching is opt-in per application or
+//! per request and skipped for sampled (temperature > 0) requests unless forced.
+//!
+//! When a similarity threshold is configured, exact misses fall through to
+//! the semantic layer, which serves near-duplicate prompts (see `semantic`).
+
+mod semantic;
+
+pub use semantic::{prompt_text, Embedder, SemanticCache};
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use md5::{Digest, Md5};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::Serialize;
+
+use crate::{ChatRequest, ChatResponse, error::Result};
+
+/// TTL used when caching is requested without one
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Bump when the cached response shape changes
+const KEY_PREFIX: &str = "barq:cache:v1";
+
+/// How a request was served with respect to the cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheStatus {
+    /// Caching was not requested
+    Off,
+    /// Caching was requested but skipped (sampled request, Redis unavailable)
+    Bypass,
+    Miss,
+    Hit,
+    /// Served for a sufficiently similar earlier prompt
+    SemanticHit,
+}
+
+impl CacheStatus {
+    /// Value for the `x-barq-cache` response header; `None` when off
+    pub fn header_value(self) -> Option<&'static str> {
+        match self {
+            CacheStatus::Off => None,
+            CacheStatus::Bypass => Some("bypass"),
+            CacheStatus::Miss => Some("miss"),
+            CacheStatus::Hit => Some("hit"),
+            CacheStatus::SemanticHit => Some("semantic-hit"),
+        }
+    }
+}
+
+/// Caching defaults of the calling application
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheSettings {
+    /// TTL for cached responses; `None` leaves caching to each request
+    pub ttl: Option<Duration>,
+    /// Minimum similarity for semantic hits; `None` disables them
+    pub similarity: Option<f32>,
+}
+
+/// Counters since startup, as reported by the admin API
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub enabled: bool,
+    /// Vector store behind the semantic layer, if it is configured
+    pub semantic_store: Option<String>,
+    pub hits: u64,
+    pub semantic_hits: u64,
+    pub misses: u64,
+    pub bypassed: u64,
+    pub errors: u64,
+    pub hit_rate: f64,
+    /// Provider cost avoided by serving hits
+    pub saved_cost: f64,
+    pub saved_tokens: u64,
+    /// Spent embedding prompts for semantic lookups
+    pub embedding_cost: f64,
+}
+
+/// Redis-backed response cache; a no-op when Redis is not reachable and no
+/// semantic layer is configured
+pub struct ResponseCache {
+    conn: Option<ConnectionManager>,
+    semantic: Option<SemanticCache>,
+    hits: AtomicU64,
+    semantic_hits: AtomicU64,
+    misses: AtomicU64,
+    bypassed: AtomicU64,
+    errors: AtomicU64,
+    /// Costs in micro-dollars so they fit an atomic
+    saved_micros: AtomicU64,
+    saved_tokens: AtomicU64,
+    embedding_micros: AtomicU64,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl ResponseCache {
+    /// A cache that never stores anything
+    pub fn disabled() -> Self {
+        Self::with_connection(None)
+    }
+
+    fn with_connection(conn: Option<ConnectionManager>) -> Self {
+        Self {
+            conn,
+            semantic: None,
+            hits: AtomicU64::new(0),
+            semantic_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            bypassed: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            saved_micros: AtomicU64::new(0),
+            saved_tokens: AtomicU64::new(0),
+            embedding_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Add a semantic layer consulted after exact misses
+    pub fn with_semantic(mut self, semantic: SemanticCache) -> Self {
+        self.semantic = Some(semantic);
+        self
+    }
+
+    /// Connect to Redis, falling back to a disabled cache if it is unreachable
+    pub async fn connect(url: &str) -> Self {
+        let connect = async {
+            redis::Client::open(url)?.get_connection_manager().await
+        };
+        match tokio::time::timeout(Duration::from_secs(5), connect).await {
+            Ok(Ok(conn)) => Self::with_connection(Some(conn)),
+            Ok(Err(e)) => {
+                tracing::warn!("Redis unavailable, response cache disabled: {}", e);
+                Self::disabled()
+            }
+            Err(_) => {
+                tracing::warn!("Redis connection timed out, response cache disabled");
+                Self::disabled()
+            }
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.conn.is_some() || self.semantic.is_some()
+    }
+
+    /// Whether a Redis connection was configured
+    pub fn has_redis(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    /// Round-trip latency to Redis, or the error
+    pub async fn ping(&self) -> std::result::Result<Duration, String> {
+        let mut conn = self.conn.clone().ok_or_else(|| "Not configured".to_string())?;
+        let start = Instant::now();
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut conn)
+            .await
+            .map(|_| start.elapsed())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Serve `request` from the cache or run `complete` and store its result
+    ///
+    /// `settings` are the calling application's defaults. Exact matches are
+    /// tried first, then prompts similar enough per the effective threshold.
+    /// Hits are returned with zero cost and the lookup time as latency.
+    pub async fn complete<F, Fut>(
+        &self,
+        request: &ChatRequest,
+        scope: &str,
+        settings: CacheSettings,
+        embedder: &dyn Embedder,
+        complete: F,
+    ) -> Result<(ChatResponse, CacheStatus)>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<ChatResponse>>,
+    {
+        let ttl = match cache_ttl(request, settings.ttl) {
+            Some(ttl) => ttl,
+            None if request.cache.is_none() && settings.ttl.is_none() => {
+                return Ok((complete().await?, CacheStatus::Off));
+            }
+            None => {
+                self.bypassed.fetch_add(1, Ordering::Relaxed);
+                return Ok((complete().await?, CacheStatus::Bypass));
+            }
+        };
+
+        let semantic = self.semantic.as_ref().zip(similarity_threshold(request, settings));
+        if self.conn.is_none() && semantic.is_none() {
+            self.bypassed.fetch_add(1, Ordering::Relaxed);
+            return Ok((complete().await?, CacheStatus::Bypass));
+        }
+
+        let key = cache_key(request, scope);
+        let start = Instant::now();
+
+        if let Some(ref conn) = self.conn {
+            if let Some(response) = self.lookup(conn, &key).await {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok((self.served(response, start), CacheStatus::Hit));
+            }
+        }
+
+        let mut entry = None;
+        if let Some((cache, threshold)) = semantic {
+            match cache.lookup(embedder, request, scope, threshold).await {
+                Ok(Some((found, hit))) => {
+                    self.embedding_micros.fetch_add(micros(found.embedding_cost), Ordering::Relaxed);
+                    if let Some(response) = hit {
+                        self.semantic_hits.fetch_add(1, Ordering::Relaxed);
+                        return Ok((self.served(response, start), CacheStatus::SemanticHit));
+                    }
+                    entry = Some(found);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    self.errors.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(store = %cache.store_name(), error = %e, "Semantic cache lookup failed");
+                }
+            }
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let response = complete().await?;
+
+        if let Some(ref conn) = self.conn {
+            match serde_json::to_string(&response) {
+                Ok(value) => {
+                    if let Err(e) = conn.clone().set_ex::<_, _, ()>(&key, value, ttl.as_secs()).await {
+                        self.errors.fetch_add(1, Ordering::Relaxed);
+                        tracing::warn!(error = %e, "Response cache store failed");
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to serialise response for cache"),
+            }
+        }
+
+        if let (Some((cache, _)), Some(entry)) = (semantic, entry) {
+            if let Err(e) = cache.store(entry, &response, ttl).await {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(store = %cache.store_name(), error = %e, "Semantic cache store failed");
+            }
+        }
+
+        Ok((response, CacheStatus::Miss))
+    }
+
+    /// Cached response stored under `key`, if any
+    async fn lookup(&self, conn: &ConnectionManager, key: &str) -> Option<ChatResponse> {
+        match conn.clone().get::<_, Option<String>>(key).await {
+            Ok(Some(cached)) => match serde_json::from_str(&cached) {
+                Ok(response) => return Some(response),
+                Err(e) => {
+                    self.errors.fetch_add(1, Ordering::Relaxed);
+                    tracing::warn!(key = %key, error = %e, "Discarding unreadable cache entry");
+                }
+            },
+            Ok(None) => {}
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                tracing::warn!(error = %e, "Response cache lookup failed");
+            }
+        }
+        None
+    }
+
+    /// Record the savings of a hit and reprice it for the caller
+    fn served(&self, mut response: ChatResponse, start: Instant) -> ChatResponse {
+        self.saved_micros.fetch_add(micros(response.cost), Ordering::Relaxed);
+        self.saved_tokens.fetch_add(response.usage.total_tokens as u64, Ordering::Relaxed);
+        response.cost = 0.0;
+        response.latency_ms = start.elapsed().as_millis() as u64;
+        response
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let semantic_hits = self.semantic_hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let served = hits + semantic_hits;
+        CacheStats {
+            enabled: self.is_enabled(),
+            semantic_store: self.semantic.as_ref().map(|s| s.store_name().to_string()),
+            hits,
+            semantic_hits,
+            misses,
+            bypassed: self.bypassed.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            hit_rate: if served + misses == 0 { 0.0 } else { served as f64 / (served + misses) as f64 },
+            saved_cost: self.saved_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+            saved_tokens: self.saved_tokens.load(Ordering::Relaxed),
+            embedding_cost: self.embedding_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0,
+        }
+    }
+}
+
+/// TTL to cache `request` with, or `None` if it must not be cached
+///
+/// Request options win over the application default. A TTL of zero turns
+/// caching off, and sampled requests are only cached when forced.
+pub fn cache_ttl(request: &ChatRequest, app_ttl: Option<Duration>) -> Option<Duration> {
+    let ttl = match request.cache {
+        Some(ref options) => options.ttl.map(Duration::from_secs).or(app_ttl).unwrap_or(DEFAULT_CACHE_TTL),
+        None => app_ttl?,
+    };
+    let forced = request.cache.as_ref().is_some_and(|o| o.force);
+
+    if ttl.is_zero() || (request.temperature > 0.0 && !forced) {
+        return None;
+    }
+    Some(ttl)
+}
+
+/// Similarity threshold for semantic lookups, or `None` to skip them
+///
+/// A request's own threshold wins over the application's; 0 opts out.
+pub fn similarity_threshold(request: &ChatRequest, settings: CacheSettings) -> Option<f32> {
+    request
+        .cache
+        .as_ref()
+        .and_then(|o| o.similarity)
+        .or(settings.similarity)
+        .filter(|t| *t > 0.0 && *t <= 1.0)
+}
+
+fn micros(cost: f64) -> u64 {
+    (cost * 1_000_000.0).round() as u64
+}
+
+/// Redis key for `request` within `scope` (the calling application, so
+/// tenants never see each other's responses)
+///
+/// Only fields that shape the output are hashed; `serde_json` objects keep
+/// keys sorted, which makes the serialisation canonical.
+pub fn cache_key(request: &ChatRequest, scope: &str) -> String {
+    let canonical = serde_json::json!({
+        "model": request.model,
+        "provider": request.provider,
+        "messages": request.messages,
+        "temperature": request.temperature,
+        "max_tokens": request.max_tokens,
+        "top_p": request.top_p,
+        "stop": request.stop,
+        "tools": request.tools,
+        "tool_choice": request.tool_choice,
+        "response_format": request.response_format,
+        "n": request.n,
+        "seed": request.seed,
+        "logprobs": request.logprobs,
+        "top_logprobs": request.top_logprobs,
+    });
+
+    let mut hasher = Md5::new();
+    hasher.update(canonical.to_string().as_bytes());
+    format!("{}:{}:{:x}", KEY_PREFIX, scope, hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CacheOptions, Message};
+
+    fn request(temperature: f32) -> ChatRequest {
+        ChatRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![Message::user("What is 2 + 2?")],
+            temperature,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let mut other = request(0.0);
+        other.user_id = Some("someone".to_string());
+        assert_eq!(cache_key(&request(0.0), "app"), cache_key(&other, "app"));
+        assert_ne!(cache_key(&request(0.0), "app"), cache_key(&request(0.0), "other-app"));
+
+        other.messages.push(Message::assistant("4"));
+        assert_ne!(cache_key(&request(0.0), "app"), cache_key(&other, "app"));
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let app = Some(Duration::from_secs(60));
+        assert_eq!(cache_ttl(&request(0.0), None), None);
+        assert_eq!(cache_ttl(&request(0.0), app), app);
+        assert_eq!(cache_ttl(&request(0.7), app), None);
+
+        let mut forced = request(0.7);
+        forced.cache = Some(CacheOptions { ttl: Some(10), force: true, similarity: None });
+        assert_eq!(cache_ttl(&forced, app), Some(Duration::from_secs(10)));
+
+        let mut opted_out = request(0.0);
+        opted_out.cache = CacheOptions::from_directives("no-store");
+        assert_eq!(cache_ttl(&opted_out, app), None);
+
+        let mut opted_in = request(0.0);
+        opted_in.cache = CacheOptions::from_directives("max-age=300");
+        assert_eq!(cache_ttl(&opted_in, None), Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn test_similarity_threshold() {
+        let app = CacheSettings { ttl: None, similarity: Some(0.95) };
+        assert_eq!(similarity_threshold(&request(0.0), CacheSettings::default()), None);
+        assert_eq!(similarity_threshold(&request(0.0), app), Some(0.95));
+
+        let mut stricter = request(0.0);
+        stricter.cache = CacheOptions::from_directives("similarity=0.99");
+        assert_eq!(similarity_threshold(&stricter, app), Some(0.99));
+
+        let mut opted_out = request(0.0);
+        opted_out.cache = CacheOptions::from_directives("similarity=0");
+        assert_eq!(similarity_threshold(&opted_out, app), None);
+    }
+
+    struct NoEmbedder;
+
+    #[async_trait::async_trait]
+    impl Embedder for NoEmbedder {
+        async fn embed(&self, _request: &crate::EmbeddingRequest) -> Result<crate::EmbeddingResponse> {
+            Err(crate::SynapseError::Internal("embedder called".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disabled_cache_bypasses() {
+        let cache = ResponseCache::disabled();
+        let mut req = request(0.0);
+        req.cache = Some(CacheOptions::default());
+
+        let result = cache.complete(&req, "app", CacheSettings::default(), &NoEmbedder, || async {
+            Err(crate::SynapseError::Internal("provider called".to_string()))
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(cache.stats().bypassed, 1);
+    }
+}
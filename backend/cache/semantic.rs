@@ -0,0 +1,302 @@
+//! Semantic response cache
+//!
+//! Embeds the last user message and looks for a previously answered prompt
+//! close enough to reuse. Neighbours must share the caller's scope and the
+//! rest of the request (model, parameters, earlier turns), so only the final
+//! question may differ.
+
+use async_trait::async_trait;
+use md5::{Digest, Md5};
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+use crate::{ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, error::Result};
+use crate::vector::{VectorFilter, VectorRecord, VectorStore};
+
+/// Neighbours fetched per lookup; expired ones are skipped and deleted
+const CANDIDATES: usize = 3;
+
+/// Source of embeddings, normally the hub's own embedding path
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse>;
+}
+
+/// A prompt embedded for lookup, kept so a miss can be stored without
+/// embedding twice
+pub struct SemanticEntry {
+    /// Record id, the same for every request with this scope, context and
+    /// prompt, so storing it again replaces the earlier answer
+    id: String,
+    vector: Vec<f32>,
+    filter: VectorFilter,
+    pub embedding_cost: f64,
+}
+
+pub struct SemanticCache {
+    store: Arc<dyn VectorStore>,
+    embedding_model: String,
+    collection: String,
+    ready: OnceCell<()>,
+}
+
+impl SemanticCache {
+    pub fn new(store: Arc<dyn VectorStore>, embedding_model: impl Into<String>, collection: impl Into<String>) -> Self {
+        Self {
+            store,
+            embedding_model: embedding_model.into(),
+            collection: collection.into(),
+            ready: OnceCell::new(),
+        }
+    }
+
+    pub fn store_name(&self) -> &str {
+        self.store.name()
+    }
+
+    /// Embed the request's prompt and look for a neighbour at or above
+    /// `threshold`; `None` when the request has no user text to embed
+    pub async fn lookup(
+        &self,
+        embedder: &dyn Embedder,
+        request: &ChatRequest,
+        scope: &str,
+        threshold: f32,
+    ) -> Result<Option<(SemanticEntry, Option<ChatResponse>)>> {
+        let Some(prompt) = prompt_text(request) else {
+            return Ok(None);
+        };
+        let context = context_key(request);
+        let id = record_id(scope, &context, &prompt);
+
+        let embedding = embedder.embed(&EmbeddingRequest {
+            model: self.embedding_model.clone(),
+            input: vec![prompt],
+            provider: None,
+            dimensions: None,
+            user_id: request.user_id.clone(),
+        }).await?;
+
+        let Some(vector) = embedding.data.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let mut filter = VectorFilter::new();
+        filter.insert("scope".to_string(), scope.into());
+        filter.insert("context".to_string(), context.into());
+
+        let entry = SemanticEntry { id, vector, filter, embedding_cost: embedding.cost };
+
+        self.ready
+            .get_or_try_init(|| self.store.ensure_collection(&self.collection, entry.vector.len()))
+            .await?;
+
+        let now = chrono::Utc::now().timestamp();
+        let (live, expired): (Vec<_>, Vec<_>) = self.store
+            .query(&self.collection, &entry.vector, CANDIDATES, &entry.filter)
+            .await?
+            .into_iter()
+            .partition(|m| m.payload["expires_at"].as_i64().is_some_and(|at| at > now));
+        if !expired.is_empty() {
+            let ids: Vec<String> = expired.into_iter().map(|m| m.id).collect();
+            if let Err(e) = self.store.delete(&self.collection, &ids).await {
+                tracing::warn!(error = %e, "Failed to delete expired semantic cache entries");
+            }
+        }
+        let hit = live
+            .into_iter()
+            .filter(|m| m.score >= threshold)
+            .find_map(|m| serde_json::from_str::<ChatResponse>(m.payload["response"].as_str()?).ok());
+
+        Ok(Some((entry, hit)))
+    }
+
+    /// Remember `response` for prompts near the one in `entry`
+    pub async fn store(&self, entry: SemanticEntry, response: &ChatResponse, ttl: std::time::Duration) -> Result<()> {
+        let mut payload = serde_json::Value::Object(entry.filter);
+        payload["expires_at"] = (chrono::Utc::now().timestamp() + ttl.as_secs() as i64).into();
+        payload["response"] = serde_json::to_string(response).unwrap_or_default().into();
+
+        self.store.upsert(&self.collection, vec![VectorRecord {
+            id: entry.id,
+            vector: entry.vector,
+            payload,
+        }]).await
+    }
+}
+
+/// Text of the last user message, the part of the request that is embedded
+pub fn prompt_text(request: &ChatRequest) -> Option<String> {
+    request
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.text())
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// A UUID derived from the scope, context and prompt, as vector databases
+/// such as Qdrant only take UUIDs or integers for ids
+fn record_id(scope: &str, context: &str, prompt: &str) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(serde_json::json!([scope, context, prompt]).to_string().as_bytes());
+    uuid::Uuid::from_bytes(hasher.finalize().into()).to_string()
+}
+
+/// Hash of everything except the last user message
+fn context_key(request: &ChatRequest) -> String {
+    let mut context = request.clone();
+    if let Some(pos) = context.messages.iter().rposition(|m| m.role == "user") {
+        context.messages.remove(pos);
+    }
+    super::cache_key(&context, "context")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Message, TokenUsage};
+    use crate::vector::MemoryVectorStore;
+
+    /// Maps prompts onto fixed directions so similarity is predictable
+    struct FakeEmbedder;
+
+    #[async_trait]
+    impl Embedder for FakeEmbedder {
+        async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+            let vector = match request.input[0].as_str() {
+                "What are your opening hours?" => vec![1.0, 0.0, 0.0],
+                "When are you open?" => vec![0.98, 0.2, 0.0],
+                _ => vec![0.0, 0.0, 1.0],
+            };
+            Ok(EmbeddingResponse {
+                provider: "fake".to_string(),
+                model: request.model.clone(),
+                data: vec![vector],
+                usage: TokenUsage::default(),
+                latency_ms: 0,
+                cost: 0.0001,
+            })
+        }
+    }
+
+    fn request(prompt: &str) -> ChatRequest {
+        ChatRequest {
+            messages: vec![Message::system("You are a shop assistant"), Message::user(prompt)],
+            temperature: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn response(text: &str) -> ChatResponse {
+        ChatResponse {
+            id: "resp-1".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4".to_string(),
+            choices: vec![crate::Choice {
+                index: 0,
+                logprobs: None,
+                message: Message::assistant(text),
+                finish_reason: "stop".to_string(),
+            }],
+            usage: TokenUsage::default(),
+            created: chrono::Utc::now(),
+            latency_ms: 10,
+            cost: 0.002,
+            system_fingerprint: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_semantic_lookup() {
+        let cache = SemanticCache::new(Arc::new(MemoryVectorStore::new()), "embed", "semantic");
+        let ttl = std::time::Duration::from_secs(60);
+
+        let (entry, hit) = cache.lookup(&FakeEmbedder, &request("What are your opening hours?"), "app", 0.9)
+            .await.unwrap().unwrap();
+        assert!(hit.is_none());
+        cache.store(entry, &response("9 to 5"), ttl).await.unwrap();
+
+        let (_, hit) = cache.lookup(&FakeEmbedder, &request("When are you open?"), "app", 0.9)
+            .await.unwrap().unwrap();
+        assert_eq!(hit.unwrap().choices[0].message.content, "9 to 5");
+
+        // Other applications, unrelated prompts and different context never match
+        let (_, hit) = cache.lookup(&FakeEmbedder, &request("When are you open?"), "other", 0.9)
+            .await.unwrap().unwrap();
+        assert!(hit.is_none());
+        let (_, hit) = cache.lookup(&FakeEmbedder, &request("Do you ship abroad?"), "app", 0.9)
+            .await.unwrap().unwrap();
+        assert!(hit.is_none());
+        let mut other_model = request("When are you open?");
+        other_model.model = "gpt-4o-mini".to_string();
+        let (_, hit) = cache.lookup(&FakeEmbedder, &other_model, "app", 0.9).await.unwrap().unwrap();
+        assert!(hit.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_response_cache_semantic_hit() {
+        let semantic = SemanticCache::new(Arc::new(MemoryVectorStore::new()), "embed", "semantic");
+        let cache = super::super::ResponseCache::disabled().with_semantic(semantic);
+        let settings = super::super::CacheSettings {
+            ttl: Some(std::time::Duration::from_secs(60)),
+            similarity: Some(0.9),
+        };
+
+        let (first, status) = cache
+            .complete(&request("What are your opening hours?"), "app", settings, &FakeEmbedder, || async {
+                Ok(response("9 to 5"))
+            })
+            .await
+            .unwrap();
+        assert_eq!(status, super::super::CacheStatus::Miss);
+        assert_eq!(first.cost, 0.002);
+
+        let (second, status) = cache
+            .complete(&request("When are you open?"), "app", settings, &FakeEmbedder, || async {
+                Err(crate::SynapseError::Internal("provider called".to_string()))
+            })
+            .await
+            .unwrap();
+        assert_eq!(status, super::super::CacheStatus::SemanticHit);
+        assert_eq!(second.cost, 0.0);
+
+        let stats = cache.stats();
+        assert_eq!((stats.semantic_hits, stats.misses), (1, 1));
+        assert!((stats.saved_cost - 0.002).abs() < 1e-9);
+        assert!((stats.embedding_cost - 0.0002).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entry_replaced() {
+        let cache = SemanticCache::new(Arc::new(MemoryVectorStore::new()), "embed", "semantic");
+        let prompt = request("What are your opening hours?");
+
+        let (entry, _) = cache.lookup(&FakeEmbedder, &prompt, "app", 0.9).await.unwrap().unwrap();
+        cache.store(entry, &response("8 to 4"), std::time::Duration::ZERO).await.unwrap();
+
+        // The expired answer is no hit, and the lookup deletes it
+        let (entry, hit) = cache.lookup(&FakeEmbedder, &prompt, "app", 0.9).await.unwrap().unwrap();
+        assert!(hit.is_none());
+        let left = cache.store.query("semantic", &entry.vector, CANDIDATES, &entry.filter).await.unwrap();
+        assert!(left.is_empty());
+
+        cache.store(entry, &response("9 to 5"), std::time::Duration::from_secs(60)).await.unwrap();
+        let (entry, hit) = cache.lookup(&FakeEmbedder, &prompt, "app", 0.9).await.unwrap().unwrap();
+        assert_eq!(hit.unwrap().choices[0].message.content, "9 to 5");
+
+        // Storing the same prompt again replaces its record
+        cache.store(entry, &response("10 to 6"), std::time::Duration::from_secs(60)).await.unwrap();
+        let (entry, hit) = cache.lookup(&FakeEmbedder, &prompt, "app", 0.9).await.unwrap().unwrap();
+        assert_eq!(hit.unwrap().choices[0].message.content, "10 to 6");
+        let stored = cache.store.query("semantic", &entry.vector, CANDIDATES, &entry.filter).await.unwrap();
+        assert_eq!(stored.len(), 1);
+    }
+
+    #[test]
+    fn test_prompt_text() {
+        assert_eq!(prompt_text(&request("Hi")).as_deref(), Some("Hi"));
+        assert!(prompt_text(&ChatRequest::default()).is_none());
+    }
+}
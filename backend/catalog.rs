@@ -0,0 +1,316 @@
+//! Model catalog
+//!
+//! Discovers models from each configured provider's list-models endpoint,
+//! merges them with the `ProviderModel` overrides configured on the provider
+//! or account and caches the result. Backs `GET /v1/models` and the gRPC
+//! `ModelsService::List`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::{ModelCapability, ProviderModel, providers::ProviderAdapter};
+
+/// How long discovered models are served before the next refresh
+pub const DEFAULT_CATALOG_TTL: Duration = Duration::from_secs(600);
+
+/// Upper bound for a single provider's list-models call
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// A model available through the gateway
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CatalogModel {
+    pub id: String,
+    pub name: String,
+    /// Provider ID, e.g. "openai"
+    pub provider: String,
+    /// Provider account the model was discovered through, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<String>,
+    pub capabilities: Vec<ModelCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_token_cost: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_token_cost: Option<f64>,
+    /// True when the provider's endpoint reported the model
+    pub discovered: bool,
+}
+
+/// A provider or account to discover models from
+pub struct CatalogSource {
+    /// Stable cache key, e.g. "account:<id>"
+    pub key: String,
+    pub provider_id: String,
+    pub account_id: Option<String>,
+    pub adapter: Arc<dyn ProviderAdapter>,
+    /// Configured models; these win over discovered metadata
+    pub overrides: Vec<ProviderModel>,
+}
+
+/// Cached catalog of models across all configured providers
+pub struct ModelCatalog {
+    ttl: Duration,
+    /// Models per source key
+    entries: RwLock<HashMap<String, Vec<CatalogModel>>>,
+    last_refresh: RwLock<Option<Instant>>,
+    /// Serialises refreshes so concurrent callers do not all hit providers
+    refresh_lock: Mutex<()>,
+}
+
+impl ModelCatalog {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: RwLock::new(HashMap::new()),
+            last_refresh: RwLock::new(None),
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
+    /// Whether the catalog has never been refreshed or has outlived its TTL
+    pub async fn is_stale(&self) -> bool {
+        match *self.last_refresh.read().await {
+            Some(at) => at.elapsed() >= self.ttl,
+            None => true,
+        }
+    }
+
+    /// Query every source and replace the cached models
+    ///
+    /// A source whose endpoint fails keeps its previously discovered models,
+    /// or just its configured overrides if it was never reached.
+    pub async fn refresh(&self, sources: Vec<CatalogSource>) {
+        let _guard = self.refresh_lock.lock().await;
+
+        let results = futures_util::future::join_all(sources.into_iter().map(|source| async move {
+            let discovered = tokio::time::timeout(DISCOVERY_TIMEOUT, source.adapter.list_models()).await;
+            (source, discovered)
+        }))
+        .await;
+
+        let mut entries = self.entries.write().await;
+        let mut refreshed = HashMap::with_capacity(results.len());
+
+        for (source, discovered) in results {
+            let discovered = match discovered {
+                Ok(Ok(models)) => Some(models),
+                Ok(Err(e)) => {
+                    tracing::warn!(source = %source.key, error = %e, "Model discovery failed");
+                    None
+                }
+                Err(_) => {
+                    tracing::warn!(source = %source.key, "Model discovery timed out");
+                    None
+                }
+            };
+
+            let entry = match (discovered, entries.remove(&source.key)) {
+                (Some(ids), _) => merge_models(&source, ids),
+                (None, Some(previous)) => previous,
+                (None, None) => merge_models(&source, Vec::new()),
+            };
+            refreshed.insert(source.key, entry);
+        }
+
+        tracing::debug!(
+            sources = refreshed.len(),
+            models = refreshed.values().map(Vec::len).sum::<usize>(),
+            "Model catalog refreshed"
+        );
+
+        *entries = refreshed;
+        *self.last_refresh.write().await = Some(Instant::now());
+    }
+
+    /// All cached models, one entry per provider and model ID
+    pub async fn models(&self) -> Vec<CatalogModel> {
+        let entries = self.entries.read().await;
+
+        let mut models: Vec<CatalogModel> = Vec::new();
+        let mut seen: HashMap<(String, String), usize> = HashMap::new();
+
+        let mut keys: Vec<&String> = entries.keys().collect();
+        keys.sort();
+        for key in keys {
+            for model in &entries[key] {
+                let id = (model.provider.clone(), model.id.clone());
+                match seen.get(&id) {
+                    // Prefer an entry that carries configured pricing
+                    Some(&idx) if models[idx].input_token_cost.is_none() && model.input_token_cost.is_some() => {
+                        models[idx] = model.clone();
+                    }
+                    Some(_) => {}
+                    None => {
+                        seen.insert(id, models.len());
+                        models.push(model.clone());
+                    }
+                }
+            }
+        }
+
+        models.sort_by(|a, b| a.provider.cmp(&b.provider).then_with(|| a.id.cmp(&b.id)));
+        models
+    }
+}
+
+impl Default for ModelCatalog {
+    fn default() -> Self {
+        Self::new(DEFAULT_CATALOG_TTL)
+    }
+}
+
+/// Combine discovered model IDs with configured overrides
+fn merge_models(source: &CatalogSource, discovered: Vec<String>) -> Vec<CatalogModel> {
+    let overrides: HashMap<&str, &ProviderModel> =
+        source.overrides.iter().map(|m| (m.id.as_str(), m)).collect();
+
+    let mut models: Vec<CatalogModel> = discovered
+        .iter()
+        .map(|id| match overrides.get(id.as_str()) {
+            Some(model) => catalog_model(source, model, true),
+            None => CatalogModel {
+                id: id.clone(),
+                name: id.clone(),
+                provider: source.provider_id.clone(),
+                account_id: source.account_id.clone(),
+                capabilities: ModelCapability::infer(id),
+                input_token_cost: None,
+                output_token_cost: None,
+                discovered: true,
+            },
+        })
+        .collect();
+
+    // Configured models stay listed even when the endpoint omits them
+    // (e.g. Azure deployments or fine-tunes hidden from the listing)
+    for model in &source.overrides {
+        if !discovered.iter().any(|id| id == &model.id) {
+            models.push(catalog_model(source, model, false));
+        }
+    }
+
+    models
+}
+
+fn catalog_model(source: &CatalogSource, model: &ProviderModel, discovered: bool) -> CatalogModel {
+    CatalogModel {
+        id: model.id.clone(),
+        name: model.name.clone(),
+        provider: source.provider_id.clone(),
+        account_id: source.account_id.clone(),
+        capabilities: if model.capabilities.is_empty() {
+            ModelCapability::infer(&model.id)
+        } else {
+            model.capabilities.clone()
+        },
+        input_token_cost: model.input_token_cost,
+        output_token_cost: model.output_token_cost,
+        discovered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::{ChatRequest, ChatResponse, Provider, error::{ProviderError, Result, SynapseError}};
+
+    struct StubAdapter {
+        provider: Provider,
+        models: Option<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl ProviderAdapter for StubAdapter {
+        async fn chat(&self, _request: &ChatRequest) -> Result<ChatResponse> {
+            Err(SynapseError::Provider(ProviderError::Network("unused".into())))
+        }
+
+        async fn list_models(&self) -> Result<Vec<String>> {
+            self.models
+                .clone()
+                .ok_or_else(|| SynapseError::Provider(ProviderError::Network("down".to_string())))
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn provider(&self) -> &Provider {
+            &self.provider
+        }
+    }
+
+    fn source(models: Option<Vec<&str>>, overrides: Vec<ProviderModel>) -> CatalogSource {
+        CatalogSource {
+            key: "account:1".to_string(),
+            provider_id: "openai".to_string(),
+            account_id: Some("1".to_string()),
+            adapter: Arc::new(StubAdapter {
+                provider: Provider::default(),
+                models: models.map(|m| m.into_iter().map(String::from).collect()),
+            }),
+            overrides,
+        }
+    }
+
+    fn override_model(id: &str) -> ProviderModel {
+        ProviderModel {
+            id: id.to_string(),
+            name: "Custom Name".to_string(),
+            capabilities: vec![ModelCapability::LLM],
+            input_token_cost: Some(2.5),
+            output_token_cost: Some(10.0),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_merges_overrides() {
+        let catalog = ModelCatalog::default();
+        assert!(catalog.is_stale().await);
+
+        catalog
+            .refresh(vec![source(
+                Some(vec!["gpt-4o", "text-embedding-3-small"]),
+                vec![override_model("gpt-4o"), override_model("ft:gpt-4o:acme")],
+            )])
+            .await;
+
+        let models = catalog.models().await;
+        assert_eq!(models.len(), 3);
+        assert!(!catalog.is_stale().await);
+
+        let gpt = models.iter().find(|m| m.id == "gpt-4o").unwrap();
+        assert_eq!(gpt.name, "Custom Name");
+        assert_eq!(gpt.input_token_cost, Some(2.5));
+        assert!(gpt.discovered);
+
+        let embed = models.iter().find(|m| m.id == "text-embedding-3-small").unwrap();
+        assert_eq!(embed.capabilities, vec![ModelCapability::Embedding]);
+
+        let fine_tune = models.iter().find(|m| m.id == "ft:gpt-4o:acme").unwrap();
+        assert!(!fine_tune.discovered);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_keeps_previous_models() {
+        let catalog = ModelCatalog::default();
+        catalog.refresh(vec![source(Some(vec!["gpt-4o", "gpt-4o-mini"]), Vec::new())]).await;
+        catalog.refresh(vec![source(None, Vec::new())]).await;
+
+        assert_eq!(catalog.models().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_source_lists_overrides() {
+        let catalog = ModelCatalog::default();
+        catalog.refresh(vec![source(None, vec![override_model("gpt-4o")])]).await;
+
+        let models = catalog.models().await;
+        assert_eq!(models.len(), 1);
+        assert!(!models[0].discovered);
+    }
+}
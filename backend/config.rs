@@ -12,10 +12,16 @@ pub struct Config {
     pub database: DatabaseConfig,
     /// Redis configuration
     pub redis: RedisConfig,
+    /// Response cache configuration
+    pub cache: CacheConfig,
     /// Provider configurations
     pub providers: ProvidersConfig,
     /// Logging configuration
     pub logging: LoggingConfig,
+    /// Trace export configuration
+    pub telemetry: TelemetryConfig,
+    /// Background health probing
+    pub health: HealthConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -37,6 +43,15 @@ pub struct RedisConfig {
     pub url: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct CacheConfig {
+    /// Embedding model for the semantic cache; unset disables it
+    pub semantic_embedding_model: Option<String>,
+    /// `VectorDb` provider account holding cache entries; in-memory when unset
+    pub semantic_vector_account: Option<String>,
+    pub semantic_collection: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ProvidersConfig {
     pub openai_api_key: Option<String>,
@@ -50,9 +65,23 @@ pub struct ProvidersConfig {
 #[derive(Debug, Clone, Deserialize)]
 pub struct LoggingConfig {
     pub level: String,
+    /// "json" or "text"
     pub format: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct HealthConfig {
+    /// Seconds between probes of providers, accounts, Redis and the database
+    pub probe_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector base URL; traces are not exported when unset
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
 impl Config {
     /// Load configuration from environment variables and config files
     pub fn load() -> Result<Self, config::ConfigError> {
@@ -99,6 +128,12 @@ impl Config {
                 url: env::var("REDIS_URL")
                     .unwrap_or_else(|_| "redis://localhost:6379".to_string()),
             },
+            cache: CacheConfig {
+                semantic_embedding_model: env::var("SEMANTIC_CACHE_EMBEDDING_MODEL").ok(),
+                semantic_vector_account: env::var("SEMANTIC_CACHE_VECTOR_ACCOUNT").ok(),
+                semantic_collection: env::var("SEMANTIC_CACHE_COLLECTION")
+                    .unwrap_or_else(|_| "barq_semantic_cache".to_string()),
+            },
             providers: ProvidersConfig {
                 openai_api_key: env::var("OPENAI_API_KEY").ok(),
                 anthropic_api_key: env::var("ANTHROPIC_API_KEY").ok(),
@@ -111,6 +146,17 @@ impl Config {
                 level: config.get("logging.level").unwrap_or_else(|_| "info".to_string()),
                 format: config.get("logging.format").unwrap_or_else(|_| "json".to_string()),
             },
+            telemetry: TelemetryConfig {
+                otlp_endpoint: env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|url| !url.is_empty()),
+                service_name: env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "barq-hub".to_string()),
+            },
+            health: HealthConfig {
+                probe_interval_secs: env::var("HEALTH_PROBE_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|secs| secs.parse().ok())
+                    .filter(|secs| *secs > 0)
+                    .unwrap_or(crate::health::DEFAULT_PROBE_INTERVAL.as_secs()),
+            },
         })
     }
 
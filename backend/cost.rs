@@ -53,14 +53,19 @@ impl CostManager {
         self.entries.write().await.push(entry.clone());
 
         // Update budget if exists
-        let mut budgets = self.budgets.write().await;
-        if let Some(budget) = budgets.get_mut(user_id) {
-            budget.spent_this_month += cost;
-        }
+        self.charge(user_id, cost).await;
 
         Ok(entry)
     }
 
+    /// Add spend to an entity's budget, if it has one, without a ledger
+    /// entry; used to count the same request against an agent as well
+    pub async fn charge(&self, entity_id: &str, cost: f64) {
+        if let Some(budget) = self.budgets.write().await.get_mut(entity_id) {
+            budget.spent_this_month += cost;
+        }
+    }
+
     /// Calculate cost based on usage and provider pricing
     pub fn calculate_cost(usage: &TokenUsage, provider: &Provider) -> f64 {
         let input_cost = (usage.prompt_tokens as f64 / 1_000_000.0) 
@@ -71,14 +76,13 @@ impl CostManager {
     }
 
     /// Check if a user can make a request (budget check)
+    #[tracing::instrument(name = "budget.check", skip(self))]
     pub async fn can_request(&self, user_id: &str, estimated_cost: f64) -> Result<bool> {
         let budgets = self.budgets.read().await;
         
         if let Some(budget) = budgets.get(user_id) {
-            if budget.enforce_limit {
-                if budget.spent_this_month + estimated_cost > budget.monthly_limit {
-                    return Err(SynapseError::Cost(CostError::BudgetExceeded(user_id.to_string())));
-                }
+            if budget.enforce_limit && budget.spent_this_month + estimated_cost > budget.monthly_limit {
+                return Err(SynapseError::Cost(CostError::BudgetExceeded(user_id.to_string())));
             }
         }
         
@@ -212,6 +216,7 @@ mod tests {
             name: "Test".to_string(),
             provider_type: ProviderType::OpenAI,
             api_key: "test".to_string(),
+            models: Vec::new(),
             base_url: "https://api.openai.com/v1".to_string(),
             pricing: ProviderPricing {
                 input_token_cost: 30.0,   // $30/1M
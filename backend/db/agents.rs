@@ -82,6 +82,43 @@ impl AgentRepository {
             .await
     }
 
+    /// Replace every editable field, returning the updated row
+    pub async fn update(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        system_prompt: Option<&str>,
+        llm_config: &JsonValue,
+        embedding_config: Option<&JsonValue>,
+        vector_db_config: Option<&JsonValue>,
+        knowledge_collection: Option<&str>,
+        enabled: bool,
+        status: &str,
+    ) -> Result<Option<AgentRow>, sqlx::Error> {
+        sqlx::query_as::<_, AgentRow>(
+            r#"
+            UPDATE agents
+            SET name = $2, description = $3, system_prompt = $4, llm_config = $5, embedding_config = $6,
+                vector_db_config = $7, knowledge_collection = $8, enabled = $9, status = $10, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(name)
+        .bind(description)
+        .bind(system_prompt)
+        .bind(llm_config)
+        .bind(embedding_config)
+        .bind(vector_db_config)
+        .bind(knowledge_collection)
+        .bind(enabled)
+        .bind(status)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
     pub async fn update_llm_config(&self, id: &str, llm_config: &JsonValue) -> Result<bool, sqlx::Error> {
         let result = sqlx::query("UPDATE agents SET llm_config = $1, updated_at = NOW() WHERE id = $2")
             .bind(llm_config)
@@ -21,11 +21,51 @@ pub struct ApplicationRow {
     pub expires_at: Option<DateTime<Utc>>,
     pub requests_today: i32,
     pub requests_reset_at: DateTime<Utc>,
+    /// Response cache TTL; 0 means the application does not cache by default
+    #[sqlx(default)]
+    pub cache_ttl_seconds: i32,
+    /// Minimum similarity for semantic cache hits; 0 disables semantic lookups
+    #[sqlx(default)]
+    pub semantic_cache_threshold: f32,
+    /// Record completions in `request_logs`
+    #[sqlx(default)]
+    pub request_logging: bool,
+    /// Days to keep request logs; 0 uses the default
+    #[sqlx(default)]
+    pub log_retention_days: i32,
+    /// Mask personal data and credentials in request logs
+    #[sqlx(default)]
+    pub log_redaction: bool,
+}
+
+impl ApplicationRow {
+    /// Check whether the application was granted a scope.
+    /// `*` grants everything and `llm:*` grants every `llm:` scope.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        let Some(granted) = self.scopes.as_array() else {
+            return false;
+        };
+        granted.iter().filter_map(|s| s.as_str()).any(|s| {
+            s == "*"
+                || s == scope
+                || s.strip_suffix('*').is_some_and(|prefix| prefix.ends_with(':') && scope.starts_with(prefix))
+        })
+    }
+
+    /// Response cache defaults for requests made with this application's key
+    pub fn cache_settings(&self) -> crate::cache::CacheSettings {
+        crate::cache::CacheSettings {
+            ttl: (self.cache_ttl_seconds > 0).then(|| std::time::Duration::from_secs(self.cache_ttl_seconds as u64)),
+            similarity: (self.semantic_cache_threshold > 0.0).then_some(self.semantic_cache_threshold),
+        }
+    }
 }
 
 enum CreateApplicationArg {
     String(String),
     Int(i32),
+    Float(f32),
+    Bool(bool),
     Json(JsonValue),
     DateTime(DateTime<Utc>),
 }
@@ -49,11 +89,17 @@ impl ApplicationRepository {
         scopes: &JsonValue,
         rate_limit: i32,
         expires_at: Option<DateTime<Utc>>,
+        cache_ttl_seconds: i32,
+        semantic_cache_threshold: f32,
+        request_logging: bool,
+        log_retention_days: i32,
+        log_redaction: bool,
     ) -> Result<ApplicationRow, sqlx::Error> {
         sqlx::query_as::<_, ApplicationRow>(
             r#"
-            INSERT INTO applications (id, name, description, api_key_hash, api_key_prefix, scopes, rate_limit, status, expires_at, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, 'active', $8, NOW(), NOW())
+            INSERT INTO applications (id, name, description, api_key_hash, api_key_prefix, scopes, rate_limit, status, expires_at, cache_ttl_seconds, semantic_cache_threshold,
+                request_logging, log_retention_days, log_redaction, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, 'active', $8, $9, $10, $11, $12, $13, NOW(), NOW())
             RETURNING *
             "#
         )
@@ -65,6 +111,11 @@ impl ApplicationRepository {
         .bind(scopes)
         .bind(rate_limit)
         .bind(expires_at)
+        .bind(cache_ttl_seconds)
+        .bind(semantic_cache_threshold)
+        .bind(request_logging)
+        .bind(log_retention_days)
+        .bind(log_redaction)
         .fetch_one(&self.pool)
         .await
     }
@@ -156,6 +207,11 @@ impl ApplicationRepository {
         scopes: Option<&JsonValue>,
         rate_limit: Option<i32>,
         expires_at: Option<DateTime<Utc>>,
+        cache_ttl_seconds: Option<i32>,
+        semantic_cache_threshold: Option<f32>,
+        request_logging: Option<bool>,
+        log_retention_days: Option<i32>,
+        log_redaction: Option<bool>,
     ) -> Result<Option<ApplicationRow>, sqlx::Error> {
         // Build dynamic update query
         let mut query = "UPDATE applications SET updated_at = NOW()".to_string();
@@ -192,6 +248,31 @@ impl ApplicationRepository {
              args.push(CreateApplicationArg::DateTime(e));
              arg_index += 1;
         }
+        if let Some(ttl) = cache_ttl_seconds {
+            query.push_str(&format!(", cache_ttl_seconds = ${}", arg_index));
+            args.push(CreateApplicationArg::Int(ttl));
+            arg_index += 1;
+        }
+        if let Some(threshold) = semantic_cache_threshold {
+            query.push_str(&format!(", semantic_cache_threshold = ${}", arg_index));
+            args.push(CreateApplicationArg::Float(threshold));
+            arg_index += 1;
+        }
+        if let Some(enabled) = request_logging {
+            query.push_str(&format!(", request_logging = ${}", arg_index));
+            args.push(CreateApplicationArg::Bool(enabled));
+            arg_index += 1;
+        }
+        if let Some(days) = log_retention_days {
+            query.push_str(&format!(", log_retention_days = ${}", arg_index));
+            args.push(CreateApplicationArg::Int(days));
+            arg_index += 1;
+        }
+        if let Some(redact) = log_redaction {
+            query.push_str(&format!(", log_redaction = ${}", arg_index));
+            args.push(CreateApplicationArg::Bool(redact));
+            arg_index += 1;
+        }
 
         query.push_str(&format!(" WHERE id = ${} RETURNING *", arg_index));
         // Id is the last arg
@@ -202,6 +283,8 @@ impl ApplicationRepository {
             match arg {
                 CreateApplicationArg::String(s) => query_builder = query_builder.bind(s),
                 CreateApplicationArg::Int(i) => query_builder = query_builder.bind(i),
+                CreateApplicationArg::Float(f) => query_builder = query_builder.bind(f),
+                CreateApplicationArg::Bool(b) => query_builder = query_builder.bind(b),
                 CreateApplicationArg::Json(j) => query_builder = query_builder.bind(j),
                 CreateApplicationArg::DateTime(d) => query_builder = query_builder.bind(d),
             }
@@ -44,6 +44,11 @@ impl CostRepository {
     }
 
     // Cost Entries
+
+    /// Insert a cost entry; a `user_id` that is not a hub user (an agent's
+    /// or application's end user) is stored as NULL rather than failing the
+    /// foreign key
+    #[tracing::instrument(name = "db.insert", skip_all, fields(db.table = "cost_entries"))]
     pub async fn create_entry(
         &self,
         id: &str,
@@ -60,7 +65,7 @@ impl CostRepository {
         sqlx::query_as::<_, CostEntryRow>(
             r#"
             INSERT INTO cost_entries (id, user_id, agent_id, application_id, provider, model, prompt_tokens, completion_tokens, total_tokens, cost, request_id, created_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
+            VALUES ($1, (SELECT id FROM users WHERE id = $2), $3, $4, $5, $6, $7, $8, $9, $10, $11, NOW())
             RETURNING *
             "#
         )
@@ -0,0 +1,331 @@
+//! Knowledge collection, document and chunk repository
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use serde_json::Value as JsonValue;
+use super::DbPool;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct KnowledgeCollectionRow {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub agent_id: Option<String>,
+    pub document_count: i32,
+    pub chunk_count: i32,
+    pub embedding_model: Option<String>,
+    pub embedding_provider: Option<String>,
+    pub vector_account_id: Option<String>,
+    pub chunking: JsonValue,
+    pub metadata: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct KnowledgeDocumentRow {
+    pub id: String,
+    pub collection_id: String,
+    pub title: String,
+    /// Extracted text
+    pub content: String,
+    pub content_type: String,
+    pub file_size: i64,
+    pub chunk_count: i32,
+    pub status: String,
+    pub chunks_embedded: i32,
+    pub error: Option<String>,
+    pub metadata: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+/// A stored chunk with the fields of its document that search needs
+#[derive(Debug, Clone, FromRow)]
+pub struct KnowledgeChunkRow {
+    pub id: String,
+    pub document_id: String,
+    pub collection_id: String,
+    pub title: String,
+    pub document_metadata: JsonValue,
+    pub chunk_index: i32,
+    pub content: String,
+    pub metadata: JsonValue,
+}
+
+/// A chunk to store: position, text and metadata
+pub struct NewKnowledgeChunk {
+    pub id: String,
+    pub chunk_index: i32,
+    pub content: String,
+    pub metadata: JsonValue,
+}
+
+pub struct KnowledgeRepository {
+    pool: DbPool,
+}
+
+impl KnowledgeRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    // Collections
+
+    pub async fn create_collection(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        agent_id: Option<&str>,
+        embedding_model: &str,
+        embedding_provider: Option<&str>,
+        vector_account_id: Option<&str>,
+        chunking: &JsonValue,
+    ) -> Result<KnowledgeCollectionRow, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeCollectionRow>(
+            r#"
+            INSERT INTO knowledge_collections (id, name, description, agent_id, embedding_model, embedding_provider,
+                vector_account_id, chunking, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(name)
+        .bind(description)
+        .bind(agent_id)
+        .bind(embedding_model)
+        .bind(embedding_provider)
+        .bind(vector_account_id)
+        .bind(chunking)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn find_collection(&self, id: &str) -> Result<Option<KnowledgeCollectionRow>, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeCollectionRow>("SELECT * FROM knowledge_collections WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn list_collections(&self) -> Result<Vec<KnowledgeCollectionRow>, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeCollectionRow>("SELECT * FROM knowledge_collections ORDER BY created_at DESC")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    pub async fn delete_collection(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM knowledge_collections WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Recount documents and embedded chunks after a document changes
+    pub async fn refresh_collection_counts(&self, id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE knowledge_collections SET
+                document_count = (SELECT COUNT(*) FROM knowledge_documents WHERE collection_id = $1),
+                chunk_count = (SELECT COALESCE(SUM(chunk_count), 0) FROM knowledge_documents
+                               WHERE collection_id = $1 AND status = 'completed'),
+                updated_at = NOW()
+            WHERE id = $1
+            "#
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // Documents
+
+    pub async fn create_document(
+        &self,
+        id: &str,
+        collection_id: &str,
+        title: &str,
+        content: &str,
+        content_type: &str,
+        file_size: i64,
+        metadata: &JsonValue,
+        created_by: Option<&str>,
+    ) -> Result<KnowledgeDocumentRow, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeDocumentRow>(
+            r#"
+            INSERT INTO knowledge_documents (id, collection_id, title, content, content_type, file_size, status,
+                metadata, created_by, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, 'pending', $7, $8, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(collection_id)
+        .bind(title)
+        .bind(content)
+        .bind(content_type)
+        .bind(file_size)
+        .bind(metadata)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn find_document(&self, id: &str) -> Result<Option<KnowledgeDocumentRow>, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeDocumentRow>("SELECT * FROM knowledge_documents WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn list_documents(&self, collection_id: &str) -> Result<Vec<KnowledgeDocumentRow>, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeDocumentRow>(
+            "SELECT * FROM knowledge_documents WHERE collection_id = $1 ORDER BY created_at DESC"
+        )
+        .bind(collection_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Lease the oldest document whose ingestion has not finished and is not
+    /// leased to a running replica
+    pub async fn claim_document(&self, owner: &str, lease_secs: i64) -> Result<Option<KnowledgeDocumentRow>, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeDocumentRow>(
+            r#"
+            UPDATE knowledge_documents SET lease_owner = $1,
+                lease_expires_at = NOW() + make_interval(secs => $2)
+            WHERE id = (
+                SELECT id FROM knowledge_documents
+                WHERE status IN ('pending', 'processing') AND (lease_expires_at IS NULL OR lease_expires_at < NOW())
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#
+        )
+        .bind(owner)
+        .bind(lease_secs as f64)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Extend `owner`'s lease on a document; `false` once it has passed to
+    /// another replica or the document is gone
+    pub async fn renew_document_lease(&self, id: &str, owner: &str, lease_secs: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE knowledge_documents SET lease_expires_at = NOW() + make_interval(secs => $1)
+            WHERE id = $2 AND lease_owner = $3
+            "#
+        )
+        .bind(lease_secs as f64)
+        .bind(id)
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Give up `owner`'s lease, leaving the status as is
+    pub async fn release_document(&self, id: &str, owner: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE knowledge_documents SET lease_owner = NULL, lease_expires_at = NULL WHERE id = $1 AND lease_owner = $2"
+        )
+        .bind(id)
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Move a document's job to `status`, resetting progress when it starts
+    pub async fn set_document_status(&self, id: &str, status: &str, error: Option<&str>) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE knowledge_documents SET status = $1, error = $2,
+                chunks_embedded = CASE WHEN $1 = 'processing' THEN 0 ELSE chunks_embedded END,
+                updated_at = NOW()
+            WHERE id = $3
+            "#
+        )
+        .bind(status)
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn set_document_progress(&self, id: &str, chunk_count: i32, chunks_embedded: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE knowledge_documents SET chunk_count = $1, chunks_embedded = $2, updated_at = NOW() WHERE id = $3"
+        )
+        .bind(chunk_count)
+        .bind(chunks_embedded)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_document(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM knowledge_documents WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Chunks
+
+    /// Chunks of a collection's completed documents
+    pub async fn list_searchable_chunks(&self, collection_id: &str) -> Result<Vec<KnowledgeChunkRow>, sqlx::Error> {
+        sqlx::query_as::<_, KnowledgeChunkRow>(
+            r#"
+            SELECT c.id, c.document_id, d.collection_id, d.title, d.metadata AS document_metadata,
+                c.chunk_index, c.content, c.metadata
+            FROM knowledge_chunks c
+            JOIN knowledge_documents d ON d.id = c.document_id
+            WHERE d.collection_id = $1 AND d.status = 'completed'
+            ORDER BY c.document_id, c.chunk_index
+            "#
+        )
+        .bind(collection_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Replace a document's chunks
+    pub async fn replace_chunks(&self, document_id: &str, chunks: &[NewKnowledgeChunk]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM knowledge_chunks WHERE document_id = $1")
+            .bind(document_id)
+            .execute(&mut *tx)
+            .await?;
+
+        for chunk in chunks {
+            sqlx::query(
+                r#"
+                INSERT INTO knowledge_chunks (id, document_id, content, chunk_index, metadata, created_at)
+                VALUES ($1, $2, $3, $4, $5, NOW())
+                "#
+            )
+            .bind(&chunk.id)
+            .bind(document_id)
+            .bind(&chunk.content)
+            .bind(chunk.chunk_index)
+            .bind(&chunk.metadata)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+}
@@ -1,5 +1,8 @@
 //! Database module for PostgreSQL persistence
 
+// Repository inserts bind every column positionally
+#![allow(clippy::too_many_arguments)]
+
 pub mod pool;
 mod users;
 mod sessions;
@@ -8,13 +11,23 @@ mod provider_accounts;
 mod audit;
 mod costs;
 mod applications;
+mod request_logs;
+mod threads;
+mod knowledge;
+mod workflows;
+mod voice;
 
 pub use pool::DbPool;
 pub use users::UserRepository;
 pub use sessions::SessionRepository;
-pub use agents::AgentRepository;
-pub use provider_accounts::{ProviderAccountRepository, ProviderAccountRow};
+pub use agents::{AgentRepository, AgentRow};
+pub use provider_accounts::{ProviderAccountRepository, ProviderAccountRow, ACCOUNTS_CHANGED_CHANNEL};
 pub use audit::AuditRepository;
 pub use costs::CostRepository;
-pub use applications::ApplicationRepository;
+pub use applications::{ApplicationRepository, ApplicationRow};
+pub use request_logs::{RequestLogFilter, RequestLogRepository, RequestLogRow};
+pub use threads::{NewThreadMessage, ThreadMessageRow, ThreadRepository, ThreadRow};
+pub use knowledge::{KnowledgeChunkRow, KnowledgeCollectionRow, KnowledgeDocumentRow, KnowledgeRepository, NewKnowledgeChunk};
+pub use workflows::{NodeExecutionRow, WorkflowExecutionRow, WorkflowRepository, WorkflowRow, WorkflowVersionRow};
 
+pub use voice::{NewTtsVoice, NewVoiceUsage, SttModelRow, TtsVoiceRow, VoiceAccountRow, VoiceProviderRow, VoiceRepository};
@@ -17,7 +17,7 @@ pub async fn init_pool(database_url: &str) -> Result<DbPool, sqlx::Error> {
 }
 
 /// Run database migrations
-pub async fn run_migrations(pool: &DbPool) -> Result<(), sqlx::Error> {
+pub async fn run_migrations(_pool: &DbPool) -> Result<(), sqlx::Error> {
     // The init-db.sql is run by Docker on startup
     // This is for any runtime migrations if needed
     tracing::info!("Database pool initialized successfully");
@@ -4,6 +4,10 @@ use chrono::{DateTime, Utc};
 use sqlx::FromRow;
 use serde_json::Value as JsonValue;
 use super::DbPool;
+use crate::providers::account_manager::{AccountConfig, VectorDbConfig};
+
+/// Channel notified by the `provider_accounts_changed` trigger
+pub const ACCOUNTS_CHANGED_CHANNEL: &str = "provider_accounts_changed";
 
 #[derive(Debug, Clone, FromRow)]
 pub struct ProviderAccountRow {
@@ -25,6 +29,130 @@ pub struct ProviderAccountRow {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Providers whose accounts only serve embeddings or reranking
+const EMBEDDING_ONLY: &[&str] = &["jina", "voyage"];
+
+impl ProviderAccountRow {
+    /// Build a provider for this account, priced from its entry for
+    /// `model`; `None` when no API key is configured or the hub has no
+    /// adapter for the provider
+    pub fn to_provider(&self, model: &str) -> Option<crate::Provider> {
+        let api_key = self.api_key_encrypted.clone().unwrap_or_default();
+        if api_key.is_empty() {
+            return None;
+        }
+        let provider_type = adapter_type(&self.provider_id)?;
+        let base_url = provider_base_url(&self.provider_id, self.endpoint.as_deref())?;
+
+        let models: Vec<crate::ProviderModel> = serde_json::from_value(self.models.clone()).unwrap_or_default();
+        let pricing = models
+            .iter()
+            .find(|m| m.id == model)
+            .map(|m| crate::ProviderPricing {
+                input_token_cost: m.input_token_cost.unwrap_or(0.0),
+                output_token_cost: m.output_token_cost.unwrap_or(0.0),
+            })
+            .unwrap_or_default();
+
+        Some(crate::Provider {
+            id: self.provider_id.clone(),
+            name: self.name.clone(),
+            provider_type,
+            api_key,
+            models,
+            base_url,
+            pricing,
+            enabled: true,
+            health: crate::ProviderHealth::default(),
+            headers: std::collections::HashMap::new(),
+        })
+    }
+
+    /// Whether the account is for an LLM provider the hub can chat with,
+    /// rather than a vector database or an embedding-only service
+    pub fn serves_chat(&self) -> bool {
+        adapter_type(&self.provider_id).is_some() && !EMBEDDING_ONLY.contains(&self.provider_id.as_str())
+    }
+
+    /// Build a provider for the router, priced from the first listed model
+    /// with both token prices
+    ///
+    /// `None` for accounts that do not serve chat or list no prices, so cost
+    /// ranking never sees a made-up price.
+    pub fn to_routed_provider(&self) -> Option<crate::Provider> {
+        if !self.serves_chat() {
+            return None;
+        }
+        let models: Vec<crate::ProviderModel> = serde_json::from_value(self.models.clone()).unwrap_or_default();
+        let priced = models.iter().find(|m| m.input_token_cost.is_some() && m.output_token_cost.is_some())?;
+        self.to_provider(&priced.id)
+    }
+
+    /// Router id of the account, kept apart from its provider's so every
+    /// account has its own health score
+    pub fn route_id(&self) -> String {
+        format!("account:{}", self.id)
+    }
+
+    /// Connection settings for a vector database account, from its stored
+    /// config or else its endpoint and key
+    pub fn vector_db_config(&self) -> Option<VectorDbConfig> {
+        match serde_json::from_value(self.config.clone()) {
+            Ok(AccountConfig::VectorDb(config)) => Some(config),
+            _ => self.endpoint.clone().filter(|url| !url.is_empty()).map(|url| VectorDbConfig {
+                url,
+                api_key: self.api_key_encrypted.clone(),
+                collection_name: None,
+            }),
+        }
+    }
+}
+
+/// Adapter type for a provider id; `None` for providers without an adapter,
+/// such as vector databases
+fn adapter_type(provider_id: &str) -> Option<crate::ProviderType> {
+    Some(match provider_id {
+        // Jina and Voyage speak OpenAI's embeddings API
+        "openai" | "jina" | "voyage" => crate::ProviderType::OpenAI,
+        "anthropic" => crate::ProviderType::Anthropic,
+        "mistral" => crate::ProviderType::Mistral,
+        "cohere" => crate::ProviderType::Cohere,
+        "groq" => crate::ProviderType::Groq,
+        "together" => crate::ProviderType::Together,
+        "gemini" => crate::ProviderType::Gemini,
+        "azure" | "azure-openai" => crate::ProviderType::AzureOpenAI,
+        "bedrock" => crate::ProviderType::Bedrock,
+        "local" | "ollama" => crate::ProviderType::Local,
+        _ => return None,
+    })
+}
+
+/// Base URL for a provider, honouring a custom account endpoint; Azure and
+/// Bedrock accounts must set their own
+fn provider_base_url(provider_id: &str, custom_endpoint: Option<&str>) -> Option<String> {
+    if let Some(endpoint) = custom_endpoint {
+        if !endpoint.is_empty() {
+            return Some(endpoint.to_string());
+        }
+    }
+
+    let url = match provider_id {
+        "openai" => "https://api.openai.com/v1",
+        "anthropic" => "https://api.anthropic.com/v1",
+        "mistral" => "https://api.mistral.ai/v1",
+        "cohere" => "https://api.cohere.ai/v1",
+        "jina" => "https://api.jina.ai/v1",
+        "voyage" => "https://api.voyageai.com/v1",
+        "groq" => "https://api.groq.com/openai/v1",
+        "together" => "https://api.together.xyz/v1",
+        // The Gemini adapter appends the API version itself
+        "gemini" => "https://generativelanguage.googleapis.com",
+        "local" | "ollama" => "http://localhost:11434",
+        _ => return None,
+    };
+    Some(url.to_string())
+}
+
 pub struct ProviderAccountRepository {
     pool: DbPool,
 }
@@ -0,0 +1,139 @@
+//! Request log repository for database operations
+
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, Postgres, QueryBuilder};
+use serde_json::Value as JsonValue;
+use super::DbPool;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RequestLogRow {
+    pub id: String,
+    pub application_id: String,
+    pub user_id: Option<String>,
+    pub request_id: String,
+    pub model: String,
+    pub provider: Option<String>,
+    /// Provider or account that produced the response
+    pub account: Option<String>,
+    /// "success" or "error"
+    pub status: String,
+    pub error: Option<String>,
+    pub request: JsonValue,
+    pub response: Option<JsonValue>,
+    pub attempts: JsonValue,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub cost: f64,
+    pub latency_ms: i64,
+    pub cache_status: Option<String>,
+    pub streamed: bool,
+    pub redacted: bool,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Search criteria; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct RequestLogFilter {
+    pub application_id: Option<String>,
+    pub user_id: Option<String>,
+    pub model: Option<String>,
+    pub status: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+pub struct RequestLogRepository {
+    pool: DbPool,
+}
+
+impl RequestLogRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    #[tracing::instrument(name = "db.insert", skip_all, fields(db.table = "request_logs"))]
+    pub async fn create(&self, row: &RequestLogRow) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO request_logs (id, application_id, user_id, request_id, model, provider, account, status, error,
+                request, response, attempts, prompt_tokens, completion_tokens, cost, latency_ms, cache_status,
+                streamed, redacted, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
+            "#
+        )
+        .bind(&row.id)
+        .bind(&row.application_id)
+        .bind(&row.user_id)
+        .bind(&row.request_id)
+        .bind(&row.model)
+        .bind(&row.provider)
+        .bind(&row.account)
+        .bind(&row.status)
+        .bind(&row.error)
+        .bind(&row.request)
+        .bind(&row.response)
+        .bind(&row.attempts)
+        .bind(row.prompt_tokens)
+        .bind(row.completion_tokens)
+        .bind(row.cost)
+        .bind(row.latency_ms)
+        .bind(&row.cache_status)
+        .bind(row.streamed)
+        .bind(row.redacted)
+        .bind(row.created_at)
+        .bind(row.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<RequestLogRow>, sqlx::Error> {
+        sqlx::query_as::<_, RequestLogRow>("SELECT * FROM request_logs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Logs matching `filter`, newest first
+    pub async fn search(&self, filter: &RequestLogFilter) -> Result<Vec<RequestLogRow>, sqlx::Error> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT * FROM request_logs WHERE expires_at > NOW()");
+
+        if let Some(ref application_id) = filter.application_id {
+            query.push(" AND application_id = ").push_bind(application_id.clone());
+        }
+        if let Some(ref user_id) = filter.user_id {
+            query.push(" AND user_id = ").push_bind(user_id.clone());
+        }
+        if let Some(ref model) = filter.model {
+            query.push(" AND model = ").push_bind(model.clone());
+        }
+        if let Some(ref status) = filter.status {
+            query.push(" AND status = ").push_bind(status.clone());
+        }
+        if let Some(from) = filter.from {
+            query.push(" AND created_at >= ").push_bind(from);
+        }
+        if let Some(to) = filter.to {
+            query.push(" AND created_at < ").push_bind(to);
+        }
+
+        query
+            .push(" ORDER BY created_at DESC LIMIT ")
+            .push_bind(filter.limit)
+            .push(" OFFSET ")
+            .push_bind(filter.offset);
+
+        query.build_query_as::<RequestLogRow>().fetch_all(&self.pool).await
+    }
+
+    /// Delete logs past their retention; returns how many were removed
+    pub async fn purge_expired(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM request_logs WHERE expires_at <= NOW()")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
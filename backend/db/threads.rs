@@ -0,0 +1,154 @@
+//! Agent conversation thread repository
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use serde_json::Value as JsonValue;
+use super::DbPool;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ThreadRow {
+    pub id: String,
+    pub agent_id: String,
+    pub user_id: String,
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    /// Messages covered by `summary`, counted from the start
+    pub summarized_count: i32,
+    pub message_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ThreadMessageRow {
+    pub id: String,
+    pub thread_id: String,
+    pub position: i32,
+    pub role: String,
+    pub message: JsonValue,
+    pub token_count: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A message to append: role, serialized message and estimated tokens
+pub struct NewThreadMessage {
+    pub role: String,
+    pub message: JsonValue,
+    pub token_count: i32,
+}
+
+pub struct ThreadRepository {
+    pool: DbPool,
+}
+
+impl ThreadRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, id: &str, agent_id: &str, user_id: &str, title: Option<&str>) -> Result<ThreadRow, sqlx::Error> {
+        sqlx::query_as::<_, ThreadRow>(
+            r#"
+            INSERT INTO agent_threads (id, agent_id, user_id, title, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, NOW(), NOW())
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(agent_id)
+        .bind(user_id)
+        .bind(title)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<ThreadRow>, sqlx::Error> {
+        sqlx::query_as::<_, ThreadRow>("SELECT * FROM agent_threads WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// A user's threads with an agent, most recently active first
+    pub async fn list_by_owner(&self, agent_id: &str, user_id: &str) -> Result<Vec<ThreadRow>, sqlx::Error> {
+        sqlx::query_as::<_, ThreadRow>(
+            "SELECT * FROM agent_threads WHERE agent_id = $1 AND user_id = $2 ORDER BY updated_at DESC"
+        )
+        .bind(agent_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Messages in conversation order
+    pub async fn list_messages(&self, thread_id: &str) -> Result<Vec<ThreadMessageRow>, sqlx::Error> {
+        sqlx::query_as::<_, ThreadMessageRow>(
+            "SELECT * FROM agent_thread_messages WHERE thread_id = $1 ORDER BY position"
+        )
+        .bind(thread_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Append messages after the last one, returning the updated thread
+    ///
+    /// The thread row is locked for the transaction so concurrent turns get
+    /// consecutive positions.
+    pub async fn append_messages(&self, thread_id: &str, messages: &[NewThreadMessage]) -> Result<ThreadRow, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let (count,): (i32,) = sqlx::query_as("SELECT message_count FROM agent_threads WHERE id = $1 FOR UPDATE")
+            .bind(thread_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        for (offset, message) in messages.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO agent_thread_messages (id, thread_id, position, role, message, token_count, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, NOW())
+                "#
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(thread_id)
+            .bind(count + offset as i32)
+            .bind(&message.role)
+            .bind(&message.message)
+            .bind(message.token_count)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let thread = sqlx::query_as::<_, ThreadRow>(
+            "UPDATE agent_threads SET message_count = $1, updated_at = NOW() WHERE id = $2 RETURNING *"
+        )
+        .bind(count + messages.len() as i32)
+        .bind(thread_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(thread)
+    }
+
+    /// Replace the rolling summary, now covering the first `summarized_count` messages
+    pub async fn update_summary(&self, thread_id: &str, summary: &str, summarized_count: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE agent_threads SET summary = $1, summarized_count = $2, updated_at = NOW() WHERE id = $3"
+        )
+        .bind(summary)
+        .bind(summarized_count)
+        .bind(thread_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM agent_threads WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
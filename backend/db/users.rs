@@ -115,6 +115,22 @@ impl UserRepository {
         Ok(row.0)
     }
 
+    /// Permissions of an enabled user, from the role named on the user and
+    /// any roles assigned in `user_roles`
+    pub async fn permissions(&self, id: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT jsonb_array_elements_text(r.permissions)
+            FROM users u
+            JOIN roles r ON r.name = u.role OR r.id IN (SELECT role_id FROM user_roles WHERE user_id = u.id)
+            WHERE u.id = $1 AND u.enabled
+            "#
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
     pub async fn count_by_role(&self, role: &str) -> Result<i64, sqlx::Error> {
         let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE role = $1")
             .bind(role)
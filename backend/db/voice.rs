@@ -0,0 +1,474 @@
+//! Speech provider, account, voice and usage repository
+//!
+//! TTS and STT keep providers and accounts in tables of the same shape, so
+//! the queries take a [`VoiceKind`] to pick the tables.
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use serde_json::Value as JsonValue;
+use super::DbPool;
+use crate::voice::{self, VoiceKind};
+
+#[derive(Debug, Clone, FromRow)]
+pub struct VoiceProviderRow {
+    pub id: String,
+    pub name: String,
+    pub provider_type: String,
+    pub description: Option<String>,
+    pub api_base_url: Option<String>,
+    pub supports_streaming: bool,
+    /// Always false for TTS
+    pub supports_diarization: bool,
+    /// USD per million characters (TTS) or per hour of audio (STT)
+    pub price: f64,
+    pub enabled: bool,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct VoiceAccountRow {
+    pub id: String,
+    pub provider_id: String,
+    pub name: String,
+    pub api_key_encrypted: String,
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub quota_config: JsonValue,
+    pub enabled: bool,
+    pub is_default: bool,
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct TtsVoiceRow {
+    pub id: String,
+    pub provider_id: String,
+    pub voice_id: String,
+    pub name: String,
+    pub language: String,
+    pub gender: Option<String>,
+    pub age: Option<String>,
+    pub style: Option<String>,
+    pub description: Option<String>,
+    pub preview_url: Option<String>,
+    pub quality_tier: String,
+    pub usage_count: i64,
+    pub is_custom: bool,
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct SttModelRow {
+    pub id: String,
+    pub provider_id: String,
+    pub model_id: String,
+    pub name: String,
+    pub languages: JsonValue,
+    pub word_error_rate: Option<f64>,
+    pub latency_ms: Option<i32>,
+    pub supports_streaming: bool,
+    pub supports_diarization: bool,
+    pub max_audio_length_seconds: Option<i32>,
+    pub quality_tier: String,
+}
+
+/// A voice as listed by its provider
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NewTtsVoice {
+    pub voice_id: String,
+    pub name: String,
+    pub language: String,
+    pub gender: Option<String>,
+    pub age: Option<String>,
+    pub description: Option<String>,
+    pub preview_url: Option<String>,
+    pub quality_tier: String,
+    pub is_custom: bool,
+}
+
+/// One billed TTS or STT request
+pub struct NewVoiceUsage<'a> {
+    pub kind: VoiceKind,
+    pub user_id: &'a str,
+    pub provider_id: &'a str,
+    pub model_or_voice_id: Option<&'a str>,
+    /// Characters for TTS, seconds of audio for STT
+    pub amount: u64,
+    pub cost: f64,
+    pub request_id: &'a str,
+    pub language: Option<&'a str>,
+}
+
+const ACCOUNT_COLUMNS: &str =
+    "id, provider_id, name, api_key_encrypted, endpoint, region, COALESCE(quota_config, '{}') AS quota_config, \
+     COALESCE(enabled, true) AS enabled, COALESCE(is_default, false) AS is_default, COALESCE(priority, 0) AS priority, created_at";
+
+const VOICE_COLUMNS: &str =
+    "id, provider_id, voice_id, name, language, gender, age, style, description, preview_url, \
+     COALESCE(quality_tier, 'standard') AS quality_tier, COALESCE(usage_count, 0) AS usage_count, \
+     COALESCE(is_custom, false) AS is_custom, COALESCE(enabled, true) AS enabled";
+
+const MODEL_COLUMNS: &str =
+    "id, provider_id, model_id, name, COALESCE(languages, '[]') AS languages, word_error_rate::float8 AS word_error_rate, latency_ms, \
+     COALESCE(supports_streaming, false) AS supports_streaming, COALESCE(supports_diarization, false) AS supports_diarization, \
+     max_audio_length_seconds, COALESCE(quality_tier, 'standard') AS quality_tier";
+
+fn provider_columns(kind: VoiceKind) -> String {
+    let (price, diarization) = match kind {
+        VoiceKind::Tts => ("price_per_million_chars", "false"),
+        VoiceKind::Stt => ("price_per_hour", "COALESCE(supports_diarization, false)"),
+    };
+    format!(
+        "id, name, provider_type, description, api_base_url, COALESCE(supports_streaming, false) AS supports_streaming, \
+         {diarization} AS supports_diarization, COALESCE({price}, 0)::float8 AS price, \
+         COALESCE(enabled, true) AS enabled, COALESCE(is_default, false) AS is_default"
+    )
+}
+
+pub struct VoiceRepository {
+    pool: DbPool,
+}
+
+impl VoiceRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    // Providers
+
+    pub async fn list_providers(&self, kind: VoiceKind) -> Result<Vec<VoiceProviderRow>, sqlx::Error> {
+        sqlx::query_as::<_, VoiceProviderRow>(&format!(
+            "SELECT {} FROM {} ORDER BY is_default DESC, name",
+            provider_columns(kind),
+            kind.providers_table(),
+        ))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn get_provider(&self, kind: VoiceKind, id: &str) -> Result<Option<VoiceProviderRow>, sqlx::Error> {
+        sqlx::query_as::<_, VoiceProviderRow>(&format!(
+            "SELECT {} FROM {} WHERE id = $1",
+            provider_columns(kind),
+            kind.providers_table(),
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// The enabled provider with this id, or else the default one of this
+    /// type
+    pub async fn find_provider(&self, kind: VoiceKind, id_or_type: &str) -> Result<Option<VoiceProviderRow>, sqlx::Error> {
+        sqlx::query_as::<_, VoiceProviderRow>(&format!(
+            "SELECT {} FROM {} WHERE enabled IS NOT FALSE AND (id = $1 OR provider_type = $1) \
+             ORDER BY id = $1 DESC, is_default DESC, name LIMIT 1",
+            provider_columns(kind),
+            kind.providers_table(),
+        ))
+        .bind(id_or_type)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// The default enabled provider, or the first one if none is marked
+    pub async fn default_provider(&self, kind: VoiceKind) -> Result<Option<VoiceProviderRow>, sqlx::Error> {
+        sqlx::query_as::<_, VoiceProviderRow>(&format!(
+            "SELECT {} FROM {} WHERE enabled IS NOT FALSE ORDER BY is_default DESC, name LIMIT 1",
+            provider_columns(kind),
+            kind.providers_table(),
+        ))
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Accounts
+
+    pub async fn create_account(
+        &self,
+        kind: VoiceKind,
+        id: &str,
+        provider_id: &str,
+        name: &str,
+        api_key_encrypted: &str,
+        endpoint: Option<&str>,
+        region: Option<&str>,
+        quota_config: &JsonValue,
+        is_default: bool,
+        priority: i32,
+    ) -> Result<VoiceAccountRow, sqlx::Error> {
+        sqlx::query_as::<_, VoiceAccountRow>(&format!(
+            r#"
+            INSERT INTO {} (id, provider_id, name, api_key_encrypted, endpoint, region, quota_config, is_default, priority, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW(), NOW())
+            RETURNING {}
+            "#,
+            kind.accounts_table(),
+            ACCOUNT_COLUMNS,
+        ))
+        .bind(id)
+        .bind(provider_id)
+        .bind(name)
+        .bind(api_key_encrypted)
+        .bind(endpoint)
+        .bind(region)
+        .bind(quota_config)
+        .bind(is_default)
+        .bind(priority)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn list_accounts(&self, kind: VoiceKind, provider_id: Option<&str>) -> Result<Vec<VoiceAccountRow>, sqlx::Error> {
+        sqlx::query_as::<_, VoiceAccountRow>(&format!(
+            "SELECT {} FROM {} WHERE $1::text IS NULL OR provider_id = $1 ORDER BY provider_id, is_default DESC, priority DESC, created_at",
+            ACCOUNT_COLUMNS,
+            kind.accounts_table(),
+        ))
+        .bind(provider_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn delete_account(&self, kind: VoiceKind, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(&format!("DELETE FROM {} WHERE id = $1", kind.accounts_table()))
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Take `amount` from the quota of the first enabled account of a
+    /// provider that has room for it: the default account first, then by
+    /// priority. `None` when every account is exhausted or there is none.
+    ///
+    /// The provider's accounts are locked for the transaction so concurrent
+    /// requests cannot both spend the last of a quota.
+    pub async fn reserve_account(&self, kind: VoiceKind, provider_id: &str, amount: u64) -> Result<Option<VoiceAccountRow>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let accounts = sqlx::query_as::<_, VoiceAccountRow>(&format!(
+            "SELECT {} FROM {} WHERE provider_id = $1 AND enabled IS NOT FALSE \
+             ORDER BY is_default DESC, priority DESC, created_at FOR UPDATE",
+            ACCOUNT_COLUMNS,
+            kind.accounts_table(),
+        ))
+        .bind(provider_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let now = Utc::now();
+        for mut account in accounts {
+            let quotas = match voice::parse_quotas(&account.quota_config) {
+                Ok(quotas) => quotas,
+                Err(e) => {
+                    tracing::warn!("Skipping {} account {} with invalid quota_config: {}", kind.as_str(), account.id, e);
+                    continue;
+                }
+            };
+            let Some(quotas) = voice::reserve(&quotas, amount, now) else {
+                continue;
+            };
+            account.quota_config = serde_json::to_value(&quotas).unwrap_or_default();
+            sqlx::query(&format!("UPDATE {} SET quota_config = $1, updated_at = NOW() WHERE id = $2", kind.accounts_table()))
+                .bind(&account.quota_config)
+                .bind(&account.id)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            return Ok(Some(account));
+        }
+
+        tx.commit().await?;
+        Ok(None)
+    }
+
+    /// Correct an account's quota usage by `delta`
+    pub async fn adjust_quota(&self, kind: VoiceKind, account_id: &str, delta: i64) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let config: Option<JsonValue> = sqlx::query_scalar(&format!(
+            "SELECT COALESCE(quota_config, '{{}}') FROM {} WHERE id = $1 FOR UPDATE",
+            kind.accounts_table(),
+        ))
+        .bind(account_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if let Some(Ok(mut quotas)) = config.as_ref().map(voice::parse_quotas) {
+            voice::adjust(&mut quotas, delta, Utc::now());
+            sqlx::query(&format!("UPDATE {} SET quota_config = $1, updated_at = NOW() WHERE id = $2", kind.accounts_table()))
+                .bind(serde_json::to_value(&quotas).unwrap_or_default())
+                .bind(account_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await
+    }
+
+    // Voices
+
+    pub async fn list_voices(&self, provider_id: Option<&str>, language: Option<&str>) -> Result<Vec<TtsVoiceRow>, sqlx::Error> {
+        sqlx::query_as::<_, TtsVoiceRow>(&format!(
+            r#"
+            SELECT {} FROM tts_voices
+            WHERE enabled IS NOT FALSE
+              AND ($1::text IS NULL OR provider_id = $1)
+              AND ($2::text IS NULL OR language ILIKE $2 || '%')
+            ORDER BY provider_id, language, name
+            "#,
+            VOICE_COLUMNS,
+        ))
+        .bind(provider_id)
+        .bind(language)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// An enabled voice by catalog id or provider voice id, optionally
+    /// within one provider
+    pub async fn find_voice(&self, voice: &str, provider_id: Option<&str>) -> Result<Option<TtsVoiceRow>, sqlx::Error> {
+        sqlx::query_as::<_, TtsVoiceRow>(&format!(
+            r#"
+            SELECT {} FROM tts_voices
+            WHERE enabled IS NOT FALSE
+              AND (id = $1 OR voice_id = $1)
+              AND ($2::text IS NULL OR provider_id = $2)
+              AND provider_id IN (SELECT id FROM tts_providers WHERE enabled IS NOT FALSE)
+            ORDER BY id = $1 DESC, usage_count DESC NULLS LAST
+            LIMIT 1
+            "#,
+            VOICE_COLUMNS,
+        ))
+        .bind(voice)
+        .bind(provider_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Replace a provider's catalog with the voices it lists: new voices are
+    /// added, known ones updated, and ones it no longer lists disabled.
+    /// Returns how many voices were disabled.
+    pub async fn sync_voices(&self, provider_id: &str, voices: &[NewTtsVoice]) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for voice in voices {
+            sqlx::query(
+                r#"
+                INSERT INTO tts_voices (id, provider_id, voice_id, name, language, gender, age, description, preview_url, quality_tier, is_custom, enabled, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, true, NOW())
+                ON CONFLICT (provider_id, voice_id) DO UPDATE SET
+                    name = EXCLUDED.name,
+                    language = EXCLUDED.language,
+                    gender = COALESCE(EXCLUDED.gender, tts_voices.gender),
+                    age = COALESCE(EXCLUDED.age, tts_voices.age),
+                    description = COALESCE(EXCLUDED.description, tts_voices.description),
+                    preview_url = COALESCE(EXCLUDED.preview_url, tts_voices.preview_url),
+                    quality_tier = EXCLUDED.quality_tier,
+                    is_custom = EXCLUDED.is_custom,
+                    enabled = true
+                "#
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(provider_id)
+            .bind(&voice.voice_id)
+            .bind(&voice.name)
+            .bind(&voice.language)
+            .bind(&voice.gender)
+            .bind(&voice.age)
+            .bind(&voice.description)
+            .bind(&voice.preview_url)
+            .bind(&voice.quality_tier)
+            .bind(voice.is_custom)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let voice_ids: Vec<&str> = voices.iter().map(|voice| voice.voice_id.as_str()).collect();
+        let disabled = sqlx::query(
+            "UPDATE tts_voices SET enabled = false WHERE provider_id = $1 AND enabled IS NOT FALSE AND NOT (voice_id = ANY($2))"
+        )
+        .bind(provider_id)
+        .bind(&voice_ids)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+        Ok(disabled)
+    }
+
+    pub async fn increment_voice_usage(&self, provider_id: &str, voice_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE tts_voices SET usage_count = COALESCE(usage_count, 0) + 1 WHERE provider_id = $1 AND voice_id = $2")
+            .bind(provider_id)
+            .bind(voice_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // STT models
+
+    pub async fn list_models(&self, provider_id: Option<&str>) -> Result<Vec<SttModelRow>, sqlx::Error> {
+        sqlx::query_as::<_, SttModelRow>(&format!(
+            "SELECT {} FROM stt_models WHERE enabled IS NOT FALSE AND ($1::text IS NULL OR provider_id = $1) ORDER BY provider_id, name",
+            MODEL_COLUMNS,
+        ))
+        .bind(provider_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// An enabled model by catalog id or provider model id, optionally
+    /// within one provider
+    pub async fn find_model(&self, model: &str, provider_id: Option<&str>) -> Result<Option<SttModelRow>, sqlx::Error> {
+        sqlx::query_as::<_, SttModelRow>(&format!(
+            r#"
+            SELECT {} FROM stt_models
+            WHERE enabled IS NOT FALSE
+              AND (id = $1 OR model_id = $1)
+              AND ($2::text IS NULL OR provider_id = $2)
+              AND provider_id IN (SELECT id FROM stt_providers WHERE enabled IS NOT FALSE)
+            ORDER BY id = $1 DESC, provider_id IN (SELECT id FROM stt_providers WHERE is_default) DESC
+            LIMIT 1
+            "#,
+            MODEL_COLUMNS,
+        ))
+        .bind(model)
+        .bind(provider_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Usage
+
+    /// Record a request; a user id without a `users` row is stored as NULL
+    pub async fn record_usage(&self, usage: &NewVoiceUsage<'_>) -> Result<(), sqlx::Error> {
+        let amount = i32::try_from(usage.amount).unwrap_or(i32::MAX);
+        let (characters, seconds) = match usage.kind {
+            VoiceKind::Tts => (amount, 0),
+            VoiceKind::Stt => (0, amount),
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO voice_usage (id, user_id, usage_type, provider_id, model_or_voice_id, characters_processed, audio_duration_seconds, cost, request_id, language, created_at)
+            VALUES ($1, (SELECT id FROM users WHERE id = $2), $3, $4, $5, $6, $7, $8::float8::numeric, $9, $10, NOW())
+            "#
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(usage.user_id)
+        .bind(usage.kind.as_str())
+        .bind(usage.provider_id)
+        .bind(usage.model_or_voice_id)
+        .bind(characters)
+        .bind(seconds)
+        .bind(usage.cost)
+        .bind(usage.request_id)
+        .bind(usage.language)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
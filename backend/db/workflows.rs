@@ -0,0 +1,550 @@
+//! Workflow, version and execution repository
+
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use serde_json::Value as JsonValue;
+use super::DbPool;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct WorkflowRow {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub nodes: JsonValue,
+    pub edges: JsonValue,
+    pub status: String,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct WorkflowVersionRow {
+    pub workflow_id: String,
+    pub version: i32,
+    pub nodes: JsonValue,
+    pub edges: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct WorkflowExecutionRow {
+    pub id: String,
+    pub workflow_id: String,
+    pub workflow_version: i32,
+    pub status: String,
+    pub variables: JsonValue,
+    pub results: JsonValue,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub started_by: Option<String>,
+    pub lease_owner: Option<String>,
+    pub lease_expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct NodeExecutionRow {
+    pub id: String,
+    pub execution_id: String,
+    pub node_id: String,
+    pub status: String,
+    pub input: JsonValue,
+    pub output: JsonValue,
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub struct WorkflowRepository {
+    pool: DbPool,
+}
+
+impl WorkflowRepository {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    // Workflows
+
+    /// Create a workflow with its definition saved as version 1
+    pub async fn create_workflow(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        nodes: &JsonValue,
+        edges: &JsonValue,
+        status: &str,
+        created_by: Option<&str>,
+    ) -> Result<WorkflowRow, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query_as::<_, WorkflowRow>(
+            r#"
+            INSERT INTO workflows (id, name, description, nodes, edges, status, version, created_at, updated_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, 1, NOW(), NOW(), $7)
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(name)
+        .bind(description)
+        .bind(nodes)
+        .bind(edges)
+        .bind(status)
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO workflow_versions (workflow_id, version, nodes, edges, created_at, created_by) VALUES ($1, 1, $2, $3, NOW(), $4)"
+        )
+        .bind(id)
+        .bind(nodes)
+        .bind(edges)
+        .bind(created_by)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    pub async fn find_workflow(&self, id: &str) -> Result<Option<WorkflowRow>, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowRow>("SELECT * FROM workflows WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn list_workflows(&self) -> Result<Vec<WorkflowRow>, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowRow>("SELECT * FROM workflows ORDER BY updated_at DESC")
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Update a workflow; a new definition is saved as the next version
+    pub async fn update_workflow(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+        status: &str,
+        definition: Option<(&JsonValue, &JsonValue)>,
+        updated_by: Option<&str>,
+    ) -> Result<Option<WorkflowRow>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let row = match definition {
+            None => sqlx::query_as::<_, WorkflowRow>(
+                r#"
+                UPDATE workflows SET name = $1, description = $2, status = $3, updated_at = NOW()
+                WHERE id = $4
+                RETURNING *
+                "#
+            )
+            .bind(name)
+            .bind(description)
+            .bind(status)
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?,
+            Some((nodes, edges)) => {
+                let row = sqlx::query_as::<_, WorkflowRow>(
+                    r#"
+                    UPDATE workflows SET name = $1, description = $2, status = $3, nodes = $4, edges = $5,
+                        version = version + 1, updated_at = NOW()
+                    WHERE id = $6
+                    RETURNING *
+                    "#
+                )
+                .bind(name)
+                .bind(description)
+                .bind(status)
+                .bind(nodes)
+                .bind(edges)
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?;
+
+                if let Some(ref row) = row {
+                    sqlx::query(
+                        "INSERT INTO workflow_versions (workflow_id, version, nodes, edges, created_at, created_by) VALUES ($1, $2, $3, $4, NOW(), $5)"
+                    )
+                    .bind(id)
+                    .bind(row.version)
+                    .bind(nodes)
+                    .bind(edges)
+                    .bind(updated_by)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                row
+            }
+        };
+        tx.commit().await?;
+        Ok(row)
+    }
+
+    pub async fn delete_workflow(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM workflows WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Versions
+
+    pub async fn list_versions(&self, workflow_id: &str) -> Result<Vec<WorkflowVersionRow>, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowVersionRow>(
+            "SELECT * FROM workflow_versions WHERE workflow_id = $1 ORDER BY version DESC"
+        )
+        .bind(workflow_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn find_version(&self, workflow_id: &str, version: i32) -> Result<Option<WorkflowVersionRow>, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowVersionRow>(
+            "SELECT * FROM workflow_versions WHERE workflow_id = $1 AND version = $2"
+        )
+        .bind(workflow_id)
+        .bind(version)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Executions
+
+    pub async fn create_execution(
+        &self,
+        id: &str,
+        workflow_id: &str,
+        workflow_version: i32,
+        variables: &JsonValue,
+        started_by: Option<&str>,
+    ) -> Result<WorkflowExecutionRow, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowExecutionRow>(
+            r#"
+            INSERT INTO workflow_executions (id, workflow_id, workflow_version, status, variables, results, started_at, started_by)
+            VALUES ($1, $2, $3, 'pending', $4, '{}', NOW(), $5)
+            RETURNING *
+            "#
+        )
+        .bind(id)
+        .bind(workflow_id)
+        .bind(workflow_version)
+        .bind(variables)
+        .bind(started_by)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    pub async fn find_execution(&self, id: &str) -> Result<Option<WorkflowExecutionRow>, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowExecutionRow>("SELECT * FROM workflow_executions WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    pub async fn list_executions(&self, workflow_id: &str, limit: i64) -> Result<Vec<WorkflowExecutionRow>, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowExecutionRow>(
+            "SELECT * FROM workflow_executions WHERE workflow_id = $1 ORDER BY started_at DESC LIMIT $2"
+        )
+        .bind(workflow_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Lease the oldest pending execution, or a running one whose lease
+    /// expired, to `owner`, marking it running
+    ///
+    /// A pending execution still leased is one resumed while its previous
+    /// run finishes its last nodes; it waits for that run to let go.
+    pub async fn claim_execution(&self, owner: &str, lease_secs: i64) -> Result<Option<WorkflowExecutionRow>, sqlx::Error> {
+        sqlx::query_as::<_, WorkflowExecutionRow>(
+            r#"
+            UPDATE workflow_executions SET status = 'running', lease_owner = $1,
+                lease_expires_at = NOW() + make_interval(secs => $2)
+            WHERE id = (
+                SELECT id FROM workflow_executions
+                WHERE status IN ('pending', 'running') AND (lease_expires_at IS NULL OR lease_expires_at < NOW())
+                ORDER BY started_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#
+        )
+        .bind(owner)
+        .bind(lease_secs as f64)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Extend `owner`'s lease; returns the execution's status, or `None` once
+    /// the lease has passed to another replica
+    pub async fn renew_lease(&self, id: &str, owner: &str, lease_secs: i64) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE workflow_executions SET lease_expires_at = NOW() + make_interval(secs => $1)
+            WHERE id = $2 AND lease_owner = $3
+            RETURNING status
+            "#
+        )
+        .bind(lease_secs as f64)
+        .bind(id)
+        .bind(owner)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Record the outcome of a run still leased to `owner` and not paused or
+    /// cancelled in the meantime
+    pub async fn finish_execution(
+        &self,
+        id: &str,
+        owner: &str,
+        status: &str,
+        results: Option<&JsonValue>,
+        error: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE workflow_executions SET status = $1, results = COALESCE($2, results), error = $3,
+                completed_at = NOW(), lease_owner = NULL, lease_expires_at = NULL
+            WHERE id = $4 AND lease_owner = $5 AND status = 'running'
+            "#
+        )
+        .bind(status)
+        .bind(results)
+        .bind(error)
+        .bind(id)
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Park a run still leased to `owner` until an approval node is
+    /// decided, giving up the lease
+    pub async fn wait_execution(&self, id: &str, owner: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE workflow_executions SET status = 'waiting', lease_owner = NULL, lease_expires_at = NULL
+            WHERE id = $1 AND lease_owner = $2 AND status = 'running'
+            "#
+        )
+        .bind(id)
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Fail an execution that is not over, wherever it is running
+    pub async fn fail_execution(&self, id: &str, error: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE workflow_executions SET status = 'failed', error = $1, completed_at = NOW()
+            WHERE id = $2 AND status IN ('pending', 'running', 'waiting', 'paused')
+            "#
+        )
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Give up `owner`'s lease, leaving the status as is
+    pub async fn release_execution(&self, id: &str, owner: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE workflow_executions SET lease_owner = NULL, lease_expires_at = NULL WHERE id = $1 AND lease_owner = $2"
+        )
+        .bind(id)
+        .bind(owner)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Move an execution from one of `from` to `to`; `None` if it is in
+    /// another status
+    ///
+    /// Returning to `pending` clears the error so a worker picks the
+    /// execution up again; `cancelled` completes it. The lease stays with
+    /// the replica running the execution, which lets go once it notices.
+    pub async fn transition_execution(&self, id: &str, from: &[&str], to: &str) -> Result<Option<WorkflowExecutionRow>, sqlx::Error> {
+        let from: Vec<String> = from.iter().map(|s| s.to_string()).collect();
+        sqlx::query_as::<_, WorkflowExecutionRow>(
+            r#"
+            UPDATE workflow_executions SET status = $1,
+                error = CASE WHEN $1 = 'pending' THEN NULL ELSE error END,
+                completed_at = CASE WHEN $1 = 'cancelled' THEN NOW() ELSE NULL END
+            WHERE id = $2 AND status = ANY($3)
+            RETURNING *
+            "#
+        )
+        .bind(to)
+        .bind(id)
+        .bind(&from)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    // Node executions
+
+    /// Record a node attempt starting; returns the node's record id
+    pub async fn start_node(
+        &self,
+        id: &str,
+        execution_id: &str,
+        node_id: &str,
+        input: &JsonValue,
+    ) -> Result<String, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO node_executions (id, execution_id, node_id, status, input, output, attempts, started_at)
+            VALUES ($1, $2, $3, 'running', $4, '{}', 1, NOW())
+            ON CONFLICT (execution_id, node_id) DO UPDATE SET status = 'running', input = $4, output = '{}',
+                error = NULL, attempts = node_executions.attempts + 1, started_at = NOW(), completed_at = NULL
+            RETURNING id
+            "#
+        )
+        .bind(id)
+        .bind(execution_id)
+        .bind(node_id)
+        .bind(input)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    /// Record a node left out because no branch leading to it was taken
+    pub async fn skip_node(&self, id: &str, execution_id: &str, node_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO node_executions (id, execution_id, node_id, status, input, output, started_at, completed_at)
+            VALUES ($1, $2, $3, 'skipped', '{}', '{}', NOW(), NOW())
+            ON CONFLICT (execution_id, node_id) DO UPDATE SET status = 'skipped', completed_at = NOW()
+            "#
+        )
+        .bind(id)
+        .bind(execution_id)
+        .bind(node_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record an approval node waiting for a decision, due by
+    /// `timeout_secs` from now when set
+    pub async fn wait_node(
+        &self,
+        id: &str,
+        execution_id: &str,
+        node_id: &str,
+        input: &JsonValue,
+        timeout_secs: Option<u64>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO node_executions (id, execution_id, node_id, status, input, output, attempts, started_at, expires_at)
+            VALUES ($1, $2, $3, 'waiting', $4, '{}', 1, NOW(), NOW() + make_interval(secs => $5))
+            ON CONFLICT (execution_id, node_id) DO UPDATE SET status = 'waiting', input = $4, output = '{}',
+                error = NULL, attempts = node_executions.attempts + 1, started_at = NOW(), completed_at = NULL,
+                expires_at = NOW() + make_interval(secs => $5)
+            "#
+        )
+        .bind(id)
+        .bind(execution_id)
+        .bind(node_id)
+        .bind(input)
+        .bind(timeout_secs.map(|secs| secs as f64))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Record the decision on a waiting approval node; `None` if the node is
+    /// not waiting, e.g. because someone else decided first
+    pub async fn decide_node(
+        &self,
+        execution_id: &str,
+        node_id: &str,
+        status: &str,
+        output: &JsonValue,
+        error: Option<&str>,
+    ) -> Result<Option<NodeExecutionRow>, sqlx::Error> {
+        sqlx::query_as::<_, NodeExecutionRow>(
+            r#"
+            UPDATE node_executions SET status = $1, output = $2, error = $3, completed_at = NOW()
+            WHERE execution_id = $4 AND node_id = $5 AND status = 'waiting'
+            RETURNING *
+            "#
+        )
+        .bind(status)
+        .bind(output)
+        .bind(error)
+        .bind(execution_id)
+        .bind(node_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    /// Waiting approval nodes past their deadline
+    pub async fn list_expired_approvals(&self) -> Result<Vec<NodeExecutionRow>, sqlx::Error> {
+        sqlx::query_as::<_, NodeExecutionRow>(
+            "SELECT * FROM node_executions WHERE status = 'waiting' AND expires_at < NOW() ORDER BY expires_at"
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Mark the nodes a cancelled execution left running or waiting
+    pub async fn cancel_running_nodes(&self, execution_id: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE node_executions SET status = 'cancelled', completed_at = NOW()
+            WHERE execution_id = $1 AND status IN ('running', 'waiting')
+            "#
+        )
+        .bind(execution_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn finish_node(
+        &self,
+        id: &str,
+        status: &str,
+        output: &JsonValue,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE node_executions SET status = $1, output = $2, error = $3, completed_at = NOW() WHERE id = $4"
+        )
+        .bind(status)
+        .bind(output)
+        .bind(error)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_node_executions(&self, execution_id: &str) -> Result<Vec<NodeExecutionRow>, sqlx::Error> {
+        sqlx::query_as::<_, NodeExecutionRow>(
+            "SELECT * FROM node_executions WHERE execution_id = $1 ORDER BY started_at, node_id"
+        )
+        .bind(execution_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
@@ -26,6 +26,12 @@ pub enum SynapseError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -54,6 +60,9 @@ pub enum ProviderError {
     #[error("Provider rate limited")]
     RateLimited,
 
+    #[error("Provider quota exceeded: {0}")]
+    QuotaExceeded(String),
+
     #[error("Provider authentication failed")]
     AuthFailed,
 
@@ -62,6 +71,12 @@ pub enum ProviderError {
 
     #[error("Network error: {0}")]
     Network(String),
+
+    #[error("Operation not supported by provider: {0}")]
+    Unsupported(String),
+
+    #[error("Model output did not match the requested format: {0}")]
+    InvalidOutput(String),
 }
 
 /// Routing-specific errors
@@ -107,18 +122,28 @@ pub struct ErrorResponse {
     pub details: Option<serde_json::Value>,
 }
 
-impl IntoResponse for SynapseError {
-    fn into_response(self) -> Response {
-        let (status, error_code) = match &self {
+impl SynapseError {
+    /// HTTP status and stable error code for this error
+    pub fn status_and_code(&self) -> (StatusCode, &'static str) {
+        match self {
             SynapseError::Provider(ProviderError::RateLimited) => {
                 (StatusCode::TOO_MANY_REQUESTS, "RATE_LIMITED")
             }
+            SynapseError::Provider(ProviderError::QuotaExceeded(_)) => {
+                (StatusCode::TOO_MANY_REQUESTS, "QUOTA_EXCEEDED")
+            }
             SynapseError::Provider(ProviderError::AuthFailed) => {
                 (StatusCode::UNAUTHORIZED, "AUTH_FAILED")
             }
             SynapseError::Provider(ProviderError::Timeout) => {
                 (StatusCode::GATEWAY_TIMEOUT, "PROVIDER_TIMEOUT")
             }
+            SynapseError::Provider(ProviderError::Unsupported(_)) => {
+                (StatusCode::BAD_REQUEST, "UNSUPPORTED_OPERATION")
+            }
+            SynapseError::Provider(ProviderError::InvalidOutput(_)) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "INVALID_OUTPUT")
+            }
             SynapseError::Provider(_) => (StatusCode::BAD_GATEWAY, "PROVIDER_ERROR"),
             SynapseError::Routing(RoutingError::NoProvidersAvailable) => {
                 (StatusCode::SERVICE_UNAVAILABLE, "NO_PROVIDERS")
@@ -130,11 +155,19 @@ impl IntoResponse for SynapseError {
             SynapseError::Cost(_) => (StatusCode::INTERNAL_SERVER_ERROR, "COST_ERROR"),
             SynapseError::Validation(_) => (StatusCode::BAD_REQUEST, "VALIDATION_ERROR"),
             SynapseError::NotFound(_) => (StatusCode::NOT_FOUND, "NOT_FOUND"),
+            SynapseError::Unauthorized(_) => (StatusCode::UNAUTHORIZED, "UNAUTHORIZED"),
+            SynapseError::Forbidden(_) => (StatusCode::FORBIDDEN, "FORBIDDEN"),
             SynapseError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "DATABASE_ERROR"),
             SynapseError::DatabaseError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "DATABASE_ERROR"),
             SynapseError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, "CONFIG_ERROR"),
             SynapseError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
-        };
+        }
+    }
+}
+
+impl IntoResponse for SynapseError {
+    fn into_response(self) -> Response {
+        let (status, error_code) = self.status_and_code();
 
         let body = Json(json!({
             "error": self.to_string(),
@@ -145,6 +178,29 @@ impl IntoResponse for SynapseError {
     }
 }
 
+impl From<SynapseError> for tonic::Status {
+    fn from(err: SynapseError) -> Self {
+        let message = err.to_string();
+        match err {
+            SynapseError::Provider(ProviderError::RateLimited) => tonic::Status::resource_exhausted(message),
+            SynapseError::Provider(ProviderError::QuotaExceeded(_)) => tonic::Status::resource_exhausted(message),
+            SynapseError::Provider(ProviderError::AuthFailed) => tonic::Status::unauthenticated(message),
+            SynapseError::Provider(ProviderError::Timeout) => tonic::Status::deadline_exceeded(message),
+            SynapseError::Provider(ProviderError::Unsupported(_)) => tonic::Status::unimplemented(message),
+            SynapseError::Provider(ProviderError::InvalidOutput(_)) => tonic::Status::failed_precondition(message),
+            SynapseError::Provider(_) => tonic::Status::unavailable(message),
+            SynapseError::Routing(RoutingError::NoProvidersAvailable) => tonic::Status::unavailable(message),
+            SynapseError::Routing(_) => tonic::Status::invalid_argument(message),
+            SynapseError::Cost(CostError::BudgetExceeded(_)) => tonic::Status::resource_exhausted(message),
+            SynapseError::Validation(_) => tonic::Status::invalid_argument(message),
+            SynapseError::NotFound(_) => tonic::Status::not_found(message),
+            SynapseError::Unauthorized(_) => tonic::Status::unauthenticated(message),
+            SynapseError::Forbidden(_) => tonic::Status::permission_denied(message),
+            _ => tonic::Status::internal(message),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,6 +217,15 @@ mod tests {
         assert_eq!(err.to_string(), "No providers available");
     }
 
+    #[test]
+    fn test_status_mapping() {
+        let status: tonic::Status = SynapseError::Cost(CostError::BudgetExceeded("app".to_string())).into();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        let status: tonic::Status = SynapseError::Validation("bad input".to_string()).into();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
     #[test]
     fn test_cost_error_display() {
         let err = CostError::BudgetExceeded("user123".to_string());
@@ -3,7 +3,6 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -177,6 +176,14 @@ impl AuditService {
             .with_details(serde_json::json!({"execution_id": execution_id}));
         self.log(event).await;
     }
+
+    /// A decision on an approval node: `approve`, `reject` or `expire`
+    pub async fn log_workflow_decision(&self, user_id: &str, action: &str, workflow_id: &str, details: serde_json::Value) {
+        let event = AuditEvent::new(user_id, action, "workflow")
+            .with_resource(workflow_id)
+            .with_details(details);
+        self.log(event).await;
+    }
 }
 
 #[cfg(test)]
@@ -78,6 +78,7 @@ pub struct ApiKey {
 pub struct AuthService {
     user_repo: Option<UserRepository>,
     session_repo: Option<SessionRepository>,
+    #[allow(dead_code)]
     pool: Option<DbPool>,
     // Fallback in-memory (for when DB is not available)
     fallback_users: Arc<tokio::sync::RwLock<std::collections::HashMap<String, User>>>,
@@ -213,7 +214,7 @@ impl AuthService {
         let session = Session::new(&user.id, 24);
 
         if let Some(ref repo) = self.session_repo {
-            if let Ok(_) = repo.create(&session.id, &session.token, &session.user_id, session.expires_at).await {
+            if repo.create(&session.id, &session.token, &session.user_id, session.expires_at).await.is_ok() {
                 // Update last login
                 if let Some(ref user_repo) = self.user_repo {
                     let _ = user_repo.update_last_login(&user.id).await;
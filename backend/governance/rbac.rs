@@ -28,6 +28,13 @@ impl Role {
     }
 }
 
+/// Whether granted permissions include `permission`, directly, through
+/// `*` or through a wildcard such as `workflows:*`
+pub fn grants<'a>(granted: impl IntoIterator<Item = &'a str>, permission: &str) -> bool {
+    let wildcard = permission.split_once(':').map(|(resource, _)| format!("{}:*", resource));
+    granted.into_iter().any(|p| p == "*" || p == permission || Some(p) == wildcard.as_deref())
+}
+
 pub struct RBACService {
     roles: Arc<RwLock<HashMap<String, Role>>>,
     user_roles: Arc<RwLock<HashMap<String, String>>>,
@@ -89,31 +96,10 @@ impl RBACService {
     }
 
     pub async fn check_permission(&self, user_id: &str, permission: &str) -> bool {
-        let role = match self.get_user_role(user_id).await {
-            Some(r) => r,
-            None => return false,
-        };
-
-        // Admin has all permissions
-        if role.permissions.contains("*") {
-            return true;
-        }
-
-        // Check exact permission
-        if role.permissions.contains(permission) {
-            return true;
+        match self.get_user_role(user_id).await {
+            Some(role) => grants(role.permissions.iter().map(String::as_str), permission),
+            None => false,
         }
-
-        // Check wildcard (e.g., "workflows:*")
-        let parts: Vec<&str> = permission.split(':').collect();
-        if parts.len() >= 2 {
-            let wildcard = format!("{}:*", parts[0]);
-            if role.permissions.contains(&wildcard) {
-                return true;
-            }
-        }
-
-        false
     }
 
     pub async fn list_roles(&self) -> Vec<Role> {
@@ -151,4 +137,14 @@ mod tests {
         assert!(rbac.check_permission("admin1", "anything:here").await);
         assert!(rbac.check_permission("admin1", "workflows:delete").await);
     }
+
+    #[test]
+    fn test_grants() {
+        assert!(grants(["workflows:*"], "workflows:approve"));
+        assert!(grants(["agents:read", "workflows:approve"], "workflows:approve"));
+        assert!(!grants(["workflows:read"], "workflows:approve"));
+        assert!(!grants(["work:*"], "workflows:approve"));
+        assert!(grants(["*"], "anything"));
+        assert!(!grants([], "workflows:approve"));
+    }
 }
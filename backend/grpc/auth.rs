@@ -1,10 +1,22 @@
 //! API Key Authentication Interceptor for gRPC
 //!
 //! Validates API keys from request metadata against the database.
+//! [`ApiKeyAuthLayer`] applies the check to every call on the server and
+//! stores the resolved [`ApplicationRow`] in the request extensions so
+//! services can enforce scopes and attribute usage.
 
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::metadata::MetadataMap;
+use tonic::transport::Body;
 use tonic::{Request, Status};
-use crate::db::ApplicationRepository;
+use tower::Layer;
+use crate::db::{ApplicationRepository, ApplicationRow};
+
+/// gRPC paths that are reachable without an API key
+const PUBLIC_PATH_PREFIXES: &[&str] = &["/grpc.health.v1.Health/"];
 
 /// Authentication interceptor that validates API keys
 #[derive(Clone)]
@@ -18,9 +30,16 @@ impl ApiKeyInterceptor {
     }
 
     /// Validate the API key from the request metadata
-    pub async fn validate(&self, request: Request<()>) -> Result<Request<()>, Status> {
+    pub async fn validate(&self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let app = self.authenticate(request.metadata()).await?;
+        request.extensions_mut().insert(app);
+        Ok(request)
+    }
+
+    /// Resolve the calling application from request metadata
+    pub async fn authenticate(&self, metadata: &MetadataMap) -> Result<ApplicationRow, Status> {
         // Extract API key from metadata
-        let api_key = self.extract_api_key(&request)?;
+        let api_key = extract_api_key(metadata)?;
 
         // Validate against database
         match self.app_repo.validate_api_key(&api_key).await {
@@ -28,15 +47,18 @@ impl ApiKeyInterceptor {
                 if app.status != "active" {
                     return Err(Status::permission_denied("API key is suspended or expired"));
                 }
-                
-                // Update last_used timestamp (fire and forget)
+                if app.expires_at.is_some_and(|at| at <= chrono::Utc::now()) {
+                    return Err(Status::permission_denied("API key is suspended or expired"));
+                }
+
+                // Count the request and bump last_used (fire and forget)
                 let repo = self.app_repo.clone();
                 let app_id = app.id.clone();
                 tokio::spawn(async move {
-                    let _ = repo.update_last_used(&app_id).await;
+                    let _ = repo.increment_requests(&app_id).await;
                 });
-                
-                Ok(request)
+
+                Ok(app)
             }
             Ok(None) => Err(Status::unauthenticated("Invalid API key")),
             Err(e) => {
@@ -45,28 +67,114 @@ impl ApiKeyInterceptor {
             }
         }
     }
+}
 
-    fn extract_api_key<T>(&self, request: &Request<T>) -> Result<String, Status> {
-        // Try x-api-key header first
-        if let Some(key) = request.metadata().get("x-api-key") {
-            return key
-                .to_str()
-                .map(|s| s.to_string())
-                .map_err(|_| Status::invalid_argument("Invalid API key format"));
+fn extract_api_key(metadata: &MetadataMap) -> Result<String, Status> {
+    // Try x-api-key header first
+    if let Some(key) = metadata.get("x-api-key") {
+        return key
+            .to_str()
+            .map(|s| s.to_string())
+            .map_err(|_| Status::invalid_argument("Invalid API key format"));
+    }
+
+    // Try authorization header (Bearer token style)
+    if let Some(auth) = metadata.get("authorization") {
+        let auth_str = auth
+            .to_str()
+            .map_err(|_| Status::invalid_argument("Invalid authorization header"))?;
+
+        if let Some(key) = auth_str.strip_prefix("Bearer ") {
+            return Ok(key.to_string());
         }
+    }
 
-        // Try authorization header (Bearer token style)
-        if let Some(auth) = request.metadata().get("authorization") {
-            let auth_str = auth
-                .to_str()
-                .map_err(|_| Status::invalid_argument("Invalid authorization header"))?;
-            
-            if let Some(key) = auth_str.strip_prefix("Bearer ") {
-                return Ok(key.to_string());
-            }
+    Err(Status::unauthenticated("Missing API key. Provide via 'x-api-key' or 'Authorization: Bearer <key>'"))
+}
+
+/// Fetch the authenticated application and check it was granted `scope`
+pub fn require_scope<T>(request: &Request<T>, scope: &str) -> Result<ApplicationRow, Status> {
+    let app = request
+        .extensions()
+        .get::<ApplicationRow>()
+        .cloned()
+        .ok_or_else(|| Status::unauthenticated("Request is not authenticated"))?;
+
+    if !app.has_scope(scope) {
+        return Err(Status::permission_denied(format!("API key is missing the '{}' scope", scope)));
+    }
+
+    Ok(app)
+}
+
+// ============================================================================
+// Tower layer
+// ============================================================================
+
+/// Tower layer that runs [`ApiKeyInterceptor`] in front of every gRPC service
+#[derive(Clone)]
+pub struct ApiKeyAuthLayer {
+    interceptor: ApiKeyInterceptor,
+}
+
+impl ApiKeyAuthLayer {
+    pub fn new(interceptor: ApiKeyInterceptor) -> Self {
+        Self { interceptor }
+    }
+}
+
+impl<S> Layer<S> for ApiKeyAuthLayer {
+    type Service = ApiKeyAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ApiKeyAuthService {
+            inner,
+            interceptor: self.interceptor.clone(),
         }
+    }
+}
 
-        Err(Status::unauthenticated("Missing API key. Provide via 'x-api-key' or 'Authorization: Bearer <key>'"))
+/// Service produced by [`ApiKeyAuthLayer`]
+#[derive(Clone)]
+pub struct ApiKeyAuthService<S> {
+    inner: S,
+    interceptor: ApiKeyInterceptor,
+}
+
+impl<S> Service<http::Request<Body>> for ApiKeyAuthService<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: http::Request<Body>) -> Self::Future {
+        // Take the service that was driven to readiness and leave a clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let interceptor = self.interceptor.clone();
+
+        Box::pin(async move {
+            let path = req.uri().path();
+            if PUBLIC_PATH_PREFIXES.iter().any(|p| path.starts_with(p)) {
+                return inner.call(req).await;
+            }
+
+            let metadata = MetadataMap::from_headers(req.headers().clone());
+            match interceptor.authenticate(&metadata).await {
+                Ok(app) => {
+                    req.extensions_mut().insert(app);
+                    inner.call(req).await
+                }
+                Err(status) => Ok(status.to_http()),
+            }
+        })
     }
 }
 
@@ -77,3 +185,79 @@ macro_rules! require_auth {
         $interceptor.validate($request).await?
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_api_key_header() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-api-key", "sk-synapse-abc".parse().unwrap());
+        assert_eq!(extract_api_key(&metadata).unwrap(), "sk-synapse-abc");
+    }
+
+    #[test]
+    fn test_extract_api_key_bearer() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("authorization", "Bearer sk-synapse-xyz".parse().unwrap());
+        assert_eq!(extract_api_key(&metadata).unwrap(), "sk-synapse-xyz");
+    }
+
+    #[test]
+    fn test_extract_api_key_missing() {
+        let metadata = MetadataMap::new();
+        let err = extract_api_key(&metadata).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+    }
+
+    fn test_app(scopes: serde_json::Value) -> ApplicationRow {
+        let now = chrono::Utc::now();
+        ApplicationRow {
+            id: "app-1".to_string(),
+            name: "Test App".to_string(),
+            description: None,
+            api_key_hash: String::new(),
+            api_key_prefix: "sk-synapse-".to_string(),
+            scopes,
+            rate_limit: 60,
+            status: "active".to_string(),
+            created_at: now,
+            updated_at: now,
+            last_used: None,
+            expires_at: None,
+            requests_today: 0,
+            requests_reset_at: now,
+            cache_ttl_seconds: 0,
+            semantic_cache_threshold: 0.0,
+            request_logging: false,
+            log_retention_days: 0,
+            log_redaction: true,
+        }
+    }
+
+    #[test]
+    fn test_require_scope() {
+        let mut request = Request::new(());
+        request.extensions_mut().insert(test_app(serde_json::json!(["llm:chat"])));
+
+        assert!(require_scope(&request, "llm:chat").is_ok());
+        let err = require_scope(&request, "llm:models").unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[test]
+    fn test_scope_wildcards() {
+        assert!(test_app(serde_json::json!(["*"])).has_scope("llm:models"));
+        assert!(test_app(serde_json::json!(["llm:*"])).has_scope("llm:chat"));
+        assert!(!test_app(serde_json::json!(["llm:*"])).has_scope("knowledge:search"));
+        assert!(!test_app(serde_json::json!([])).has_scope("llm:chat"));
+    }
+
+    #[test]
+    fn test_require_scope_unauthenticated() {
+        let request = Request::new(());
+        let err = require_scope(&request, "llm:chat").unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Unauthenticated);
+    }
+}
@@ -3,6 +3,9 @@
 //! Provides LLM chat completions via gRPC.
 
 use std::sync::Arc;
+use futures_util::StreamExt;
+use tracing::Instrument;
+use tokio::sync::mpsc;
 use tonic::{Request, Response, Status};
 use tokio_stream::wrappers::ReceiverStream;
 
@@ -11,8 +14,13 @@ use crate::grpc::barq::{
     chat_service_server::ChatService,
     ChatRequest, ChatResponse, ChatChunk,
 };
-use crate::grpc::auth::ApiKeyInterceptor;
-use crate::db::ProviderAccountRepository;
+use crate::grpc::auth::{require_scope, ApiKeyInterceptor};
+use crate::grpc::convert::{chat_request_from_proto, chat_response_to_proto, tool_call_to_proto, usage_to_proto};
+use crate::db::{ApplicationRow, CostRepository};
+use crate::providers::{ChatStream, ProviderAdapter, StreamAccumulator};
+use crate::metrics::{self, metrics};
+use crate::request_log::{self, LogEntry, Outcome};
+use crate::types::TokenUsage;
 
 pub struct ChatServiceImpl {
     state: Arc<AppState>,
@@ -24,6 +32,27 @@ impl ChatServiceImpl {
     pub fn new(state: Arc<AppState>, auth: ApiKeyInterceptor) -> Self {
         Self { state, auth }
     }
+
+    /// Record usage for the calling application and convert to the wire format
+    async fn finish(
+        &self,
+        app: &ApplicationRow,
+        user_id: Option<&str>,
+        response: crate::ChatResponse,
+    ) -> Response<ChatResponse> {
+        record_usage(
+            &self.state,
+            &app.id,
+            user_id,
+            &response.provider,
+            &response.model,
+            &response.usage,
+            response.cost,
+            &response.id,
+        ).await;
+
+        Response::new(chat_response_to_proto(response))
+    }
 }
 
 #[tonic::async_trait]
@@ -32,174 +61,51 @@ impl ChatService for ChatServiceImpl {
         &self,
         request: Request<ChatRequest>,
     ) -> Result<Response<ChatResponse>, Status> {
-        let req = request.into_inner();
-        
-        // Convert to internal format
-        let messages: Vec<crate::types::Message> = req.messages
-            .into_iter()
-            .map(|m| crate::types::Message {
-                role: m.role,
-                content: m.content,
-                function_call: None,
-                tool_calls: None,
-            })
-            .collect();
-
-        let chat_request = crate::types::ChatRequest {
-            model: req.model.clone(),
-            provider: req.provider.clone(),
-            messages,
-            temperature: req.temperature.unwrap_or(0.7),
-            max_tokens: req.max_tokens.map(|t| t as u32).unwrap_or(2048),
-            ..Default::default()
-        };
-
-        // If provider is specified, query database directly for account
-        if let Some(ref provider_id) = req.provider {
-            if let Some(ref pool) = self.state.db_pool {
-                let repo = ProviderAccountRepository::new(pool.clone());
-                
-                // Get the best available account from database
-                if let Ok(Some(account_row)) = repo.get_default(provider_id).await {
-                    let api_key = account_row.api_key_encrypted.clone().unwrap_or_default();
-                    
-                    if !api_key.is_empty() {
-                        let provider_type = match provider_id.as_str() {
-                            "openai" => crate::ProviderType::OpenAI,
-                            "anthropic" => crate::ProviderType::Anthropic,
-                            "mistral" => crate::ProviderType::Mistral,
-                            "cohere" => crate::ProviderType::Cohere,
-                            "groq" => crate::ProviderType::Groq,
-                            "together" => crate::ProviderType::Together,
-                            "gemini" => crate::ProviderType::Gemini,
-                            "azure" | "azure-openai" => crate::ProviderType::AzureOpenAI,
-                            "bedrock" => crate::ProviderType::Bedrock,
-                            _ => crate::ProviderType::OpenAI,
-                        };
-
-                        let base_url = get_provider_base_url(provider_id, account_row.endpoint.as_deref());
-
-                        let provider = crate::Provider {
-                            id: provider_id.clone(),
-                            name: account_row.name.clone(),
-                            provider_type,
-                            api_key,
-                            models: Vec::new(),
-                            base_url,
-                            pricing: crate::ProviderPricing {
-                                input_token_cost: 0.0,
-                                output_token_cost: 0.0,
-                            },
-                            enabled: true,
-                            health: crate::ProviderHealth::default(),
-                            headers: std::collections::HashMap::new(),
-                        };
-
-                        let adapter = crate::providers::create_adapter(provider, self.state.http_client.clone());
-                        match adapter.chat(&chat_request).await {
-                            Ok(response) => {
-                                // TODO: Record usage to database for billing/audit
-                                return Ok(Response::new(ChatResponse {
-                                    id: response.id,
-                                    model: response.model,
-                                    content: response.choices.first()
-                                        .map(|c| c.message.content.clone())
-                                        .unwrap_or_default(),
-                                    input_tokens: response.usage.prompt_tokens as i32,
-                                    output_tokens: response.usage.completion_tokens as i32,
-                                    provider: response.provider.clone(),
-                                }));
-                            }
-                            Err(e) => {
-                                tracing::warn!(provider = %provider_id, error = %e, "Provider failed, trying router");
-                            }
-                        }
-                    }
-                } else {
-                    // Try to get any enabled account for this provider
-                    if let Ok(accounts) = repo.list_by_provider(provider_id).await {
-                        for account_row in accounts {
-                            if !account_row.enabled {
-                                continue;
-                            }
-                            let api_key = account_row.api_key_encrypted.clone().unwrap_or_default();
-                            if api_key.is_empty() {
-                                continue;
-                            }
+        let app = require_scope(&request, "llm:chat")?;
+        let chat_request = chat_request_from_proto(request.into_inner())?;
+        let user_id = chat_request.user_id.clone();
+        let start = std::time::Instant::now();
 
-                            let provider_type = match provider_id.as_str() {
-                                "openai" => crate::ProviderType::OpenAI,
-                                "anthropic" => crate::ProviderType::Anthropic,
-                                "mistral" => crate::ProviderType::Mistral,
-                                "cohere" => crate::ProviderType::Cohere,
-                                "groq" => crate::ProviderType::Groq,
-                                "together" => crate::ProviderType::Together,
-                                "gemini" => crate::ProviderType::Gemini,
-                                "azure" | "azure-openai" => crate::ProviderType::AzureOpenAI,
-                                "bedrock" => crate::ProviderType::Bedrock,
-                                _ => crate::ProviderType::OpenAI,
-                            };
+        // Responses are cached per application so tenants never share them
+        let (result, attempts) = request_log::track_attempts(
+            self.state.response_cache.complete(
+                &chat_request,
+                &app.id,
+                app.cache_settings(),
+                self.state.as_ref(),
+                || self.state.route_chat(&chat_request),
+            ),
+        ).await;
 
-                            let base_url = get_provider_base_url(provider_id, account_row.endpoint.as_deref());
-
-                            let provider = crate::Provider {
-                                id: provider_id.clone(),
-                                name: account_row.name.clone(),
-                                provider_type,
-                                api_key,
-                                models: Vec::new(),
-                                base_url,
-                                pricing: crate::ProviderPricing {
-                                    input_token_cost: 0.0,
-                                    output_token_cost: 0.0,
-                                },
-                                enabled: true,
-                                health: crate::ProviderHealth::default(),
-                                headers: std::collections::HashMap::new(),
-                            };
-
-                            let adapter = crate::providers::create_adapter(provider, self.state.http_client.clone());
-                            match adapter.chat(&chat_request).await {
-                                Ok(response) => {
-                                    return Ok(Response::new(ChatResponse {
-                                        id: response.id,
-                                        model: response.model,
-                                        content: response.choices.first()
-                                            .map(|c| c.message.content.clone())
-                                            .unwrap_or_default(),
-                                        input_tokens: response.usage.prompt_tokens as i32,
-                                        output_tokens: response.usage.completion_tokens as i32,
-                                        provider: response.provider.clone(),
-                                    }));
-                                }
-                                Err(e) => {
-                                    tracing::warn!(provider = %provider_id, account = %account_row.name, error = %e, "Account failed, trying next");
-                                    continue;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        // Fallback to router
-        match self.state.router.route(&chat_request).await {
-            Ok(response) => {
-                Ok(Response::new(ChatResponse {
-                    id: response.id,
-                    model: response.model,
-                    content: response.choices.first()
-                        .map(|c| c.message.content.clone())
-                        .unwrap_or_default(),
-                    input_tokens: response.usage.prompt_tokens as i32,
-                    output_tokens: response.usage.completion_tokens as i32,
-                    provider: response.provider.clone(),
-                }))
+        match result {
+            Ok((response, cache_status)) => {
+                let entry = LogEntry {
+                    request_id: &response.id,
+                    request: &chat_request,
+                    outcome: Outcome::Success(&response),
+                    attempts,
+                    latency_ms: start.elapsed().as_millis() as u64,
+                    cache_status: Some(cache_status),
+                    streamed: false,
+                };
+                metrics().observe_chat(&app.id, &entry);
+                request_log::record(self.state.db_pool.as_ref(), &app, entry).await;
+                Ok(self.finish(&app, user_id.as_deref(), response).await)
             }
             Err(e) => {
                 tracing::error!("Chat completion error: {}", e);
-                Err(Status::internal(format!("Completion failed: {}", e)))
+                let entry = LogEntry {
+                    request_id: &uuid::Uuid::new_v4().to_string(),
+                    request: &chat_request,
+                    outcome: Outcome::Failure(e.to_string()),
+                    attempts,
+                    latency_ms: start.elapsed().as_millis() as u64,
+                    cache_status: None,
+                    streamed: false,
+                };
+                metrics().observe_chat(&app.id, &entry);
+                request_log::record(self.state.db_pool.as_ref(), &app, entry).await;
+                Err(e.into())
             }
         }
     }
@@ -210,154 +116,195 @@ impl ChatService for ChatServiceImpl {
         &self,
         request: Request<ChatRequest>,
     ) -> Result<Response<Self::CompleteStreamStream>, Status> {
-        let req = request.into_inner();
-        let (tx, rx) = tokio::sync::mpsc::channel(32);
+        let app = require_scope(&request, "llm:chat")?;
+        let req = chat_request_from_proto(request.into_inner())?;
+        let start = std::time::Instant::now();
 
-        // Get provider account from database
-        let provider_id = req.provider.clone().unwrap_or_else(|| "openai".to_string());
-        
-        let (api_key, base_url, _provider_name) = if let Some(ref pool) = self.state.db_pool {
-            let repo = ProviderAccountRepository::new(pool.clone());
-            
-            if let Ok(Some(account)) = repo.get_default(&provider_id).await {
-                let key = account.api_key_encrypted.clone().unwrap_or_default();
-                let url = get_provider_base_url(&provider_id, account.endpoint.as_deref());
-                (key, url, account.name.clone())
-            } else if let Ok(accounts) = repo.list_by_provider(&provider_id).await {
-                if let Some(account) = accounts.into_iter().find(|a| a.enabled && !a.api_key_encrypted.clone().unwrap_or_default().is_empty()) {
-                    let key = account.api_key_encrypted.clone().unwrap_or_default();
-                    let url = get_provider_base_url(&provider_id, account.endpoint.as_deref());
-                    (key, url, account.name.clone())
-                } else {
-                    return Err(Status::not_found("No enabled account found for provider"));
-                }
-            } else {
-                return Err(Status::not_found("Provider not found"));
+        let (opened, attempts) = request_log::track_attempts(self.state.open_chat_stream(&req)).await;
+        let (adapter, stream) = match opened {
+            Ok(opened) => opened,
+            Err(e) => {
+                tracing::error!("Chat stream error: {}", e);
+                let entry = LogEntry {
+                    request_id: &uuid::Uuid::new_v4().to_string(),
+                    request: &req,
+                    outcome: Outcome::Failure(e.to_string()),
+                    attempts,
+                    latency_ms: start.elapsed().as_millis() as u64,
+                    cache_status: None,
+                    streamed: true,
+                };
+                metrics().observe_chat(&app.id, &entry);
+                request_log::record(self.state.db_pool.as_ref(), &app, entry).await;
+                return Err(e.into());
             }
-        } else {
-            return Err(Status::unavailable("Database not available"));
         };
+        let (tx, rx) = tokio::sync::mpsc::channel(32);
+        let state = self.state.clone();
 
-        if api_key.is_empty() {
-            return Err(Status::unauthenticated("No API key configured for provider"));
-        }
-
-        let model = req.model.clone();
-        let messages = req.messages.clone();
-        let temperature = req.temperature.unwrap_or(0.7);
-        let max_tokens = req.max_tokens.unwrap_or(2048);
-        let http_client = self.state.http_client.clone();
-
-        // Spawn task to handle streaming
         tokio::spawn(async move {
-            let url = format!("{}/chat/completions", base_url);
-            
-            let payload = serde_json::json!({
-                "model": model,
-                "messages": messages.iter().map(|m| {
-                    serde_json::json!({
-                        "role": m.role,
-                        "content": m.content
-                    })
-                }).collect::<Vec<_>>(),
-                "temperature": temperature,
-                "max_tokens": max_tokens,
-                "stream": true
-            });
-
-            let response = http_client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", api_key))
-                .header("Content-Type", "application/json")
-                .json(&payload)
-                .send()
-                .await;
-
-            match response {
-                Ok(resp) => {
-                    if !resp.status().is_success() {
-                        let _ = tx.send(Err(Status::internal(format!("Provider error: {}", resp.status())))).await;
-                        return;
-                    }
+            forward_stream(&state, &app, &req, adapter, stream, tx, start, attempts).await;
+        }.in_current_span());
 
-                    let mut stream = resp.bytes_stream();
-                    use futures_util::StreamExt;
-                    
-                    let mut buffer = String::new();
-                    let stream_id = uuid::Uuid::new_v4().to_string();
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
 
-                    while let Some(chunk_result) = stream.next().await {
-                        match chunk_result {
-                            Ok(bytes) => {
-                                buffer.push_str(&String::from_utf8_lossy(&bytes));
-                                
-                                // Process complete SSE lines
-                                while let Some(line_end) = buffer.find('\n') {
-                                    let line = buffer[..line_end].trim().to_string();
-                                    buffer = buffer[line_end + 1..].to_string();
-                                    
-                                    if line.starts_with("data: ") {
-                                        let data = &line[6..];
-                                        if data == "[DONE]" {
-                                            let _ = tx.send(Ok(ChatChunk {
-                                                id: stream_id.clone(),
-                                                delta: String::new(),
-                                                done: true,
-                                            })).await;
-                                            return;
-                                        }
-                                        
-                                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
-                                            if let Some(delta) = json["choices"][0]["delta"]["content"].as_str() {
-                                                let _ = tx.send(Ok(ChatChunk {
-                                                    id: stream_id.clone(),
-                                                    delta: delta.to_string(),
-                                                    done: false,
-                                                })).await;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                let _ = tx.send(Err(Status::internal(format!("Stream error: {}", e)))).await;
-                                return;
-                            }
-                        }
-                    }
+/// Relay provider chunks to the client, then send a final chunk with usage
+/// and cost and record the usage (and request log) against the application
+#[allow(clippy::too_many_arguments)]
+async fn forward_stream(
+    state: &AppState,
+    app: &ApplicationRow,
+    req: &crate::ChatRequest,
+    adapter: Arc<dyn ProviderAdapter>,
+    mut stream: ChatStream,
+    tx: mpsc::Sender<Result<ChatChunk, Status>>,
+    start: std::time::Instant,
+    attempts: Vec<request_log::Attempt>,
+) {
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let provider_name = adapter.name().to_string();
+    let mut accumulator = StreamAccumulator::new();
+    let mut failed = false;
+    let mut stream_error = None;
+    let mut first_token = true;
 
-                    // Send done signal if not already sent
-                    let _ = tx.send(Ok(ChatChunk {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        delta: String::new(),
-                        done: true,
-                    })).await;
+    while let Some(item) = stream.next().await {
+        match item {
+            Ok(chunk) => {
+                accumulator.push(&chunk);
+                if chunk.delta.is_empty() {
+                    continue;
                 }
-                Err(e) => {
-                    let _ = tx.send(Err(Status::internal(format!("Request failed: {}", e)))).await;
+                if std::mem::take(&mut first_token) {
+                    metrics().observe_ttft(&metrics::Labels {
+                        provider: &adapter.provider().id,
+                        model: &req.model,
+                        account: metrics::routed_account(&attempts),
+                        application: &app.id,
+                    }, start.elapsed());
                 }
+                let sent = tx.send(Ok(ChatChunk {
+                    id: stream_id.clone(),
+                    delta: chunk.delta,
+                    model: req.model.clone(),
+                    provider: provider_name.clone(),
+                    ..Default::default()
+                })).await;
+                if sent.is_err() {
+                    // Client went away; stop reading but still bill what was generated
+                    failed = true;
+                    break;
+                }
+            }
+            Err(e) => {
+                tracing::warn!(provider = %provider_name, error = %e, "Provider stream failed");
+                stream_error = Some(e.to_string());
+                let _ = tx.send(Err(e.into())).await;
+                failed = true;
+                break;
             }
-        });
+        }
+    }
 
-        Ok(Response::new(ReceiverStream::new(rx)))
+    let summary = accumulator.finish(req);
+    let cost = adapter.estimate_cost(&summary.usage);
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    let mut message = crate::Message::assistant(summary.content.clone());
+    message.tool_calls = (!summary.tool_calls.is_empty()).then(|| summary.tool_calls.clone());
+    let logged = crate::ChatResponse {
+        id: stream_id.clone(),
+        provider: adapter.provider().id.clone(),
+        model: req.model.clone(),
+        choices: vec![crate::Choice {
+            index: 0,
+            message,
+            finish_reason: summary.finish_reason.clone(),
+            logprobs: None,
+        }],
+        usage: summary.usage.clone(),
+        created: chrono::Utc::now(),
+        latency_ms,
+        cost,
+        system_fingerprint: None,
+    };
+    let entry = LogEntry {
+        request_id: &stream_id,
+        request: req,
+        outcome: match stream_error {
+            Some(e) => Outcome::Failure(e),
+            None => Outcome::Success(&logged),
+        },
+        attempts,
+        latency_ms,
+        cache_status: None,
+        streamed: true,
+    };
+    metrics().observe_chat(&app.id, &entry);
+    request_log::record(state.db_pool.as_ref(), app, entry).await;
+
+    if !failed {
+        let _ = tx.send(Ok(ChatChunk {
+            id: stream_id.clone(),
+            delta: String::new(),
+            done: true,
+            model: req.model.clone(),
+            provider: provider_name,
+            tool_calls: summary.tool_calls.into_iter().map(tool_call_to_proto).collect(),
+            finish_reason: Some(summary.finish_reason),
+            usage: Some(usage_to_proto(&summary.usage)),
+            cost: Some(cost),
+            latency_ms: Some(latency_ms),
+        })).await;
     }
+
+    record_usage(
+        state,
+        &app.id,
+        req.user_id.as_deref(),
+        &adapter.provider().id,
+        &req.model,
+        &summary.usage,
+        cost,
+        &stream_id,
+    ).await;
 }
 
-fn get_provider_base_url(provider_id: &str, custom_endpoint: Option<&str>) -> String {
-    if let Some(endpoint) = custom_endpoint {
-        if !endpoint.is_empty() {
-            return endpoint.to_string();
-        }
+/// Attribute usage to an application in the cost ledger and, when a
+/// database is available, in `cost_entries`; a `user_id` that is not a hub
+/// user is kept in the ledger only
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn record_usage(
+    state: &AppState,
+    application_id: &str,
+    user_id: Option<&str>,
+    provider: &str,
+    model: &str,
+    usage: &TokenUsage,
+    cost: f64,
+    request_id: &str,
+) {
+    if let Err(e) = state.cost_manager.record_cost(provider, model, usage, cost, application_id, request_id).await {
+        tracing::warn!(application = %application_id, error = %e, "Failed to record cost");
     }
 
-    match provider_id {
-        "openai" => "https://api.openai.com/v1".to_string(),
-        "anthropic" => "https://api.anthropic.com/v1".to_string(),
-        "mistral" => "https://api.mistral.ai/v1".to_string(),
-        "cohere" => "https://api.cohere.ai/v1".to_string(),
-        "groq" => "https://api.groq.com/openai/v1".to_string(),
-        "together" => "https://api.together.xyz/v1".to_string(),
-        "gemini" => "https://generativelanguage.googleapis.com/v1".to_string(),
-        _ => format!("https://api.{}.com/v1", provider_id),
+    if let Some(ref pool) = state.db_pool {
+        let repo = CostRepository::new(pool.clone());
+        if let Err(e) = repo.create_entry(
+            &uuid::Uuid::new_v4().to_string(),
+            user_id,
+            None,
+            Some(application_id),
+            provider,
+            model,
+            usage.prompt_tokens as i32,
+            usage.completion_tokens as i32,
+            cost,
+            Some(request_id),
+        ).await {
+            tracing::warn!(application = %application_id, error = %e, "Failed to persist cost entry");
+        }
     }
 }
@@ -0,0 +1,258 @@
+//! Conversions between protobuf messages and internal types
+
+use tonic::Status;
+
+use crate::grpc::barq;
+use crate::types::{
+    self, FunctionCall, FunctionDefinition, JsonSchemaFormat, ProviderPreference, ResponseFormat,
+    Tool, TokenUsage, ToolCall,
+};
+
+/// Convert a gRPC chat request into the internal request type
+pub fn chat_request_from_proto(req: barq::ChatRequest) -> Result<types::ChatRequest, Status> {
+    let routing_preference = req.routing_preference();
+
+    let messages = req.messages.into_iter().map(message_from_proto).collect();
+
+    let tools = if req.tools.is_empty() {
+        None
+    } else {
+        Some(req.tools.into_iter().map(tool_from_proto).collect::<Result<Vec<_>, _>>()?)
+    };
+
+    // tool_choice is either a bare mode ("auto") or a JSON object
+    let tool_choice = req.tool_choice.map(|choice| {
+        serde_json::from_str(&choice).unwrap_or(serde_json::Value::String(choice))
+    });
+
+    let response_format = req.response_format.map(response_format_from_proto).transpose()?;
+
+    Ok(types::ChatRequest {
+        model: req.model,
+        provider: req.provider,
+        messages,
+        temperature: req.temperature.unwrap_or(0.7),
+        max_tokens: req.max_tokens.map(|t| t as u32).unwrap_or(2048),
+        top_p: req.top_p,
+        stop: if req.stop.is_empty() { None } else { Some(req.stop) },
+        tools,
+        tool_choice,
+        response_format,
+        retry_invalid_output: req.retry_invalid_output,
+        cache: req.cache.map(|c| types::CacheOptions {
+            ttl: c.ttl_seconds,
+            force: c.force,
+            similarity: c.similarity,
+        }),
+        n: None,
+        seed: None,
+        logprobs: None,
+        top_logprobs: None,
+        provider_preference: preference_from_proto(routing_preference),
+        user_id: req.user_id,
+        metadata: req.metadata.into_iter().collect(),
+    })
+}
+
+fn message_from_proto(m: barq::Message) -> types::Message {
+    types::Message {
+        role: m.role,
+        content: m.content.into(),
+        function_call: None,
+        tool_calls: if m.tool_calls.is_empty() {
+            None
+        } else {
+            Some(m.tool_calls.into_iter().map(tool_call_from_proto).collect())
+        },
+        tool_call_id: m.tool_call_id,
+    }
+}
+
+fn tool_from_proto(tool: barq::Tool) -> Result<Tool, Status> {
+    let function = tool
+        .function
+        .ok_or_else(|| Status::invalid_argument("Tool is missing its function definition"))?;
+
+    let parameters = function
+        .parameters_json
+        .map(|json| {
+            serde_json::from_str(&json).map_err(|e| {
+                Status::invalid_argument(format!("Invalid parameters_json for tool '{}': {}", function.name, e))
+            })
+        })
+        .transpose()?;
+
+    Ok(Tool {
+        tool_type: if tool.r#type.is_empty() { "function".to_string() } else { tool.r#type },
+        function: FunctionDefinition {
+            name: function.name,
+            description: function.description,
+            parameters,
+        },
+    })
+}
+
+fn tool_call_from_proto(call: barq::ToolCall) -> ToolCall {
+    let function = call.function.unwrap_or_default();
+    ToolCall {
+        id: call.id,
+        call_type: if call.r#type.is_empty() { "function".to_string() } else { call.r#type },
+        function: FunctionCall {
+            name: function.name,
+            arguments: function.arguments,
+        },
+    }
+}
+
+fn response_format_from_proto(format: barq::ResponseFormat) -> Result<ResponseFormat, Status> {
+    match format.r#type.as_str() {
+        "" | "text" => Ok(ResponseFormat::Text),
+        "json_object" => Ok(ResponseFormat::JsonObject),
+        "json_schema" => {
+            let schema = format
+                .json_schema
+                .ok_or_else(|| Status::invalid_argument("response_format json_schema requires a schema"))?;
+            let parsed = serde_json::from_str(&schema.schema_json)
+                .map_err(|e| Status::invalid_argument(format!("Invalid schema_json: {}", e)))?;
+            Ok(ResponseFormat::JsonSchema {
+                json_schema: JsonSchemaFormat {
+                    name: schema.name,
+                    description: schema.description,
+                    schema: parsed,
+                    strict: schema.strict,
+                },
+            })
+        }
+        other => Err(Status::invalid_argument(format!("Unknown response_format type: {}", other))),
+    }
+}
+
+fn preference_from_proto(preference: barq::RoutingPreference) -> Option<ProviderPreference> {
+    match preference {
+        barq::RoutingPreference::Unspecified => None,
+        barq::RoutingPreference::CostOptimal => Some(ProviderPreference::CostOptimal),
+        barq::RoutingPreference::LatencyOptimal => Some(ProviderPreference::LatencyOptimal),
+        barq::RoutingPreference::QualityTier => Some(ProviderPreference::QualityTier),
+        barq::RoutingPreference::LoadBalanced => Some(ProviderPreference::LoadBalanced),
+    }
+}
+
+/// Convert an internal chat response into the gRPC response
+pub fn chat_response_to_proto(response: types::ChatResponse) -> barq::ChatResponse {
+    let choice = response.choices.into_iter().next();
+    let (content, finish_reason, tool_calls) = match choice {
+        Some(c) => (
+            c.message.content.text(),
+            c.finish_reason,
+            c.message.tool_calls.unwrap_or_default(),
+        ),
+        None => (String::new(), String::new(), Vec::new()),
+    };
+
+    barq::ChatResponse {
+        id: response.id,
+        model: response.model,
+        content,
+        input_tokens: response.usage.prompt_tokens as i32,
+        output_tokens: response.usage.completion_tokens as i32,
+        provider: response.provider,
+        usage: Some(usage_to_proto(&response.usage)),
+        cost: response.cost,
+        latency_ms: response.latency_ms,
+        finish_reason,
+        tool_calls: tool_calls.into_iter().map(tool_call_to_proto).collect(),
+        created: response.created.timestamp(),
+    }
+}
+
+pub fn tool_call_to_proto(call: ToolCall) -> barq::ToolCall {
+    barq::ToolCall {
+        id: call.id,
+        r#type: call.call_type,
+        function: Some(barq::FunctionCall {
+            name: call.function.name,
+            arguments: call.function.arguments,
+        }),
+    }
+}
+
+pub fn usage_to_proto(usage: &TokenUsage) -> barq::Usage {
+    barq::Usage {
+        prompt_tokens: usage.prompt_tokens as i32,
+        completion_tokens: usage.completion_tokens as i32,
+        total_tokens: usage.total_tokens as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_request_defaults() {
+        let req = chat_request_from_proto(barq::ChatRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![barq::Message {
+                role: "user".to_string(),
+                content: "Hello".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(req.temperature, 0.7);
+        assert_eq!(req.max_tokens, 2048);
+        assert!(req.tools.is_none());
+        assert!(req.stop.is_none());
+        assert!(req.provider_preference.is_none());
+    }
+
+    #[test]
+    fn test_tools_and_response_format() {
+        let req = chat_request_from_proto(barq::ChatRequest {
+            model: "gpt-4o".to_string(),
+            tools: vec![barq::Tool {
+                r#type: String::new(),
+                function: Some(barq::FunctionDefinition {
+                    name: "lookup".to_string(),
+                    description: None,
+                    parameters_json: Some(r#"{"type":"object"}"#.to_string()),
+                }),
+            }],
+            tool_choice: Some("auto".to_string()),
+            response_format: Some(barq::ResponseFormat {
+                r#type: "json_object".to_string(),
+                json_schema: None,
+            }),
+            routing_preference: barq::RoutingPreference::LoadBalanced as i32,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let tools = req.tools.unwrap();
+        assert_eq!(tools[0].tool_type, "function");
+        assert_eq!(tools[0].function.parameters, Some(serde_json::json!({"type": "object"})));
+        assert_eq!(req.tool_choice, Some(serde_json::json!("auto")));
+        assert!(matches!(req.response_format, Some(ResponseFormat::JsonObject)));
+        assert!(matches!(req.provider_preference, Some(ProviderPreference::LoadBalanced)));
+    }
+
+    #[test]
+    fn test_invalid_tool_parameters_rejected() {
+        let err = chat_request_from_proto(barq::ChatRequest {
+            tools: vec![barq::Tool {
+                r#type: "function".to_string(),
+                function: Some(barq::FunctionDefinition {
+                    name: "broken".to_string(),
+                    description: None,
+                    parameters_json: Some("{not json".to_string()),
+                }),
+            }],
+            ..Default::default()
+        })
+        .unwrap_err();
+
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,78 @@
+//! Embeddings Service gRPC Implementation
+//!
+//! Provides vector embeddings via gRPC.
+
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+
+use crate::api::AppState;
+use crate::grpc::barq::{
+    embeddings_service_server::EmbeddingsService,
+    Embedding, EmbeddingsRequest, EmbeddingsResponse,
+};
+use crate::grpc::auth::{require_scope, ApiKeyInterceptor};
+use crate::grpc::chat_service::record_usage;
+use crate::grpc::convert::usage_to_proto;
+use crate::types::EmbeddingRequest;
+
+pub struct EmbeddingsServiceImpl {
+    state: Arc<AppState>,
+    #[allow(dead_code)]
+    auth: ApiKeyInterceptor,
+}
+
+impl EmbeddingsServiceImpl {
+    pub fn new(state: Arc<AppState>, auth: ApiKeyInterceptor) -> Self {
+        Self { state, auth }
+    }
+}
+
+#[tonic::async_trait]
+impl EmbeddingsService for EmbeddingsServiceImpl {
+    async fn create(
+        &self,
+        request: Request<EmbeddingsRequest>,
+    ) -> Result<Response<EmbeddingsResponse>, Status> {
+        let app = require_scope(&request, "embedding:create")?;
+        let req = request.into_inner();
+
+        if req.input.is_empty() {
+            return Err(Status::invalid_argument("input must contain at least one text"));
+        }
+
+        let embedding_request = EmbeddingRequest {
+            model: req.model,
+            input: req.input,
+            provider: req.provider,
+            dimensions: req.dimensions.map(|d| d as u32),
+            user_id: req.user_id,
+        };
+
+        let response = self.state.embed(&embedding_request).await?;
+
+        let request_id = uuid::Uuid::new_v4().to_string();
+        record_usage(
+            &self.state,
+            &app.id,
+            embedding_request.user_id.as_deref(),
+            &response.provider,
+            &response.model,
+            &response.usage,
+            response.cost,
+            &request_id,
+        ).await;
+
+        Ok(Response::new(EmbeddingsResponse {
+            model: response.model,
+            provider: response.provider,
+            usage: Some(usage_to_proto(&response.usage)),
+            cost: response.cost,
+            latency_ms: response.latency_ms,
+            data: response.data
+                .into_iter()
+                .enumerate()
+                .map(|(index, values)| Embedding { index: index as i32, values })
+                .collect(),
+        }))
+    }
+}
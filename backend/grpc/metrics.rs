@@ -0,0 +1,85 @@
+//! gRPC call metrics
+//!
+//! [`GrpcMetricsLayer`] counts every call by method and status code and
+//! times it until response headers. Failures reported in trailers (for
+//! example mid-stream) count under the code of the headers, normally `Ok`.
+
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::transport::Body;
+use tower::Layer;
+
+use crate::metrics::metrics;
+
+/// Tower layer recording [`crate::metrics`] for every gRPC call
+#[derive(Clone, Default)]
+pub struct GrpcMetricsLayer;
+
+impl<S> Layer<S> for GrpcMetricsLayer {
+    type Service = GrpcMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcMetricsService { inner }
+    }
+}
+
+/// Service produced by [`GrpcMetricsLayer`]
+#[derive(Clone)]
+pub struct GrpcMetricsService<S> {
+    inner: S,
+}
+
+impl<S> Service<http::Request<Body>> for GrpcMetricsService<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let method = req.uri().path().to_string();
+        let start = std::time::Instant::now();
+
+        Box::pin(async move {
+            let result = inner.call(req).await;
+            let code = match result {
+                Ok(ref response) => status_code(response.headers()),
+                Err(_) => "Unknown".to_string(),
+            };
+            metrics().observe_grpc(&method, &code, start.elapsed());
+            result
+        })
+    }
+}
+
+/// Name of the `grpc-status` in `headers`, `Ok` when it is left to trailers
+fn status_code(headers: &http::HeaderMap) -> String {
+    let code = headers
+        .get("grpc-status")
+        .map(|value| tonic::Code::from_bytes(value.as_bytes()))
+        .unwrap_or(tonic::Code::Ok);
+    format!("{:?}", code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_code() {
+        let mut headers = http::HeaderMap::new();
+        assert_eq!(status_code(&headers), "Ok");
+
+        headers.insert("grpc-status", "16".parse().unwrap());
+        assert_eq!(status_code(&headers), "Unauthenticated");
+    }
+}
@@ -2,13 +2,25 @@
 //!
 //! Provides gRPC services with API key authentication.
 
+// tonic handlers and helpers return `Status` as their error type
+#![allow(clippy::result_large_err)]
+
 pub mod auth;
 pub mod chat_service;
+pub mod convert;
+pub mod embeddings_service;
+pub mod metrics;
 pub mod models_service;
+pub mod tokenize_service;
+pub mod trace;
 
-pub use auth::ApiKeyInterceptor;
+pub use auth::{ApiKeyAuthLayer, ApiKeyInterceptor};
 pub use chat_service::ChatServiceImpl;
+pub use embeddings_service::EmbeddingsServiceImpl;
+pub use metrics::GrpcMetricsLayer;
 pub use models_service::ModelsServiceImpl;
+pub use tokenize_service::TokenizeServiceImpl;
+pub use trace::GrpcTraceLayer;
 
 // Include generated protobuf code
 pub mod barq {
@@ -0,0 +1,79 @@
+//! Tokenize Service gRPC Implementation
+//!
+//! Estimates prompt token counts so clients can size requests before sending them.
+
+use tonic::{Request, Response, Status};
+
+use crate::grpc::barq::{
+    tokenize_service_server::TokenizeService,
+    TokenizeRequest, TokenizeResponse,
+};
+use crate::grpc::auth::require_scope;
+use crate::providers::estimate_tokens;
+
+/// Per-message framing overhead (role markers, separators) in chat formats
+const TOKENS_PER_MESSAGE: u32 = 4;
+
+#[derive(Default)]
+pub struct TokenizeServiceImpl;
+
+impl TokenizeServiceImpl {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[tonic::async_trait]
+impl TokenizeService for TokenizeServiceImpl {
+    async fn count(
+        &self,
+        request: Request<TokenizeRequest>,
+    ) -> Result<Response<TokenizeResponse>, Status> {
+        require_scope(&request, "llm:chat")?;
+        let req = request.into_inner();
+
+        let token_count = count_tokens(&req);
+
+        Ok(Response::new(TokenizeResponse {
+            model: req.model,
+            token_count: token_count as i32,
+            method: "estimate".to_string(),
+        }))
+    }
+}
+
+fn count_tokens(req: &TokenizeRequest) -> u32 {
+    let messages: u32 = req.messages
+        .iter()
+        .map(|m| estimate_tokens(&m.content) + TOKENS_PER_MESSAGE)
+        .sum();
+
+    messages + req.text.as_deref().map(estimate_tokens).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::barq::Message;
+
+    #[test]
+    fn test_count_tokens() {
+        let req = TokenizeRequest {
+            model: "gpt-4o".to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: "Hello, how are you?".to_string(),
+                ..Default::default()
+            }],
+            text: Some("12345678".to_string()),
+        };
+
+        // 19 chars -> 5 tokens, + 4 framing, + 8 chars -> 2 tokens
+        assert_eq!(count_tokens(&req), 11);
+    }
+
+    #[test]
+    fn test_count_tokens_empty() {
+        assert_eq!(count_tokens(&TokenizeRequest::default()), 0);
+    }
+}
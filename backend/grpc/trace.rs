@@ -0,0 +1,74 @@
+//! gRPC request spans
+//!
+//! [`GrpcTraceLayer`] runs every call in a server span that continues the
+//! caller's trace when the request carries `traceparent` metadata.
+
+use std::task::{Context, Poll};
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::transport::Body;
+use tower::Layer;
+use tracing::Instrument;
+
+use crate::telemetry;
+
+/// Tower layer opening a `grpc.request` span per call
+#[derive(Clone, Default)]
+pub struct GrpcTraceLayer;
+
+impl<S> Layer<S> for GrpcTraceLayer {
+    type Service = GrpcTraceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcTraceService { inner }
+    }
+}
+
+/// Service produced by [`GrpcTraceLayer`]
+#[derive(Clone)]
+pub struct GrpcTraceService<S> {
+    inner: S,
+}
+
+impl<S> Service<http::Request<Body>> for GrpcTraceService<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<Body>) -> Self::Future {
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        let method = req.uri().path().trim_start_matches('/').to_string();
+        let span = tracing::info_span!(
+            "grpc.request",
+            otel.name = %method,
+            otel.kind = "server",
+            rpc.system = "grpc",
+            rpc.method = %method,
+            rpc.grpc.status_code = tracing::field::Empty,
+        );
+        telemetry::continue_trace(&span, |name| req.headers().get(name).and_then(|v| v.to_str().ok()));
+
+        Box::pin(
+            async move {
+                let result = inner.call(req).await;
+                if let Ok(ref response) = result {
+                    if let Some(code) = response.headers().get("grpc-status").and_then(|v| v.to_str().ok()) {
+                        tracing::Span::current().record("rpc.grpc.status_code", code);
+                    }
+                }
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
@@ -0,0 +1,219 @@
+//! Background health probing
+//!
+//! [`HealthMonitor`] keeps the latest probe result and a short history for
+//! every provider, database account, Redis and PostgreSQL. The prober in
+//! `main.rs` refreshes it on an interval so health endpoints answer from
+//! memory instead of calling every upstream inline.
+
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::providers::ProviderAdapter;
+use crate::ProviderHealth;
+
+/// Probe interval when `HEALTH_PROBE_INTERVAL_SECS` is not set
+pub const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Probes that take longer count as failures
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Probe results kept per component
+const HISTORY_LEN: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ComponentKind {
+    /// Provider configured from the environment
+    Provider,
+    /// Provider account stored in the database
+    Account,
+    Redis,
+    Database,
+}
+
+/// A provider or account to probe through its adapter's health check
+pub struct ProbeTarget {
+    pub key: String,
+    pub kind: ComponentKind,
+    pub name: String,
+    pub provider_id: String,
+    pub adapter: std::sync::Arc<dyn ProviderAdapter>,
+}
+
+/// Outcome of one probe
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProbeResult {
+    pub checked_at: DateTime<Utc>,
+    pub healthy: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Run `check`, timing it and turning timeouts and errors into failures
+pub async fn probe<F, E>(check: F) -> ProbeResult
+where
+    F: Future<Output = Result<bool, E>>,
+    E: std::fmt::Display,
+{
+    let start = Instant::now();
+    let error = match tokio::time::timeout(PROBE_TIMEOUT, check).await {
+        Ok(Ok(true)) => None,
+        Ok(Ok(false)) => Some("Health check failed".to_string()),
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("Timed out after {}s", PROBE_TIMEOUT.as_secs())),
+    };
+
+    ProbeResult {
+        checked_at: Utc::now(),
+        healthy: error.is_none(),
+        latency_ms: start.elapsed().as_millis() as u64,
+        error,
+    }
+}
+
+/// Something probed, with its summarised health and recent results
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentStatus {
+    pub key: String,
+    pub kind: ComponentKind,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    pub health: ProviderHealth,
+    /// Latest result first
+    pub history: VecDeque<ProbeResult>,
+}
+
+impl ComponentStatus {
+    pub fn latest(&self) -> Option<&ProbeResult> {
+        self.history.front()
+    }
+
+    fn record(&mut self, result: ProbeResult) {
+        if result.healthy {
+            self.health.last_success = Some(result.checked_at);
+        } else {
+            self.health.last_error = Some(result.checked_at);
+        }
+        self.health.healthy = result.healthy;
+
+        self.history.push_front(result);
+        self.history.truncate(HISTORY_LEN);
+
+        let failures = self.history.iter().filter(|r| !r.healthy).count();
+        self.health.error_rate = failures as f64 / self.history.len() as f64;
+
+        let latencies: Vec<u64> = self.history.iter().filter(|r| r.healthy).map(|r| r.latency_ms).collect();
+        self.health.avg_latency_ms = (!latencies.is_empty())
+            .then(|| latencies.iter().sum::<u64>() as f64 / latencies.len() as f64);
+    }
+}
+
+/// Latest probe results by component key
+#[derive(Default)]
+pub struct HealthMonitor {
+    components: RwLock<BTreeMap<String, ComponentStatus>>,
+}
+
+impl HealthMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a probe result, creating the component on first sight
+    pub async fn record(
+        &self,
+        key: &str,
+        kind: ComponentKind,
+        name: &str,
+        provider_id: Option<&str>,
+        result: ProbeResult,
+    ) -> ProviderHealth {
+        let mut components = self.components.write().await;
+        let component = components.entry(key.to_string()).or_insert_with(|| ComponentStatus {
+            key: key.to_string(),
+            kind,
+            name: name.to_string(),
+            provider_id: provider_id.map(str::to_string),
+            health: ProviderHealth::default(),
+            history: VecDeque::new(),
+        });
+        component.name = name.to_string();
+        component.record(result);
+        component.health.clone()
+    }
+
+    /// Forget components that are no longer configured
+    pub async fn retain(&self, keys: &HashSet<String>) {
+        self.components.write().await.retain(|key, _| keys.contains(key));
+    }
+
+    pub async fn get(&self, key: &str) -> Option<ComponentStatus> {
+        self.components.read().await.get(key).cloned()
+    }
+
+    /// Every component, ordered by key
+    pub async fn components(&self) -> Vec<ComponentStatus> {
+        self.components.read().await.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(healthy: bool, latency_ms: u64) -> ProbeResult {
+        ProbeResult {
+            checked_at: Utc::now(),
+            healthy,
+            latency_ms,
+            error: (!healthy).then(|| "down".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_summarises_history() {
+        let monitor = HealthMonitor::new();
+        monitor.record("provider:openai", ComponentKind::Provider, "OpenAI", Some("openai"), result(true, 100)).await;
+        monitor.record("provider:openai", ComponentKind::Provider, "OpenAI", Some("openai"), result(true, 300)).await;
+        let health = monitor
+            .record("provider:openai", ComponentKind::Provider, "OpenAI", Some("openai"), result(false, 15000))
+            .await;
+
+        assert!(!health.healthy);
+        assert!(health.last_success.is_some() && health.last_error.is_some());
+        assert_eq!(health.avg_latency_ms, Some(200.0));
+        assert!((health.error_rate - 1.0 / 3.0).abs() < 1e-9);
+
+        let status = monitor.get("provider:openai").await.unwrap();
+        assert_eq!(status.history.len(), 3);
+        assert!(!status.latest().unwrap().healthy);
+
+        for _ in 0..HISTORY_LEN {
+            monitor.record("provider:openai", ComponentKind::Provider, "OpenAI", Some("openai"), result(true, 50)).await;
+        }
+        let status = monitor.get("provider:openai").await.unwrap();
+        assert_eq!(status.history.len(), HISTORY_LEN);
+        assert_eq!(status.health.error_rate, 0.0);
+
+        monitor.retain(&HashSet::new()).await;
+        assert!(monitor.components().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        assert!(probe(async { Ok::<_, String>(true) }).await.healthy);
+
+        let failed = probe(async { Err::<bool, _>("connection refused") }).await;
+        assert!(!failed.healthy);
+        assert_eq!(failed.error.as_deref(), Some("connection refused"));
+    }
+}
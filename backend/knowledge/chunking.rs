@@ -0,0 +1,191 @@
+//! Splitting document text into chunks for embedding
+//!
+//! Sizes are in characters. Chunks end at a paragraph, sentence or word
+//! boundary when one falls in the last fifth of the window, and consecutive
+//! chunks share `chunk_overlap` characters so a passage cut in two is still
+//! retrievable from either side.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, SynapseError};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkStrategy {
+    /// Fixed-size windows over the whole document
+    #[default]
+    Fixed,
+    /// Split at Markdown headings first, then by size within each section
+    Heading,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkingConfig {
+    #[serde(default)]
+    pub strategy: ChunkStrategy,
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+    #[serde(default = "default_chunk_overlap")]
+    pub chunk_overlap: usize,
+}
+
+fn default_chunk_size() -> usize {
+    1000
+}
+
+fn default_chunk_overlap() -> usize {
+    200
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ChunkStrategy::default(),
+            chunk_size: default_chunk_size(),
+            chunk_overlap: default_chunk_overlap(),
+        }
+    }
+}
+
+impl ChunkingConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size < 50 {
+            return Err(SynapseError::Validation("chunkSize must be at least 50".to_string()));
+        }
+        if self.chunk_overlap >= self.chunk_size {
+            return Err(SynapseError::Validation("chunkOverlap must be smaller than chunkSize".to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub index: usize,
+    pub content: String,
+    /// Nearest heading above the chunk, with the heading strategy
+    pub heading: Option<String>,
+}
+
+/// Split `text` according to `config`
+pub fn chunk_text(text: &str, config: &ChunkingConfig) -> Vec<Chunk> {
+    let sections = match config.strategy {
+        ChunkStrategy::Fixed => vec![(None, text.to_string())],
+        ChunkStrategy::Heading => sections(text),
+    };
+
+    let mut chunks = Vec::new();
+    for (heading, body) in sections {
+        for content in windows(&body, config.chunk_size, config.chunk_overlap) {
+            chunks.push(Chunk { index: chunks.len(), content, heading: heading.clone() });
+        }
+    }
+    chunks
+}
+
+/// Markdown heading text, if `line` is a heading
+fn heading(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+        Some(trimmed[level..].trim())
+    } else {
+        None
+    }
+}
+
+/// Sections of a Markdown document, each starting at a heading
+fn sections(text: &str) -> Vec<(Option<String>, String)> {
+    let mut sections: Vec<(Option<String>, String)> = vec![(None, String::new())];
+    for line in text.lines() {
+        if let Some(title) = heading(line) {
+            sections.push((Some(title.to_string()), String::new()));
+        }
+        let body = &mut sections.last_mut().expect("at least one section").1;
+        body.push_str(line);
+        body.push('\n');
+    }
+    sections.retain(|(_, body)| !body.trim().is_empty());
+    sections
+}
+
+/// Overlapping windows of at most `size` characters
+fn windows(text: &str, size: usize, overlap: usize) -> Vec<String> {
+    let chars: Vec<char> = text.trim().chars().collect();
+    let mut windows = Vec::new();
+    let mut start = 0;
+
+    while start < chars.len() {
+        let mut end = (start + size).min(chars.len());
+        if end < chars.len() {
+            end = break_point(&chars[start..end]).map_or(end, |at| start + at);
+        }
+
+        let window: String = chars[start..end].iter().collect();
+        if !window.trim().is_empty() {
+            windows.push(window.trim().to_string());
+        }
+        if end == chars.len() {
+            break;
+        }
+        // Always move forward, even when the break point sits inside the overlap
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+    windows
+}
+
+/// Preferred end of a window: after a paragraph break, a sentence or a word
+/// in the last fifth of the window
+fn break_point(window: &[char]) -> Option<usize> {
+    let min = window.len() - window.len() / 5;
+    let after = |pred: &dyn Fn(usize) -> bool| (min..window.len()).rev().find(|&i| pred(i)).map(|i| i + 1);
+
+    after(&|i| window[i] == '\n' && i > 0 && window[i - 1] == '\n')
+        .or_else(|| after(&|i| window[i].is_whitespace() && i > 0 && matches!(window[i - 1], '.' | '!' | '?')))
+        .or_else(|| after(&|i| window[i].is_whitespace()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_chunks_overlap() {
+        let text = "word ".repeat(100);
+        let config = ChunkingConfig { strategy: ChunkStrategy::Fixed, chunk_size: 100, chunk_overlap: 20 };
+        let chunks = chunk_text(&text, &config);
+
+        assert!(chunks.len() > 5);
+        assert!(chunks.iter().all(|c| c.content.chars().count() <= 100));
+        assert!(chunks.iter().all(|c| c.content.starts_with("word")));
+        assert_eq!(chunks.iter().map(|c| c.index).collect::<Vec<_>>(), (0..chunks.len()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_prefers_sentence_boundary() {
+        let text = format!("{}. {}", "a".repeat(90), "b".repeat(60));
+        let config = ChunkingConfig { strategy: ChunkStrategy::Fixed, chunk_size: 100, chunk_overlap: 0 };
+        let chunks = chunk_text(&text, &config);
+        assert_eq!(chunks[0].content, format!("{}.", "a".repeat(90)));
+        assert_eq!(chunks[1].content, "b".repeat(60));
+    }
+
+    #[test]
+    fn test_heading_sections() {
+        let text = "Intro line\n\n# Refunds\nRefunds take 5 days.\n\n## Exceptions\nSale items are final.\n";
+        let chunks = chunk_text(text, &ChunkingConfig { strategy: ChunkStrategy::Heading, ..Default::default() });
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].heading, None);
+        assert_eq!(chunks[1].heading.as_deref(), Some("Refunds"));
+        assert_eq!(chunks[1].content, "# Refunds\nRefunds take 5 days.");
+        assert_eq!(chunks[2].heading.as_deref(), Some("Exceptions"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(ChunkingConfig::default().validate().is_ok());
+        assert!(ChunkingConfig { chunk_size: 100, chunk_overlap: 100, ..Default::default() }.validate().is_err());
+    }
+}
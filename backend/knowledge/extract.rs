@@ -0,0 +1,334 @@
+//! Text extraction from uploaded documents
+//!
+//! Markdown and plain text are used as-is. HTML is reduced to text with its
+//! headings kept as Markdown headings so heading-based chunking works the
+//! same for both. PDF extraction reads the text-showing operators of each
+//! content stream, which covers PDFs with standard fonts; scanned pages and
+//! CID-keyed fonts yield little or no text.
+
+use std::io::Read;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::error::{Result, SynapseError};
+
+/// Document formats accepted for ingestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Text,
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl DocumentFormat {
+    /// Format for a MIME type, falling back to the file extension of `title`
+    pub fn detect(content_type: Option<&str>, title: &str) -> Result<Self> {
+        let mime = content_type.unwrap_or_default().split(';').next().unwrap_or_default().trim();
+        match mime {
+            "text/plain" => return Ok(Self::Text),
+            "text/markdown" | "text/x-markdown" => return Ok(Self::Markdown),
+            "text/html" | "application/xhtml+xml" => return Ok(Self::Html),
+            "application/pdf" => return Ok(Self::Pdf),
+            _ => {}
+        }
+
+        let extension = title.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("txt") | Some("text") => Ok(Self::Text),
+            Some("md") | Some("markdown") => Ok(Self::Markdown),
+            Some("html") | Some("htm") => Ok(Self::Html),
+            Some("pdf") => Ok(Self::Pdf),
+            _ if mime.is_empty() => Ok(Self::Text),
+            _ => Err(SynapseError::Validation(format!("Unsupported document type '{}'", mime))),
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Text => "text/plain",
+            Self::Markdown => "text/markdown",
+            Self::Html => "text/html",
+            Self::Pdf => "application/pdf",
+        }
+    }
+}
+
+/// Extract the text of a document
+pub fn extract_text(format: DocumentFormat, bytes: &[u8]) -> Result<String> {
+    let text = match format {
+        DocumentFormat::Text | DocumentFormat::Markdown => String::from_utf8_lossy(bytes).into_owned(),
+        DocumentFormat::Html => html_to_text(&String::from_utf8_lossy(bytes)),
+        DocumentFormat::Pdf => pdf_to_text(bytes)?,
+    };
+    Ok(text.replace("\r\n", "\n"))
+}
+
+static HTML_DROP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<(script|style|head|noscript)\b.*?</(script|style|head|noscript)\s*>|<!--.*?-->").unwrap());
+static HTML_HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<h([1-6])\b[^>]*>(.*?)</h[1-6]\s*>").unwrap());
+static HTML_BREAK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)<br\s*/?>|</?(p|div|section|article|li|ul|ol|tr|table|blockquote|pre|h[1-6])\b[^>]*>").unwrap()
+});
+static HTML_TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+static BLANK_LINES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n[ \t]*(\n[ \t]*)+").unwrap());
+static SPACES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[ \t]+").unwrap());
+
+/// Visible text of an HTML document, headings as Markdown
+pub fn html_to_text(html: &str) -> String {
+    let html = HTML_DROP.replace_all(html, " ");
+    let html = HTML_HEADING.replace_all(&html, |caps: &regex::Captures| {
+        let level: usize = caps[1].parse().unwrap_or(1);
+        let title = HTML_TAG.replace_all(&caps[2], " ");
+        format!("\n\n{} {}\n\n", "#".repeat(level), title.split_whitespace().collect::<Vec<_>>().join(" "))
+    });
+    let html = HTML_BREAK.replace_all(&html, "\n");
+    let text = decode_entities(&HTML_TAG.replace_all(&html, " "));
+
+    let text = SPACES.replace_all(&text, " ");
+    let text = text.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+    BLANK_LINES.replace_all(&text, "\n\n").trim().to_string()
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let decoded = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => ' ',
+                entity => {
+                    let code = match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => entity.strip_prefix('#').and_then(|dec| dec.parse().ok()),
+                    };
+                    code.and_then(char::from_u32)?
+                }
+            };
+            Some((decoded, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Text shown by the content streams of a PDF
+pub fn pdf_to_text(bytes: &[u8]) -> Result<String> {
+    if !bytes.starts_with(b"%PDF") {
+        return Err(SynapseError::Validation("Not a PDF document".to_string()));
+    }
+
+    let mut text = String::new();
+    let mut pos = 0;
+    while let Some(start) = find(bytes, b"stream", pos) {
+        // Skip the `endstream` keyword itself
+        if start >= 3 && &bytes[start - 3..start] == b"end" {
+            pos = start + 6;
+            continue;
+        }
+        let dict_start = bytes[..start].windows(2).rposition(|w| w == b"<<").unwrap_or(start);
+        let dictionary = &bytes[dict_start..start];
+
+        let mut data_start = start + 6;
+        if bytes.get(data_start) == Some(&b'\r') {
+            data_start += 1;
+        }
+        if bytes.get(data_start) == Some(&b'\n') {
+            data_start += 1;
+        }
+        let Some(end) = find(bytes, b"endstream", data_start) else {
+            break;
+        };
+        pos = end + 9;
+
+        let raw = &bytes[data_start..end];
+        let data = if find(dictionary, b"/FlateDecode", 0).is_some() {
+            let mut inflated = Vec::new();
+            if flate2::read::ZlibDecoder::new(raw).read_to_end(&mut inflated).is_err() {
+                continue;
+            }
+            inflated
+        } else if find(dictionary, b"/Filter", 0).is_some() {
+            // Images and other encodings carry no text we can read
+            continue;
+        } else {
+            raw.to_vec()
+        };
+
+        let shown = content_stream_text(&data);
+        if !shown.trim().is_empty() {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(shown.trim());
+        }
+    }
+
+    Ok(text)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+/// Walk a content stream, collecting strings passed to text operators
+fn content_stream_text(data: &[u8]) -> String {
+    let mut text = String::new();
+    let mut pending = String::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        match data[i] {
+            b'(' => {
+                let (string, next) = literal_string(data, i + 1);
+                pending.push_str(&string);
+                i = next;
+            }
+            b'%' => {
+                while i < data.len() && data[i] != b'\n' && data[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'[' | b']' => i += 1,
+            c if c.is_ascii_alphabetic() || c == b'\'' || c == b'"' || c == b'*' => {
+                let start = i;
+                while i < data.len() && (data[i].is_ascii_alphanumeric() || matches!(data[i], b'\'' | b'"' | b'*')) {
+                    i += 1;
+                }
+                match &data[start..i] {
+                    b"Tj" | b"TJ" => text.push_str(&std::mem::take(&mut pending)),
+                    b"'" | b"\"" => {
+                        text.push('\n');
+                        text.push_str(&std::mem::take(&mut pending));
+                    }
+                    b"T*" | b"Td" | b"TD" | b"ET" => {
+                        if !text.ends_with('\n') && !text.is_empty() {
+                            text.push('\n');
+                        }
+                    }
+                    _ => pending.clear(),
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    text
+}
+
+/// Decode a literal string starting after its `(`, returning the text and
+/// the index after the closing `)`
+fn literal_string(data: &[u8], mut i: usize) -> (String, usize) {
+    let mut out = Vec::new();
+    let mut depth = 1;
+    while i < data.len() {
+        let c = data[i];
+        i += 1;
+        match c {
+            b'\\' if i < data.len() => {
+                let escaped = data[i];
+                i += 1;
+                match escaped {
+                    b'n' => out.push(b'\n'),
+                    b'r' => out.push(b'\r'),
+                    b't' => out.push(b'\t'),
+                    b'b' | b'f' => {}
+                    b'0'..=b'7' => {
+                        let mut value = (escaped - b'0') as u32;
+                        for _ in 0..2 {
+                            match data.get(i) {
+                                Some(d @ b'0'..=b'7') => {
+                                    value = value * 8 + (d - b'0') as u32;
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        out.push(value as u8);
+                    }
+                    b'\n' => {}
+                    b'\r' => {
+                        if data.get(i) == Some(&b'\n') {
+                            i += 1;
+                        }
+                    }
+                    other => out.push(other),
+                }
+            }
+            b'(' => {
+                depth += 1;
+                out.push(c);
+            }
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    // Standard fonts use a Latin-1 compatible encoding for printable text
+    (out.iter().map(|&b| b as char).collect(), i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(DocumentFormat::detect(Some("text/html; charset=utf-8"), "x").unwrap(), DocumentFormat::Html);
+        assert_eq!(DocumentFormat::detect(None, "guide.md").unwrap(), DocumentFormat::Markdown);
+        assert_eq!(DocumentFormat::detect(Some("application/octet-stream"), "report.PDF").unwrap(), DocumentFormat::Pdf);
+        assert!(DocumentFormat::detect(Some("image/png"), "logo.png").is_err());
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><title>T</title><style>p{}</style></head><body>
+            <h1>Refunds</h1><p>Refunds take <b>5</b>&nbsp;days &amp; are free.</p>
+            <script>alert(1)</script><ul><li>One</li><li>Two</li></ul></body></html>"#;
+        assert_eq!(html_to_text(html), "# Refunds\n\nRefunds take 5 days & are free.\n\nOne\n\nTwo");
+    }
+
+    #[test]
+    fn test_pdf_to_text() {
+        let content = b"BT /F1 12 Tf 72 720 Td (Hello \\(PDF\\)) Tj T* [(Wor) -20 (ld)] TJ ET";
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut pdf = b"%PDF-1.4\n1 0 obj\n<< /Length 10 /Filter /FlateDecode >>\nstream\n".to_vec();
+        pdf.extend_from_slice(&compressed);
+        pdf.extend_from_slice(b"\nendstream\nendobj\n%%EOF");
+
+        assert_eq!(pdf_to_text(&pdf).unwrap(), "Hello (PDF)\nWorld");
+        assert!(pdf_to_text(b"not a pdf").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Knowledge bases
+//!
+//! Documents uploaded to a collection are converted to text ([`extract`]),
+//! split into chunks ([`chunking`]), embedded through the hub's embedding
+//! providers and stored in the collection's vector store, whose collection
+//! name is the knowledge collection id. Ingestion runs in the background
+//! ([`worker`]); each document row tracks its job status and progress. Collections are
+//! queried through [`search`], and [`rag`] grounds chat requests in them.
+
+pub mod chunking;
+pub mod extract;
+pub mod rag;
+pub mod rerank;
+pub mod search;
+pub mod worker;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use crate::cache::Embedder;
+use crate::db::{KnowledgeChunkRow, KnowledgeCollectionRow, KnowledgeDocumentRow, KnowledgeRepository, NewKnowledgeChunk};
+use crate::error::{Result, SynapseError};
+use crate::vector::{VectorFilter, VectorRecord, VectorStore};
+use crate::{EmbeddingRequest, TokenUsage};
+use chunking::ChunkingConfig;
+
+/// Chunks embedded per request
+const EMBED_BATCH: usize = 32;
+
+/// Largest accepted upload, before base64 encoding
+pub const MAX_DOCUMENT_BYTES: usize = 20 * 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub agent_id: Option<String>,
+    pub document_count: i32,
+    pub chunk_count: i32,
+    pub embedding_model: String,
+    pub embedding_provider: Option<String>,
+    pub vector_account_id: Option<String>,
+    pub chunking: ChunkingConfig,
+    pub metadata: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Collection {
+    pub fn from_row(row: KnowledgeCollectionRow) -> Result<Self> {
+        let embedding_model = row.embedding_model
+            .ok_or_else(|| SynapseError::Internal(format!("Collection {} has no embedding model", row.id)))?;
+        let chunking = serde_json::from_value(row.chunking).unwrap_or_default();
+
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            agent_id: row.agent_id,
+            document_count: row.document_count,
+            chunk_count: row.chunk_count,
+            embedding_model,
+            embedding_provider: row.embedding_provider,
+            vector_account_id: row.vector_account_id,
+            chunking,
+            metadata: row.metadata,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Ingestion job state of a document
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestProgress {
+    /// `pending`, `processing`, `completed` or `failed`
+    pub status: String,
+    pub chunks_total: i32,
+    pub chunks_embedded: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A document without its text
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Document {
+    pub id: String,
+    pub collection_id: String,
+    pub title: String,
+    pub content_type: String,
+    pub file_size: i64,
+    pub progress: IngestProgress,
+    pub metadata: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<KnowledgeDocumentRow> for Document {
+    fn from(row: KnowledgeDocumentRow) -> Self {
+        Self {
+            id: row.id,
+            collection_id: row.collection_id,
+            title: row.title,
+            content_type: row.content_type,
+            file_size: row.file_size,
+            progress: IngestProgress {
+                status: row.status,
+                chunks_total: row.chunk_count,
+                chunks_embedded: row.chunks_embedded,
+                error: row.error,
+            },
+            metadata: row.metadata,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// Payload stored with a chunk's vector: the document's top-level metadata
+/// fields, so searches can filter on them, and the chunk's own fields
+pub fn chunk_payload(
+    collection_id: &str,
+    document_id: &str,
+    title: &str,
+    document_metadata: &JsonValue,
+    chunk_index: i32,
+    content: &str,
+    heading: &JsonValue,
+) -> JsonValue {
+    let mut payload = document_metadata.as_object().cloned().unwrap_or_default();
+    for (key, value) in [
+        ("content", JsonValue::from(content)),
+        ("document_id", JsonValue::from(document_id)),
+        ("collection_id", JsonValue::from(collection_id)),
+        ("title", JsonValue::from(title)),
+        ("chunk_index", JsonValue::from(chunk_index)),
+        ("heading", heading.clone()),
+    ] {
+        payload.insert(key.to_string(), value);
+    }
+    JsonValue::Object(payload)
+}
+
+impl From<KnowledgeChunkRow> for search::SearchChunk {
+    fn from(row: KnowledgeChunkRow) -> Self {
+        let payload = chunk_payload(
+            &row.collection_id,
+            &row.document_id,
+            &row.title,
+            &row.document_metadata,
+            row.chunk_index,
+            &row.content,
+            &row.metadata["heading"],
+        );
+        Self { id: row.id, content: row.content, payload }
+    }
+}
+
+/// Filter selecting a document's vectors
+pub fn document_filter(document_id: &str) -> VectorFilter {
+    let mut filter = VectorFilter::new();
+    filter.insert("document_id".to_string(), serde_json::Value::from(document_id));
+    filter
+}
+
+/// What embedding a document cost
+#[derive(Debug, Clone, Default)]
+pub struct IngestOutcome {
+    pub chunks: usize,
+    /// Provider that served the embeddings
+    pub provider: String,
+    pub usage: TokenUsage,
+    pub cost: f64,
+}
+
+/// Chunk, embed and store a document, recording progress on its row
+///
+/// The caller marks the document failed if this returns an error.
+pub async fn ingest_document(
+    repo: &KnowledgeRepository,
+    embedder: &dyn Embedder,
+    store: &dyn VectorStore,
+    collection: &Collection,
+    document: &KnowledgeDocumentRow,
+) -> Result<IngestOutcome> {
+    repo.set_document_status(&document.id, "processing", None).await?;
+
+    let chunks: Vec<NewKnowledgeChunk> = chunking::chunk_text(&document.content, &collection.chunking)
+        .into_iter()
+        .map(|chunk| NewKnowledgeChunk {
+            id: uuid::Uuid::new_v4().to_string(),
+            chunk_index: chunk.index as i32,
+            content: chunk.content,
+            metadata: serde_json::json!({ "heading": chunk.heading }),
+        })
+        .collect();
+    repo.replace_chunks(&document.id, &chunks).await?;
+    repo.set_document_progress(&document.id, chunks.len() as i32, 0).await?;
+
+    let mut outcome = IngestOutcome { chunks: chunks.len(), ..Default::default() };
+    let mut ready = false;
+    let mut embedded = 0;
+
+    for batch in chunks.chunks(EMBED_BATCH) {
+        let response = embedder.embed(&EmbeddingRequest {
+            model: collection.embedding_model.clone(),
+            input: batch.iter().map(|c| c.content.clone()).collect(),
+            provider: collection.embedding_provider.clone(),
+            dimensions: None,
+            user_id: document.created_by.clone(),
+        }).await?;
+        if response.data.len() != batch.len() {
+            return Err(SynapseError::Internal(format!(
+                "Expected {} embeddings, got {}",
+                batch.len(),
+                response.data.len()
+            )));
+        }
+
+        outcome.provider = response.provider;
+        outcome.usage.prompt_tokens += response.usage.prompt_tokens;
+        outcome.usage.total_tokens += response.usage.total_tokens;
+        outcome.cost += response.cost;
+
+        if !ready {
+            let dimensions = response.data.first().map_or(0, Vec::len);
+            store.ensure_collection(&collection.id, dimensions).await?;
+            // Chunks from an earlier, interrupted run have other ids
+            store.delete_matching(&collection.id, &document_filter(&document.id)).await?;
+            ready = true;
+        }
+
+        let records = batch
+            .iter()
+            .zip(response.data)
+            .map(|(chunk, vector)| VectorRecord {
+                id: chunk.id.clone(),
+                vector,
+                payload: chunk_payload(
+                    &collection.id,
+                    &document.id,
+                    &document.title,
+                    &document.metadata,
+                    chunk.chunk_index,
+                    &chunk.content,
+                    &chunk.metadata["heading"],
+                ),
+            })
+            .collect();
+        store.upsert(&collection.id, records).await?;
+
+        embedded += batch.len();
+        repo.set_document_progress(&document.id, chunks.len() as i32, embedded as i32).await?;
+    }
+
+    repo.set_document_status(&document.id, "completed", None).await?;
+    repo.refresh_collection_counts(&collection.id).await?;
+    Ok(outcome)
+}
@@ -0,0 +1,150 @@
+//! Retrieval-augmented chat
+//!
+//! A chat request with `rag` set searches a knowledge collection for the
+//! last user message, renders the results into a system message through a
+//! template, and returns the results as numbered citations.
+
+use serde::{Deserialize, Serialize};
+
+use super::search::{SearchOptions, SearchResult};
+use crate::error::{Result, SynapseError};
+use crate::Message;
+
+/// Template used unless the request sets one; `{context}` is replaced by the
+/// numbered results and `{question}` by the last user message
+pub const DEFAULT_TEMPLATE: &str =
+    "Answer using the following context where it is relevant, citing the sources you use as [n].\n\n{context}";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RagOptions {
+    #[serde(alias = "collectionId")]
+    pub collection_id: String,
+    #[serde(flatten)]
+    pub search: SearchOptions,
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+/// A retrieved chunk the answer may cite as `[index]`
+#[derive(Debug, Clone, Serialize)]
+pub struct Citation {
+    pub index: usize,
+    pub chunk_id: String,
+    pub document_id: Option<String>,
+    pub chunk_index: Option<i64>,
+    pub title: Option<String>,
+    pub score: f32,
+}
+
+/// Text of the last user message, the query to retrieve for
+pub fn question(messages: &[Message]) -> Result<String> {
+    messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.text())
+        .filter(|text| !text.trim().is_empty())
+        .ok_or_else(|| SynapseError::Validation("rag needs a user message to search for".to_string()))
+}
+
+fn render_context(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| {
+            let source = [result.title.as_deref(), result.heading.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" / ");
+            if source.is_empty() {
+                format!("[{}] {}", i + 1, result.content)
+            } else {
+                format!("[{}] ({}) {}", i + 1, source, result.content)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Add the rendered context to the conversation's system message, creating
+/// one if needed
+pub fn inject(messages: &mut Vec<Message>, template: Option<&str>, question: &str, results: &[SearchResult]) {
+    if results.is_empty() {
+        return;
+    }
+    let context = template
+        .unwrap_or(DEFAULT_TEMPLATE)
+        .replace("{context}", &render_context(results))
+        .replace("{question}", question);
+
+    match messages.first_mut() {
+        Some(first) if first.role == "system" => {
+            *first = Message::system(format!("{}\n\n{}", first.content.text(), context));
+        }
+        _ => messages.insert(0, Message::system(context)),
+    }
+}
+
+pub fn citations(results: &[SearchResult]) -> Vec<Citation> {
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| Citation {
+            index: i + 1,
+            chunk_id: result.chunk_id.clone(),
+            document_id: result.document_id.clone(),
+            chunk_index: result.chunk_index,
+            title: result.title.clone(),
+            score: result.score,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(title: Option<&str>, content: &str) -> SearchResult {
+        SearchResult {
+            chunk_id: "c1".to_string(),
+            document_id: Some("d1".to_string()),
+            title: title.map(str::to_string),
+            heading: None,
+            chunk_index: Some(3),
+            content: content.to_string(),
+            score: 0.8,
+            vector_score: None,
+            keyword_score: None,
+            rerank_score: None,
+        }
+    }
+
+    #[test]
+    fn test_inject() {
+        let results = vec![result(Some("Policy"), "Refunds take 5 days."), result(None, "Sale items are final.")];
+
+        let mut messages = vec![Message::system("Be brief."), Message::user("How long do refunds take?")];
+        let question = question(&messages).unwrap();
+        inject(&mut messages, Some("Q: {question}\n{context}"), &question, &results);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[0].content.text(),
+            "Be brief.\n\nQ: How long do refunds take?\n[1] (Policy) Refunds take 5 days.\n\n[2] Sale items are final."
+        );
+
+        let mut messages = vec![Message::user("Hi")];
+        inject(&mut messages, None, "Hi", &results);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[0].content.text().starts_with("Answer using the following context"));
+    }
+
+    #[test]
+    fn test_citations() {
+        let citations = citations(&[result(Some("Policy"), "Refunds take 5 days.")]);
+        assert_eq!(citations[0].index, 1);
+        assert_eq!(citations[0].document_id.as_deref(), Some("d1"));
+        assert_eq!(citations[0].chunk_index, Some(3));
+        assert!(question(&[Message::system("x")]).is_err());
+    }
+}
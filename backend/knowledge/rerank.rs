@@ -0,0 +1,104 @@
+//! Reranking through Cohere and Jina rerank models
+//!
+//! Both providers expose `POST {base_url}/rerank` taking a query and a list
+//! of documents and returning a relevance score per document index.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::streaming;
+use crate::telemetry::PropagateTrace;
+use crate::Provider;
+
+/// Providers with a rerank endpoint
+pub const RERANK_PROVIDERS: &[&str] = &["cohere", "jina"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RerankOptions {
+    /// `cohere` or `jina`
+    pub provider: String,
+    /// e.g. `rerank-english-v3.0` or `jina-reranker-v2-base-multilingual`
+    pub model: String,
+}
+
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Relevance of each document to `query`, in the order given
+    async fn rerank(&self, options: &RerankOptions, query: &str, documents: &[String]) -> Result<Vec<f32>>;
+}
+
+/// Call a provider's rerank endpoint
+pub async fn rerank(
+    client: &reqwest::Client,
+    provider: &Provider,
+    model: &str,
+    query: &str,
+    documents: &[String],
+) -> Result<Vec<f32>> {
+    if documents.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let response = client
+        .post(format!("{}/rerank", provider.base_url.trim_end_matches('/')))
+        .propagate_trace()
+        .bearer_auth(&provider.api_key)
+        .json(&serde_json::json!({
+            "model": model,
+            "query": query,
+            "documents": documents,
+            "top_n": documents.len(),
+        }))
+        .send()
+        .await
+        .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+    let body: serde_json::Value = streaming::check_status(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+    let results = body["results"]
+        .as_array()
+        .ok_or_else(|| SynapseError::Provider(ProviderError::InvalidResponse("rerank response has no results".to_string())))?;
+    let mut scores = vec![0.0; documents.len()];
+    for result in results {
+        let index = result["index"].as_u64().unwrap_or(u64::MAX) as usize;
+        if let Some(score) = scores.get_mut(index) {
+            *score = result["relevance_score"].as_f64().unwrap_or(0.0) as f32;
+        }
+    }
+    Ok(scores)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_rerank() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/rerank"))
+            .and(header("authorization", "Bearer key"))
+            .and(body_partial_json(serde_json::json!({"model": "rerank-v3", "query": "refunds", "top_n": 2})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "results": [{"index": 1, "relevance_score": 0.9}, {"index": 0, "relevance_score": 0.1}],
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = Provider {
+            id: "cohere".to_string(),
+            api_key: "key".to_string(),
+            base_url: format!("{}/v1", server.uri()),
+            ..Default::default()
+        };
+        let documents = vec!["shipping".to_string(), "refunds".to_string()];
+        let scores = rerank(&reqwest::Client::new(), &provider, "rerank-v3", "refunds", &documents).await.unwrap();
+        assert_eq!(scores, vec![0.1, 0.9]);
+    }
+}
@@ -0,0 +1,446 @@
+//! Hybrid search over a knowledge collection
+//!
+//! Vector matches come from the collection's vector store; keyword matches
+//! are BM25-ranked here over the collection's stored chunks. Each side's
+//! scores are brought to 0..1 (cosine similarity as is, BM25 divided by the
+//! best keyword score) and combined as `alpha * vector + (1 - alpha) *
+//! keyword`. The fused candidates can then be reranked by a rerank model and
+//! diversified with maximal marginal relevance, using word overlap between
+//! chunks as their similarity.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::rerank::{RerankOptions, Reranker};
+use crate::cache::Embedder;
+use crate::error::{Result, SynapseError};
+use crate::vector::{VectorFilter, VectorStore};
+use crate::{EmbeddingRequest, EmbeddingResponse};
+
+/// Results returned unless `topK` is set
+pub const DEFAULT_TOP_K: usize = 5;
+
+/// Candidates gathered from each side per requested result
+const CANDIDATES_PER_RESULT: usize = 4;
+
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    Vector,
+    Keyword,
+    #[default]
+    Hybrid,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOptions {
+    #[serde(default)]
+    pub mode: SearchMode,
+    #[serde(default, alias = "top_k", skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<usize>,
+    /// Exact-match filter on chunk fields and document metadata
+    #[serde(default, skip_serializing_if = "VectorFilter::is_empty")]
+    pub filter: VectorFilter,
+    /// Weight of the vector score in hybrid mode, 0.5 by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<f32>,
+    /// Drop results scoring below this, before reranking
+    #[serde(default, alias = "min_score", skip_serializing_if = "Option::is_none")]
+    pub min_score: Option<f32>,
+    /// Diversify results with MMR; 1.0 is pure relevance, 0.0 pure diversity
+    #[serde(default, alias = "mmr_lambda", skip_serializing_if = "Option::is_none")]
+    pub mmr_lambda: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rerank: Option<RerankOptions>,
+}
+
+impl SearchOptions {
+    pub fn validate(&self) -> Result<()> {
+        if self.top_k == Some(0) || self.top_k.is_some_and(|k| k > 100) {
+            return Err(SynapseError::Validation("topK must be between 1 and 100".to_string()));
+        }
+        for (name, value) in [("alpha", self.alpha), ("mmrLambda", self.mmr_lambda)] {
+            if value.is_some_and(|v| !(0.0..=1.0).contains(&v)) {
+                return Err(SynapseError::Validation(format!("{} must be between 0 and 1", name)));
+            }
+        }
+        Ok(())
+    }
+
+    fn top_k(&self) -> usize {
+        self.top_k.unwrap_or(DEFAULT_TOP_K)
+    }
+}
+
+/// A stored chunk with the payload its vector carries
+#[derive(Debug, Clone)]
+pub struct SearchChunk {
+    pub id: String,
+    pub content: String,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub chunk_id: String,
+    pub document_id: Option<String>,
+    pub title: Option<String>,
+    pub heading: Option<String>,
+    pub chunk_index: Option<i64>,
+    pub content: String,
+    /// Final score the results are ordered by
+    pub score: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vector_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keyword_score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rerank_score: Option<f32>,
+}
+
+impl SearchResult {
+    fn new(id: String, payload: &serde_json::Value) -> Self {
+        Self {
+            chunk_id: id,
+            document_id: payload["document_id"].as_str().map(str::to_string),
+            title: payload["title"].as_str().map(str::to_string),
+            heading: payload["heading"].as_str().map(str::to_string),
+            chunk_index: payload["chunk_index"].as_i64(),
+            content: payload["content"].as_str().unwrap_or_default().to_string(),
+            score: 0.0,
+            vector_score: None,
+            keyword_score: None,
+            rerank_score: None,
+        }
+    }
+}
+
+/// Search results with the query embedding that produced them, if any
+pub struct SearchOutcome {
+    pub results: Vec<SearchResult>,
+    pub embedding: Option<EmbeddingResponse>,
+}
+
+/// Lowercased alphanumeric words
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn matches(payload: &serde_json::Value, filter: &VectorFilter) -> bool {
+    filter.iter().all(|(key, value)| payload.get(key) == Some(value))
+}
+
+/// BM25 scores of `texts` for `query`, best first, omitting texts that
+/// share no word with it
+pub fn bm25(query: &str, texts: &[&str]) -> Vec<(usize, f32)> {
+    let terms: HashSet<String> = tokenize(query).into_iter().collect();
+    if terms.is_empty() || texts.is_empty() {
+        return Vec::new();
+    }
+
+    let documents: Vec<Vec<String>> = texts.iter().map(|text| tokenize(text)).collect();
+    let average_length = documents.iter().map(Vec::len).sum::<usize>() as f32 / documents.len() as f32;
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for words in &documents {
+        let unique: HashSet<&str> = words.iter().map(String::as_str).collect();
+        for term in &terms {
+            if unique.contains(term.as_str()) {
+                *document_frequency.entry(term.as_str()).or_default() += 1;
+            }
+        }
+    }
+
+    let total = documents.len() as f32;
+    let mut scores: Vec<(usize, f32)> = documents
+        .iter()
+        .enumerate()
+        .filter_map(|(i, words)| {
+            let length_norm = 1.0 - BM25_B + BM25_B * words.len() as f32 / average_length.max(1.0);
+            let score: f32 = terms
+                .iter()
+                .filter_map(|term| {
+                    let frequency = words.iter().filter(|w| *w == term).count() as f32;
+                    if frequency == 0.0 {
+                        return None;
+                    }
+                    let df = document_frequency[term.as_str()] as f32;
+                    let idf = (1.0 + (total - df + 0.5) / (df + 0.5)).ln();
+                    Some(idf * frequency * (BM25_K1 + 1.0) / (frequency + BM25_K1 * length_norm))
+                })
+                .sum();
+            (score > 0.0).then_some((i, score))
+        })
+        .collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    scores
+}
+
+/// Word-overlap (Jaccard) similarity of two texts
+fn overlap(a: &HashSet<String>, b: &HashSet<String>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+/// Pick `top_k` results balancing score against similarity to the results
+/// already picked
+pub fn mmr(results: Vec<SearchResult>, top_k: usize, lambda: f32) -> Vec<SearchResult> {
+    let words: Vec<HashSet<String>> = results.iter().map(|r| tokenize(&r.content).into_iter().collect()).collect();
+    let mut remaining: Vec<usize> = (0..results.len()).collect();
+    let mut picked: Vec<usize> = Vec::with_capacity(top_k);
+
+    while picked.len() < top_k && !remaining.is_empty() {
+        let (position, _) = remaining
+            .iter()
+            .enumerate()
+            .map(|(position, &i)| {
+                let redundancy = picked.iter().map(|&j| overlap(&words[i], &words[j])).fold(0.0, f32::max);
+                (position, lambda * results[i].score - (1.0 - lambda) * redundancy)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("remaining is not empty");
+        picked.push(remaining.remove(position));
+    }
+
+    let mut results: Vec<Option<SearchResult>> = results.into_iter().map(Some).collect();
+    picked.into_iter().filter_map(|i| results[i].take()).collect()
+}
+
+/// Search a collection
+///
+/// `chunks` are the collection's stored chunks for keyword ranking; they
+/// are only read in keyword and hybrid modes.
+#[allow(clippy::too_many_arguments)]
+pub async fn search(
+    embedder: &dyn Embedder,
+    store: &dyn VectorStore,
+    reranker: &dyn Reranker,
+    collection: &super::Collection,
+    chunks: &[SearchChunk],
+    query: &str,
+    options: &SearchOptions,
+    user_id: Option<String>,
+) -> Result<SearchOutcome> {
+    options.validate()?;
+    if query.trim().is_empty() {
+        return Err(SynapseError::Validation("query must not be empty".to_string()));
+    }
+    let top_k = options.top_k();
+    let candidates = top_k * CANDIDATES_PER_RESULT;
+    let mut fused: Vec<SearchResult> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    let mut embedding = None;
+    if options.mode != SearchMode::Keyword {
+        let response = embedder.embed(&EmbeddingRequest {
+            model: collection.embedding_model.clone(),
+            input: vec![query.to_string()],
+            provider: collection.embedding_provider.clone(),
+            dimensions: None,
+            user_id,
+        }).await?;
+        if let Some(vector) = response.data.first() {
+            for m in store.query(&collection.id, vector, candidates, &options.filter).await? {
+                index.insert(m.id.clone(), fused.len());
+                let mut result = SearchResult::new(m.id, &m.payload);
+                result.vector_score = Some(m.score);
+                fused.push(result);
+            }
+        }
+        embedding = Some(response);
+    }
+
+    if options.mode != SearchMode::Vector {
+        let eligible: Vec<&SearchChunk> = chunks.iter().filter(|c| matches(&c.payload, &options.filter)).collect();
+        let texts: Vec<&str> = eligible.iter().map(|c| c.content.as_str()).collect();
+        let ranked = bm25(query, &texts);
+        let best = ranked.first().map_or(1.0, |&(_, score)| score);
+        for (i, score) in ranked.into_iter().take(candidates) {
+            let chunk = eligible[i];
+            let slot = *index.entry(chunk.id.clone()).or_insert_with(|| {
+                fused.push(SearchResult::new(chunk.id.clone(), &chunk.payload));
+                fused.len() - 1
+            });
+            fused[slot].keyword_score = Some(score / best);
+        }
+    }
+
+    let alpha = match options.mode {
+        SearchMode::Vector => 1.0,
+        SearchMode::Keyword => 0.0,
+        SearchMode::Hybrid => options.alpha.unwrap_or(0.5),
+    };
+    for result in &mut fused {
+        result.score = alpha * result.vector_score.unwrap_or(0.0) + (1.0 - alpha) * result.keyword_score.unwrap_or(0.0);
+    }
+    if let Some(min_score) = options.min_score {
+        fused.retain(|r| r.score >= min_score);
+    }
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    if let Some(ref rerank) = options.rerank {
+        let documents: Vec<String> = fused.iter().map(|r| r.content.clone()).collect();
+        let scores = reranker.rerank(rerank, query, &documents).await?;
+        for (result, score) in fused.iter_mut().zip(scores) {
+            result.rerank_score = Some(score);
+            result.score = score;
+        }
+        fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    }
+
+    let results = match options.mmr_lambda {
+        Some(lambda) => mmr(fused, top_k, lambda),
+        None => {
+            fused.truncate(top_k);
+            fused
+        }
+    };
+    Ok(SearchOutcome { results, embedding })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{MemoryVectorStore, VectorRecord};
+    use crate::TokenUsage;
+
+    fn chunk(id: &str, content: &str) -> SearchChunk {
+        SearchChunk {
+            id: id.to_string(),
+            content: content.to_string(),
+            payload: serde_json::json!({"content": content, "document_id": "d1", "chunk_index": 0}),
+        }
+    }
+
+    fn result(id: &str, content: &str, score: f32) -> SearchResult {
+        let mut result = SearchResult::new(id.to_string(), &serde_json::json!({"content": content}));
+        result.score = score;
+        result
+    }
+
+    #[test]
+    fn test_bm25() {
+        let texts = [
+            "Refunds are issued within five days.",
+            "Shipping takes two days. Shipping is free over 50 dollars.",
+            "Contact support for refunds on sale items and refunds abroad.",
+        ];
+        let ranked = bm25("refunds", &texts);
+        assert_eq!(ranked.iter().map(|&(i, _)| i).collect::<Vec<_>>(), vec![2, 0]);
+        assert!(bm25("warranty", &texts).is_empty());
+        assert!(bm25("", &texts).is_empty());
+    }
+
+    #[test]
+    fn test_mmr_prefers_diverse_results() {
+        let results = vec![
+            result("a", "refunds take five days", 0.9),
+            result("b", "refunds take five days", 0.89),
+            result("c", "shipping is free", 0.7),
+        ];
+        let picked: Vec<String> = mmr(results.clone(), 2, 0.5).into_iter().map(|r| r.chunk_id).collect();
+        assert_eq!(picked, vec!["a", "c"]);
+
+        let picked: Vec<String> = mmr(results, 2, 1.0).into_iter().map(|r| r.chunk_id).collect();
+        assert_eq!(picked, vec!["a", "b"]);
+    }
+
+    struct FakeEmbedder;
+
+    #[async_trait::async_trait]
+    impl Embedder for FakeEmbedder {
+        async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+            Ok(EmbeddingResponse {
+                provider: "fake".to_string(),
+                model: request.model.clone(),
+                data: vec![vec![1.0, 0.0]],
+                usage: TokenUsage::default(),
+                latency_ms: 0,
+                cost: 0.0,
+            })
+        }
+    }
+
+    /// Scores documents by their length, longest first
+    struct LengthReranker;
+
+    #[async_trait::async_trait]
+    impl Reranker for LengthReranker {
+        async fn rerank(&self, _options: &RerankOptions, _query: &str, documents: &[String]) -> Result<Vec<f32>> {
+            Ok(documents.iter().map(|d| d.len() as f32).collect())
+        }
+    }
+
+    fn collection() -> crate::knowledge::Collection {
+        crate::knowledge::Collection {
+            id: "docs".to_string(),
+            name: "Docs".to_string(),
+            description: None,
+            agent_id: None,
+            document_count: 1,
+            chunk_count: 3,
+            embedding_model: "embed".to_string(),
+            embedding_provider: None,
+            vector_account_id: None,
+            chunking: Default::default(),
+            metadata: serde_json::json!({}),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        let chunks = vec![
+            chunk("a", "Refunds are issued within five days."),
+            chunk("b", "Shipping is free over 50 dollars."),
+            chunk("c", "Gift cards never expire."),
+        ];
+        let store = MemoryVectorStore::new();
+        let vectors = [vec![0.6, 0.8], vec![0.0, 1.0], vec![1.0, 0.0]];
+        store.upsert("docs", chunks.iter().zip(vectors).map(|(c, vector)| VectorRecord {
+            id: c.id.clone(),
+            vector,
+            payload: c.payload.clone(),
+        }).collect()).await.unwrap();
+
+        // "c" is closest by vector, "a" is the only keyword match
+        let options = SearchOptions { top_k: Some(2), ..Default::default() };
+        let outcome = search(&FakeEmbedder, &store, &LengthReranker, &collection(), &chunks, "refunds", &options, None)
+            .await
+            .unwrap();
+        let ids: Vec<&str> = outcome.results.iter().map(|r| r.chunk_id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert_eq!(outcome.results[0].keyword_score, Some(1.0));
+        assert!((outcome.results[0].score - 0.8).abs() < 1e-6);
+        assert!(outcome.embedding.is_some());
+
+        let options = SearchOptions { mode: SearchMode::Keyword, ..Default::default() };
+        let outcome = search(&FakeEmbedder, &store, &LengthReranker, &collection(), &chunks, "refunds", &options, None)
+            .await
+            .unwrap();
+        assert_eq!(outcome.results.len(), 1);
+        assert!(outcome.embedding.is_none());
+
+        let options = SearchOptions {
+            mode: SearchMode::Vector,
+            rerank: Some(RerankOptions { provider: "cohere".to_string(), model: "rerank".to_string() }),
+            ..Default::default()
+        };
+        let outcome = search(&FakeEmbedder, &store, &LengthReranker, &collection(), &chunks, "refunds", &options, None)
+            .await
+            .unwrap();
+        assert_eq!(outcome.results[0].chunk_id, "a");
+        assert_eq!(outcome.results[0].rerank_score, Some(36.0));
+    }
+}
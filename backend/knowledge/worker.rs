@@ -0,0 +1,172 @@
+//! Background ingestion
+//!
+//! [`IngestionWorker`] claims documents whose ingestion has not finished,
+//! leasing each so that only one replica embeds it. A document left
+//! `processing` by a replica that stopped is claimed again once its lease
+//! lapses and ingested from the start.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::{Notify, Semaphore};
+
+use super::{ingest_document, Collection, IngestOutcome};
+use crate::cache::Embedder;
+use crate::cost::CostManager;
+use crate::db::{CostRepository, DbPool, KnowledgeDocumentRow, KnowledgeRepository};
+use crate::error::{Result, SynapseError};
+use crate::vector::VectorStore;
+
+/// Lease a replica holds on a document it ingests, renewed every third of
+/// its length
+const INGEST_LEASE_SECS: i64 = 60;
+
+/// Documents one replica ingests at a time
+const MAX_INGESTIONS: usize = 4;
+
+/// How often the worker looks for documents without being woken
+const INGEST_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What ingestion needs from the rest of the hub
+#[async_trait]
+pub trait IngestServices: Embedder {
+    /// Vector store behind a collection's vector database account
+    async fn vector_store(&self, account_id: Option<&str>) -> Result<Arc<dyn VectorStore>>;
+}
+
+/// Claims and runs ingestion jobs
+pub struct IngestionWorker {
+    pool: DbPool,
+    /// Identifies this replica as the holder of document leases
+    owner: String,
+    cost_manager: Arc<CostManager>,
+    wakeup: Notify,
+}
+
+impl IngestionWorker {
+    pub fn new(pool: DbPool, owner: String, cost_manager: Arc<CostManager>) -> Self {
+        Self { pool, owner, cost_manager, wakeup: Notify::new() }
+    }
+
+    /// Have the worker look for queued documents now
+    pub fn wake(&self) {
+        self.wakeup.notify_one();
+    }
+
+    /// Claim and ingest documents until the process exits
+    pub async fn run(self: Arc<Self>, services: Arc<dyn IngestServices>) {
+        let repo = KnowledgeRepository::new(self.pool.clone());
+        let slots = Arc::new(Semaphore::new(MAX_INGESTIONS));
+
+        loop {
+            while let Ok(slot) = slots.clone().try_acquire_owned() {
+                let document = match repo.claim_document(&self.owner, INGEST_LEASE_SECS).await {
+                    Ok(Some(document)) => document,
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Failed to claim knowledge document");
+                        break;
+                    }
+                };
+                let worker = self.clone();
+                let services = services.clone();
+                tokio::spawn(async move {
+                    worker.run_job(services.as_ref(), document).await;
+                    drop(slot);
+                    worker.wake();
+                });
+            }
+
+            tokio::select! {
+                _ = self.wakeup.notified() => {}
+                _ = tokio::time::sleep(INGEST_POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    /// Ingest a claimed document while renewing its lease
+    ///
+    /// Losing the lease drops the job at once. Failures are recorded on the
+    /// document, so it is not claimed again.
+    async fn run_job(&self, services: &dyn IngestServices, document: KnowledgeDocumentRow) {
+        let repo = KnowledgeRepository::new(self.pool.clone());
+        let id = document.id.as_str();
+        let keep_lease = async {
+            let mut interval = tokio::time::interval(Duration::from_secs(INGEST_LEASE_SECS as u64 / 3));
+            loop {
+                interval.tick().await;
+                match repo.renew_document_lease(id, &self.owner, INGEST_LEASE_SECS).await {
+                    Ok(true) => {}
+                    Ok(false) => return,
+                    Err(e) => tracing::warn!(document = %id, error = %e, "Failed to renew ingestion lease"),
+                }
+            }
+        };
+
+        let result = tokio::select! {
+            result = self.ingest(&repo, services, &document) => result,
+            _ = keep_lease => Ok(()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(document = %id, error = %e, "Knowledge ingestion failed");
+            let recorded = match repo.set_document_status(id, "failed", Some(&e.to_string())).await {
+                Ok(_) => repo.refresh_collection_counts(&document.collection_id).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = recorded {
+                tracing::warn!(document = %id, error = %e, "Failed to record ingestion failure");
+            }
+        }
+        if let Err(e) = repo.release_document(id, &self.owner).await {
+            tracing::warn!(document = %id, error = %e, "Failed to release ingestion lease");
+        }
+    }
+
+    async fn ingest(&self, repo: &KnowledgeRepository, services: &dyn IngestServices, document: &KnowledgeDocumentRow) -> Result<()> {
+        let collection = repo.find_collection(&document.collection_id).await?
+            .ok_or_else(|| SynapseError::NotFound(format!("Collection {}", document.collection_id)))
+            .and_then(Collection::from_row)?;
+        let store = services.vector_store(collection.vector_account_id.as_deref()).await?;
+        let outcome = ingest_document(repo, services, store.as_ref(), &collection, document).await?;
+        tracing::info!(document = %document.id, chunks = outcome.chunks, "Knowledge document ingested");
+
+        if outcome.cost > 0.0 || outcome.usage.total_tokens > 0 {
+            self.record_cost(&collection, document, &outcome).await;
+        }
+        Ok(())
+    }
+
+    /// Charge the embedding spend to the uploader and the collection's agent
+    async fn record_cost(&self, collection: &Collection, document: &KnowledgeDocumentRow, outcome: &IngestOutcome) {
+        let request_id = format!("ingest-{}", document.id);
+        let user_id = document.created_by.as_deref();
+        if let Err(e) = self.cost_manager.record_cost(
+            &outcome.provider,
+            &collection.embedding_model,
+            &outcome.usage,
+            outcome.cost,
+            user_id.unwrap_or("anonymous"),
+            &request_id,
+        ).await {
+            tracing::warn!(document = %document.id, error = %e, "Failed to record cost");
+        }
+        if let Some(ref agent_id) = collection.agent_id {
+            self.cost_manager.charge(agent_id, outcome.cost).await;
+        }
+        if let Err(e) = CostRepository::new(self.pool.clone()).create_entry(
+            &uuid::Uuid::new_v4().to_string(),
+            user_id,
+            collection.agent_id.as_deref(),
+            None,
+            &outcome.provider,
+            &collection.embedding_model,
+            outcome.usage.prompt_tokens as i32,
+            0,
+            outcome.cost,
+            Some(&request_id),
+        ).await {
+            tracing::warn!(document = %document.id, error = %e, "Failed to persist cost entry");
+        }
+    }
+}
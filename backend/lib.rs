@@ -9,6 +9,18 @@
 pub mod types;
 pub mod providers;
 pub mod router;
+pub mod catalog;
+pub mod cache;
+pub mod vector;
+pub mod request_log;
+pub mod metrics;
+pub mod telemetry;
+pub mod health;
+pub mod agents;
+pub mod knowledge;
+pub mod workflows;
+pub mod mail;
+pub mod voice;
 pub mod api;
 pub mod cost;
 pub mod config;
@@ -0,0 +1,193 @@
+//! Outgoing email over SMTP
+//!
+//! Sends plain-text messages through `lettre` with the SMTP settings stored
+//! under the `smtp` key of the `settings` table. `tls` upgrades the
+//! connection with STARTTLS, `ssl` connects over TLS from the start and
+//! `none` sends in the clear; credentials are sent with AUTH PLAIN when a
+//! username is set.
+
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::extension::ClientId;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+
+use crate::db::DbPool;
+use crate::error::{Result, SynapseError};
+
+/// Longest a whole SMTP session may take
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: i32,
+    pub username: String,
+    pub password: Option<String>,
+    pub from_email: String,
+    pub encryption: String, // "tls", "ssl", "none"
+}
+
+/// A plain-text message
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// The stored SMTP settings, `None` until they are saved
+pub async fn load_settings(pool: &DbPool) -> Result<Option<SmtpSettings>> {
+    let value: Option<serde_json::Value> = sqlx::query_scalar("SELECT value FROM settings WHERE key = 'smtp'")
+        .fetch_optional(pool)
+        .await?;
+    value
+        .map(|value| serde_json::from_value(value).map_err(|e| SynapseError::Config(format!("Invalid SMTP settings: {}", e))))
+        .transpose()
+}
+
+/// Send a message
+pub async fn send(settings: &SmtpSettings, email: &Email) -> Result<()> {
+    if email.to.is_empty() {
+        return Err(SynapseError::Validation("An email needs at least one recipient".to_string()));
+    }
+    let message = message(&settings.from_email, email)?;
+    let transport = transport(settings)?;
+    tokio::time::timeout(SMTP_TIMEOUT, transport.send(message))
+        .await
+        .map_err(|_| smtp_error(format!("timed out after {}s", SMTP_TIMEOUT.as_secs())))?
+        .map_err(smtp_error)?;
+    Ok(())
+}
+
+fn smtp_error(message: impl std::fmt::Display) -> SynapseError {
+    SynapseError::Internal(format!("SMTP: {}", message))
+}
+
+fn transport(settings: &SmtpSettings) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+    let port = u16::try_from(settings.port).map_err(|_| smtp_error(format!("invalid port {}", settings.port)))?;
+    let builder = match settings.encryption.as_str() {
+        "ssl" => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host).map_err(smtp_error)?,
+        "tls" => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host).map_err(smtp_error)?,
+        _ => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(settings.host.as_str()),
+    };
+    let mut builder = builder
+        .port(port)
+        .hello_name(ClientId::Domain("barq-hub".to_string()))
+        .timeout(Some(SMTP_TIMEOUT));
+    if !settings.username.is_empty() {
+        builder = builder
+            .credentials(Credentials::new(
+                settings.username.clone(),
+                settings.password.clone().unwrap_or_default(),
+            ))
+            .authentication(vec![Mechanism::Plain]);
+    }
+    Ok(builder.build())
+}
+
+fn mailbox(address: &str) -> Result<Mailbox> {
+    address.parse().map_err(|e| SynapseError::Validation(format!("Invalid email address {}: {}", address, e)))
+}
+
+fn message(from: &str, email: &Email) -> Result<Message> {
+    let mut builder = Message::builder()
+        .from(mailbox(from)?)
+        .subject(email.subject.replace(['\r', '\n'], " "))
+        .header(ContentType::TEXT_PLAIN);
+    for to in &email.to {
+        builder = builder.to(mailbox(to)?);
+    }
+    builder.body(email.body.clone()).map_err(smtp_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Accepts one message, returning the commands and the message data
+    async fn fake_server(listener: TcpListener) -> (Vec<String>, String) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut commands = Vec::new();
+        let mut data = String::new();
+        stream.get_mut().write_all(b"220 fake ESMTP\r\n").await.unwrap();
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await.unwrap() == 0 {
+                break;
+            }
+            let command = line.trim_end().to_string();
+            let reply: &[u8] = match command.split(' ').next().unwrap() {
+                "EHLO" => b"250-fake\r\n250 AUTH PLAIN\r\n",
+                "AUTH" => b"235 ok\r\n",
+                "DATA" => {
+                    stream.get_mut().write_all(b"354 go ahead\r\n").await.unwrap();
+                    loop {
+                        let mut line = String::new();
+                        stream.read_line(&mut line).await.unwrap();
+                        if line == ".\r\n" {
+                            break;
+                        }
+                        data.push_str(&line);
+                    }
+                    b"250 queued\r\n"
+                }
+                "QUIT" => {
+                    commands.push(command);
+                    stream.get_mut().write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                }
+                _ => b"250 ok\r\n",
+            };
+            commands.push(command);
+            stream.get_mut().write_all(reply).await.unwrap();
+        }
+        (commands, data)
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(fake_server(listener));
+
+        let settings = SmtpSettings {
+            host: "127.0.0.1".to_string(),
+            port: port as i32,
+            username: "hub".to_string(),
+            password: Some("secret".to_string()),
+            from_email: "noreply@example.com".to_string(),
+            encryption: "none".to_string(),
+        };
+        let email = Email {
+            to: vec!["ops@example.com".to_string(), "lead@example.com".to_string()],
+            subject: "Approval needed\r\nBcc: evil@example.com".to_string(),
+            body: "Refund of 120\n.hidden line\nThanks".to_string(),
+        };
+        send(&settings, &email).await.unwrap();
+
+        let (commands, data) = server.await.unwrap();
+        let credentials = base64::engine::general_purpose::STANDARD.encode("\0hub\0secret");
+        assert_eq!(commands, [
+            "EHLO barq-hub".to_string(),
+            format!("AUTH PLAIN {}", credentials),
+            "MAIL FROM:<noreply@example.com>".to_string(),
+            "RCPT TO:<ops@example.com>".to_string(),
+            "RCPT TO:<lead@example.com>".to_string(),
+            "DATA".to_string(),
+            "QUIT".to_string(),
+        ]);
+        assert!(data.contains("To: ops@example.com, lead@example.com\r\n"));
+        assert!(data.contains("Subject: Approval needed  Bcc: evil@example.com\r\n"));
+        assert!(!data.contains("\r\nBcc:"));
+        assert!(data.ends_with("\r\n\r\nRefund of 120\r\n..hidden line\r\nThanks\r\n"));
+    }
+}
@@ -3,27 +3,23 @@
 //! Main entry point for the server.
 
 use std::sync::Arc;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use barq_hub::{
     api::{create_router, AppState},
+    cache::{ResponseCache, SemanticCache},
     config::Config,
     Provider, ProviderPricing, ProviderType, ProviderHealth,
 };
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::try_from_default_env()
-            .unwrap_or_else(|_| "barq_hub=info,tower_http=info".into()))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
-    tracing::info!("Starting BARQ HUB v{}", env!("CARGO_PKG_VERSION"));
-
     // Load configuration
     let config = Config::load().expect("Failed to load configuration");
+
+    // Initialize logging and trace export
+    let tracer_provider = barq_hub::telemetry::init(&config.logging, &config.telemetry);
+
+    tracing::info!("Starting BARQ HUB v{}", env!("CARGO_PKG_VERSION"));
     tracing::info!("Configuration loaded");
 
     // Initialize providers from environment
@@ -31,18 +27,87 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Initialized {} providers", providers.len());
 
     // Create application state with database
-    let state = match create_state_with_database(providers.clone(), &config).await {
+    let mut state = match create_state_with_database(providers.clone(), &config).await {
         Ok(state) => {
             tracing::info!("Database connection established");
-            Arc::new(state)
+            state
         }
         Err(e) => {
             tracing::warn!("Database connection failed: {}. Using in-memory storage.", e);
             tracing::warn!("Data will NOT be persisted across restarts!");
-            Arc::new(AppState::new(providers))
+            AppState::new(providers)
         }
     };
 
+    // Response cache is optional; without Redis requests go straight to providers
+    let mut response_cache = ResponseCache::connect(&config.redis.url).await;
+    if let Some(semantic) = create_semantic_cache(&state, &config).await {
+        tracing::info!("Semantic cache using {} store", semantic.store_name());
+        response_cache = response_cache.with_semantic(semantic);
+    }
+    state.response_cache = Arc::new(response_cache);
+    tracing::info!("Response cache enabled: {}", state.response_cache.is_enabled());
+    let state = Arc::new(state);
+
+    // Keep the model catalog warm so /v1/models never waits on providers
+    {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(barq_hub::catalog::DEFAULT_CATALOG_TTL);
+            loop {
+                interval.tick().await;
+                state.refresh_model_catalog().await;
+            }
+        });
+    }
+
+    // Probe providers, accounts, Redis and the database in the background
+    {
+        let state = state.clone();
+        let period = std::time::Duration::from_secs(config.health.probe_interval_secs);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                state.probe_health().await;
+            }
+        });
+    }
+
+    // Pick up provider account changes made through other replicas
+    {
+        let state = state.clone();
+        tokio::spawn(async move { state.watch_provider_accounts().await });
+    }
+
+    // Ingest uploaded documents, including those a stopped replica left
+    if let Some(ingestion) = state.ingestion.clone() {
+        let state = state.clone();
+        tokio::spawn(async move { ingestion.run(state).await });
+    }
+
+    // Run queued workflow executions, including those a stopped replica left
+    if let Some(runner) = state.workflow_runner.clone() {
+        let state = state.clone();
+        tokio::spawn(async move { runner.run(state).await });
+    }
+
+    // Drop request logs past their application's retention period
+    if let Some(pool) = state.db_pool.clone() {
+        tokio::spawn(async move {
+            let repo = barq_hub::db::RequestLogRepository::new(pool);
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                interval.tick().await;
+                match repo.purge_expired().await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::info!("Purged {} expired request logs", purged),
+                    Err(e) => tracing::warn!("Failed to purge request logs: {}", e),
+                }
+            }
+        });
+    }
+
     // Create router
     let app = create_router(state.clone());
 
@@ -53,15 +118,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let http_listener = tokio::net::TcpListener::bind(&http_addr).await?;
     let http_server = async move {
-        axum::serve(http_listener, app).await
+        axum::serve(http_listener, app).with_graceful_shutdown(shutdown_signal()).await
     };
 
     // Start gRPC server if database is available
     let grpc_server = if let Some(ref app_repo) = state.application_repo {
         use tonic::transport::Server;
         use barq_hub::grpc::{
-            barq::{chat_service_server::ChatServiceServer, models_service_server::ModelsServiceServer},
-            ApiKeyInterceptor, ChatServiceImpl, ModelsServiceImpl,
+            barq::{
+                chat_service_server::ChatServiceServer, embeddings_service_server::EmbeddingsServiceServer,
+                models_service_server::ModelsServiceServer, tokenize_service_server::TokenizeServiceServer,
+            },
+            ApiKeyAuthLayer, ApiKeyInterceptor, ChatServiceImpl, EmbeddingsServiceImpl, GrpcMetricsLayer,
+            GrpcTraceLayer, ModelsServiceImpl, TokenizeServiceImpl,
         };
 
         let grpc_addr = "0.0.0.0:4002".parse().expect("Invalid gRPC address");
@@ -69,6 +138,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         
         let chat_service = ChatServiceImpl::new(state.clone(), auth.clone());
         let models_service = ModelsServiceImpl::new(state.clone(), auth.clone());
+        let embeddings_service = EmbeddingsServiceImpl::new(state.clone(), auth.clone());
+        let tokenize_service = TokenizeServiceImpl::new();
 
         let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
         
@@ -79,20 +150,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         // tonic_health uses the package.Service name, e.g. "barq.ChatService"
         health_reporter.set_serving::<ChatServiceServer<ChatServiceImpl>>().await;
         health_reporter.set_serving::<ModelsServiceServer<ModelsServiceImpl>>().await;
+        health_reporter.set_serving::<EmbeddingsServiceServer<EmbeddingsServiceImpl>>().await;
+        health_reporter.set_serving::<TokenizeServiceServer<TokenizeServiceImpl>>().await;
 
         tracing::info!("gRPC server listening on {}", grpc_addr);
         
+        // Every call except the health check must carry a valid application API key
         Some(Server::builder()
+            .layer(GrpcTraceLayer)
+            .layer(GrpcMetricsLayer)
+            .layer(ApiKeyAuthLayer::new(auth))
             .add_service(health_service)
             .add_service(ChatServiceServer::new(chat_service))
             .add_service(ModelsServiceServer::new(models_service))
-            .serve(grpc_addr))
+            .add_service(EmbeddingsServiceServer::new(embeddings_service))
+            .add_service(TokenizeServiceServer::new(tokenize_service))
+            .serve_with_shutdown(grpc_addr, shutdown_signal()))
     } else {
         tracing::warn!("gRPC server disabled: database not available");
         None
     };
 
-    // Run both servers concurrently
+    // Run both servers concurrently until a shutdown signal
     if let Some(grpc) = grpc_server {
         tokio::select! {
             result = http_server => {
@@ -106,15 +185,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
         }
-    } else {
-        http_server.await?;
+    } else if let Err(e) = http_server.await {
+        tracing::error!("HTTP server error: {}", e);
+    }
+
+    // Export the spans still batched; the exporter blocks while it flushes
+    let flushed = tokio::task::spawn_blocking(move || tracer_provider.shutdown()).await?;
+    if let Err(e) = flushed {
+        tracing::warn!("Failed to flush traces: {}", e);
     }
 
     Ok(())
 }
 
+/// Resolves on Ctrl+C or SIGTERM, letting in-flight requests finish
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("Shutting down");
+}
+
 /// Create state with database connection
-async fn create_state_with_database(providers: Vec<Provider>, config: &Config) -> Result<AppState, Box<dyn std::error::Error>> {
+async fn create_state_with_database(providers: Vec<Provider>, _config: &Config) -> Result<AppState, Box<dyn std::error::Error>> {
     // Get database URL from environment
     let database_url = std::env::var("DATABASE_URL")
         .or_else(|_| std::env::var("POSTGRES_URL"))
@@ -131,6 +246,51 @@ async fn create_state_with_database(providers: Vec<Provider>, config: &Config) -
     AppState::with_database(providers, &database_url).await
 }
 
+/// Semantic cache over the configured vector database account, or an
+/// in-memory store when none is set; `None` without an embedding model
+async fn create_semantic_cache(state: &AppState, config: &Config) -> Option<SemanticCache> {
+    let model = config.cache.semantic_embedding_model.clone()?;
+    let mut collection = config.cache.semantic_collection.clone();
+
+    let store: Arc<dyn barq_hub::vector::VectorStore> = match (&config.cache.semantic_vector_account, &state.db_pool) {
+        (Some(account_id), Some(pool)) => {
+            let repo = barq_hub::db::ProviderAccountRepository::new(pool.clone());
+            let account = match repo.find_by_id(account_id).await {
+                Ok(Some(account)) => account,
+                Ok(None) => {
+                    tracing::warn!("Vector account {} not found, semantic cache disabled", account_id);
+                    return None;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to load vector account {}: {}, semantic cache disabled", account_id, e);
+                    return None;
+                }
+            };
+            let Some(vector_config) = account.vector_db_config() else {
+                tracing::warn!("Account {} has no vector database settings, semantic cache disabled", account_id);
+                return None;
+            };
+            if let Some(name) = vector_config.collection_name.clone() {
+                collection = name;
+            }
+            match barq_hub::vector::create_store(&account.provider_id, &vector_config, state.http_client.clone()) {
+                Ok(store) => store,
+                Err(e) => {
+                    tracing::warn!("Semantic cache disabled: {}", e);
+                    return None;
+                }
+            }
+        }
+        (Some(account_id), None) => {
+            tracing::warn!("Vector account {} needs a database, semantic cache disabled", account_id);
+            return None;
+        }
+        (None, _) => Arc::new(barq_hub::vector::MemoryVectorStore::new()),
+    };
+
+    Some(SemanticCache::new(store, model, collection))
+}
+
 /// Initialize providers from configuration
 fn initialize_providers(config: &Config) -> Vec<Provider> {
     let mut providers = Vec::new();
@@ -0,0 +1,329 @@
+//! Prometheus metrics
+//!
+//! One process-wide registry, exported in the text format on `/metrics`.
+//! Counters and histograms are updated where completions finish; gauges for
+//! provider health, account quotas and the database pool are refreshed from
+//! live state on every scrape.
+
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use prometheus::core::Collector;
+use prometheus::{
+    CounterVec, Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+
+use crate::cache::CacheStatus;
+use crate::request_log::{Attempt, LogEntry, Outcome};
+use crate::TokenUsage;
+
+/// Label value for requests not made with an application key
+pub const HTTP_APPLICATION: &str = "http";
+
+/// Health score below which a provider is reported as tripped
+pub const CIRCUIT_OPEN_SCORE: f64 = 0.3;
+
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+const TTFT_BUCKETS: &[f64] = &[0.05, 0.1, 0.2, 0.35, 0.5, 0.75, 1.0, 2.0, 5.0, 10.0];
+const GRPC_BUCKETS: &[f64] = &[0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 30.0, 120.0];
+
+/// Who served a completion and for whom
+pub struct Labels<'a> {
+    pub provider: &'a str,
+    pub model: &'a str,
+    /// Database account, or empty for the provider's environment key
+    pub account: &'a str,
+    pub application: &'a str,
+}
+
+impl Labels<'_> {
+    fn values(&self) -> [&str; 4] {
+        [self.provider, self.model, self.account, self.application]
+    }
+}
+
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    tokens: IntCounterVec,
+    cost: CounterVec,
+    latency: HistogramVec,
+    ttft: HistogramVec,
+    attempts: IntCounterVec,
+    fallbacks: IntCounterVec,
+    retries: IntCounterVec,
+    provider_health: GaugeVec,
+    circuit_open: IntGaugeVec,
+    quota_remaining: IntGaugeVec,
+    db_connections: IntGaugeVec,
+    grpc_requests: IntCounterVec,
+    grpc_latency: HistogramVec,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// The process-wide metrics
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        const COMPLETION: &[&str] = &["provider", "model", "account", "application"];
+
+        let registry = Registry::new();
+        let metrics = Self {
+            requests: IntCounterVec::new(
+                Opts::new("barq_requests_total", "Chat completions by outcome (success, cached, error)"),
+                &["provider", "model", "account", "application", "status"],
+            ).unwrap(),
+            tokens: IntCounterVec::new(
+                Opts::new("barq_tokens_total", "Tokens processed by providers"),
+                &["provider", "model", "account", "application", "kind"],
+            ).unwrap(),
+            cost: CounterVec::new(
+                Opts::new("barq_cost_usd_total", "Provider cost in US dollars"),
+                COMPLETION,
+            ).unwrap(),
+            latency: HistogramVec::new(
+                HistogramOpts::new("barq_request_duration_seconds", "Chat completion latency")
+                    .buckets(LATENCY_BUCKETS.to_vec()),
+                COMPLETION,
+            ).unwrap(),
+            ttft: HistogramVec::new(
+                HistogramOpts::new("barq_time_to_first_token_seconds", "Time until the first streamed token")
+                    .buckets(TTFT_BUCKETS.to_vec()),
+                COMPLETION,
+            ).unwrap(),
+            attempts: IntCounterVec::new(
+                Opts::new("barq_provider_attempts_total", "Provider and account attempts by outcome"),
+                &["provider", "account", "outcome"],
+            ).unwrap(),
+            fallbacks: IntCounterVec::new(
+                Opts::new("barq_fallbacks_total", "Failed attempts after which routing moved on"),
+                &["provider", "account"],
+            ).unwrap(),
+            retries: IntCounterVec::new(
+                Opts::new("barq_retries_total", "Provider calls repeated by the hub"),
+                &["provider", "reason"],
+            ).unwrap(),
+            provider_health: GaugeVec::new(
+                Opts::new("barq_provider_health_score", "Router health score from 0 (failing) to 1"),
+                &["provider"],
+            ).unwrap(),
+            circuit_open: IntGaugeVec::new(
+                Opts::new("barq_provider_circuit_open", "1 while the health score is below the trip threshold"),
+                &["provider"],
+            ).unwrap(),
+            quota_remaining: IntGaugeVec::new(
+                Opts::new("barq_account_quota_remaining_tokens", "Tokens left in each account quota period"),
+                &["provider", "account", "period"],
+            ).unwrap(),
+            db_connections: IntGaugeVec::new(
+                Opts::new("barq_db_pool_connections", "Database pool connections by state"),
+                &["state"],
+            ).unwrap(),
+            grpc_requests: IntCounterVec::new(
+                Opts::new("barq_grpc_requests_total", "gRPC calls by method and status code"),
+                &["method", "code"],
+            ).unwrap(),
+            grpc_latency: HistogramVec::new(
+                HistogramOpts::new("barq_grpc_request_duration_seconds", "gRPC call latency until response headers")
+                    .buckets(GRPC_BUCKETS.to_vec()),
+                &["method"],
+            ).unwrap(),
+            registry,
+        };
+
+        for collector in [
+            Box::new(metrics.requests.clone()) as Box<dyn Collector>,
+            Box::new(metrics.tokens.clone()),
+            Box::new(metrics.cost.clone()),
+            Box::new(metrics.latency.clone()),
+            Box::new(metrics.ttft.clone()),
+            Box::new(metrics.attempts.clone()),
+            Box::new(metrics.fallbacks.clone()),
+            Box::new(metrics.retries.clone()),
+            Box::new(metrics.provider_health.clone()),
+            Box::new(metrics.circuit_open.clone()),
+            Box::new(metrics.quota_remaining.clone()),
+            Box::new(metrics.db_connections.clone()),
+            Box::new(metrics.grpc_requests.clone()),
+            Box::new(metrics.grpc_latency.clone()),
+        ] {
+            metrics.registry.register(collector).expect("metric names are unique");
+        }
+        metrics
+    }
+
+    /// Count a completion and the attempts made for it
+    pub fn observe_chat(&self, application: &str, entry: &LogEntry) {
+        self.observe_attempts(&entry.attempts);
+
+        match entry.outcome {
+            Outcome::Success(response) => {
+                let labels = Labels {
+                    provider: &response.provider,
+                    model: &entry.request.model,
+                    account: routed_account(&entry.attempts),
+                    application,
+                };
+                let cached = matches!(entry.cache_status, Some(CacheStatus::Hit | CacheStatus::SemanticHit));
+                let latency = Duration::from_millis(entry.latency_ms);
+                self.observe_completion(&labels, &response.usage, response.cost, latency, cached);
+            }
+            Outcome::Failure(_) => {
+                self.observe_failure(entry.request.provider.as_deref(), &entry.request.model, application)
+            }
+        }
+    }
+
+    /// Count a finished completion; cached responses add no tokens or cost
+    pub fn observe_completion(&self, labels: &Labels, usage: &TokenUsage, cost: f64, latency: Duration, cached: bool) {
+        let [provider, model, account, application] = labels.values();
+        let status = if cached { "cached" } else { "success" };
+        self.requests.with_label_values(&[provider, model, account, application, status]).inc();
+        self.latency.with_label_values(&labels.values()).observe(latency.as_secs_f64());
+
+        if !cached {
+            self.tokens
+                .with_label_values(&[provider, model, account, application, "prompt"])
+                .inc_by(usage.prompt_tokens as u64);
+            self.tokens
+                .with_label_values(&[provider, model, account, application, "completion"])
+                .inc_by(usage.completion_tokens as u64);
+            self.cost.with_label_values(&labels.values()).inc_by(cost.max(0.0));
+        }
+    }
+
+    /// Count a completion that no provider could serve
+    pub fn observe_failure(&self, provider: Option<&str>, model: &str, application: &str) {
+        self.requests
+            .with_label_values(&[provider.unwrap_or_default(), model, "", application, "error"])
+            .inc();
+    }
+
+    pub fn observe_ttft(&self, labels: &Labels, elapsed: Duration) {
+        self.ttft.with_label_values(&labels.values()).observe(elapsed.as_secs_f64());
+    }
+
+    /// Count provider attempts, and as fallbacks the failed ones that were
+    /// followed by another attempt
+    pub fn observe_attempts(&self, attempts: &[Attempt]) {
+        for attempt in attempts {
+            let outcome = if attempt.error.is_none() { "success" } else { "error" };
+            self.attempts
+                .with_label_values(&[&attempt.provider, attempt.account.as_deref().unwrap_or_default(), outcome])
+                .inc();
+        }
+
+        let Some((_, earlier)) = attempts.split_last() else {
+            return;
+        };
+        for attempt in earlier.iter().filter(|a| a.error.is_some()) {
+            self.fallbacks
+                .with_label_values(&[&attempt.provider, attempt.account.as_deref().unwrap_or_default()])
+                .inc();
+        }
+    }
+
+    pub fn observe_retry(&self, provider: &str, reason: &str) {
+        self.retries.with_label_values(&[provider, reason]).inc();
+    }
+
+    pub fn observe_grpc(&self, method: &str, code: &str, elapsed: Duration) {
+        self.grpc_requests.with_label_values(&[method, code]).inc();
+        self.grpc_latency.with_label_values(&[method]).observe(elapsed.as_secs_f64());
+    }
+
+    pub fn set_provider_health(&self, provider: &str, score: f64) {
+        self.provider_health.with_label_values(&[provider]).set(score);
+        self.circuit_open
+            .with_label_values(&[provider])
+            .set(i64::from(score < CIRCUIT_OPEN_SCORE));
+    }
+
+    pub fn set_quota_remaining(&self, provider: &str, account: &str, period: &str, tokens: u64) {
+        self.quota_remaining
+            .with_label_values(&[provider, account, period])
+            .set(tokens.min(i64::MAX as u64) as i64);
+    }
+
+    pub fn set_db_pool(&self, active: u32, idle: u32) {
+        self.db_connections.with_label_values(&["active"]).set(active as i64);
+        self.db_connections.with_label_values(&["idle"]).set(idle as i64);
+    }
+
+    /// Chat completions served since startup, whatever the outcome
+    pub fn total_requests(&self) -> u64 {
+        self.requests
+            .collect()
+            .iter()
+            .flat_map(|family| family.get_metric())
+            .map(|m| m.get_counter().get_value() as u64)
+            .sum()
+    }
+
+    /// All metrics in the Prometheus text exposition format
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::warn!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Account of the attempt that answered, or empty for an environment key
+pub fn routed_account(attempts: &[Attempt]) -> &str {
+    attempts
+        .iter()
+        .rev()
+        .find(|a| a.error.is_none())
+        .and_then(|a| a.account.as_deref())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_completion() {
+        let metrics = Metrics::new();
+        let labels = Labels { provider: "openai", model: "gpt-4o", account: "", application: "app-1" };
+        let usage = TokenUsage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 };
+
+        metrics.observe_completion(&labels, &usage, 0.01, Duration::from_millis(300), false);
+        metrics.observe_completion(&labels, &usage, 0.0, Duration::from_millis(2), true);
+        metrics.observe_failure(None, "gpt-4o", "app-1");
+        assert_eq!(metrics.total_requests(), 3);
+
+        let text = metrics.encode();
+        assert!(text.contains(
+            r#"barq_tokens_total{account="",application="app-1",kind="prompt",model="gpt-4o",provider="openai"} 10"#
+        ));
+        assert!(text.contains(
+            r#"barq_requests_total{account="",application="app-1",model="gpt-4o",provider="openai",status="cached"} 1"#
+        ));
+    }
+
+    #[test]
+    fn test_observe_attempts() {
+        let metrics = Metrics::new();
+        let attempt = |provider: &str, error: Option<&str>| Attempt {
+            provider: provider.to_string(),
+            account: None,
+            error: error.map(str::to_string),
+        };
+        metrics.observe_attempts(&[attempt("openai", Some("timeout")), attempt("anthropic", None)]);
+        metrics.observe_attempts(&[attempt("openai", Some("timeout"))]);
+        metrics.set_provider_health("openai", 0.1);
+
+        let text = metrics.encode();
+        assert!(text.contains(r#"barq_fallbacks_total{account="",provider="openai"} 1"#));
+        assert!(text.contains(r#"barq_provider_attempts_total{account="",outcome="error",provider="openai"} 2"#));
+        assert!(text.contains(r#"barq_provider_circuit_open{provider="openai"} 1"#));
+    }
+}
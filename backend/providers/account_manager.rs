@@ -29,6 +29,16 @@ impl QuotaPeriod {
         }
     }
     
+    /// Lowercase name, as serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QuotaPeriod::Minute => "minute",
+            QuotaPeriod::Hour => "hour",
+            QuotaPeriod::Day => "day",
+            QuotaPeriod::Month => "month",
+        }
+    }
+
     pub fn display_name(&self) -> &'static str {
         match self {
             QuotaPeriod::Minute => "per minute",
@@ -179,11 +189,7 @@ impl QuotaTier {
     
     /// Get remaining tokens
     pub fn remaining_tokens(&self) -> u64 {
-        if self.tokens_used >= self.token_limit {
-            0
-        } else {
-            self.token_limit - self.tokens_used
-        }
+        self.token_limit.saturating_sub(self.tokens_used)
     }
     
     /// Time until this tier resets
@@ -380,14 +386,10 @@ impl ProviderAccountManager {
 
         let make_model = |name: &str| {
             let id = name.to_lowercase().replace(" ", "-");
-            let mut caps = vec![crate::types::ModelCapability::LLM];
-            if name.contains("embed") || name.contains("embedding") {
-                caps = vec![crate::types::ModelCapability::Embedding];
-            }
             ProviderModel {
+                capabilities: crate::types::ModelCapability::infer(&id),
                 id,
                 name: name.to_string(),
-                capabilities: caps,
                 input_token_cost: None,
                 output_token_cost: None,
             }
@@ -1,8 +1,9 @@
 //! Provider adapter trait
 
 use async_trait::async_trait;
-use crate::{ChatRequest, ChatResponse, Provider};
-use crate::error::Result;
+use crate::{ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse, ModelCapability, Provider, TokenUsage};
+use crate::error::{ProviderError, Result, SynapseError};
+use super::streaming::{self, ChatStream};
 
 /// Trait that all provider adapters must implement
 #[async_trait]
@@ -10,6 +11,23 @@ pub trait ProviderAdapter: Send + Sync {
     /// Handle a chat completion request
     async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse>;
 
+    /// Stream a chat completion
+    ///
+    /// Providers without native streaming replay the full response as a
+    /// single chunk.
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let response = self.chat(request).await?;
+        Ok(streaming::from_response(response))
+    }
+
+    /// Generate embeddings for a batch of texts
+    async fn embed(&self, _request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+        Err(SynapseError::Provider(ProviderError::Unsupported(format!(
+            "{} does not support embeddings",
+            self.name()
+        ))))
+    }
+
     /// Get available models for this provider
     async fn list_models(&self) -> Result<Vec<String>>;
 
@@ -24,9 +42,52 @@ pub trait ProviderAdapter: Send + Sync {
         &self.provider().name
     }
 
+    /// Non-text input this adapter can translate to its provider's format
+    fn input_capabilities(&self) -> &'static [ModelCapability] {
+        &[]
+    }
+
+    /// Reject requests with content parts the adapter or model can't accept
+    fn check_input(&self, request: &ChatRequest) -> Result<()> {
+        let required = request.input_capabilities();
+        if required.is_empty() {
+            return Ok(());
+        }
+
+        let model_capabilities = self.provider().model_capabilities(&request.model);
+        for capability in required {
+            if !self.input_capabilities().contains(&capability) {
+                return Err(SynapseError::Provider(ProviderError::Unsupported(format!(
+                    "{} does not accept {} input",
+                    self.name(),
+                    capability
+                ))));
+            }
+            if !model_capabilities.contains(&capability) {
+                return Err(SynapseError::Provider(ProviderError::Unsupported(format!(
+                    "Model {} does not accept {} input",
+                    request.model,
+                    capability
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Cost in USD of a request with the given usage
+    fn estimate_cost(&self, usage: &TokenUsage) -> f64 {
+        let pricing = &self.provider().pricing;
+        (usage.prompt_tokens as f64 / 1_000_000.0) * pricing.input_token_cost
+            + (usage.completion_tokens as f64 / 1_000_000.0) * pricing.output_token_cost
+    }
+
     /// Estimate tokens for a request (rough estimate)
     fn estimate_tokens(&self, text: &str) -> u32 {
-        // Rough estimate: ~4 characters per token
-        (text.len() as f64 / 4.0).ceil() as u32
+        estimate_tokens(text)
     }
 }
+
+/// Provider-agnostic token estimate (~4 characters per token)
+pub fn estimate_tokens(text: &str) -> u32 {
+    (text.len() as f64 / 4.0).ceil() as u32
+}
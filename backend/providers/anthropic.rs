@@ -5,11 +5,15 @@ use chrono::Utc;
 use reqwest::Client;
 use std::time::Instant;
 
+use futures_util::{future, StreamExt};
+
 use crate::{
-    ChatRequest, ChatResponse, Choice, Message, Provider, TokenUsage,
+    ChatRequest, ChatResponse, ChatStreamChunk, Choice, ContentPart, MediaSource, Message, MessageContent,
+    ModelCapability, Provider, TokenUsage,
     error::{ProviderError, Result, SynapseError},
 };
-use super::ProviderAdapter;
+use super::{streaming, structured, ChatStream, ProviderAdapter};
+use crate::telemetry::PropagateTrace;
 
 /// Adapter for Anthropic Claude API
 pub struct AnthropicAdapter {
@@ -34,11 +38,11 @@ impl AnthropicAdapter {
 
         for msg in messages {
             if msg.role == "system" {
-                system_prompt = Some(msg.content.clone());
+                system_prompt = Some(msg.content.text());
             } else {
                 anthropic_messages.push(serde_json::json!({
                     "role": msg.role,
-                    "content": msg.content
+                    "content": content_blocks(&msg.content)
                 }));
             }
         }
@@ -51,91 +55,28 @@ impl AnthropicAdapter {
 impl ProviderAdapter for AnthropicAdapter {
     async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
         let start = Instant::now();
-        let url = format!("{}/messages", self.provider.base_url);
-
-        // Convert messages
-        let (system_prompt, messages) = self.convert_messages(&request.messages);
-
-        // Build request payload
-        let mut payload = serde_json::json!({
-            "model": request.model,
-            "messages": messages,
-            "max_tokens": request.max_tokens,
-            "temperature": request.temperature,
-        });
-
-        if let Some(system) = system_prompt {
-            payload["system"] = serde_json::Value::String(system);
-        }
-
-        if let Some(top_p) = request.top_p {
-            payload["top_p"] = serde_json::Value::from(top_p);
-        }
-
-        if let Some(ref stop) = request.stop {
-            payload["stop_sequences"] = serde_json::Value::from(stop.clone());
-        }
-
-        // Make request
-        let mut req = self.client
-            .post(&url)
-            .header("x-api-key", &self.provider.api_key)
-            .header("anthropic-version", "2023-06-01")
-            .header("Content-Type", "application/json");
-
-        // Add custom headers
-        for (key, value) in &self.provider.headers {
-            req = req.header(key, value);
-        }
-
-        let response = req
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
-
-        // Check status
-        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
-            return Err(SynapseError::Provider(ProviderError::RateLimited));
-        }
-
-        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
-            return Err(SynapseError::Provider(ProviderError::AuthFailed));
-        }
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(SynapseError::Provider(ProviderError::RequestFailed(
-                format!("Status: {}, Body: {}", status, error_text)
-            )));
-        }
+        let payload = self.build_payload(request);
+        let response = self.send_messages(&payload).await?;
 
         let body: serde_json::Value = response
             .json()
             .await
             .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
 
-        // Parse response - Anthropic returns content differently
-        let content = body["content"]
-            .as_array()
-            .and_then(|arr| arr.first())
-            .and_then(|c| c["text"].as_str())
-            .unwrap_or("")
-            .to_string();
+        let content = response_content(&body);
 
         let choices = vec![Choice {
             index: 0,
+            logprobs: None,
             message: Message {
                 role: "assistant".to_string(),
-                content,
+                content: content.into(),
                 function_call: None,
                 tool_calls: None,
+                tool_call_id: None,
             },
-            finish_reason: body["stop_reason"]
-                .as_str()
-                .unwrap_or("end_turn")
-                .to_string(),
+            finish_reason: stop_reason(body["stop_reason"].as_str().unwrap_or("end_turn")).to_string(),
         }];
 
         let usage = TokenUsage {
@@ -150,52 +91,137 @@ impl ProviderAdapter for AnthropicAdapter {
 
         Ok(ChatResponse {
             id: body["id"].as_str().unwrap_or("").to_string(),
-            provider: self.provider.name.clone(),
+            provider: self.provider.id.clone(),
             model: request.model.clone(),
             choices,
             usage,
             created: Utc::now(),
             latency_ms,
             cost,
+            system_fingerprint: None,
         })
     }
 
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let mut payload = self.build_payload(request);
+        payload["stream"] = serde_json::json!(true);
+
+        let response = self.send_messages(&payload).await?;
+        let stream = streaming::sse_events(response)
+            .filter_map(|event| future::ready(match event {
+                Err(e) => Some(Err(e)),
+                Ok(event) => parse_stream_event(&event).transpose(),
+            }));
+        Ok(Box::pin(stream))
+    }
+
     async fn list_models(&self) -> Result<Vec<String>> {
-        Ok(vec![
-            "claude-3-opus-20240229".to_string(),
-            "claude-3-sonnet-20240229".to_string(),
-            "claude-3-haiku-20240307".to_string(),
-            "claude-3-5-sonnet-20241022".to_string(),
-            "claude-3-5-haiku-20241022".to_string(),
-        ])
+        let url = format!("{}/models?limit=1000", self.provider.base_url);
+
+        let response = self.client
+            .get(&url)
+            .header("x-api-key", &self.provider.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        let body: serde_json::Value = streaming::check_status(response).await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        Ok(body["data"]
+            .as_array()
+            .map(|data| data.iter().filter_map(|m| m["id"].as_str().map(String::from)).collect())
+            .unwrap_or_default())
     }
 
     async fn health_check(&self) -> Result<bool> {
-        // Anthropic doesn't have a simple health endpoint, so we check if we can reach it
-        let url = format!("{}/messages", self.provider.base_url);
+        // Listing models is free and checks both reachability and the key
+        let url = format!("{}/models", self.provider.base_url);
         
         let response = self.client
-            .post(&url)
+            .get(&url)
             .header("x-api-key", &self.provider.api_key)
             .header("anthropic-version", "2023-06-01")
             .timeout(std::time::Duration::from_secs(10))
-            .json(&serde_json::json!({
-                "model": "claude-3-haiku-20240307",
-                "messages": [{"role": "user", "content": "ping"}],
-                "max_tokens": 1
-            }))
             .send()
             .await;
 
-        Ok(response.map(|r| r.status().is_success() || r.status().as_u16() == 400).unwrap_or(false))
+        Ok(response.map(|r| r.status().is_success()).unwrap_or(false))
     }
 
     fn provider(&self) -> &Provider {
         &self.provider
     }
+
+    fn input_capabilities(&self) -> &'static [ModelCapability] {
+        &[ModelCapability::Vision, ModelCapability::DocumentInput]
+    }
 }
 
 impl AnthropicAdapter {
+    fn build_payload(&self, request: &ChatRequest) -> serde_json::Value {
+        let (system_prompt, messages) = self.convert_messages(&request.messages);
+
+        let mut payload = serde_json::json!({
+            "model": request.model,
+            "messages": messages,
+            "max_tokens": request.max_tokens,
+            "temperature": request.temperature,
+        });
+
+        if let Some(system) = system_prompt {
+            payload["system"] = serde_json::Value::String(system);
+        }
+
+        if let Some(top_p) = request.top_p {
+            payload["top_p"] = serde_json::Value::from(top_p);
+        }
+
+        if let Some(ref stop) = request.stop {
+            payload["stop_sequences"] = serde_json::Value::from(stop.clone());
+        }
+
+        // No native JSON mode: force a single tool call whose input is the output
+        if let Some(tool) = structured::output_tool(request.response_format.as_ref()) {
+            payload["tools"] = serde_json::json!([{
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.schema,
+            }]);
+            payload["tool_choice"] = serde_json::json!({"type": "tool", "name": tool.name});
+        }
+
+        payload
+    }
+
+    async fn send_messages(&self, payload: &serde_json::Value) -> Result<reqwest::Response> {
+        let url = format!("{}/messages", self.provider.base_url);
+
+        let mut req = self.client
+            .post(&url)
+            .propagate_trace()
+            .header("x-api-key", &self.provider.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("Content-Type", "application/json");
+
+        // Add custom headers
+        for (key, value) in &self.provider.headers {
+            req = req.header(key, value);
+        }
+
+        let response = req
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        streaming::check_status(response).await
+    }
+
     fn calculate_cost(&self, usage: &TokenUsage) -> f64 {
         let input_cost = (usage.prompt_tokens as f64 / 1_000_000.0) 
             * self.provider.pricing.input_token_cost;
@@ -205,6 +231,115 @@ impl AnthropicAdapter {
     }
 }
 
+/// Message content as Anthropic content blocks; plain text stays a string
+///
+/// Audio parts are filtered out by `check_input` before reaching here.
+fn content_blocks(content: &MessageContent) -> serde_json::Value {
+    let parts = match content {
+        MessageContent::Text(text) => return serde_json::json!(text),
+        MessageContent::Parts(parts) => parts,
+    };
+
+    let source = |source: &MediaSource| match source {
+        MediaSource::Url { url } => serde_json::json!({"type": "url", "url": url}),
+        MediaSource::Base64 { media_type, data } => serde_json::json!({
+            "type": "base64",
+            "media_type": media_type,
+            "data": data,
+        }),
+    };
+
+    parts.iter().filter_map(|part| match part {
+        ContentPart::Text { text } => Some(serde_json::json!({"type": "text", "text": text})),
+        ContentPart::Image { source: s, .. } => Some(serde_json::json!({"type": "image", "source": source(s)})),
+        ContentPart::Document { source: s, name } => {
+            let mut block = serde_json::json!({"type": "document", "source": source(s)});
+            if let Some(name) = name {
+                block["title"] = serde_json::json!(name);
+            }
+            Some(block)
+        }
+        ContentPart::Audio { .. } => None,
+    }).collect()
+}
+
+/// Response text, or the forced output tool's input when structured output was requested
+fn response_content(body: &serde_json::Value) -> String {
+    let blocks = body["content"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+    if let Some(tool_use) = blocks
+        .iter()
+        .find(|b| b["type"] == "tool_use" && b["name"] == structured::OUTPUT_TOOL_NAME)
+    {
+        return tool_use["input"].to_string();
+    }
+
+    blocks
+        .iter()
+        .filter_map(|b| b["text"].as_str())
+        .collect()
+}
+
+/// The output tool is an implementation detail, so its call ends the turn
+fn stop_reason(reason: &str) -> &str {
+    match reason {
+        "tool_use" => "end_turn",
+        other => other,
+    }
+}
+
+/// Convert one Anthropic stream event into a chunk
+///
+/// Input tokens arrive with `message_start`, text with `content_block_delta`
+/// and the stop reason plus output tokens with `message_delta`. Output tool
+/// input arrives as `input_json_delta` and is relayed as text.
+fn parse_stream_event(event: &streaming::SseEvent) -> Result<Option<ChatStreamChunk>> {
+    let json: serde_json::Value = serde_json::from_str(&event.data)
+        .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+    let chunk = match json["type"].as_str().unwrap_or_default() {
+        "message_start" => {
+            let input_tokens = json["message"]["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32;
+            ChatStreamChunk {
+                usage: Some(TokenUsage {
+                    prompt_tokens: input_tokens,
+                    completion_tokens: 0,
+                    total_tokens: input_tokens,
+                }),
+                ..Default::default()
+            }
+        }
+        "content_block_delta" => ChatStreamChunk {
+            delta: json["delta"]["text"]
+                .as_str()
+                .or_else(|| json["delta"]["partial_json"].as_str())
+                .unwrap_or("")
+                .to_string(),
+            ..Default::default()
+        },
+        "message_delta" => {
+            let output_tokens = json["usage"]["output_tokens"].as_u64().unwrap_or(0) as u32;
+            ChatStreamChunk {
+                finish_reason: json["delta"]["stop_reason"].as_str().map(|r| stop_reason(r).to_string()),
+                usage: Some(TokenUsage {
+                    prompt_tokens: 0,
+                    completion_tokens: output_tokens,
+                    total_tokens: output_tokens,
+                }),
+                ..Default::default()
+            }
+        }
+        "error" => {
+            return Err(SynapseError::Provider(ProviderError::RequestFailed(
+                json["error"]["message"].as_str().unwrap_or("Stream error").to_string()
+            )));
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(chunk))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,6 +351,7 @@ mod tests {
             name: "Anthropic Test".to_string(),
             provider_type: ProviderType::Anthropic,
             api_key: "test-key".to_string(),
+            models: Vec::new(),
             base_url: "https://api.anthropic.com/v1".to_string(),
             pricing: ProviderPricing {
                 input_token_cost: 15.0,
@@ -250,4 +386,75 @@ mod tests {
         assert_eq!(system, Some("You are helpful".to_string()));
         assert_eq!(converted.len(), 2);
     }
+
+    #[test]
+    fn test_content_blocks() {
+        let content = MessageContent::Parts(vec![
+            ContentPart::Image {
+                source: MediaSource::from_url("data:image/png;base64,iVBORw0"),
+                detail: None,
+            },
+            ContentPart::Document {
+                source: MediaSource::Url { url: "https://example.com/a.pdf".to_string() },
+                name: Some("Report".to_string()),
+            },
+            ContentPart::Text { text: "Summarise".to_string() },
+        ]);
+
+        let blocks = content_blocks(&content);
+        assert_eq!(blocks[0]["source"]["type"], "base64");
+        assert_eq!(blocks[0]["source"]["media_type"], "image/png");
+        assert_eq!(blocks[1]["type"], "document");
+        assert_eq!(blocks[1]["source"]["url"], "https://example.com/a.pdf");
+        assert_eq!(blocks[2]["text"], "Summarise");
+        assert_eq!(content_blocks(&MessageContent::from("hi")), serde_json::json!("hi"));
+    }
+
+    #[test]
+    fn test_parse_stream_events() {
+        let event = |data: serde_json::Value| streaming::SseEvent { event: None, data: data.to_string() };
+
+        let start = parse_stream_event(&event(serde_json::json!({
+            "type": "message_start",
+            "message": {"usage": {"input_tokens": 25, "output_tokens": 1}}
+        }))).unwrap().unwrap();
+        assert_eq!(start.usage.unwrap().prompt_tokens, 25);
+
+        let delta = parse_stream_event(&event(serde_json::json!({
+            "type": "content_block_delta",
+            "delta": {"type": "text_delta", "text": "Hello"}
+        }))).unwrap().unwrap();
+        assert_eq!(delta.delta, "Hello");
+
+        let end = parse_stream_event(&event(serde_json::json!({
+            "type": "message_delta",
+            "delta": {"stop_reason": "end_turn"},
+            "usage": {"output_tokens": 15}
+        }))).unwrap().unwrap();
+        assert_eq!(end.finish_reason.as_deref(), Some("end_turn"));
+        assert_eq!(end.usage.unwrap().completion_tokens, 15);
+
+        assert!(parse_stream_event(&event(serde_json::json!({"type": "ping"}))).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_structured_output_tool() {
+        let adapter = AnthropicAdapter::new(test_provider());
+        let request = ChatRequest {
+            messages: vec![Message::user("Name a colour")],
+            response_format: Some(crate::ResponseFormat::JsonObject),
+            ..Default::default()
+        };
+
+        let payload = adapter.build_payload(&request);
+        assert_eq!(payload["tools"][0]["name"], structured::OUTPUT_TOOL_NAME);
+        assert_eq!(payload["tool_choice"]["type"], "tool");
+
+        let body = serde_json::json!({
+            "content": [{"type": "tool_use", "name": structured::OUTPUT_TOOL_NAME, "input": {"colour": "red"}}],
+            "stop_reason": "tool_use"
+        });
+        assert_eq!(response_content(&body), r#"{"colour":"red"}"#);
+        assert_eq!(stop_reason("tool_use"), "end_turn");
+    }
 }
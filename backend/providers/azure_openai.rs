@@ -1,12 +1,12 @@
 //! Azure OpenAI provider adapter
 
 use async_trait::async_trait;
-use std::collections::HashMap;
 use chrono::Utc;
 
-use crate::{ChatRequest, ChatResponse, Choice, Message, TokenUsage, Provider};
+use crate::{ChatRequest, ChatResponse, Choice, Message, ModelCapability, TokenUsage, Provider};
 use crate::error::{Result, SynapseError, ProviderError};
-use crate::providers::ProviderAdapter;
+use crate::providers::{content, streaming, ChatStream, ProviderAdapter};
+use crate::telemetry::PropagateTrace;
 
 pub struct AzureOpenAIAdapter {
     provider: Provider,
@@ -35,12 +35,12 @@ impl AzureOpenAIAdapter {
         input_cost + output_cost
     }
 
-    fn build_messages(&self, messages: &[Message]) -> Vec<serde_json::Value> {
+    fn build_messages(&self, messages: &[Message]) -> Result<Vec<serde_json::Value>> {
         messages.iter().map(|msg| {
-            serde_json::json!({
+            Ok(serde_json::json!({
                 "role": msg.role,
-                "content": msg.content
-            })
+                "content": content::openai_content(&msg.content)?
+            }))
         }).collect()
     }
 }
@@ -59,14 +59,19 @@ impl ProviderAdapter for AzureOpenAIAdapter {
             self.api_version
         );
 
-        let payload = serde_json::json!({
-            "messages": self.build_messages(&request.messages),
+        let mut payload = serde_json::json!({
+            "messages": self.build_messages(&request.messages)?,
             "temperature": request.temperature,
             "max_tokens": request.max_tokens,
             "top_p": request.top_p,
         });
 
+        if let Some(ref response_format) = request.response_format {
+            payload["response_format"] = serde_json::json!(response_format);
+        }
+
         let response = self.client.post(&url)
+            .propagate_trace()
             .header("api-key", &self.provider.api_key)
             .header("Content-Type", "application/json")
             .json(&payload)
@@ -104,24 +109,71 @@ impl ProviderAdapter for AzureOpenAIAdapter {
             model: body["model"].as_str().unwrap_or(&request.model).to_string(),
             choices: vec![Choice {
                 index: 0,
+                logprobs: None,
                 message: Message::assistant(&content),
                 finish_reason,
             }],
             usage,
             cost,
-            provider: "azure_openai".to_string(),
+            provider: self.provider.id.clone(),
             created: Utc::now(),
             latency_ms,
+            system_fingerprint: None,
         })
     }
 
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let url = format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            self.provider.base_url,
+            request.model,
+            self.api_version
+        );
+
+        let mut payload = serde_json::json!({
+            "messages": self.build_messages(&request.messages)?,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "top_p": request.top_p,
+            "stream": true,
+        });
+
+        if let Some(ref response_format) = request.response_format {
+            payload["response_format"] = serde_json::json!(response_format);
+        }
+
+        let response = self.client.post(&url)
+            .propagate_trace()
+            .header("api-key", &self.provider.api_key)
+            .header("Content-Type", "application/json")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        Ok(streaming::openai_stream(streaming::check_status(response).await?))
+    }
+
     async fn list_models(&self) -> Result<Vec<String>> {
-        // Azure deployments are project-specific
-        Ok(vec![
-            "gpt-4".into(),
-            "gpt-4-turbo".into(),
-            "gpt-35-turbo".into(),
-        ])
+        // Azure exposes deployments rather than models; requests address them by name
+        let url = format!("{}/openai/deployments?api-version={}", self.provider.base_url, self.api_version);
+
+        let response = self.client.get(&url)
+            .header("api-key", &self.provider.api_key)
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        let body: serde_json::Value = streaming::check_status(response).await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        Ok(body["data"]
+            .as_array()
+            .map(|data| data.iter().filter_map(|d| d["id"].as_str().map(String::from)).collect())
+            .unwrap_or_default())
     }
 
     async fn health_check(&self) -> Result<bool> {
@@ -135,11 +187,16 @@ impl ProviderAdapter for AzureOpenAIAdapter {
     }
 
     fn provider(&self) -> &Provider { &self.provider }
+
+    fn input_capabilities(&self) -> &'static [ModelCapability] {
+        &[ModelCapability::Vision, ModelCapability::AudioInput, ModelCapability::DocumentInput]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use crate::{ProviderType, ProviderPricing, ProviderHealth};
 
     fn create_test_provider() -> Provider {
@@ -148,6 +205,7 @@ mod tests {
             name: "Azure OpenAI".to_string(),
             provider_type: ProviderType::AzureOpenAI,
             api_key: "test-key".to_string(),
+            models: Vec::new(),
             base_url: "https://myresource.openai.azure.com".to_string(),
             pricing: ProviderPricing::default(),
             enabled: true,
@@ -173,7 +231,7 @@ mod tests {
             Message::user("Hello"),
         ];
         
-        let built = adapter.build_messages(&messages);
+        let built = adapter.build_messages(&messages).unwrap();
         assert_eq!(built.len(), 2);
         assert_eq!(built[0]["role"], "system");
     }
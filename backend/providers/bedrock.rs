@@ -1,16 +1,17 @@
 //! AWS Bedrock provider adapter
 
 use async_trait::async_trait;
-use std::collections::HashMap;
 use chrono::Utc;
 
 use crate::{ChatRequest, ChatResponse, Choice, Message, TokenUsage, Provider};
 use crate::error::{Result, SynapseError, ProviderError};
 use crate::providers::ProviderAdapter;
+use crate::telemetry::PropagateTrace;
 
 pub struct BedrockAdapter {
     provider: Provider,
     client: reqwest::Client,
+    #[allow(dead_code)]
     region: String,
 }
 
@@ -94,8 +95,8 @@ impl BedrockAdapter {
         let mut converted = Vec::new();
         for msg in messages {
             match msg.role.as_str() {
-                "system" => system = Some(msg.content.clone()),
-                role => converted.push(serde_json::json!({"role": role, "content": msg.content})),
+                "system" => system = Some(msg.content.text()),
+                role => converted.push(serde_json::json!({"role": role, "content": msg.content.text()})),
             }
         }
         (system, converted)
@@ -110,7 +111,7 @@ impl BedrockAdapter {
     }
 
     fn format_simple_prompt(&self, messages: &[Message]) -> String {
-        messages.iter().map(|m| m.content.clone()).collect::<Vec<_>>().join("\n\n")
+        messages.iter().map(|m| m.content.text()).collect::<Vec<_>>().join("\n\n")
     }
 
     fn parse_response(&self, model: &str, body: &serde_json::Value) -> (String, TokenUsage) {
@@ -158,6 +159,7 @@ impl ProviderAdapter for BedrockAdapter {
         let payload = self.build_request_body(request);
 
         let response = self.client.post(&url)
+            .propagate_trace()
             .header("Content-Type", "application/json")
             // AWS SigV4 would be added here in production
             .json(&payload)
@@ -183,18 +185,21 @@ impl ProviderAdapter for BedrockAdapter {
             model: request.model.clone(),
             choices: vec![Choice {
                 index: 0,
+                logprobs: None,
                 message: Message::assistant(&content),
                 finish_reason: "stop".to_string(),
             }],
             usage,
             cost,
-            provider: "bedrock".to_string(),
+            provider: self.provider.id.clone(),
             created: Utc::now(),
             latency_ms,
+            system_fingerprint: None,
         })
     }
 
     async fn list_models(&self) -> Result<Vec<String>> {
+        // ListFoundationModels needs SigV4 signing, which this adapter does not do yet
         Ok(vec![
             "anthropic.claude-3-sonnet-20240229-v1:0".into(),
             "anthropic.claude-3-haiku-20240307-v1:0".into(),
@@ -213,6 +218,7 @@ impl ProviderAdapter for BedrockAdapter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use crate::{ProviderType, ProviderPricing, ProviderHealth};
 
     fn create_test_provider() -> Provider {
@@ -221,6 +227,7 @@ mod tests {
             name: "Bedrock".to_string(),
             provider_type: ProviderType::Bedrock,
             api_key: "".to_string(),
+            models: Vec::new(),
             base_url: "https://bedrock-runtime.us-east-1.amazonaws.com".to_string(),
             pricing: ProviderPricing::default(),
             enabled: true,
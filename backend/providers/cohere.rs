@@ -5,11 +5,15 @@ use chrono::Utc;
 use reqwest::Client;
 use std::time::Instant;
 
+use futures_util::{future, StreamExt};
+
 use crate::{
-    ChatRequest, ChatResponse, Choice, Message, Provider, TokenUsage,
+    ChatRequest, ChatResponse, ChatStreamChunk, Choice, EmbeddingRequest, EmbeddingResponse, Message,
+    Provider, ResponseFormat, TokenUsage,
     error::{ProviderError, Result, SynapseError},
 };
-use super::ProviderAdapter;
+use super::{streaming, structured, ChatStream, ProviderAdapter};
+use crate::telemetry::PropagateTrace;
 
 /// Adapter for Cohere API
 pub struct CohereAdapter {
@@ -34,67 +38,12 @@ impl ProviderAdapter for CohereAdapter {
         let start = Instant::now();
         let url = format!("{}/chat", self.provider.base_url);
 
-        // Convert messages to Cohere format
-        // Cohere uses a different format: message + chat_history
-        let mut chat_history: Vec<serde_json::Value> = Vec::new();
-        let mut current_message = String::new();
-        let mut preamble = String::new();
-
-        for msg in &request.messages {
-            match msg.role.as_str() {
-                "system" => {
-                    preamble = msg.content.clone();
-                }
-                "user" => {
-                    if !current_message.is_empty() {
-                        // Previous message was user, push it to history
-                        chat_history.push(serde_json::json!({
-                            "role": "USER",
-                            "message": current_message
-                        }));
-                    }
-                    current_message = msg.content.clone();
-                }
-                "assistant" => {
-                    // Push user message first, then assistant
-                    if !current_message.is_empty() {
-                        chat_history.push(serde_json::json!({
-                            "role": "USER",
-                            "message": current_message
-                        }));
-                        current_message.clear();
-                    }
-                    chat_history.push(serde_json::json!({
-                        "role": "CHATBOT",
-                        "message": msg.content
-                    }));
-                }
-                _ => {}
-            }
-        }
-
-        // Build Cohere request payload
-        let mut payload = serde_json::json!({
-            "model": request.model,
-            "message": current_message,
-            "temperature": request.temperature,
-        });
-
-        if !chat_history.is_empty() {
-            payload["chat_history"] = serde_json::json!(chat_history);
-        }
-
-        if !preamble.is_empty() {
-            payload["preamble"] = serde_json::json!(preamble);
-        }
-
-        if request.max_tokens > 0 {
-            payload["max_tokens"] = serde_json::json!(request.max_tokens);
-        }
+        let payload = self.build_payload(request);
 
         // Make request to Cohere
         let response = self.client
             .post(&url)
+            .propagate_trace()
             .header("Authorization", format!("Bearer {}", self.provider.api_key))
             .header("Content-Type", "application/json")
             .header("Accept", "application/json")
@@ -125,19 +74,21 @@ impl ProviderAdapter for CohereAdapter {
             .await
             .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
 
-        // Parse Cohere response
-        let text = body["text"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
+        // Parse Cohere response; forced output tool calls carry the JSON instead of text
+        let text = match body["tool_calls"].as_array().and_then(|calls| output_tool_parameters(calls)) {
+            Some(parameters) => parameters,
+            None => body["text"].as_str().unwrap_or("").to_string(),
+        };
 
         let choices = vec![Choice {
             index: 0,
+            logprobs: None,
             message: Message {
                 role: "assistant".to_string(),
-                content: text,
+                content: text.into(),
                 function_call: None,
                 tool_calls: None,
+                tool_call_id: None,
             },
             finish_reason: body["finish_reason"]
                 .as_str()
@@ -160,24 +111,118 @@ impl ProviderAdapter for CohereAdapter {
 
         Ok(ChatResponse {
             id: body["generation_id"].as_str().unwrap_or("").to_string(),
-            provider: self.provider.name.clone(),
+            provider: self.provider.id.clone(),
             model: request.model.clone(),
             choices,
             usage,
             created: Utc::now(),
             latency_ms,
             cost,
+            system_fingerprint: None,
+        })
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let url = format!("{}/chat", self.provider.base_url);
+
+        let mut payload = self.build_payload(request);
+        payload["stream"] = serde_json::json!(true);
+
+        let response = self.client
+            .post(&url)
+            .propagate_trace()
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .header("Content-Type", "application/json")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        // Cohere streams newline-delimited JSON events rather than SSE
+        let stream = streaming::json_lines(streaming::check_status(response).await?)
+            .filter_map(|event| future::ready(event.map(|e| parse_stream_event(&e)).transpose()));
+        Ok(Box::pin(stream))
+    }
+
+    async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+        let start = Instant::now();
+        let url = format!("{}/embed", self.provider.base_url);
+
+        let payload = serde_json::json!({
+            "model": request.model,
+            "texts": request.input,
+            "input_type": "search_document",
+        });
+
+        let response = self.client
+            .post(&url)
+            .propagate_trace()
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(SynapseError::Provider(ProviderError::RateLimited));
+        }
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(SynapseError::Provider(ProviderError::RequestFailed(
+                format!("Status: {}, Body: {}", status, error_text)
+            )));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        let data: Vec<Vec<f32>> = serde_json::from_value(body["embeddings"].clone())
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        let input_tokens = body["meta"]["billed_units"]["input_tokens"].as_u64().unwrap_or(0) as u32;
+        let usage = TokenUsage {
+            prompt_tokens: input_tokens,
+            completion_tokens: 0,
+            total_tokens: input_tokens,
+        };
+        let cost = self.calculate_cost(&usage);
+
+        Ok(EmbeddingResponse {
+            provider: self.provider.id.clone(),
+            model: request.model.clone(),
+            data,
+            usage,
+            latency_ms: start.elapsed().as_millis() as u64,
+            cost,
         })
     }
 
     async fn list_models(&self) -> Result<Vec<String>> {
-        Ok(vec![
-            "command-r-plus".to_string(),
-            "command-r".to_string(),
-            "command".to_string(),
-            "command-light".to_string(),
-            "command-a-03-2025".to_string(),
-        ])
+        let url = format!("{}/models?page_size=1000", self.provider.base_url);
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        let body: serde_json::Value = streaming::check_status(response).await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        Ok(body["models"]
+            .as_array()
+            .map(|models| models.iter().filter_map(|m| m["name"].as_str().map(String::from)).collect())
+            .unwrap_or_default())
     }
 
     async fn health_check(&self) -> Result<bool> {
@@ -196,9 +241,92 @@ impl ProviderAdapter for CohereAdapter {
     fn provider(&self) -> &Provider {
         &self.provider
     }
+
+    fn estimate_cost(&self, usage: &TokenUsage) -> f64 {
+        self.calculate_cost(usage)
+    }
 }
 
 impl CohereAdapter {
+    fn build_payload(&self, request: &ChatRequest) -> serde_json::Value {
+        // Convert messages to Cohere format
+        // Cohere uses a different format: message + chat_history
+        let mut chat_history: Vec<serde_json::Value> = Vec::new();
+        let mut current_message = String::new();
+        let mut preamble = String::new();
+
+        for msg in &request.messages {
+            match msg.role.as_str() {
+                "system" => {
+                    preamble = msg.content.text();
+                }
+                "user" => {
+                    if !current_message.is_empty() {
+                        // Previous message was user, push it to history
+                        chat_history.push(serde_json::json!({
+                            "role": "USER",
+                            "message": current_message
+                        }));
+                    }
+                    current_message = msg.content.text();
+                }
+                "assistant" => {
+                    // Push user message first, then assistant
+                    if !current_message.is_empty() {
+                        chat_history.push(serde_json::json!({
+                            "role": "USER",
+                            "message": current_message
+                        }));
+                        current_message.clear();
+                    }
+                    chat_history.push(serde_json::json!({
+                        "role": "CHATBOT",
+                        "message": msg.content.text()
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        // Build Cohere request payload
+        let mut payload = serde_json::json!({
+            "model": request.model,
+            "message": current_message,
+            "temperature": request.temperature,
+        });
+
+        if !chat_history.is_empty() {
+            payload["chat_history"] = serde_json::json!(chat_history);
+        }
+
+        if !preamble.is_empty() {
+            payload["preamble"] = serde_json::json!(preamble);
+        }
+
+        if request.max_tokens > 0 {
+            payload["max_tokens"] = serde_json::json!(request.max_tokens);
+        }
+
+        match request.response_format {
+            Some(ResponseFormat::JsonObject) => {
+                payload["response_format"] = serde_json::json!({"type": "json_object"});
+            }
+            Some(ResponseFormat::JsonSchema { .. }) => {
+                if let Some(tool) = structured::output_tool(request.response_format.as_ref()) {
+                    payload["tools"] = serde_json::json!([{
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameter_definitions": parameter_definitions(&tool.schema),
+                    }]);
+                    payload["tool_choice"] = serde_json::json!("REQUIRED");
+                }
+            }
+            Some(ResponseFormat::Text) | None => {}
+        }
+
+        payload
+    }
+
     fn calculate_cost(&self, usage: &TokenUsage) -> f64 {
         // Default Cohere pricing (can be customized per model)
         let input_cost = (usage.prompt_tokens as f64 / 1_000_000.0) * 2.5;  // $2.5/1M input
@@ -206,3 +334,78 @@ impl CohereAdapter {
         input_cost + output_cost
     }
 }
+
+/// Cohere tools take flat parameter definitions, so only top-level
+/// properties are described; nesting is still enforced by validation
+fn parameter_definitions(schema: &serde_json::Value) -> serde_json::Value {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+
+    let definitions = schema["properties"]
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, property)| {
+                    let kind = match property["type"].as_str().unwrap_or("string") {
+                        "integer" => "int",
+                        "number" => "float",
+                        "boolean" => "bool",
+                        "array" => "list",
+                        "object" => "dict",
+                        _ => "str",
+                    };
+                    let mut definition = serde_json::json!({
+                        "type": kind,
+                        "required": required.contains(&name.as_str()),
+                    });
+                    if let Some(description) = property["description"].as_str() {
+                        definition["description"] = serde_json::json!(description);
+                    }
+                    (name.clone(), definition)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    serde_json::Value::Object(definitions)
+}
+
+/// Parameters of the forced output tool call, serialised as the response text
+fn output_tool_parameters(calls: &[serde_json::Value]) -> Option<String> {
+    calls
+        .iter()
+        .find(|call| call["name"] == structured::OUTPUT_TOOL_NAME)
+        .map(|call| call["parameters"].to_string())
+}
+
+/// Convert one Cohere stream event; usage only arrives with `stream-end`
+fn parse_stream_event(event: &serde_json::Value) -> Option<ChatStreamChunk> {
+    match event["event_type"].as_str()? {
+        "text-generation" => Some(ChatStreamChunk {
+            delta: event["text"].as_str().unwrap_or("").to_string(),
+            ..Default::default()
+        }),
+        "tool-calls-generation" => Some(ChatStreamChunk {
+            delta: output_tool_parameters(event["tool_calls"].as_array()?)?,
+            ..Default::default()
+        }),
+        "stream-end" => {
+            let tokens = &event["response"]["meta"]["tokens"];
+            let prompt_tokens = tokens["input_tokens"].as_u64().unwrap_or(0) as u32;
+            let completion_tokens = tokens["output_tokens"].as_u64().unwrap_or(0) as u32;
+            Some(ChatStreamChunk {
+                finish_reason: event["finish_reason"].as_str().map(|r| r.to_lowercase()),
+                usage: Some(TokenUsage {
+                    prompt_tokens,
+                    completion_tokens,
+                    total_tokens: prompt_tokens + completion_tokens,
+                }),
+                ..Default::default()
+            })
+        }
+        _ => None,
+    }
+}
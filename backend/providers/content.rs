@@ -0,0 +1,113 @@
+//! Shared translation of multimodal message content
+//!
+//! `MessageContent` is provider-neutral; OpenAI-compatible adapters (OpenAI,
+//! Groq, Together, Mistral, Azure, vLLM) all take the same part shapes, so the
+//! conversion lives here. Adapters with their own formats (Anthropic, Gemini,
+//! Ollama) translate parts themselves.
+
+use crate::{ContentPart, MediaSource, MessageContent};
+use crate::error::{ProviderError, Result, SynapseError};
+
+/// Content as an OpenAI `content` value: a string, or an array of parts
+pub fn openai_content(content: &MessageContent) -> Result<serde_json::Value> {
+    let parts = match content {
+        MessageContent::Text(text) => return Ok(serde_json::json!(text)),
+        MessageContent::Parts(parts) => parts,
+    };
+
+    parts
+        .iter()
+        .map(|part| {
+            Ok(match part {
+                ContentPart::Text { text } => serde_json::json!({"type": "text", "text": text}),
+                ContentPart::Image { source, detail } => {
+                    let mut image_url = serde_json::json!({"url": source.to_url()});
+                    if let Some(detail) = detail {
+                        image_url["detail"] = serde_json::json!(detail);
+                    }
+                    serde_json::json!({"type": "image_url", "image_url": image_url})
+                }
+                ContentPart::Document { source, name } => {
+                    let MediaSource::Base64 { .. } = source else {
+                        return Err(unsupported("OpenAI-compatible providers only accept inline (base64) documents"));
+                    };
+                    serde_json::json!({
+                        "type": "file",
+                        "file": {
+                            "filename": name.as_deref().unwrap_or("document.pdf"),
+                            "file_data": source.to_url(),
+                        }
+                    })
+                }
+                ContentPart::Audio { data, format } => serde_json::json!({
+                    "type": "input_audio",
+                    "input_audio": {"data": data, "format": format},
+                }),
+            })
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(serde_json::Value::Array)
+}
+
+/// MIME type for a media URL, from its file extension
+pub fn guess_media_type(url: &str) -> Option<&'static str> {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let extension = path.rsplit_once('.')?.1.to_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "wav" => "audio/wav",
+        "mp3" => "audio/mp3",
+        "flac" => "audio/flac",
+        "ogg" => "audio/ogg",
+        _ => return None,
+    })
+}
+
+pub fn unsupported(message: &str) -> SynapseError {
+    SynapseError::Provider(ProviderError::Unsupported(message.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_content_parts() {
+        let content = MessageContent::Parts(vec![
+            ContentPart::Text { text: "What is this?".to_string() },
+            ContentPart::Image {
+                source: MediaSource::from_url("data:image/png;base64,iVBORw0"),
+                detail: Some("low".to_string()),
+            },
+            ContentPart::Audio { data: "UklGR".to_string(), format: "wav".to_string() },
+        ]);
+
+        let json = openai_content(&content).unwrap();
+        assert_eq!(json[0]["type"], "text");
+        assert_eq!(json[1]["image_url"]["url"], "data:image/png;base64,iVBORw0");
+        assert_eq!(json[1]["image_url"]["detail"], "low");
+        assert_eq!(json[2]["input_audio"]["format"], "wav");
+
+        assert_eq!(openai_content(&MessageContent::from("hi")).unwrap(), serde_json::json!("hi"));
+    }
+
+    #[test]
+    fn test_document_url_rejected() {
+        let content = MessageContent::Parts(vec![ContentPart::Document {
+            source: MediaSource::Url { url: "https://example.com/a.pdf".to_string() },
+            name: None,
+        }]);
+        assert!(openai_content(&content).is_err());
+    }
+
+    #[test]
+    fn test_guess_media_type() {
+        assert_eq!(guess_media_type("https://x.test/cat.JPG?size=2"), Some("image/jpeg"));
+        assert_eq!(guess_media_type("gs://bucket/report.pdf"), Some("application/pdf"));
+        assert_eq!(guess_media_type("https://x.test/image"), None);
+    }
+}
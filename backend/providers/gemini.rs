@@ -2,11 +2,16 @@
 
 use async_trait::async_trait;
 use chrono::Utc;
-use std::collections::HashMap;
 
-use crate::{ChatRequest, ChatResponse, Choice, Message, TokenUsage, Provider};
+use futures_util::StreamExt;
+
+use crate::{
+    ChatRequest, ChatResponse, ChatStreamChunk, Choice, ContentPart, MediaSource, Message, MessageContent,
+    ModelCapability, ResponseFormat, TokenUsage, Provider,
+};
 use crate::error::{Result, SynapseError, ProviderError};
-use crate::providers::ProviderAdapter;
+use crate::providers::{content, streaming, structured, ChatStream, ProviderAdapter};
+use crate::telemetry::PropagateTrace;
 
 pub struct GeminiAdapter {
     provider: Provider,
@@ -29,24 +34,56 @@ impl GeminiAdapter {
         input_cost + output_cost
     }
 
-    fn convert_messages(&self, messages: &[Message]) -> Vec<serde_json::Value> {
+    fn convert_messages(&self, messages: &[Message]) -> Result<Vec<serde_json::Value>> {
         messages.iter().filter_map(|msg| {
             let role = match msg.role.as_str() {
                 "system" => return None, // System is handled separately
                 "assistant" => "model",
                 _ => "user",
             };
-            Some(serde_json::json!({
+            Some(content_parts(&msg.content).map(|parts| serde_json::json!({
                 "role": role,
-                "parts": [{"text": msg.content}]
-            }))
+                "parts": parts
+            })))
         }).collect()
     }
 
+    fn build_payload(&self, request: &ChatRequest) -> Result<serde_json::Value> {
+        let contents = self.convert_messages(&request.messages)?;
+        let system_instruction = self.get_system_instruction(&request.messages);
+
+        let mut payload = serde_json::json!({
+            "contents": contents,
+            "generationConfig": {
+                "temperature": request.temperature,
+                "maxOutputTokens": request.max_tokens,
+            }
+        });
+
+        if let Some(sys) = system_instruction {
+            payload["systemInstruction"] = serde_json::json!({
+                "parts": [{"text": sys}]
+            });
+        }
+
+        match request.response_format {
+            Some(ResponseFormat::JsonObject) => {
+                payload["generationConfig"]["responseMimeType"] = "application/json".into();
+            }
+            Some(ResponseFormat::JsonSchema { ref json_schema }) => {
+                payload["generationConfig"]["responseMimeType"] = "application/json".into();
+                payload["generationConfig"]["responseSchema"] = structured::gemini_schema(&json_schema.schema);
+            }
+            Some(ResponseFormat::Text) | None => {}
+        }
+
+        Ok(payload)
+    }
+
     fn get_system_instruction(&self, messages: &[Message]) -> Option<String> {
         messages.iter()
             .find(|m| m.role == "system")
-            .map(|m| m.content.clone())
+            .map(|m| m.content.text())
     }
 }
 
@@ -62,24 +99,10 @@ impl ProviderAdapter for GeminiAdapter {
             self.provider.api_key
         );
 
-        let contents = self.convert_messages(&request.messages);
-        let system_instruction = self.get_system_instruction(&request.messages);
-
-        let mut payload = serde_json::json!({
-            "contents": contents,
-            "generationConfig": {
-                "temperature": request.temperature,
-                "maxOutputTokens": request.max_tokens,
-            }
-        });
-
-        if let Some(sys) = system_instruction {
-            payload["systemInstruction"] = serde_json::json!({
-                "parts": [{"text": sys}]
-            });
-        }
+        let payload = self.build_payload(request)?;
 
         let response = self.client.post(&url)
+            .propagate_trace()
             .json(&payload)
             .send()
             .await
@@ -113,23 +136,70 @@ impl ProviderAdapter for GeminiAdapter {
             model: request.model.clone(),
             choices: vec![Choice {
                 index: 0,
+                logprobs: None,
                 message: Message::assistant(&content),
                 finish_reason: "stop".to_string(),
             }],
             usage,
             cost,
-            provider: "gemini".to_string(),
+            provider: self.provider.id.clone(),
             created: Utc::now(),
             latency_ms,
+            system_fingerprint: None,
         })
     }
 
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let url = format!(
+            "{}/v1beta/models/{}:streamGenerateContent?alt=sse&key={}",
+            self.provider.base_url,
+            request.model,
+            self.provider.api_key
+        );
+
+        let response = self.client.post(&url)
+            .propagate_trace()
+            .json(&self.build_payload(request)?)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        let stream = streaming::sse_events(streaming::check_status(response).await?)
+            .map(|event| {
+                let event = event?;
+                let json: serde_json::Value = serde_json::from_str(&event.data)
+                    .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+                Ok(parse_stream_chunk(&json))
+            });
+        Ok(Box::pin(stream))
+    }
+
     async fn list_models(&self) -> Result<Vec<String>> {
-        Ok(vec![
-            "gemini-1.5-pro".into(),
-            "gemini-1.5-flash".into(),
-            "gemini-1.0-pro".into(),
-        ])
+        let url = format!(
+            "{}/v1beta/models?pageSize=1000&key={}",
+            self.provider.base_url,
+            self.provider.api_key
+        );
+
+        let response = self.client.get(&url)
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        let body: serde_json::Value = streaming::check_status(response).await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        // Names come back as "models/gemini-1.5-pro"
+        Ok(body["models"]
+            .as_array()
+            .map(|models| models.iter()
+                .filter_map(|m| m["name"].as_str())
+                .map(|name| name.trim_start_matches("models/").to_string())
+                .collect())
+            .unwrap_or_default())
     }
 
     async fn health_check(&self) -> Result<bool> {
@@ -146,11 +216,68 @@ impl ProviderAdapter for GeminiAdapter {
     fn provider(&self) -> &Provider {
         &self.provider
     }
+
+    fn input_capabilities(&self) -> &'static [ModelCapability] {
+        &[ModelCapability::Vision, ModelCapability::AudioInput, ModelCapability::DocumentInput]
+    }
+}
+
+/// Message content as Gemini `parts`: text, `inlineData` or `fileData`
+fn content_parts(content: &MessageContent) -> Result<Vec<serde_json::Value>> {
+    let parts = match content {
+        MessageContent::Text(text) => return Ok(vec![serde_json::json!({"text": text})]),
+        MessageContent::Parts(parts) => parts,
+    };
+
+    let media = |source: &MediaSource| match source {
+        MediaSource::Base64 { media_type, data } => Ok(serde_json::json!({
+            "inlineData": {"mimeType": media_type, "data": data}
+        })),
+        MediaSource::Url { url } => {
+            let mime_type = content::guess_media_type(url)
+                .ok_or_else(|| content::unsupported("Gemini needs a media type; send base64 data or a URL with a file extension"))?;
+            Ok(serde_json::json!({
+                "fileData": {"mimeType": mime_type, "fileUri": url}
+            }))
+        }
+    };
+
+    parts.iter().map(|part| match part {
+        ContentPart::Text { text } => Ok(serde_json::json!({"text": text})),
+        ContentPart::Image { source, .. } | ContentPart::Document { source, .. } => media(source),
+        ContentPart::Audio { data, format } => Ok(serde_json::json!({
+            "inlineData": {"mimeType": format!("audio/{}", format), "data": data}
+        })),
+    }).collect()
+}
+
+/// Convert one `streamGenerateContent` chunk; usage totals are cumulative
+fn parse_stream_chunk(json: &serde_json::Value) -> ChatStreamChunk {
+    let candidate = &json["candidates"][0];
+
+    let delta = candidate["content"]["parts"]
+        .as_array()
+        .map(|parts| parts.iter().filter_map(|p| p["text"].as_str()).collect::<String>())
+        .unwrap_or_default();
+
+    let usage = json["usageMetadata"].as_object().map(|meta| TokenUsage {
+        prompt_tokens: meta.get("promptTokenCount").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+        completion_tokens: meta.get("candidatesTokenCount").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+        total_tokens: meta.get("totalTokenCount").and_then(|v| v.as_u64()).unwrap_or(0) as u32,
+    });
+
+    ChatStreamChunk {
+        delta,
+        finish_reason: candidate["finishReason"].as_str().map(|r| r.to_lowercase()),
+        usage,
+        ..Default::default()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use crate::{ProviderType, ProviderPricing, ProviderHealth};
 
     fn create_test_provider() -> Provider {
@@ -159,6 +286,7 @@ mod tests {
             name: "Gemini".to_string(),
             provider_type: ProviderType::Gemini,
             api_key: "test-key".to_string(),
+            models: Vec::new(),
             base_url: "https://generativelanguage.googleapis.com".to_string(),
             pricing: ProviderPricing::default(),
             enabled: true,
@@ -185,7 +313,35 @@ mod tests {
             Message::assistant("Hi there"),
         ];
         
-        let converted = adapter.convert_messages(&messages);
+        let converted = adapter.convert_messages(&messages).unwrap();
         assert_eq!(converted.len(), 2); // System is filtered out
     }
+
+    #[test]
+    fn test_content_parts() {
+        let parts = content_parts(&MessageContent::Parts(vec![
+            ContentPart::Text { text: "Transcribe".to_string() },
+            ContentPart::Audio { data: "UklGR".to_string(), format: "wav".to_string() },
+            ContentPart::Image { source: MediaSource::Url { url: "gs://bucket/cat.png".to_string() }, detail: None },
+        ])).unwrap();
+        assert_eq!(parts[1]["inlineData"]["mimeType"], "audio/wav");
+        assert_eq!(parts[2]["fileData"]["mimeType"], "image/png");
+
+        let unknown = MessageContent::Parts(vec![ContentPart::Image {
+            source: MediaSource::Url { url: "https://example.com/image".to_string() },
+            detail: None,
+        }]);
+        assert!(content_parts(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_parse_stream_chunk() {
+        let chunk = parse_stream_chunk(&serde_json::json!({
+            "candidates": [{"content": {"parts": [{"text": "Hi"}, {"text": " there"}]}, "finishReason": "STOP"}],
+            "usageMetadata": {"promptTokenCount": 4, "candidatesTokenCount": 2, "totalTokenCount": 6}
+        }));
+        assert_eq!(chunk.delta, "Hi there");
+        assert_eq!(chunk.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(chunk.usage.unwrap().total_tokens, 6);
+    }
 }
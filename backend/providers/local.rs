@@ -5,11 +5,15 @@ use chrono::Utc;
 use reqwest::Client;
 use std::time::Instant;
 
+use futures_util::StreamExt;
+
 use crate::{
-    ChatRequest, ChatResponse, Choice, Message, Provider, TokenUsage,
+    ChatRequest, ChatResponse, ChatStreamChunk, Choice, ContentPart, MediaSource, Message, MessageContent,
+    ModelCapability, Provider, ResponseFormat, TokenUsage,
     error::{ProviderError, Result, SynapseError},
 };
-use super::ProviderAdapter;
+use super::{content, streaming, ChatStream, ProviderAdapter};
+use crate::telemetry::PropagateTrace;
 
 /// Adapter for local LLM servers (Ollama, llama.cpp server, vLLM)
 pub struct LocalAdapter {
@@ -44,6 +48,52 @@ impl ProviderAdapter for LocalAdapter {
         }
     }
 
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let is_ollama = self.provider.base_url.contains("11434")
+            || self.provider.base_url.contains("ollama");
+
+        let messages = build_messages(request, is_ollama)?;
+
+        let (url, mut payload) = if is_ollama {
+            (format!("{}/api/chat", self.provider.base_url), serde_json::json!({
+                "model": request.model,
+                "messages": messages,
+                "stream": true,
+                "options": {
+                    "temperature": request.temperature,
+                    "num_predict": request.max_tokens as i32,
+                }
+            }))
+        } else {
+            (format!("{}/chat/completions", self.provider.base_url), serde_json::json!({
+                "model": request.model,
+                "messages": messages,
+                "temperature": request.temperature,
+                "max_tokens": request.max_tokens,
+                "stream": true,
+            }))
+        };
+        apply_response_format(&mut payload, request, is_ollama);
+
+        let response = self.client
+            .post(&url)
+            .propagate_trace()
+            .header("Content-Type", "application/json")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+        let response = streaming::check_status(response).await?;
+
+        if !is_ollama {
+            return Ok(streaming::openai_stream(response));
+        }
+
+        // Ollama streams one JSON object per line and reports counts on the last
+        let stream = streaming::json_lines(response).map(|line| line.map(|l| parse_ollama_chunk(&l)));
+        Ok(Box::pin(stream))
+    }
+
     async fn list_models(&self) -> Result<Vec<String>> {
         let url = if self.provider.base_url.contains("11434") {
             format!("{}/api/tags", self.provider.base_url)
@@ -105,6 +155,14 @@ impl ProviderAdapter for LocalAdapter {
     fn provider(&self) -> &Provider {
         &self.provider
     }
+
+    fn estimate_cost(&self, _usage: &TokenUsage) -> f64 {
+        0.0 // Local models are free
+    }
+
+    fn input_capabilities(&self) -> &'static [ModelCapability] {
+        &[ModelCapability::Vision]
+    }
 }
 
 impl LocalAdapter {
@@ -112,15 +170,9 @@ impl LocalAdapter {
     async fn chat_ollama(&self, request: &ChatRequest, start: Instant) -> Result<ChatResponse> {
         let url = format!("{}/api/chat", self.provider.base_url);
 
-        let messages: Vec<serde_json::Value> = request.messages
-            .iter()
-            .map(|m| serde_json::json!({
-                "role": m.role,
-                "content": m.content
-            }))
-            .collect();
+        let messages = build_messages(request, true)?;
 
-        let payload = serde_json::json!({
+        let mut payload = serde_json::json!({
             "model": request.model,
             "messages": messages,
             "stream": false,
@@ -129,9 +181,11 @@ impl LocalAdapter {
                 "num_predict": request.max_tokens as i32,
             }
         });
+        apply_response_format(&mut payload, request, true);
 
         let response = self.client
             .post(&url)
+            .propagate_trace()
             .json(&payload)
             .send()
             .await
@@ -154,11 +208,13 @@ impl LocalAdapter {
 
         let choices = vec![Choice {
             index: 0,
+            logprobs: None,
             message: Message {
                 role: "assistant".to_string(),
-                content,
+                content: content.into(),
                 function_call: None,
                 tool_calls: None,
+                tool_call_id: None,
             },
             finish_reason: "stop".to_string(),
         }];
@@ -175,13 +231,14 @@ impl LocalAdapter {
 
         Ok(ChatResponse {
             id: uuid::Uuid::new_v4().to_string(),
-            provider: self.provider.name.clone(),
+            provider: self.provider.id.clone(),
             model: request.model.clone(),
             choices,
             usage,
             created: Utc::now(),
             latency_ms,
             cost: 0.0, // Local models are free
+            system_fingerprint: None,
         })
     }
 
@@ -189,20 +246,17 @@ impl LocalAdapter {
     async fn chat_openai_compatible(&self, request: &ChatRequest, start: Instant) -> Result<ChatResponse> {
         let url = format!("{}/chat/completions", self.provider.base_url);
 
-        let payload = serde_json::json!({
+        let mut payload = serde_json::json!({
             "model": request.model,
-            "messages": request.messages.iter().map(|m| {
-                serde_json::json!({
-                    "role": m.role,
-                    "content": m.content
-                })
-            }).collect::<Vec<_>>(),
+            "messages": build_messages(request, false)?,
             "temperature": request.temperature,
             "max_tokens": request.max_tokens,
         });
+        apply_response_format(&mut payload, request, false);
 
         let response = self.client
             .post(&url)
+            .propagate_trace()
             .header("Content-Type", "application/json")
             .json(&payload)
             .send()
@@ -226,6 +280,7 @@ impl LocalAdapter {
             .enumerate()
             .map(|(i, choice)| Choice {
                 index: i,
+                logprobs: None,
                 message: Message {
                     role: choice["message"]["role"]
                         .as_str()
@@ -234,9 +289,10 @@ impl LocalAdapter {
                     content: choice["message"]["content"]
                         .as_str()
                         .unwrap_or("")
-                        .to_string(),
+                        .into(),
                     function_call: None,
                     tool_calls: None,
+                    tool_call_id: None,
                 },
                 finish_reason: choice["finish_reason"]
                     .as_str()
@@ -255,17 +311,90 @@ impl LocalAdapter {
 
         Ok(ChatResponse {
             id: body["id"].as_str().unwrap_or("").to_string(),
-            provider: self.provider.name.clone(),
+            provider: self.provider.id.clone(),
             model: request.model.clone(),
             choices,
             usage,
             created: Utc::now(),
             latency_ms,
             cost: 0.0, // Local models are free
+            system_fingerprint: None,
         })
     }
 }
 
+/// Messages for Ollama's `/api/chat` or an OpenAI-compatible server
+fn build_messages(request: &ChatRequest, is_ollama: bool) -> Result<Vec<serde_json::Value>> {
+    request.messages.iter().map(|m| {
+        if is_ollama {
+            ollama_message(m)
+        } else {
+            Ok(serde_json::json!({
+                "role": m.role,
+                "content": content::openai_content(&m.content)?
+            }))
+        }
+    }).collect()
+}
+
+/// Ollama's `format` takes "json" or a JSON schema; other servers get `response_format` as-is
+fn apply_response_format(payload: &mut serde_json::Value, request: &ChatRequest, is_ollama: bool) {
+    let Some(ref response_format) = request.response_format else {
+        return;
+    };
+    if !is_ollama {
+        payload["response_format"] = serde_json::json!(response_format);
+        return;
+    }
+    match response_format {
+        ResponseFormat::JsonObject => payload["format"] = "json".into(),
+        ResponseFormat::JsonSchema { json_schema } => payload["format"] = json_schema.schema.clone(),
+        ResponseFormat::Text => {}
+    }
+}
+
+/// Ollama takes text plus a list of base64 `images`
+fn ollama_message(message: &Message) -> Result<serde_json::Value> {
+    let mut json = serde_json::json!({
+        "role": message.role,
+        "content": message.content.text()
+    });
+
+    if let MessageContent::Parts(_) = message.content {
+        let images = message.content.media().map(|part| match part {
+            ContentPart::Image { source: MediaSource::Base64 { data, .. }, .. } => Ok(data.clone()),
+            ContentPart::Image { .. } => Err(content::unsupported("Ollama only accepts inline (base64) images")),
+            _ => Err(content::unsupported("Ollama only accepts image parts")),
+        }).collect::<Result<Vec<_>>>()?;
+        if !images.is_empty() {
+            json["images"] = serde_json::json!(images);
+        }
+    }
+
+    Ok(json)
+}
+
+/// Convert one line of an Ollama `/api/chat` stream
+fn parse_ollama_chunk(json: &serde_json::Value) -> ChatStreamChunk {
+    let done = json["done"].as_bool().unwrap_or(false);
+    let usage = done.then(|| {
+        let prompt_tokens = json["prompt_eval_count"].as_u64().unwrap_or(0) as u32;
+        let completion_tokens = json["eval_count"].as_u64().unwrap_or(0) as u32;
+        TokenUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    });
+
+    ChatStreamChunk {
+        delta: json["message"]["content"].as_str().unwrap_or("").to_string(),
+        finish_reason: done.then(|| json["done_reason"].as_str().unwrap_or("stop").to_string()),
+        usage,
+        ..Default::default()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,6 +406,7 @@ mod tests {
             name: "Local Ollama".to_string(),
             provider_type: ProviderType::Local,
             api_key: "".to_string(),
+            models: Vec::new(),
             base_url: "http://localhost:11434".to_string(),
             pricing: ProviderPricing::default(),
             enabled: true,
@@ -291,4 +421,23 @@ mod tests {
         let adapter = LocalAdapter::new(provider.clone());
         assert_eq!(adapter.name(), "Local Ollama");
     }
+
+    #[test]
+    fn test_parse_ollama_chunk() {
+        let chunk = parse_ollama_chunk(&serde_json::json!({
+            "message": {"role": "assistant", "content": "Hi"},
+            "done": false
+        }));
+        assert_eq!(chunk.delta, "Hi");
+        assert!(chunk.usage.is_none());
+
+        let last = parse_ollama_chunk(&serde_json::json!({
+            "message": {"role": "assistant", "content": ""},
+            "done": true,
+            "prompt_eval_count": 12,
+            "eval_count": 30
+        }));
+        assert_eq!(last.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(last.usage.unwrap().total_tokens, 42);
+    }
 }
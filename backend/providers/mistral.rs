@@ -6,10 +6,12 @@ use reqwest::Client;
 use std::time::Instant;
 
 use crate::{
-    ChatRequest, ChatResponse, Choice, Message, Provider, TokenUsage,
+    ChatRequest, ChatResponse, Choice, EmbeddingRequest, EmbeddingResponse, Message, ModelCapability, Provider,
+    TokenUsage,
     error::{ProviderError, Result, SynapseError},
 };
-use super::ProviderAdapter;
+use super::{content, streaming, ChatStream, ProviderAdapter};
+use crate::telemetry::PropagateTrace;
 
 /// Adapter for Mistral AI API
 pub struct MistralAdapter {
@@ -35,22 +37,22 @@ impl ProviderAdapter for MistralAdapter {
         let url = format!("{}/chat/completions", self.provider.base_url);
 
         // Build request payload (Mistral uses OpenAI-compatible format)
-        let payload = serde_json::json!({
+        let mut payload = serde_json::json!({
             "model": request.model,
-            "messages": request.messages.iter().map(|m| {
-                serde_json::json!({
-                    "role": m.role,
-                    "content": m.content
-                })
-            }).collect::<Vec<_>>(),
+            "messages": build_messages(request)?,
             "temperature": request.temperature,
             "max_tokens": request.max_tokens,
             "top_p": request.top_p,
         });
 
+        if let Some(ref response_format) = request.response_format {
+            payload["response_format"] = serde_json::json!(response_format);
+        }
+
         // Make request
         let mut req = self.client
             .post(&url)
+            .propagate_trace()
             .header("Authorization", format!("Bearer {}", self.provider.api_key))
             .header("Content-Type", "application/json");
 
@@ -97,6 +99,7 @@ impl ProviderAdapter for MistralAdapter {
             .enumerate()
             .map(|(i, choice)| Choice {
                 index: i,
+                logprobs: None,
                 message: Message {
                     role: choice["message"]["role"]
                         .as_str()
@@ -105,9 +108,10 @@ impl ProviderAdapter for MistralAdapter {
                     content: choice["message"]["content"]
                         .as_str()
                         .unwrap_or("")
-                        .to_string(),
+                        .into(),
                     function_call: None,
                     tool_calls: None,
+                    tool_call_id: None,
                 },
                 finish_reason: choice["finish_reason"]
                     .as_str()
@@ -127,25 +131,137 @@ impl ProviderAdapter for MistralAdapter {
 
         Ok(ChatResponse {
             id: body["id"].as_str().unwrap_or("").to_string(),
-            provider: self.provider.name.clone(),
+            provider: self.provider.id.clone(),
             model: request.model.clone(),
             choices,
             usage,
             created: Utc::now(),
             latency_ms,
             cost,
+            system_fingerprint: None,
+        })
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let url = format!("{}/chat/completions", self.provider.base_url);
+
+        // Mistral streams OpenAI-style chunks and includes usage on the last one
+        let mut payload = serde_json::json!({
+            "model": request.model,
+            "messages": build_messages(request)?,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "top_p": request.top_p,
+            "stream": true,
+        });
+
+        if let Some(ref response_format) = request.response_format {
+            payload["response_format"] = serde_json::json!(response_format);
+        }
+
+        let mut req = self.client
+            .post(&url)
+            .propagate_trace()
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .header("Content-Type", "application/json");
+
+        for (key, value) in &self.provider.headers {
+            req = req.header(key, value);
+        }
+
+        let response = req
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        Ok(streaming::openai_stream(streaming::check_status(response).await?))
+    }
+
+    async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+        let start = Instant::now();
+        let url = format!("{}/embeddings", self.provider.base_url);
+
+        // Mistral embeddings follow the OpenAI request/response shape
+        let payload = serde_json::json!({
+            "model": request.model,
+            "input": request.input,
+        });
+
+        let response = self.client
+            .post(&url)
+            .propagate_trace()
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .header("Content-Type", "application/json")
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(SynapseError::Provider(ProviderError::RateLimited));
+        }
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(SynapseError::Provider(ProviderError::RequestFailed(
+                format!("Status: {}, Body: {}", status, error_text)
+            )));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        let data: Vec<Vec<f32>> = body["data"]
+            .as_array()
+            .ok_or_else(|| SynapseError::Provider(ProviderError::InvalidResponse(
+                "Missing data array".to_string()
+            )))?
+            .iter()
+            .map(|item| serde_json::from_value(item["embedding"].clone()).unwrap_or_default())
+            .collect();
+
+        let prompt_tokens = body["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as u32;
+        let usage = TokenUsage {
+            prompt_tokens,
+            completion_tokens: 0,
+            total_tokens: prompt_tokens,
+        };
+        let cost = self.calculate_cost(&usage);
+
+        Ok(EmbeddingResponse {
+            provider: self.provider.id.clone(),
+            model: request.model.clone(),
+            data,
+            usage,
+            latency_ms: start.elapsed().as_millis() as u64,
+            cost,
         })
     }
 
     async fn list_models(&self) -> Result<Vec<String>> {
-        Ok(vec![
-            "mistral-large-latest".to_string(),
-            "mistral-medium-latest".to_string(),
-            "mistral-small-latest".to_string(),
-            "mistral-tiny".to_string(),
-            "open-mixtral-8x7b".to_string(),
-            "open-mixtral-8x22b".to_string(),
-        ])
+        let url = format!("{}/models", self.provider.base_url);
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        let body: serde_json::Value = streaming::check_status(response).await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        Ok(body["data"]
+            .as_array()
+            .map(|data| data.iter().filter_map(|m| m["id"].as_str().map(String::from)).collect())
+            .unwrap_or_default())
     }
 
     async fn health_check(&self) -> Result<bool> {
@@ -164,6 +280,10 @@ impl ProviderAdapter for MistralAdapter {
     fn provider(&self) -> &Provider {
         &self.provider
     }
+
+    fn input_capabilities(&self) -> &'static [ModelCapability] {
+        &[ModelCapability::Vision]
+    }
 }
 
 impl MistralAdapter {
@@ -176,6 +296,16 @@ impl MistralAdapter {
     }
 }
 
+/// Messages in Mistral's OpenAI-compatible format
+fn build_messages(request: &ChatRequest) -> Result<Vec<serde_json::Value>> {
+    request.messages.iter().map(|m| {
+        Ok(serde_json::json!({
+            "role": m.role,
+            "content": content::openai_content(&m.content)?
+        }))
+    }).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,6 +317,7 @@ mod tests {
             name: "Mistral Test".to_string(),
             provider_type: ProviderType::Mistral,
             api_key: "test-key".to_string(),
+            models: Vec::new(),
             base_url: "https://api.mistral.ai/v1".to_string(),
             pricing: ProviderPricing {
                 input_token_cost: 8.0,
@@ -3,6 +3,9 @@
 //! This module contains adapters for connecting to various LLM providers.
 
 mod adapter;
+pub mod content;
+pub mod streaming;
+pub mod structured;
 mod openai;
 mod anthropic;
 mod mistral;
@@ -13,7 +16,8 @@ mod azure_openai;
 mod cohere;
 pub mod account_manager;
 
-pub use adapter::ProviderAdapter;
+pub use adapter::{estimate_tokens, ProviderAdapter};
+pub use streaming::{ChatStream, StreamAccumulator, StreamSummary};
 pub use account_manager::{ProviderAccountManager, ProviderAccount, QuotaPeriod, ProviderCategory};
 pub use openai::OpenAIAdapter;
 pub use anthropic::AnthropicAdapter;
@@ -6,10 +6,12 @@ use reqwest::Client;
 use std::time::Instant;
 
 use crate::{
-    ChatRequest, ChatResponse, Choice, Message, Provider, TokenUsage,
+    ChatRequest, ChatResponse, Choice, EmbeddingRequest, EmbeddingResponse, Message, Provider,
+    ModelCapability, ProviderType, TokenUsage, ToolCall,
     error::{ProviderError, Result, SynapseError},
 };
-use super::ProviderAdapter;
+use super::{content, streaming, ChatStream, ProviderAdapter};
+use crate::telemetry::PropagateTrace;
 
 /// Adapter for OpenAI API (and compatible APIs like Azure, Groq, Together)
 pub struct OpenAIAdapter {
@@ -27,30 +29,100 @@ impl OpenAIAdapter {
 impl ProviderAdapter for OpenAIAdapter {
     async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
         let start = Instant::now();
-        let url = format!("{}/chat/completions", self.provider.base_url);
 
-        // Build request payload
-        let payload = serde_json::json!({
+        let payload = self.build_payload(request)?;
+        let response = self.send_chat(&payload).await?;
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        // Parse response
+        let choices: Vec<Choice> = body["choices"]
+            .as_array()
+            .ok_or_else(|| SynapseError::Provider(ProviderError::InvalidResponse(
+                "Missing choices array".to_string()
+            )))?
+            .iter()
+            .enumerate()
+            .map(|(i, choice)| Choice {
+                index: i,
+                logprobs: Some(choice["logprobs"].clone()).filter(|l| !l.is_null()),
+                message: Message {
+                    role: choice["message"]["role"]
+                        .as_str()
+                        .unwrap_or("assistant")
+                        .to_string(),
+                    content: choice["message"]["content"]
+                        .as_str()
+                        .unwrap_or("")
+                        .into(),
+                    function_call: None,
+                    tool_calls: serde_json::from_value::<Vec<ToolCall>>(choice["message"]["tool_calls"].clone()).ok(),
+                    tool_call_id: None,
+                },
+                finish_reason: choice["finish_reason"]
+                    .as_str()
+                    .unwrap_or("stop")
+                    .to_string(),
+            })
+            .collect();
+
+        let usage = TokenUsage {
+            prompt_tokens: body["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+            completion_tokens: body["usage"]["completion_tokens"].as_u64().unwrap_or(0) as u32,
+            total_tokens: body["usage"]["total_tokens"].as_u64().unwrap_or(0) as u32,
+        };
+
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        // Calculate cost
+        let cost = self.calculate_cost(&usage);
+
+        Ok(ChatResponse {
+            id: body["id"].as_str().unwrap_or("").to_string(),
+            provider: self.provider.id.clone(),
+            model: request.model.clone(),
+            choices,
+            usage,
+            created: Utc::now(),
+            latency_ms,
+            cost,
+            system_fingerprint: body["system_fingerprint"].as_str().map(String::from),
+        })
+    }
+
+    async fn chat_stream(&self, request: &ChatRequest) -> Result<ChatStream> {
+        let mut payload = self.build_payload(request)?;
+        payload["stream"] = serde_json::json!(true);
+        // Only OpenAI itself accepts stream_options; Groq reports usage in x_groq
+        if self.provider.provider_type == ProviderType::OpenAI {
+            payload["stream_options"] = serde_json::json!({"include_usage": true});
+        }
+
+        let response = self.send_chat(&payload).await?;
+        Ok(streaming::openai_stream(response))
+    }
+
+    async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+        let start = Instant::now();
+        let url = format!("{}/embeddings", self.provider.base_url);
+
+        let mut payload = serde_json::json!({
             "model": request.model,
-            "messages": request.messages.iter().map(|m| {
-                serde_json::json!({
-                    "role": m.role,
-                    "content": m.content
-                })
-            }).collect::<Vec<_>>(),
-            "temperature": request.temperature,
-            "max_tokens": request.max_tokens,
-            "top_p": request.top_p,
-            "stop": request.stop,
+            "input": request.input,
         });
+        if let Some(dimensions) = request.dimensions {
+            payload["dimensions"] = serde_json::json!(dimensions);
+        }
 
-        // Make request
         let mut req = self.client
             .post(&url)
+            .propagate_trace()
             .header("Authorization", format!("Bearer {}", self.provider.api_key))
             .header("Content-Type", "application/json");
 
-        // Add custom headers
         for (key, value) in &self.provider.headers {
             req = req.header(key, value);
         }
@@ -61,7 +133,6 @@ impl ProviderAdapter for OpenAIAdapter {
             .await
             .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
 
-        // Check status
         if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
             return Err(SynapseError::Provider(ProviderError::RateLimited));
         }
@@ -83,69 +154,65 @@ impl ProviderAdapter for OpenAIAdapter {
             .await
             .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
 
-        // Parse response
-        let choices: Vec<Choice> = body["choices"]
+        let mut items: Vec<(u64, Vec<f32>)> = body["data"]
             .as_array()
             .ok_or_else(|| SynapseError::Provider(ProviderError::InvalidResponse(
-                "Missing choices array".to_string()
+                "Missing data array".to_string()
             )))?
             .iter()
             .enumerate()
-            .map(|(i, choice)| Choice {
-                index: i,
-                message: Message {
-                    role: choice["message"]["role"]
-                        .as_str()
-                        .unwrap_or("assistant")
-                        .to_string(),
-                    content: choice["message"]["content"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
-                    function_call: None,
-                    tool_calls: None,
-                },
-                finish_reason: choice["finish_reason"]
-                    .as_str()
-                    .unwrap_or("stop")
-                    .to_string(),
+            .map(|(i, item)| {
+                let index = item["index"].as_u64().unwrap_or(i as u64);
+                let vector = serde_json::from_value(item["embedding"].clone()).unwrap_or_default();
+                (index, vector)
             })
             .collect();
+        items.sort_by_key(|(index, _)| *index);
 
+        let prompt_tokens = body["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as u32;
         let usage = TokenUsage {
-            prompt_tokens: body["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as u32,
-            completion_tokens: body["usage"]["completion_tokens"].as_u64().unwrap_or(0) as u32,
-            total_tokens: body["usage"]["total_tokens"].as_u64().unwrap_or(0) as u32,
+            prompt_tokens,
+            completion_tokens: 0,
+            total_tokens: body["usage"]["total_tokens"].as_u64().unwrap_or(prompt_tokens as u64) as u32,
         };
-
-        let latency_ms = start.elapsed().as_millis() as u64;
-
-        // Calculate cost
         let cost = self.calculate_cost(&usage);
 
-        Ok(ChatResponse {
-            id: body["id"].as_str().unwrap_or("").to_string(),
-            provider: self.provider.name.clone(),
+        Ok(EmbeddingResponse {
+            provider: self.provider.id.clone(),
             model: request.model.clone(),
-            choices,
+            data: items.into_iter().map(|(_, vector)| vector).collect(),
             usage,
-            created: Utc::now(),
-            latency_ms,
+            latency_ms: start.elapsed().as_millis() as u64,
             cost,
         })
     }
 
     async fn list_models(&self) -> Result<Vec<String>> {
-        // Return commonly used models for OpenAI
-        Ok(vec![
-            "gpt-4".to_string(),
-            "gpt-4-turbo".to_string(),
-            "gpt-4-turbo-preview".to_string(),
-            "gpt-4o".to_string(),
-            "gpt-4o-mini".to_string(),
-            "gpt-3.5-turbo".to_string(),
-            "gpt-3.5-turbo-16k".to_string(),
-        ])
+        let url = format!("{}/models", self.provider.base_url);
+
+        let mut req = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .timeout(std::time::Duration::from_secs(15));
+
+        for (key, value) in &self.provider.headers {
+            req = req.header(key, value);
+        }
+
+        let response = req
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        let body: serde_json::Value = streaming::check_status(response).await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        Ok(body["data"]
+            .as_array()
+            .map(|data| data.iter().filter_map(|m| m["id"].as_str().map(String::from)).collect())
+            .unwrap_or_default())
     }
 
     async fn health_check(&self) -> Result<bool> {
@@ -164,9 +231,84 @@ impl ProviderAdapter for OpenAIAdapter {
     fn provider(&self) -> &Provider {
         &self.provider
     }
+
+    fn input_capabilities(&self) -> &'static [ModelCapability] {
+        &[ModelCapability::Vision, ModelCapability::AudioInput, ModelCapability::DocumentInput]
+    }
 }
 
 impl OpenAIAdapter {
+    /// Build the `/chat/completions` payload shared by blocking and streaming calls
+    fn build_payload(&self, request: &ChatRequest) -> Result<serde_json::Value> {
+        let messages = request.messages.iter().map(|m| {
+            let mut message = serde_json::json!({
+                "role": m.role,
+                "content": content::openai_content(&m.content)?
+            });
+            if let Some(ref tool_calls) = m.tool_calls {
+                message["tool_calls"] = serde_json::json!(tool_calls);
+            }
+            if let Some(ref tool_call_id) = m.tool_call_id {
+                message["tool_call_id"] = serde_json::json!(tool_call_id);
+            }
+            Ok(message)
+        }).collect::<Result<Vec<_>>>()?;
+
+        let mut payload = serde_json::json!({
+            "model": request.model,
+            "messages": messages,
+            "temperature": request.temperature,
+            "max_tokens": request.max_tokens,
+            "top_p": request.top_p,
+            "stop": request.stop,
+        });
+        if let Some(ref tools) = request.tools {
+            payload["tools"] = serde_json::json!(tools);
+        }
+        if let Some(ref tool_choice) = request.tool_choice {
+            payload["tool_choice"] = tool_choice.clone();
+        }
+        if let Some(ref response_format) = request.response_format {
+            payload["response_format"] = serde_json::json!(response_format);
+        }
+        if let Some(n) = request.n {
+            payload["n"] = serde_json::json!(n);
+        }
+        if let Some(seed) = request.seed {
+            payload["seed"] = serde_json::json!(seed);
+        }
+        if let Some(logprobs) = request.logprobs {
+            payload["logprobs"] = serde_json::json!(logprobs);
+        }
+        if let Some(top_logprobs) = request.top_logprobs {
+            payload["top_logprobs"] = serde_json::json!(top_logprobs);
+        }
+        Ok(payload)
+    }
+
+    async fn send_chat(&self, payload: &serde_json::Value) -> Result<reqwest::Response> {
+        let url = format!("{}/chat/completions", self.provider.base_url);
+
+        let mut req = self.client
+            .post(&url)
+            .propagate_trace()
+            .header("Authorization", format!("Bearer {}", self.provider.api_key))
+            .header("Content-Type", "application/json");
+
+        // Add custom headers
+        for (key, value) in &self.provider.headers {
+            req = req.header(key, value);
+        }
+
+        let response = req
+            .json(payload)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+
+        streaming::check_status(response).await
+    }
+
     fn calculate_cost(&self, usage: &TokenUsage) -> f64 {
         let input_cost = (usage.prompt_tokens as f64 / 1_000_000.0) 
             * self.provider.pricing.input_token_cost;
@@ -187,6 +329,7 @@ mod tests {
             name: "OpenAI Test".to_string(),
             provider_type: ProviderType::OpenAI,
             api_key: "test-key".to_string(),
+            models: Vec::new(),
             base_url: "https://api.openai.com/v1".to_string(),
             pricing: ProviderPricing {
                 input_token_cost: 30.0,  // $30/1M
@@ -233,6 +376,6 @@ mod tests {
         let estimate = adapter.estimate_tokens(text);
         
         // ~28 chars / 4 = ~7 tokens
-        assert!(estimate >= 5 && estimate <= 10);
+        assert!((5..=10).contains(&estimate));
     }
 }
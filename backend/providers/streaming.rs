@@ -0,0 +1,351 @@
+//! Shared streaming support for provider adapters
+//!
+//! Adapters turn their provider's wire format (server-sent events or
+//! newline-delimited JSON) into a [`ChatStream`] of [`ChatStreamChunk`]s.
+//! Consumers feed the chunks through a [`StreamAccumulator`] to recover the
+//! full completion, usage and finish reason once the stream ends.
+
+use std::pin::Pin;
+
+use futures_util::{future, stream, Stream, StreamExt};
+
+use crate::{
+    ChatRequest, ChatResponse, ChatStreamChunk, FunctionCall, TokenUsage, ToolCall, ToolCallDelta,
+    error::{ProviderError, Result, SynapseError},
+};
+use super::estimate_tokens;
+
+/// Stream of incremental chat completion chunks
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<ChatStreamChunk>> + Send>>;
+
+/// A single server-sent event
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    /// Value of the preceding `event:` line, if any
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Map non-success HTTP statuses to provider errors
+pub async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(SynapseError::Provider(ProviderError::RateLimited));
+    }
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(SynapseError::Provider(ProviderError::AuthFailed));
+    }
+    let error_text = response.text().await.unwrap_or_default();
+    Err(SynapseError::Provider(ProviderError::RequestFailed(
+        format!("Status: {}, Body: {}", status, error_text)
+    )))
+}
+
+/// Split a streaming response body into lines
+pub fn lines(response: reqwest::Response) -> Pin<Box<dyn Stream<Item = Result<String>> + Send>> {
+    let body = response.bytes_stream().boxed();
+
+    // Buffer raw bytes so multi-byte characters split across reads survive
+    stream::unfold((body, Vec::<u8>::new(), false), |(mut body, mut buffer, mut finished)| async move {
+        loop {
+            if let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                let line = String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string();
+                return Some((Ok(line), (body, buffer, finished)));
+            }
+            if finished {
+                if buffer.is_empty() {
+                    return None;
+                }
+                let line = String::from_utf8_lossy(&std::mem::take(&mut buffer)).trim_end().to_string();
+                return Some((Ok(line), (body, buffer, finished)));
+            }
+            match body.next().await {
+                Some(Ok(bytes)) => buffer.extend_from_slice(&bytes),
+                Some(Err(e)) => {
+                    buffer.clear();
+                    return Some((
+                        Err(SynapseError::Provider(ProviderError::Network(e.to_string()))),
+                        (body, buffer, true),
+                    ));
+                }
+                None => finished = true,
+            }
+        }
+    })
+    .boxed()
+}
+
+/// Parse a response body as server-sent events
+pub fn sse_events(response: reqwest::Response) -> Pin<Box<dyn Stream<Item = Result<SseEvent>> + Send>> {
+    lines(response)
+        .scan(None::<String>, |event, line| {
+            let item = match line {
+                Err(e) => Some(Err(e)),
+                Ok(line) => parse_sse_line(event, &line).map(Ok),
+            };
+            future::ready(Some(item))
+        })
+        .filter_map(future::ready)
+        .boxed()
+}
+
+fn parse_sse_line(event: &mut Option<String>, line: &str) -> Option<SseEvent> {
+    if line.is_empty() {
+        *event = None;
+        return None;
+    }
+    if let Some(name) = line.strip_prefix("event:") {
+        *event = Some(name.trim().to_string());
+        return None;
+    }
+    line.strip_prefix("data:").map(|data| SseEvent {
+        event: event.clone(),
+        data: data.trim_start().to_string(),
+    })
+}
+
+/// Parse a response body as newline-delimited JSON
+pub fn json_lines(response: reqwest::Response) -> Pin<Box<dyn Stream<Item = Result<serde_json::Value>> + Send>> {
+    lines(response)
+        .filter_map(|line| future::ready(match line {
+            Err(e) => Some(Err(e)),
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(serde_json::from_str(&line).map_err(|e| {
+                SynapseError::Provider(ProviderError::InvalidResponse(e.to_string()))
+            })),
+        }))
+        .boxed()
+}
+
+/// Stream an OpenAI-style `chat.completion.chunk` SSE response
+pub fn openai_stream(response: reqwest::Response) -> ChatStream {
+    sse_events(response)
+        .take_while(|event| future::ready(!matches!(event, Ok(e) if e.data == "[DONE]")))
+        .filter_map(|event| future::ready(match event {
+            Err(e) => Some(Err(e)),
+            Ok(event) => serde_json::from_str::<serde_json::Value>(&event.data)
+                .ok()
+                .map(|json| Ok(parse_openai_chunk(&json))),
+        }))
+        .boxed()
+}
+
+/// Convert one OpenAI-style stream chunk
+pub fn parse_openai_chunk(json: &serde_json::Value) -> ChatStreamChunk {
+    let choice = &json["choices"][0];
+
+    let tool_calls = choice["delta"]["tool_calls"]
+        .as_array()
+        .map(|calls| {
+            calls.iter().map(|call| ToolCallDelta {
+                index: call["index"].as_u64().unwrap_or(0) as usize,
+                id: call["id"].as_str().map(String::from),
+                name: call["function"]["name"].as_str().map(String::from),
+                arguments: call["function"]["arguments"].as_str().unwrap_or("").to_string(),
+            }).collect()
+        })
+        .unwrap_or_default();
+
+    // Groq reports usage under `x_groq` rather than at the top level
+    let usage = [&json["usage"], &json["x_groq"]["usage"]]
+        .into_iter()
+        .find(|u| u.is_object())
+        .map(|u| TokenUsage {
+            prompt_tokens: u["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+            completion_tokens: u["completion_tokens"].as_u64().unwrap_or(0) as u32,
+            total_tokens: u["total_tokens"].as_u64().unwrap_or(0) as u32,
+        });
+
+    ChatStreamChunk {
+        delta: choice["delta"]["content"].as_str().unwrap_or("").to_string(),
+        tool_calls,
+        finish_reason: choice["finish_reason"].as_str().map(String::from),
+        usage,
+    }
+}
+
+/// Replay a complete response as a stream, for providers without native streaming
+pub fn from_response(response: ChatResponse) -> ChatStream {
+    let choice = response.choices.into_iter().next();
+    let (content, finish_reason, tool_calls) = match choice {
+        Some(c) => (c.message.content.text(), Some(c.finish_reason), c.message.tool_calls.unwrap_or_default()),
+        None => (String::new(), None, Vec::new()),
+    };
+
+    let chunk = ChatStreamChunk {
+        delta: content,
+        tool_calls: tool_calls
+            .into_iter()
+            .enumerate()
+            .map(|(index, call)| ToolCallDelta {
+                index,
+                id: Some(call.id),
+                name: Some(call.function.name),
+                arguments: call.function.arguments,
+            })
+            .collect(),
+        finish_reason,
+        usage: Some(response.usage),
+    };
+
+    stream::iter(vec![Ok(chunk)]).boxed()
+}
+
+/// Final state of a finished stream
+#[derive(Debug, Clone)]
+pub struct StreamSummary {
+    pub content: String,
+    pub tool_calls: Vec<ToolCall>,
+    pub finish_reason: String,
+    pub usage: TokenUsage,
+    /// True when the provider did not report usage and it was estimated
+    pub usage_estimated: bool,
+}
+
+/// Collects chunks so the final usage and completion can be reported
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    content: String,
+    tool_calls: Vec<ToolCall>,
+    finish_reason: Option<String>,
+    usage: Option<TokenUsage>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &ChatStreamChunk) {
+        self.content.push_str(&chunk.delta);
+
+        for delta in &chunk.tool_calls {
+            while self.tool_calls.len() <= delta.index {
+                self.tool_calls.push(ToolCall {
+                    id: String::new(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall { name: String::new(), arguments: String::new() },
+                });
+            }
+            let call = &mut self.tool_calls[delta.index];
+            if let Some(ref id) = delta.id {
+                call.id = id.clone();
+            }
+            if let Some(ref name) = delta.name {
+                call.function.name.push_str(name);
+            }
+            call.function.arguments.push_str(&delta.arguments);
+        }
+
+        if let Some(ref reason) = chunk.finish_reason {
+            self.finish_reason = Some(reason.clone());
+        }
+
+        // Some providers split usage across events (input first, output last)
+        if let Some(ref usage) = chunk.usage {
+            let merged = self.usage.get_or_insert_with(TokenUsage::default);
+            merged.prompt_tokens = merged.prompt_tokens.max(usage.prompt_tokens);
+            merged.completion_tokens = merged.completion_tokens.max(usage.completion_tokens);
+            merged.total_tokens = merged.prompt_tokens + merged.completion_tokens;
+        }
+    }
+
+    pub fn finish(self, request: &ChatRequest) -> StreamSummary {
+        let usage_estimated = self.usage.is_none();
+        let usage = self.usage.unwrap_or_else(|| {
+            let prompt_tokens = request.messages.iter().map(|m| estimate_tokens(&m.content.text())).sum();
+            let completion_tokens = estimate_tokens(&self.content);
+            TokenUsage {
+                prompt_tokens,
+                completion_tokens,
+                total_tokens: prompt_tokens + completion_tokens,
+            }
+        });
+
+        StreamSummary {
+            content: self.content,
+            tool_calls: self.tool_calls,
+            finish_reason: self.finish_reason.unwrap_or_else(|| "stop".to_string()),
+            usage,
+            usage_estimated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Message;
+
+    #[test]
+    fn test_parse_sse_line() {
+        let mut event = None;
+        assert_eq!(parse_sse_line(&mut event, "event: content_block_delta"), None);
+        assert_eq!(
+            parse_sse_line(&mut event, "data: {\"x\":1}"),
+            Some(SseEvent { event: Some("content_block_delta".to_string()), data: "{\"x\":1}".to_string() })
+        );
+        assert_eq!(parse_sse_line(&mut event, ""), None);
+        assert_eq!(event, None);
+    }
+
+    #[test]
+    fn test_parse_openai_chunk() {
+        let chunk = parse_openai_chunk(&serde_json::json!({
+            "choices": [{"delta": {"content": "Hel"}, "finish_reason": null}]
+        }));
+        assert_eq!(chunk.delta, "Hel");
+        assert!(chunk.finish_reason.is_none());
+        assert!(chunk.usage.is_none());
+
+        let chunk = parse_openai_chunk(&serde_json::json!({
+            "choices": [],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}
+        }));
+        assert_eq!(chunk.usage.unwrap().total_tokens, 15);
+    }
+
+    #[test]
+    fn test_accumulator_merges_tool_calls_and_usage() {
+        let mut acc = StreamAccumulator::new();
+        acc.push(&ChatStreamChunk {
+            tool_calls: vec![ToolCallDelta {
+                index: 0,
+                id: Some("call_1".to_string()),
+                name: Some("lookup".to_string()),
+                arguments: "{\"q\":".to_string(),
+            }],
+            usage: Some(TokenUsage { prompt_tokens: 12, completion_tokens: 0, total_tokens: 12 }),
+            ..Default::default()
+        });
+        acc.push(&ChatStreamChunk {
+            tool_calls: vec![ToolCallDelta { index: 0, id: None, name: None, arguments: "\"rust\"}".to_string() }],
+            finish_reason: Some("tool_calls".to_string()),
+            usage: Some(TokenUsage { prompt_tokens: 0, completion_tokens: 7, total_tokens: 7 }),
+            ..Default::default()
+        });
+
+        let summary = acc.finish(&ChatRequest::default());
+        assert_eq!(summary.tool_calls[0].function.arguments, "{\"q\":\"rust\"}");
+        assert_eq!(summary.finish_reason, "tool_calls");
+        assert_eq!(summary.usage.total_tokens, 19);
+        assert!(!summary.usage_estimated);
+    }
+
+    #[test]
+    fn test_accumulator_estimates_missing_usage() {
+        let mut acc = StreamAccumulator::new();
+        acc.push(&ChatStreamChunk { delta: "12345678".to_string(), ..Default::default() });
+
+        let request = ChatRequest { messages: vec![Message::user("1234")], ..Default::default() };
+        let summary = acc.finish(&request);
+        assert!(summary.usage_estimated);
+        assert_eq!(summary.usage.prompt_tokens, 1);
+        assert_eq!(summary.usage.completion_tokens, 2);
+        assert_eq!(summary.content, "12345678");
+    }
+}
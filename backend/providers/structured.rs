@@ -0,0 +1,276 @@
+//! Structured output (`response_format`)
+//!
+//! Providers with native JSON modes get the format mapped into their payload
+//! by their adapter; Anthropic and Cohere are steered through a forced tool
+//! call built from `output_tool`. Either way the hub validates the returned
+//! content here and can retry once with the validation error fed back.
+
+use crate::{ChatRequest, ChatResponse, Message, ResponseFormat};
+use crate::error::{ProviderError, Result, SynapseError};
+use super::ProviderAdapter;
+
+/// Name of the tool used to emulate structured output
+pub const OUTPUT_TOOL_NAME: &str = "json_output";
+
+/// Tool definition that forces a model to answer with JSON
+pub struct OutputTool {
+    pub name: &'static str,
+    pub description: String,
+    pub schema: serde_json::Value,
+}
+
+/// The forced tool for a JSON response format, if one was requested
+pub fn output_tool(format: Option<&ResponseFormat>) -> Option<OutputTool> {
+    match format? {
+        ResponseFormat::Text => None,
+        ResponseFormat::JsonObject => Some(OutputTool {
+            name: OUTPUT_TOOL_NAME,
+            description: "Respond with a JSON object.".to_string(),
+            schema: serde_json::json!({"type": "object"}),
+        }),
+        ResponseFormat::JsonSchema { json_schema } => Some(OutputTool {
+            name: OUTPUT_TOOL_NAME,
+            description: json_schema
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("Respond with the {} JSON object.", json_schema.name)),
+            schema: json_schema.schema.clone(),
+        }),
+    }
+}
+
+/// Check content against the requested format
+///
+/// Returns the JSON re-serialised without surrounding Markdown fences, or a
+/// description of why it does not match.
+pub fn validate(format: &ResponseFormat, content: &str) -> std::result::Result<String, String> {
+    let schema = match format {
+        ResponseFormat::Text => return Ok(content.to_string()),
+        ResponseFormat::JsonObject => None,
+        ResponseFormat::JsonSchema { json_schema } => Some(&json_schema.schema),
+    };
+
+    let value: serde_json::Value = serde_json::from_str(strip_fences(content))
+        .map_err(|e| format!("response is not valid JSON: {}", e))?;
+
+    match schema {
+        None if !value.is_object() => return Err("response is not a JSON object".to_string()),
+        None => {}
+        Some(schema) => {
+            let compiled = compile(schema).map_err(|e| format!("invalid JSON schema: {}", e))?;
+            let result = compiled.validate(&value).map_err(|errors| {
+                errors
+                    .take(5)
+                    .map(|e| match e.instance_path.to_string() {
+                        path if path.is_empty() => e.to_string(),
+                        path => format!("{} (at {})", e, path),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            });
+            result?;
+        }
+    }
+
+    Ok(value.to_string())
+}
+
+/// Reject requests whose schema cannot be compiled before calling a provider
+pub fn check_schema(format: Option<&ResponseFormat>) -> Result<()> {
+    if let Some(ResponseFormat::JsonSchema { json_schema }) = format {
+        compile(&json_schema.schema)
+            .map_err(|e| SynapseError::Validation(format!("Invalid JSON schema '{}': {}", json_schema.name, e)))?;
+    }
+    Ok(())
+}
+
+fn compile(schema: &serde_json::Value) -> std::result::Result<jsonschema::JSONSchema, String> {
+    jsonschema::JSONSchema::compile(schema).map_err(|e| e.to_string())
+}
+
+/// Models often wrap JSON in ```json fences despite instructions
+fn strip_fences(content: &str) -> &str {
+    let trimmed = content.trim();
+    trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed)
+}
+
+/// Run a completion and enforce its `response_format`
+///
+/// Plain requests pass straight through. With `retry_invalid_output` set, an
+/// invalid reply is sent back to the model once together with the validation
+/// error; usage and cost then cover both attempts. Streaming is not
+/// validated since content is relayed as it arrives.
+pub async fn chat(adapter: &dyn ProviderAdapter, request: &ChatRequest) -> Result<ChatResponse> {
+    let format = match request.response_format {
+        Some(ref format @ (ResponseFormat::JsonObject | ResponseFormat::JsonSchema { .. })) => format,
+        _ => return adapter.chat(request).await,
+    };
+    check_schema(Some(format))?;
+
+    let mut response = adapter.chat(request).await?;
+    let error = match check_response(format, &mut response) {
+        Ok(()) => return Ok(response),
+        Err(error) => error,
+    };
+
+    if !request.retry_invalid_output {
+        return Err(invalid_output(&error));
+    }
+
+    tracing::info!(provider = %adapter.name(), model = %request.model, error = %error, "Retrying invalid structured output");
+    crate::metrics::metrics().observe_retry(&adapter.provider().id, "invalid_output");
+
+    let previous = response.choices.first().map(|c| c.message.content.text()).unwrap_or_default();
+    let mut retry = request.clone();
+    retry.messages.push(Message::assistant(previous));
+    retry.messages.push(Message::user(format!(
+        "Your previous reply did not match the required JSON format: {}. \
+         Reply again with only the corrected JSON.",
+        error
+    )));
+
+    let mut second = adapter.chat(&retry).await?;
+    second.usage.prompt_tokens += response.usage.prompt_tokens;
+    second.usage.completion_tokens += response.usage.completion_tokens;
+    second.usage.total_tokens += response.usage.total_tokens;
+    second.cost += response.cost;
+    second.latency_ms += response.latency_ms;
+
+    match check_response(format, &mut second) {
+        Ok(()) => Ok(second),
+        Err(error) => Err(invalid_output(&format!("{} (after retry)", error))),
+    }
+}
+
+/// Validate every choice, normalising valid content in place
+fn check_response(format: &ResponseFormat, response: &mut ChatResponse) -> std::result::Result<(), String> {
+    for choice in &mut response.choices {
+        let normalised = validate(format, &choice.message.content.text())?;
+        choice.message.content = normalised.into();
+    }
+    Ok(())
+}
+
+fn invalid_output(error: &str) -> SynapseError {
+    SynapseError::Provider(ProviderError::InvalidOutput(error.to_string()))
+}
+
+/// Gemini's `responseSchema` accepts an OpenAPI subset of JSON Schema;
+/// drop keywords it rejects and fold `["T", "null"]` types into `nullable`
+pub fn gemini_schema(schema: &serde_json::Value) -> serde_json::Value {
+    const KEYWORDS: &[&str] = &[
+        "type", "format", "title", "description", "nullable", "enum", "maxItems", "minItems",
+        "required", "minProperties", "maxProperties", "minLength", "maxLength", "pattern",
+        "minimum", "maximum", "propertyOrdering",
+    ];
+
+    let Some(object) = schema.as_object() else {
+        return schema.clone();
+    };
+
+    let mut out = serde_json::Map::new();
+    for (key, value) in object {
+        match key.as_str() {
+            "type" => match value.as_array() {
+                Some(types) => {
+                    if let Some(t) = types.iter().find(|t| t.as_str() != Some("null")) {
+                        out.insert("type".to_string(), t.clone());
+                    }
+                    if types.iter().any(|t| t.as_str() == Some("null")) {
+                        out.insert("nullable".to_string(), serde_json::json!(true));
+                    }
+                }
+                None => {
+                    out.insert(key.clone(), value.clone());
+                }
+            },
+            "properties" => {
+                let properties = value
+                    .as_object()
+                    .map(|props| props.iter().map(|(k, v)| (k.clone(), gemini_schema(v))).collect())
+                    .unwrap_or_default();
+                out.insert(key.clone(), serde_json::Value::Object(properties));
+            }
+            "items" => {
+                out.insert(key.clone(), gemini_schema(value));
+            }
+            "anyOf" => {
+                let variants = value.as_array().map(|v| v.iter().map(gemini_schema).collect()).unwrap_or_default();
+                out.insert(key.clone(), serde_json::Value::Array(variants));
+            }
+            k if KEYWORDS.contains(&k) => {
+                out.insert(key.clone(), value.clone());
+            }
+            _ => {}
+        }
+    }
+    serde_json::Value::Object(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JsonSchemaFormat;
+
+    fn person_format() -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: "person".to_string(),
+                description: None,
+                schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+                    "required": ["name", "age"],
+                    "additionalProperties": false
+                }),
+                strict: Some(true),
+            },
+        }
+    }
+
+    #[test]
+    fn test_validate_schema() {
+        let format = person_format();
+        assert_eq!(
+            validate(&format, "```json\n{\"name\": \"Ada\", \"age\": 36}\n```").unwrap(),
+            r#"{"age":36,"name":"Ada"}"#
+        );
+
+        let err = validate(&format, r#"{"name": "Ada", "age": "old"}"#).unwrap_err();
+        assert!(err.contains("/age"), "{}", err);
+        assert!(validate(&format, "not json").unwrap_err().contains("not valid JSON"));
+    }
+
+    #[test]
+    fn test_validate_json_object() {
+        assert!(validate(&ResponseFormat::JsonObject, "{}").is_ok());
+        assert!(validate(&ResponseFormat::JsonObject, "[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_output_tool() {
+        assert!(output_tool(Some(&ResponseFormat::Text)).is_none());
+        let tool = output_tool(Some(&person_format())).unwrap();
+        assert_eq!(tool.name, OUTPUT_TOOL_NAME);
+        assert_eq!(tool.schema["required"][0], "name");
+    }
+
+    #[test]
+    fn test_gemini_schema() {
+        let schema = gemini_schema(&serde_json::json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {"nickname": {"type": ["string", "null"]}},
+            "additionalProperties": false
+        }));
+        assert!(schema.get("$schema").is_none());
+        assert!(schema.get("additionalProperties").is_none());
+        assert_eq!(schema["properties"]["nickname"]["type"], "string");
+        assert_eq!(schema["properties"]["nickname"]["nullable"], true);
+    }
+}
@@ -0,0 +1,222 @@
+//! Request/response log
+//!
+//! Applications that opt in get every completion recorded in `request_logs`:
+//! the request as routed, the response or error, latency and every provider
+//! attempt made along the way. Prompts and completions are redacted unless
+//! the application turns that off, and rows expire after the application's
+//! retention period.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::CacheStatus;
+use crate::db::{ApplicationRow, DbPool, RequestLogRepository, RequestLogRow};
+use crate::{ChatRequest, ChatResponse, SynapseError};
+
+/// Retention for applications that did not choose one
+pub const DEFAULT_RETENTION_DAYS: i32 = 30;
+
+/// One provider or account tried while serving a request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attempt {
+    pub provider: String,
+    /// Database account used, when not the provider's environment key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Why the attempt failed; `None` for the one that answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+tokio::task_local! {
+    static ATTEMPTS: RefCell<Vec<Attempt>>;
+}
+
+/// Run `future`, collecting the attempts recorded while it routes
+pub async fn track_attempts<F: Future>(future: F) -> (F::Output, Vec<Attempt>) {
+    ATTEMPTS
+        .scope(RefCell::new(Vec::new()), async {
+            let output = future.await;
+            (output, ATTEMPTS.with(|attempts| attempts.take()))
+        })
+        .await
+}
+
+/// Note an attempt against `provider`; a no-op outside `track_attempts`
+pub fn record_attempt(provider: &str, account: Option<&str>, error: Option<&SynapseError>) {
+    let _ = ATTEMPTS.try_with(|attempts| {
+        attempts.borrow_mut().push(Attempt {
+            provider: provider.to_string(),
+            account: account.map(str::to_string),
+            error: error.map(|e| e.to_string()),
+        })
+    });
+}
+
+/// Outcome of a logged completion
+pub enum Outcome<'a> {
+    Success(&'a ChatResponse),
+    /// Error message of a failed completion
+    Failure(String),
+}
+
+/// Everything recorded about one completion
+pub struct LogEntry<'a> {
+    pub request_id: &'a str,
+    pub request: &'a ChatRequest,
+    pub outcome: Outcome<'a>,
+    pub attempts: Vec<Attempt>,
+    pub latency_ms: u64,
+    pub cache_status: Option<CacheStatus>,
+    pub streamed: bool,
+}
+
+/// Persist `entry` if `app` has request logging enabled
+///
+/// Failures are logged and swallowed so they never fail the completion.
+pub async fn record(pool: Option<&DbPool>, app: &ApplicationRow, entry: LogEntry<'_>) {
+    let Some(pool) = pool else {
+        return;
+    };
+    if !app.request_logging {
+        return;
+    }
+
+    let row = build_row(app, entry);
+    if let Err(e) = RequestLogRepository::new(pool.clone()).create(&row).await {
+        tracing::warn!(application = %app.id, error = %e, "Failed to write request log");
+    }
+}
+
+fn build_row(app: &ApplicationRow, entry: LogEntry<'_>) -> RequestLogRow {
+    let mut request = serde_json::to_value(entry.request).unwrap_or_default();
+    let (mut response, error, usage, cost) = match entry.outcome {
+        Outcome::Success(response) => (
+            Some(serde_json::to_value(response).unwrap_or_default()),
+            None,
+            response.usage.clone(),
+            response.cost,
+        ),
+        Outcome::Failure(ref e) => (None, Some(e.clone()), Default::default(), 0.0),
+    };
+
+    if app.log_redaction {
+        redact_value(&mut request);
+        if let Some(ref mut response) = response {
+            redact_value(response);
+        }
+    }
+
+    let routed = entry.attempts.iter().rev().find(|a| a.error.is_none());
+    let provider = match entry.outcome {
+        Outcome::Success(response) => Some(response.provider.clone()),
+        Outcome::Failure(_) => entry.request.provider.clone(),
+    };
+    let account = routed.and_then(|a| a.account.clone());
+    let retention_days = if app.log_retention_days > 0 { app.log_retention_days } else { DEFAULT_RETENTION_DAYS };
+    let now = chrono::Utc::now();
+
+    RequestLogRow {
+        id: uuid::Uuid::new_v4().to_string(),
+        application_id: app.id.clone(),
+        user_id: entry.request.user_id.clone(),
+        request_id: entry.request_id.to_string(),
+        model: entry.request.model.clone(),
+        provider,
+        account,
+        status: if error.is_none() { "success" } else { "error" }.to_string(),
+        error,
+        request,
+        response,
+        attempts: serde_json::to_value(&entry.attempts).unwrap_or_default(),
+        prompt_tokens: usage.prompt_tokens as i32,
+        completion_tokens: usage.completion_tokens as i32,
+        cost,
+        latency_ms: entry.latency_ms as i64,
+        cache_status: entry.cache_status.and_then(|s| s.header_value()).map(str::to_string),
+        streamed: entry.streamed,
+        redacted: app.log_redaction,
+        created_at: now,
+        expires_at: now + chrono::Duration::days(retention_days as i64),
+    }
+}
+
+static REDACTIONS: LazyLock<Vec<(Regex, &'static str)>> = LazyLock::new(|| {
+    [
+        (r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}", "[EMAIL]"),
+        (r"(?i)\bbearer\s+[A-Za-z0-9._~+/=-]{8,}", "[TOKEN]"),
+        (r"\b(?:sk|pk|rk)-[A-Za-z0-9_-]{16,}", "[API_KEY]"),
+        (r"\b(?:\d[ -]?){12,18}\d\b", "[CARD_NUMBER]"),
+        (r"\b\d{3}-\d{2}-\d{4}\b", "[SSN]"),
+        (r"\+?\b\d{1,3}[ .-]?\(?\d{3}\)?[ .-]\d{3}[ .-]\d{4}\b", "[PHONE]"),
+    ]
+    .into_iter()
+    .map(|(pattern, replacement)| (Regex::new(pattern).expect("valid redaction pattern"), replacement))
+    .collect()
+});
+
+/// Mask email addresses, credentials, card, social security and phone
+/// numbers in `text`
+pub fn redact(text: &str) -> String {
+    REDACTIONS.iter().fold(text.to_string(), |text, (pattern, replacement)| {
+        pattern.replace_all(&text, *replacement).into_owned()
+    })
+}
+
+/// Redact every string inside a JSON document
+pub fn redact_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => *text = redact(text),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_value),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("Mail jane.doe@example.com or call +1 415-555-0100"),
+            "Mail [EMAIL] or call [PHONE]"
+        );
+        assert_eq!(redact("card 4111 1111 1111 1111, ssn 123-45-6789"), "card [CARD_NUMBER], ssn [SSN]");
+        assert_eq!(redact("Authorization: Bearer abcdef123456"), "Authorization: [TOKEN]");
+        assert_eq!(redact("key sk-proj-abcdefghijklmnop1234"), "key [API_KEY]");
+        assert_eq!(redact("gpt-4o has 128000 tokens"), "gpt-4o has 128000 tokens");
+
+        let mut value = serde_json::json!({"messages": [{"content": "me@example.com"}], "temperature": 0.2});
+        redact_value(&mut value);
+        assert_eq!(value["messages"][0]["content"], "[EMAIL]");
+        assert_eq!(value["temperature"], 0.2);
+    }
+
+    #[tokio::test]
+    async fn test_track_attempts() {
+        let error = SynapseError::Internal("boom".to_string());
+        let ((), attempts) = track_attempts(async {
+            record_attempt("openai", None, Some(&error));
+            tokio::task::yield_now().await;
+            record_attempt("anthropic", Some("team-b"), None);
+        })
+        .await;
+
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts[0].error.as_deref().is_some_and(|e| e.contains("boom")));
+        assert_eq!(attempts[1], Attempt {
+            provider: "anthropic".to_string(),
+            account: Some("team-b".to_string()),
+            error: None,
+        });
+
+        // Outside a tracked scope recording is a no-op
+        record_attempt("openai", None, None);
+    }
+}
@@ -6,14 +6,16 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::{
-    Provider, ProviderPreference, ChatRequest, ChatResponse,
-    error::{Result, RoutingError, SynapseError},
-    providers::{ProviderAdapter, create_adapter},
+    Provider, ProviderPreference, ChatRequest, ChatResponse, EmbeddingRequest, EmbeddingResponse,
+    error::{ProviderError, Result, RoutingError, SynapseError},
+    providers::{structured, ChatStream, ProviderAdapter, create_adapter},
+    request_log, telemetry,
 };
 
 /// Smart router for selecting the best provider
 pub struct SmartRouter {
-    /// Available adapters indexed by provider ID
+    /// Available adapters indexed by route ID: the provider ID for configured
+    /// providers, `account:{id}` for provider accounts
     adapters: Vec<(String, Arc<dyn ProviderAdapter>)>,
     /// Round-robin counter for load balancing
     round_robin_counter: RwLock<usize>,
@@ -24,14 +26,16 @@ pub struct SmartRouter {
 impl SmartRouter {
     /// Create a new router with the given providers
     pub fn new(providers: Vec<Provider>, client: reqwest::Client) -> Self {
-        let adapters: Vec<_> = providers
+        Self::from_routes(providers.into_iter().map(|p| (p.id.clone(), p)).collect(), client)
+    }
+
+    /// Create a router from providers keyed by their route ID, so several
+    /// accounts of one provider are scored separately
+    pub fn from_routes(routes: Vec<(String, Provider)>, client: reqwest::Client) -> Self {
+        let adapters: Vec<_> = routes
             .into_iter()
-            .filter(|p| p.enabled)
-            .map(|p| {
-                let id = p.id.clone();
-                let adapter = create_adapter(p, client.clone());
-                (id, adapter)
-            })
+            .filter(|(_, p)| p.enabled)
+            .map(|(id, p)| (id, create_adapter(p, client.clone())))
             .collect();
 
         Self {
@@ -46,9 +50,13 @@ impl SmartRouter {
         let preference = request.provider_preference.unwrap_or(ProviderPreference::CostOptimal);
         
         let adapter = self.select_provider(preference).await?;
+        adapter.check_input(request)?;
         
         // Execute the request
-        adapter.chat(request).await
+        let call = structured::chat(adapter.as_ref(), request);
+        let result = telemetry::attempt(&adapter.provider().id, None, &request.model, call).await;
+        request_log::record_attempt(&adapter.provider().id, None, result.as_ref().err());
+        result
     }
 
     /// Route with fallback - try multiple providers on failure
@@ -64,17 +72,26 @@ impl SmartRouter {
         // Get ordered list of adapters to try
         let adapters = self.get_fallback_order().await;
 
-        for (provider_id, adapter) in adapters {
-            match adapter.chat(request).await {
+        for (route_id, adapter) in adapters {
+            // Skip providers that can't take the request's images, audio or documents
+            if let Err(e) = adapter.check_input(request) {
+                last_error = Some(e);
+                continue;
+            }
+            let (provider_id, account) = attempt_labels(&route_id, adapter.as_ref());
+            let call = structured::chat(adapter.as_ref(), request);
+            match telemetry::attempt(provider_id, account, &request.model, call).await {
                 Ok(response) => {
                     // Update health score on success
-                    self.update_health_score(&provider_id, true).await;
+                    self.update_health_score(&route_id, true).await;
+                    request_log::record_attempt(provider_id, account, None);
                     return Ok(response);
                 }
                 Err(e) => {
                     // Update health score on failure
-                    self.update_health_score(&provider_id, false).await;
-                    tracing::warn!(provider = %provider_id, error = %e, "Provider failed, trying next");
+                    self.update_health_score(&route_id, false).await;
+                    request_log::record_attempt(provider_id, account, Some(&e));
+                    tracing::warn!(provider = %provider_id, route = %route_id, error = %e, "Provider failed, trying next");
                     last_error = Some(e);
                 }
             }
@@ -87,24 +104,115 @@ impl SmartRouter {
 
     /// Route directly to a specific provider by ID
     pub async fn route_to_provider(&self, provider_id: &str, request: &ChatRequest) -> Result<ChatResponse> {
-        let adapter = self.adapters
-            .iter()
-            .find(|(id, _)| id == provider_id || id.to_lowercase() == provider_id.to_lowercase())
-            .map(|(_, adapter)| adapter.clone())
-            .ok_or_else(|| SynapseError::Routing(RoutingError::ProviderNotFound(provider_id.to_string())))?;
+        let (route_id, adapter) = self.find_adapter(provider_id)?;
+        adapter.check_input(request)?;
         
-        match adapter.chat(request).await {
+        let (provider_id, account) = attempt_labels(&route_id, adapter.as_ref());
+        let call = structured::chat(adapter.as_ref(), request);
+        match telemetry::attempt(provider_id, account, &request.model, call).await {
             Ok(response) => {
-                self.update_health_score(provider_id, true).await;
+                self.update_health_score(&route_id, true).await;
+                request_log::record_attempt(provider_id, account, None);
                 Ok(response)
             }
             Err(e) => {
-                self.update_health_score(provider_id, false).await;
+                self.update_health_score(&route_id, false).await;
+                request_log::record_attempt(provider_id, account, Some(&e));
                 Err(e)
             }
         }
     }
 
+    /// Open a streaming completion
+    ///
+    /// Uses the explicit provider when given. Otherwise the provider chosen by
+    /// the request's preference is tried first, then the rest in fallback
+    /// order. Fallback only happens while opening the stream; errors after
+    /// the first chunk are surfaced to the caller.
+    pub async fn route_stream(&self, request: &ChatRequest) -> Result<(Arc<dyn ProviderAdapter>, ChatStream)> {
+        let mut adapters = match request.provider {
+            Some(ref provider_id) => vec![self.find_adapter(provider_id)?],
+            None => self.get_fallback_order().await,
+        };
+
+        if request.provider.is_none() {
+            if let Some(preference) = request.provider_preference {
+                let preferred = self.select_provider(preference).await?;
+                if let Some(pos) = adapters.iter().position(|(_, a)| Arc::ptr_eq(a, &preferred)) {
+                    let entry = adapters.remove(pos);
+                    adapters.insert(0, entry);
+                }
+            }
+        }
+
+        let mut last_error = None;
+        for (route_id, adapter) in adapters {
+            if let Err(e) = adapter.check_input(request) {
+                last_error = Some(e);
+                continue;
+            }
+            let (provider_id, account) = attempt_labels(&route_id, adapter.as_ref());
+            let call = adapter.chat_stream(request);
+            match telemetry::attempt(provider_id, account, &request.model, call).await {
+                Ok(stream) => {
+                    self.update_health_score(&route_id, true).await;
+                    request_log::record_attempt(provider_id, account, None);
+                    return Ok((adapter, stream));
+                }
+                Err(e) => {
+                    self.update_health_score(&route_id, false).await;
+                    request_log::record_attempt(provider_id, account, Some(&e));
+                    tracing::warn!(provider = %provider_id, route = %route_id, error = %e, "Streaming provider failed, trying next");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(||
+            SynapseError::Routing(RoutingError::NoProvidersAvailable)
+        ))
+    }
+
+    /// Generate embeddings, using the explicit provider when given and
+    /// otherwise the healthiest provider that supports embeddings
+    pub async fn embed(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+        let adapters = match request.provider {
+            Some(ref provider_id) => vec![self.find_adapter(provider_id)?],
+            None => self.get_fallback_order().await,
+        };
+
+        let mut last_error = None;
+        for (route_id, adapter) in adapters {
+            match adapter.embed(request).await {
+                Ok(response) => {
+                    self.update_health_score(&route_id, true).await;
+                    return Ok(response);
+                }
+                Err(SynapseError::Provider(ProviderError::Unsupported(reason))) => {
+                    last_error = Some(SynapseError::Provider(ProviderError::Unsupported(reason)));
+                }
+                Err(e) => {
+                    self.update_health_score(&route_id, false).await;
+                    tracing::warn!(provider = %adapter.provider().id, route = %route_id, error = %e, "Embedding provider failed, trying next");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(||
+            SynapseError::Routing(RoutingError::NoProvidersAvailable)
+        ))
+    }
+
+    /// Look up an adapter by route or provider ID (case-insensitive)
+    fn find_adapter(&self, provider_id: &str) -> Result<(String, Arc<dyn ProviderAdapter>)> {
+        self.adapters
+            .iter()
+            .find(|(id, adapter)| id.eq_ignore_ascii_case(provider_id) || adapter.provider().id.eq_ignore_ascii_case(provider_id))
+            .cloned()
+            .ok_or_else(|| SynapseError::Routing(RoutingError::ProviderNotFound(provider_id.to_string())))
+    }
+
     /// Select a provider based on the given preference
     async fn select_provider(&self, preference: ProviderPreference) -> Result<Arc<dyn ProviderAdapter>> {
         if self.adapters.is_empty() {
@@ -204,7 +312,7 @@ impl SmartRouter {
     }
 
     /// Update health score for a provider
-    async fn update_health_score(&self, provider_id: &str, success: bool) {
+    pub async fn update_health_score(&self, provider_id: &str, success: bool) {
         let mut scores = self.health_scores.write().await;
         let current = scores.get(provider_id).copied().unwrap_or(0.5);
         
@@ -218,6 +326,21 @@ impl SmartRouter {
         scores.insert(provider_id.to_string(), new_score);
     }
 
+    /// Carry over the health scores of the router this one replaces
+    pub async fn inherit_health(&self, previous: &SmartRouter) {
+        let scores = previous.health_scores.read().await.clone();
+        *self.health_scores.write().await = scores;
+    }
+
+    /// Current health score of every provider, 0.5 until first observed
+    pub async fn health_scores(&self) -> std::collections::HashMap<String, f64> {
+        let scores = self.health_scores.read().await;
+        self.adapters
+            .iter()
+            .map(|(id, _)| (id.clone(), scores.get(id).copied().unwrap_or(0.5)))
+            .collect()
+    }
+
     /// Get list of available provider names
     pub fn list_providers(&self) -> Vec<String> {
         self.adapters
@@ -240,6 +363,14 @@ impl SmartRouter {
     }
 }
 
+/// Provider and account an attempt is logged under; account routes carry
+/// the account name
+fn attempt_labels<'a>(route_id: &str, adapter: &'a dyn ProviderAdapter) -> (&'a str, Option<&'a str>) {
+    let provider_id = adapter.provider().id.as_str();
+    let account = (route_id != provider_id).then(|| adapter.name());
+    (provider_id, account)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,6 +383,7 @@ mod tests {
                 name: "Cheap Provider".to_string(),
                 provider_type: ProviderType::OpenAI,
                 api_key: "test".to_string(),
+                models: Vec::new(),
                 base_url: "https://api.openai.com/v1".to_string(),
                 pricing: ProviderPricing {
                     input_token_cost: 1.0,
@@ -266,6 +398,7 @@ mod tests {
                 name: "Expensive GPT-4 Provider".to_string(),
                 provider_type: ProviderType::OpenAI,
                 api_key: "test".to_string(),
+                models: Vec::new(),
                 base_url: "https://api.openai.com/v1".to_string(),
                 pricing: ProviderPricing {
                     input_token_cost: 30.0,
@@ -280,23 +413,89 @@ mod tests {
 
     #[test]
     fn test_router_creation() {
-        let router = SmartRouter::new(test_providers());
+        let router = SmartRouter::new(test_providers(), reqwest::Client::new());
         assert_eq!(router.list_providers().len(), 2);
     }
 
     #[tokio::test]
     async fn test_cost_optimal_routing() {
-        let router = SmartRouter::new(test_providers());
+        let router = SmartRouter::new(test_providers(), reqwest::Client::new());
         let adapter = router.select_cheapest().await.unwrap();
         assert!(adapter.provider().name.contains("Cheap"));
     }
 
+    #[tokio::test]
+    async fn test_inherit_health() {
+        let previous = SmartRouter::new(test_providers(), reqwest::Client::new());
+        previous.update_health_score("cheap", false).await;
+
+        let router = SmartRouter::new(test_providers(), reqwest::Client::new());
+        router.inherit_health(&previous).await;
+        let scores = router.health_scores().await;
+        assert!((scores["cheap"] - 0.45).abs() < 1e-9);
+        assert_eq!(scores["expensive"], 0.5);
+    }
+
     #[test]
     fn test_disabled_providers_filtered() {
         let mut providers = test_providers();
         providers[0].enabled = false;
         
-        let router = SmartRouter::new(providers);
+        let router = SmartRouter::new(providers, reqwest::Client::new());
         assert_eq!(router.list_providers().len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_account_routes_scored_separately() {
+        let providers = test_providers();
+        let mut second = providers[0].clone();
+        second.name = "Cheap Backup".to_string();
+        let routes = vec![
+            ("account:a".to_string(), providers[0].clone()),
+            ("account:b".to_string(), second),
+        ];
+        let router = SmartRouter::from_routes(routes, reqwest::Client::new());
+
+        router.update_health_score("account:a", false).await;
+        let scores = router.health_scores().await;
+        assert!((scores["account:a"] - 0.45).abs() < 1e-9);
+        assert_eq!(scores["account:b"], 0.5);
+
+        // Explicit providers still resolve to their accounts
+        let (route_id, adapter) = router.find_adapter("cheap").unwrap();
+        assert_eq!(route_id, "account:a");
+        assert_eq!(attempt_labels(&route_id, adapter.as_ref()), ("cheap", Some("Cheap Provider")));
+    }
+
+    #[tokio::test]
+    async fn test_route_stream_unknown_provider() {
+        let router = SmartRouter::new(test_providers(), reqwest::Client::new());
+        let request = ChatRequest {
+            provider: Some("missing".to_string()),
+            ..Default::default()
+        };
+
+        let err = router.route_stream(&request).await.err().unwrap();
+        assert!(matches!(err, SynapseError::Routing(RoutingError::ProviderNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_image_rejected_for_text_only_model() {
+        let router = SmartRouter::new(test_providers(), reqwest::Client::new());
+        let mut message = crate::Message::user("");
+        message.content = crate::MessageContent::Parts(vec![crate::ContentPart::Image {
+            source: crate::MediaSource::from_url("https://example.com/cat.png"),
+            detail: None,
+        }]);
+        let request = ChatRequest {
+            model: "gpt-3.5-turbo".to_string(),
+            provider: Some("cheap".to_string()),
+            messages: vec![message],
+            ..Default::default()
+        };
+
+        // Rejected before any network call
+        let err = router.route_with_fallback(&request).await.unwrap_err();
+        assert!(matches!(err, SynapseError::Provider(ProviderError::Unsupported(_))));
+    }
 }
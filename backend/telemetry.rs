@@ -0,0 +1,310 @@
+//! Tracing, log output and OpenTelemetry export
+//!
+//! Spans are bridged to OpenTelemetry by `tracing-opentelemetry`, so each
+//! one carries a W3C trace context and children inherit the trace of their
+//! parent. HTTP and gRPC request spans continue the caller's trace through
+//! [`continue_trace`], and outgoing provider requests carry the current
+//! context via [`PropagateTrace`]. When an OTLP endpoint is configured spans
+//! are batched and sent to `{endpoint}/v1/traces` as OTLP/HTTP JSON. JSON
+//! logs include the trace and span id of the event's span.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Instrument, Subscriber};
+use tracing_opentelemetry::{OpenTelemetrySpanExt, OtelData};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{LoggingConfig, TelemetryConfig};
+use crate::error::Result;
+
+/// Install the global subscriber: env filter, text or JSON logs per
+/// `logging.format`, and the OpenTelemetry layer with an OTLP exporter if
+/// configured
+///
+/// Shut the returned provider down on exit to export the spans it still
+/// holds.
+pub fn init(logging: &LoggingConfig, telemetry: &TelemetryConfig) -> SdkTracerProvider {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| format!("barq_hub={},tower_http=info", logging.level).into());
+
+    let mut provider = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name(telemetry.service_name.clone()).build());
+    let exporter = telemetry.otlp_endpoint.as_deref().map(|endpoint| {
+        SpanExporter::builder()
+            .with_http()
+            .with_protocol(Protocol::HttpJson)
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()
+    });
+    let exporter = match exporter {
+        Some(Ok(exporter)) => {
+            provider = provider.with_batch_exporter(exporter);
+            Ok(telemetry.otlp_endpoint.clone())
+        }
+        Some(Err(e)) => Err(e),
+        None => Ok(None),
+    };
+    let provider = provider.build();
+    let tracer = provider.tracer("barq-hub");
+    global::set_tracer_provider(provider.clone());
+
+    let fmt = if logging.format.eq_ignore_ascii_case("json") {
+        tracing_subscriber::fmt::layer().event_format(JsonFormat).boxed()
+    } else {
+        tracing_subscriber::fmt::layer().boxed()
+    };
+
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .with(fmt)
+        .with(filter)
+        .init();
+
+    match exporter {
+        Ok(Some(endpoint)) => tracing::info!(endpoint = %endpoint, "Exporting traces over OTLP"),
+        Ok(None) => {}
+        Err(e) => tracing::warn!(error = %e, "Failed to set up OTLP trace export"),
+    }
+    provider
+}
+
+// ============================================================================
+// Trace context
+// ============================================================================
+
+/// Have `span` continue the trace of an incoming request, read from its
+/// `traceparent` and `tracestate` headers by `header`
+///
+/// Must be called before the span is first entered.
+pub fn continue_trace<'a>(span: &tracing::Span, header: impl Fn(&str) -> Option<&'a str>) {
+    let context = global::get_text_map_propagator(|propagator| {
+        let carrier: HashMap<String, String> = propagator
+            .fields()
+            .filter_map(|name| header(name).map(|value| (name.to_string(), value.to_string())))
+            .collect();
+        propagator.extract(&carrier)
+    });
+    let _ = span.set_parent(context);
+}
+
+/// Adds the current trace context to outgoing requests
+pub trait PropagateTrace {
+    fn propagate_trace(self) -> Self;
+}
+
+impl PropagateTrace for reqwest::RequestBuilder {
+    fn propagate_trace(self) -> Self {
+        let context = tracing::Span::current().context();
+        let mut carrier = HashMap::new();
+        global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut carrier));
+        carrier.into_iter().fold(self, |request, (name, value)| request.header(name, value))
+    }
+}
+
+/// Run one provider or account attempt in a client span, marking it failed
+/// when `call` errors
+pub async fn attempt<T>(
+    provider: &str,
+    account: Option<&str>,
+    model: &str,
+    call: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    let span = tracing::info_span!(
+        "provider.attempt",
+        otel.kind = "client",
+        otel.status_code = tracing::field::Empty,
+        otel.status_description = tracing::field::Empty,
+        provider,
+        account = account.unwrap_or_default(),
+        model,
+        error = tracing::field::Empty,
+    );
+    let result = call.instrument(span.clone()).await;
+    if let Err(ref e) = result {
+        span.record("otel.status_code", "error");
+        span.record("otel.status_description", tracing::field::display(e));
+        span.record("error", tracing::field::display(e));
+    }
+    result
+}
+
+// ============================================================================
+// JSON logs
+// ============================================================================
+
+/// Records event fields into a JSON object
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), json!(value));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        self.0.insert(field.name().to_string(), json!(value.to_string()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), json!(format!("{:?}", value)));
+    }
+}
+
+/// One JSON object per line with the event's fields, its span name and the
+/// trace and span id of that span
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let metadata = event.metadata();
+        let mut line = Map::new();
+        line.insert("timestamp".to_string(), json!(chrono::Utc::now().to_rfc3339()));
+        line.insert("level".to_string(), json!(metadata.level().as_str()));
+        line.insert("target".to_string(), json!(metadata.target()));
+
+        let mut fields = Map::new();
+        event.record(&mut JsonVisitor(&mut fields));
+        line.insert("fields".to_string(), Value::Object(fields));
+
+        if let Some(span) = ctx.lookup_current() {
+            line.insert("span".to_string(), json!(span.name()));
+            if let Some(data) = span.extensions().get::<OtelData>() {
+                if let (Some(trace_id), Some(span_id)) = (data.trace_id(), data.span_id()) {
+                    line.insert("trace_id".to_string(), json!(trace_id.to_string()));
+                    line.insert("span_id".to_string(), json!(span_id.to_string()));
+                }
+            }
+        }
+
+        writeln!(writer, "{}", Value::Object(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use opentelemetry::trace::{SpanKind, Status};
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+    use tracing_subscriber::Registry;
+
+    const REMOTE: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn remote_header(name: &str) -> Option<&'static str> {
+        (name == "traceparent").then_some(REMOTE)
+    }
+
+    #[tokio::test]
+    async fn test_attempt_continues_remote_trace() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let subscriber = Registry::default().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let mut propagated = None;
+        {
+            let request = tracing::info_span!("http.request", otel.kind = "server");
+            continue_trace(&request, remote_header);
+            let failed = attempt("openai", Some("primary"), "gpt-4o", async {
+                let outgoing = reqwest::Client::new().get("http://localhost").propagate_trace().build().unwrap();
+                propagated = outgoing.headers().get("traceparent").map(|v| v.to_str().unwrap().to_string());
+                Err::<(), _>(crate::SynapseError::Internal("upstream failed".to_string()))
+            })
+            .instrument(request)
+            .await;
+            assert!(failed.is_err());
+        }
+
+        let spans = exporter.get_finished_spans().unwrap();
+        assert_eq!(spans.len(), 2);
+        let (attempt, request) = (&spans[0], &spans[1]);
+        assert_eq!(attempt.name, "provider.attempt");
+        assert_eq!(attempt.span_kind, SpanKind::Client);
+        assert!(matches!(attempt.status, Status::Error { .. }));
+        assert_eq!(attempt.parent_span_id, request.span_context.span_id());
+        assert!(attempt.attributes.iter().any(|kv| kv.key.as_str() == "provider" && kv.value.as_str() == "openai"));
+        assert_eq!(request.span_kind, SpanKind::Server);
+        assert_eq!(request.span_context.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(request.parent_span_id.to_string(), "00f067aa0ba902b7");
+
+        // Upstream requests carry the attempt's span
+        let expected = format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", attempt.span_context.span_id());
+        assert_eq!(propagated, Some(expected));
+    }
+
+    #[test]
+    fn test_json_format_includes_trace_ids() {
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = SdkTracerProvider::builder().build();
+        let buffer = Buffer::default();
+        let output = buffer.clone();
+        let subscriber = Registry::default()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+            .with(tracing_subscriber::fmt::layer().event_format(JsonFormat).with_writer(move || output.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("http.request");
+            continue_trace(&span, remote_header);
+            let _entered = span.enter();
+            tracing::info!(status = 200, "Request completed");
+        });
+
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(text.trim()).unwrap();
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["fields"]["message"], "Request completed");
+        assert_eq!(line["fields"]["status"], 200);
+        assert_eq!(line["span"], "http.request");
+        assert_eq!(line["trace_id"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(line["span_id"].as_str().map(str::len), Some(16));
+    }
+}
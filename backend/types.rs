@@ -56,6 +56,24 @@ impl Default for Provider {
     }
 }
 
+impl Provider {
+    /// Capabilities of one of this provider's models
+    ///
+    /// Configured capabilities extend what is inferred from the model ID, so
+    /// older configs that only list "llm" keep vision models usable.
+    pub fn model_capabilities(&self, model: &str) -> Vec<ModelCapability> {
+        let mut capabilities = ModelCapability::infer(model);
+        if let Some(configured) = self.models.iter().find(|m| m.id == model) {
+            for capability in &configured.capabilities {
+                if !capabilities.contains(capability) {
+                    capabilities.push(*capability);
+                }
+            }
+        }
+        capabilities
+    }
+}
+
 /// Type of LLM provider
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -103,6 +121,68 @@ pub enum ModelCapability {
     TTS,
     STT,
     ImageGeneration,
+    /// Accepts image parts in chat messages
+    Vision,
+    /// Accepts audio parts in chat messages
+    AudioInput,
+    /// Accepts PDF/document parts in chat messages
+    DocumentInput,
+}
+
+impl ModelCapability {
+    /// Best-effort capability guess from a model ID
+    pub fn infer(model_id: &str) -> Vec<Self> {
+        let id = model_id.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|p| id.contains(p));
+
+        if has(&["embed"]) {
+            return vec![Self::Embedding];
+        }
+        if has(&["tts"]) {
+            return vec![Self::TTS];
+        }
+        if has(&["whisper", "transcribe"]) {
+            return vec![Self::STT];
+        }
+        if has(&["dall-e", "imagen"]) {
+            return vec![Self::ImageGeneration];
+        }
+
+        let mut capabilities = vec![Self::LLM];
+        let claude = id.contains("claude") && !has(&["claude-2", "claude-instant"]);
+        let gemini = id.contains("gemini");
+        let openai_multimodal = has(&["gpt-4o", "gpt-4-turbo", "gpt-4.1", "gpt-5", "o1", "o3", "o4"])
+            && !has(&["o1-mini", "o3-mini"]);
+
+        if claude || gemini || openai_multimodal
+            || has(&["vision", "llava", "pixtral", "llama-4", "-vl", "gemma-3"])
+        {
+            capabilities.push(Self::Vision);
+        }
+        if claude || gemini || openai_multimodal {
+            capabilities.push(Self::DocumentInput);
+        }
+        if gemini || has(&["-audio"]) {
+            capabilities.push(Self::AudioInput);
+        }
+        capabilities
+    }
+}
+
+impl std::fmt::Display for ModelCapability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::LLM => "text",
+            Self::Embedding => "embedding",
+            Self::TTS => "text-to-speech",
+            Self::STT => "speech-to-text",
+            Self::ImageGeneration => "image generation",
+            Self::Vision => "image",
+            Self::AudioInput => "audio",
+            Self::DocumentInput => "document",
+        };
+        f.write_str(name)
+    }
 }
 
 /// Configuration for a specific model under a provider
@@ -177,6 +257,30 @@ pub struct ChatRequest {
     pub top_p: Option<f32>,
     /// Stop sequences
     pub stop: Option<Vec<String>>,
+    /// Tools the model may call
+    pub tools: Option<Vec<Tool>>,
+    /// Tool selection: "auto", "none", "required" or `{"type": "function", ...}`
+    pub tool_choice: Option<serde_json::Value>,
+    /// Requested output format (plain text, JSON object or JSON schema)
+    pub response_format: Option<ResponseFormat>,
+    /// Retry once with the validation error if output does not match `response_format`
+    #[serde(default)]
+    pub retry_invalid_output: bool,
+    /// Response cache control; overrides the calling application's setting
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheOptions>,
+    /// Number of choices to generate (OpenAI-compatible providers only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// Sampling seed for best-effort determinism
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Return token log probabilities
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    /// Number of alternatives per token when `logprobs` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u32>,
     /// Provider preference for routing (deprecated, use `provider` instead)
     pub provider_preference: Option<ProviderPreference>,
     /// User ID for tracking
@@ -194,6 +298,21 @@ fn default_max_tokens() -> u32 {
     2048
 }
 
+impl ChatRequest {
+    /// Input capabilities the messages need beyond plain text
+    pub fn input_capabilities(&self) -> Vec<ModelCapability> {
+        let mut capabilities = Vec::new();
+        for part in self.messages.iter().flat_map(|m| m.content.media()) {
+            if let Some(capability) = part.required_capability() {
+                if !capabilities.contains(&capability) {
+                    capabilities.push(capability);
+                }
+            }
+        }
+        capabilities
+    }
+}
+
 impl Default for ChatRequest {
     fn default() -> Self {
         Self {
@@ -204,6 +323,15 @@ impl Default for ChatRequest {
             max_tokens: 2048,
             top_p: None,
             stop: None,
+            tools: None,
+            tool_choice: None,
+            response_format: None,
+            retry_invalid_output: false,
+            cache: None,
+            n: None,
+            seed: None,
+            logprobs: None,
+            top_logprobs: None,
             provider_preference: None,
             user_id: None,
             metadata: std::collections::HashMap::new(),
@@ -230,6 +358,9 @@ pub struct ChatResponse {
     pub latency_ms: u64,
     /// Cost of this request
     pub cost: f64,
+    /// Backend configuration fingerprint, when the provider reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
 }
 
 /// A single completion choice
@@ -241,6 +372,9 @@ pub struct Choice {
     pub message: Message,
     /// Reason for stopping
     pub finish_reason: String,
+    /// Token log probabilities, passed through from the provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<serde_json::Value>,
 }
 
 /// A chat message
@@ -248,39 +382,195 @@ pub struct Choice {
 pub struct Message {
     /// Role: "system", "user", "assistant", "function"
     pub role: String,
-    /// Message content
-    pub content: String,
+    /// Message content: text, or typed parts for multimodal input
+    pub content: MessageContent,
     /// Function call (if applicable)
     pub function_call: Option<FunctionCall>,
     /// Tool calls (for assistant messages)
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// Tool call this message answers (for "tool" messages)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
 }
 
 impl Message {
-    pub fn system(content: impl Into<String>) -> Self {
+    pub fn system(content: impl Into<MessageContent>) -> Self {
         Self {
             role: "system".to_string(),
             content: content.into(),
             function_call: None,
             tool_calls: None,
+            tool_call_id: None,
         }
     }
 
-    pub fn user(content: impl Into<String>) -> Self {
+    pub fn user(content: impl Into<MessageContent>) -> Self {
         Self {
             role: "user".to_string(),
             content: content.into(),
             function_call: None,
             tool_calls: None,
+            tool_call_id: None,
         }
     }
 
-    pub fn assistant(content: impl Into<String>) -> Self {
+    pub fn assistant(content: impl Into<MessageContent>) -> Self {
         Self {
             role: "assistant".to_string(),
             content: content.into(),
             function_call: None,
             tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+}
+
+/// Message content: plain text or a list of typed parts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// Text of the message; for parts, the text parts concatenated
+    pub fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::Text { text } => Some(text.as_str()),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Text(text) => text.is_empty(),
+            Self::Parts(parts) => parts.is_empty(),
+        }
+    }
+
+    /// Non-text parts, if any
+    pub fn media(&self) -> impl Iterator<Item = &ContentPart> {
+        let parts = match self {
+            Self::Text(_) => &[][..],
+            Self::Parts(parts) => parts.as_slice(),
+        };
+        parts.iter().filter(|part| !matches!(part, ContentPart::Text { .. }))
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<&String> for MessageContent {
+    fn from(text: &String) -> Self {
+        Self::Text(text.clone())
+    }
+}
+
+impl From<Vec<ContentPart>> for MessageContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        Self::Parts(parts)
+    }
+}
+
+impl std::fmt::Display for MessageContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+impl PartialEq<&str> for MessageContent {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, Self::Text(text) if text == other)
+    }
+}
+
+/// One part of a multimodal message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    Image {
+        source: MediaSource,
+        /// OpenAI detail hint: "low", "high" or "auto"
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<String>,
+    },
+    /// PDF or other document
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+    Audio {
+        /// Base64-encoded audio
+        data: String,
+        /// Encoding, e.g. "wav" or "mp3"
+        format: String,
+    },
+}
+
+impl ContentPart {
+    /// Model capability needed to accept this part
+    pub fn required_capability(&self) -> Option<ModelCapability> {
+        match self {
+            Self::Text { .. } => None,
+            Self::Image { .. } => Some(ModelCapability::Vision),
+            Self::Document { .. } => Some(ModelCapability::DocumentInput),
+            Self::Audio { .. } => Some(ModelCapability::AudioInput),
+        }
+    }
+}
+
+/// Where a media part's bytes come from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Url { url: String },
+    Base64 { media_type: String, data: String },
+}
+
+impl MediaSource {
+    /// Parse a URL, unpacking `data:<type>;base64,<data>` URLs
+    pub fn from_url(url: &str) -> Self {
+        url.strip_prefix("data:")
+            .and_then(|rest| rest.split_once(";base64,"))
+            .map(|(media_type, data)| Self::Base64 {
+                media_type: media_type.to_string(),
+                data: data.to_string(),
+            })
+            .unwrap_or_else(|| Self::Url { url: url.to_string() })
+    }
+
+    /// URL form, packing inline data as a data URL
+    pub fn to_url(&self) -> String {
+        match self {
+            Self::Url { url } => url.clone(),
+            Self::Base64 { media_type, data } => format!("data:{};base64,{}", media_type, data),
         }
     }
 }
@@ -301,6 +591,85 @@ pub struct ToolCall {
     pub function: FunctionCall,
 }
 
+/// Tool offered to the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    /// Tool type, currently always "function"
+    #[serde(rename = "type", default = "default_tool_type")]
+    pub tool_type: String,
+    pub function: FunctionDefinition,
+}
+
+fn default_tool_type() -> String {
+    "function".to_string()
+}
+
+/// Function the model may call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON Schema for the arguments
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+}
+
+/// Output format requested from the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema { json_schema: JsonSchemaFormat },
+}
+
+/// Named JSON Schema for structured output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Per-request response cache control
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheOptions {
+    /// Seconds to keep the response; 0 disables caching for this request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u64>,
+    /// Cache even when sampling is non-deterministic (temperature > 0)
+    #[serde(default)]
+    pub force: bool,
+    /// Minimum cosine similarity for serving a near-duplicate prompt from
+    /// the semantic cache; overrides the application threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+impl CacheOptions {
+    /// Parse `Cache-Control`-style directives: `max-age=<secs>`, `no-store`
+    /// or `no-cache`, plus the hub's own `force` and `similarity=<0..1>`
+    pub fn from_directives(value: &str) -> Option<Self> {
+        let mut options = None::<Self>;
+        for directive in value.split(',').map(str::trim) {
+            let (name, arg) = directive.split_once('=').unwrap_or((directive, ""));
+            let entry = options.get_or_insert_with(Default::default);
+            match name.to_ascii_lowercase().as_str() {
+                "max-age" => entry.ttl = arg.trim_matches('"').parse().ok(),
+                "no-store" | "no-cache" => entry.ttl = Some(0),
+                "force" => entry.force = true,
+                "similarity" => entry.similarity = arg.trim_matches('"').parse().ok(),
+                _ => {}
+            }
+        }
+        options.filter(|o| *o != Self::default())
+    }
+}
+
 /// Token usage statistics
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TokenUsage {
@@ -312,6 +681,69 @@ pub struct TokenUsage {
     pub total_tokens: u32,
 }
 
+/// Incremental piece of a streamed chat completion
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatStreamChunk {
+    /// Text appended to the completion
+    pub delta: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallDelta>,
+    /// Set once the provider reports why generation stopped
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// Usage reported so far; providers may send partial counts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+/// Fragment of a tool call, merged by `index` across chunks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+// ============================================================================
+// Embedding Types
+// ============================================================================
+
+/// Embedding request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingRequest {
+    /// Embedding model to use
+    pub model: String,
+    /// Texts to embed
+    pub input: Vec<String>,
+    /// Explicit provider to use
+    pub provider: Option<String>,
+    /// Output dimensions (for models that support shortening)
+    pub dimensions: Option<u32>,
+    /// User ID for tracking
+    pub user_id: Option<String>,
+}
+
+/// Embedding response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingResponse {
+    /// Provider that served the request
+    pub provider: String,
+    /// Model that produced the vectors
+    pub model: String,
+    /// One vector per input, in input order
+    pub data: Vec<Vec<f32>>,
+    /// Token usage statistics
+    pub usage: TokenUsage,
+    /// Latency in milliseconds
+    pub latency_ms: u64,
+    /// Cost of this request
+    pub cost: f64,
+}
+
 // ============================================================================
 // Routing Types
 // ============================================================================
@@ -441,6 +873,7 @@ mod tests {
             name: "OpenAI".to_string(),
             provider_type: ProviderType::OpenAI,
             api_key: "sk-test".to_string(),
+            models: Vec::new(),
             base_url: "https://api.openai.com/v1".to_string(),
             pricing: ProviderPricing {
                 input_token_cost: 0.03,
@@ -468,6 +901,27 @@ mod tests {
         assert!(json.contains("Hello"));
     }
 
+    #[test]
+    fn test_response_format_serialization() {
+        let format: ResponseFormat = serde_json::from_value(serde_json::json!({
+            "type": "json_schema",
+            "json_schema": {"name": "answer", "schema": {"type": "object"}}
+        })).unwrap();
+        assert!(matches!(format, ResponseFormat::JsonSchema { ref json_schema } if json_schema.name == "answer"));
+
+        let json = serde_json::to_value(ResponseFormat::JsonObject).unwrap();
+        assert_eq!(json["type"], "json_object");
+    }
+
+    #[test]
+    fn test_tool_defaults_to_function() {
+        let tool: Tool = serde_json::from_value(serde_json::json!({
+            "function": {"name": "get_weather"}
+        })).unwrap();
+        assert_eq!(tool.tool_type, "function");
+        assert!(tool.function.parameters.is_none());
+    }
+
     #[test]
     fn test_message_helpers() {
         let system = Message::system("You are a helpful assistant");
@@ -480,6 +934,46 @@ mod tests {
         assert_eq!(assistant.role, "assistant");
     }
 
+    #[test]
+    fn test_message_content_forms() {
+        let text: Message = serde_json::from_value(serde_json::json!({
+            "role": "user", "content": "Hello", "function_call": null, "tool_calls": null
+        })).unwrap();
+        assert_eq!(text.content, "Hello");
+
+        let parts: Message = serde_json::from_value(serde_json::json!({
+            "role": "user",
+            "content": [
+                {"type": "text", "text": "What is in "},
+                {"type": "text", "text": "this image?"},
+                {"type": "image", "source": {"type": "url", "url": "https://example.com/cat.png"}}
+            ],
+            "function_call": null,
+            "tool_calls": null
+        })).unwrap();
+        assert_eq!(parts.content.text(), "What is in this image?");
+        assert_eq!(parts.content.media().count(), 1);
+    }
+
+    #[test]
+    fn test_media_source_data_url() {
+        let source = MediaSource::from_url("data:image/jpeg;base64,/9j/4AAQ");
+        assert_eq!(source, MediaSource::Base64 {
+            media_type: "image/jpeg".to_string(),
+            data: "/9j/4AAQ".to_string(),
+        });
+        assert_eq!(source.to_url(), "data:image/jpeg;base64,/9j/4AAQ");
+    }
+
+    #[test]
+    fn test_infer_input_capabilities() {
+        assert!(ModelCapability::infer("gpt-4o-mini").contains(&ModelCapability::Vision));
+        assert!(ModelCapability::infer("meta-llama/Llama-3.2-90B-Vision-Instruct-Turbo").contains(&ModelCapability::Vision));
+        assert!(ModelCapability::infer("gemini-1.5-pro").contains(&ModelCapability::AudioInput));
+        assert_eq!(ModelCapability::infer("gpt-3.5-turbo"), vec![ModelCapability::LLM]);
+        assert_eq!(ModelCapability::infer("text-embedding-3-small"), vec![ModelCapability::Embedding]);
+    }
+
     #[test]
     fn test_token_usage_default() {
         let usage = TokenUsage::default();
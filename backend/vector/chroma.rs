@@ -0,0 +1,265 @@
+//! Chroma vector store over its v2 REST API
+//!
+//! Uses the default tenant and database. Chroma addresses records by
+//! collection id, so ids are looked up by name and cached. Payloads are
+//! stored as flat metadata (see [`flat_metadata`]).
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+use super::{flat_metadata, require_filter, send_json, VectorFilter, VectorMatch, VectorRecord, VectorStore};
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::account_manager::VectorDbConfig;
+
+const COLLECTIONS_PATH: &str = "/api/v2/tenants/default_tenant/databases/default_database/collections";
+
+pub struct ChromaStore {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+    /// Collection name to Chroma collection id
+    ids: RwLock<HashMap<String, String>>,
+}
+
+impl ChromaStore {
+    pub fn new(config: &VectorDbConfig, client: reqwest::Client) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone().filter(|k| !k.is_empty()),
+            client,
+            ids: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path));
+        match self.api_key {
+            Some(ref key) => request.header("x-chroma-token", key),
+            None => request,
+        }
+    }
+
+    /// Id of a collection, `None` when it does not exist
+    async fn collection_id(&self, collection: &str) -> Result<Option<String>> {
+        if let Some(id) = self.ids.read().await.get(collection) {
+            return Ok(Some(id.clone()));
+        }
+
+        let response = self
+            .request(reqwest::Method::GET, &format!("{}/{}", COLLECTIONS_PATH, collection))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+        // Chroma answers 404 in recent releases and 400 in older ones
+        if matches!(response.status().as_u16(), 400 | 404) {
+            return Ok(None);
+        }
+        let body: serde_json::Value = crate::providers::streaming::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+        Ok(self.remember(collection, &body).await)
+    }
+
+    async fn remember(&self, collection: &str, body: &serde_json::Value) -> Option<String> {
+        let id = body["id"].as_str()?.to_string();
+        self.ids.write().await.insert(collection.to_string(), id.clone());
+        Some(id)
+    }
+
+    async fn existing_collection_id(&self, collection: &str) -> Result<String> {
+        self.collection_id(collection)
+            .await?
+            .ok_or_else(|| SynapseError::NotFound(format!("Chroma collection {}", collection)))
+    }
+
+    async fn post(&self, collection_id: &str, operation: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let path = format!("{}/{}/{}", COLLECTIONS_PATH, collection_id, operation);
+        send_json(self.request(reqwest::Method::POST, &path).json(&body)).await
+    }
+}
+
+/// Chroma `where` clause for an exact-match filter
+fn chroma_where(filter: &VectorFilter) -> serde_json::Value {
+    let clauses: Vec<_> = filter
+        .iter()
+        .map(|(key, value)| serde_json::json!({ key.clone(): {"$eq": value} }))
+        .collect();
+    match clauses.len() {
+        1 => clauses.into_iter().next().unwrap_or_default(),
+        _ => serde_json::json!({"$and": clauses}),
+    }
+}
+
+#[async_trait]
+impl VectorStore for ChromaStore {
+    fn name(&self) -> &str {
+        "chroma"
+    }
+
+    async fn ensure_collection(&self, collection: &str, _dimensions: usize) -> Result<()> {
+        if self.ids.read().await.contains_key(collection) {
+            return Ok(());
+        }
+        let body = send_json(self.request(reqwest::Method::POST, COLLECTIONS_PATH).json(&serde_json::json!({
+            "name": collection,
+            "get_or_create": true,
+            "metadata": {"hnsw:space": "cosine"},
+        }))).await?;
+        self.remember(collection, &body).await;
+        Ok(())
+    }
+
+    async fn drop_collection(&self, collection: &str) -> Result<()> {
+        if self.collection_id(collection).await?.is_none() {
+            return Ok(());
+        }
+        send_json(self.request(reqwest::Method::DELETE, &format!("{}/{}", COLLECTIONS_PATH, collection))).await?;
+        self.ids.write().await.remove(collection);
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let collection_id = self.existing_collection_id(collection).await?;
+
+        let mut ids = Vec::with_capacity(records.len());
+        let mut embeddings = Vec::with_capacity(records.len());
+        let mut metadatas = Vec::with_capacity(records.len());
+        for record in records {
+            metadatas.push(flat_metadata(&record.payload));
+            ids.push(record.id);
+            embeddings.push(record.vector);
+        }
+
+        self.post(&collection_id, "upsert", serde_json::json!({
+            "ids": ids,
+            "embeddings": embeddings,
+            "metadatas": metadatas,
+        })).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let Some(collection_id) = self.collection_id(collection).await? else {
+            return Ok(());
+        };
+        self.post(&collection_id, "delete", serde_json::json!({"ids": ids})).await?;
+        Ok(())
+    }
+
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()> {
+        require_filter(filter)?;
+        let Some(collection_id) = self.collection_id(collection).await? else {
+            return Ok(());
+        };
+        self.post(&collection_id, "delete", serde_json::json!({"where": chroma_where(filter)})).await?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let Some(collection_id) = self.collection_id(collection).await? else {
+            return Ok(Vec::new());
+        };
+
+        let mut body = serde_json::json!({
+            "query_embeddings": [vector],
+            "n_results": top_k,
+            "include": ["metadatas", "distances"],
+        });
+        if !filter.is_empty() {
+            body["where"] = chroma_where(filter);
+        }
+        let response = self.post(&collection_id, "query", body).await?;
+
+        // Results are nested per query embedding; we send one
+        let ids = response["ids"][0].as_array().cloned().unwrap_or_default();
+        Ok(ids
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| VectorMatch {
+                id: id.as_str().unwrap_or_default().to_string(),
+                // Cosine distance
+                score: 1.0 - response["distances"][0][i].as_f64().unwrap_or(1.0) as f32,
+                payload: response["metadatas"][0][i].clone(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_chroma_where() {
+        let mut filter = VectorFilter::new();
+        filter.insert("scope".to_string(), serde_json::json!("app-1"));
+        assert_eq!(chroma_where(&filter), serde_json::json!({"scope": {"$eq": "app-1"}}));
+
+        filter.insert("lang".to_string(), serde_json::json!("en"));
+        assert_eq!(chroma_where(&filter)["$and"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_and_query() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path(COLLECTIONS_PATH))
+            .and(body_partial_json(serde_json::json!({"name": "docs", "get_or_create": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"id": "c-1", "name": "docs"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/c-1/upsert", COLLECTIONS_PATH)))
+            .and(body_partial_json(serde_json::json!({"ids": ["a"], "metadatas": [{"title": "Guide"}]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(format!("{}/c-1/query", COLLECTIONS_PATH)))
+            .and(body_partial_json(serde_json::json!({"n_results": 3, "where": {"title": {"$eq": "Guide"}}})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ids": [["a"]],
+                "distances": [[0.25]],
+                "metadatas": [[{"title": "Guide"}]],
+            })))
+            .mount(&server)
+            .await;
+
+        let config = VectorDbConfig { url: server.uri(), api_key: None, collection_name: None };
+        let store = ChromaStore::new(&config, reqwest::Client::new());
+        store.ensure_collection("docs", 2).await.unwrap();
+        store.ensure_collection("docs", 2).await.unwrap();
+        store.upsert("docs", vec![VectorRecord {
+            id: "a".to_string(),
+            vector: vec![1.0, 0.0],
+            payload: serde_json::json!({"title": "Guide", "heading": null}),
+        }]).await.unwrap();
+
+        let mut filter = VectorFilter::new();
+        filter.insert("title".to_string(), serde_json::json!("Guide"));
+        let results = store.query("docs", &[1.0, 0.0], 3, &filter).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "a");
+        assert!((results[0].score - 0.75).abs() < 1e-6);
+        assert_eq!(results[0].payload["title"], "Guide");
+    }
+}
@@ -0,0 +1,132 @@
+//! In-process vector store
+//!
+//! Brute-force cosine search over records held in memory. Suitable for a
+//! single replica or tests; contents are lost on restart.
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+use super::{cosine_similarity, require_filter, VectorFilter, VectorMatch, VectorRecord, VectorStore};
+use crate::error::Result;
+
+#[derive(Default)]
+pub struct MemoryVectorStore {
+    collections: RwLock<HashMap<String, Vec<VectorRecord>>>,
+}
+
+impl MemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn matches(payload: &serde_json::Value, filter: &VectorFilter) -> bool {
+    filter.iter().all(|(key, value)| payload.get(key) == Some(value))
+}
+
+#[async_trait]
+impl VectorStore for MemoryVectorStore {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    async fn ensure_collection(&self, collection: &str, _dimensions: usize) -> Result<()> {
+        self.collections.write().await.entry(collection.to_string()).or_default();
+        Ok(())
+    }
+
+    async fn drop_collection(&self, collection: &str) -> Result<()> {
+        self.collections.write().await.remove(collection);
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()> {
+        let mut collections = self.collections.write().await;
+        let stored = collections.entry(collection.to_string()).or_default();
+        for record in records {
+            match stored.iter_mut().find(|r| r.id == record.id) {
+                Some(existing) => *existing = record,
+                None => stored.push(record),
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()> {
+        if let Some(stored) = self.collections.write().await.get_mut(collection) {
+            stored.retain(|r| !ids.contains(&r.id));
+        }
+        Ok(())
+    }
+
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()> {
+        require_filter(filter)?;
+        if let Some(stored) = self.collections.write().await.get_mut(collection) {
+            stored.retain(|r| !matches(&r.payload, filter));
+        }
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let collections = self.collections.read().await;
+        let Some(records) = collections.get(collection) else {
+            return Ok(Vec::new());
+        };
+
+        let mut matches: Vec<VectorMatch> = records
+            .iter()
+            .filter(|r| matches(&r.payload, filter))
+            .map(|r| VectorMatch {
+                id: r.id.clone(),
+                score: cosine_similarity(vector, &r.vector),
+                payload: r.payload.clone(),
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(top_k);
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_query_with_filter() {
+        let store = MemoryVectorStore::new();
+        let record = |id: &str, vector: Vec<f32>, scope: &str| VectorRecord {
+            id: id.to_string(),
+            vector,
+            payload: serde_json::json!({"scope": scope}),
+        };
+        store.upsert("c", vec![
+            record("a", vec![1.0, 0.0], "app-1"),
+            record("b", vec![0.7, 0.7], "app-1"),
+            record("c", vec![1.0, 0.0], "app-2"),
+        ]).await.unwrap();
+
+        let mut filter = VectorFilter::new();
+        filter.insert("scope".to_string(), serde_json::json!("app-1"));
+
+        let results = store.query("c", &[1.0, 0.1], 5, &filter).await.unwrap();
+        assert_eq!(results.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert!(store.query("missing", &[1.0, 0.0], 5, &filter).await.unwrap().is_empty());
+
+        store.delete_matching("c", &filter).await.unwrap();
+        let results = store.query("c", &[1.0, 0.0], 5, &VectorFilter::new()).await.unwrap();
+        assert_eq!(results.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), vec!["c"]);
+        assert!(store.delete_matching("c", &VectorFilter::new()).await.is_err());
+
+        store.delete("c", &["c".to_string()]).await.unwrap();
+        assert!(store.query("c", &[1.0, 0.0], 5, &VectorFilter::new()).await.unwrap().is_empty());
+    }
+}
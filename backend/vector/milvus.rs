@@ -0,0 +1,172 @@
+//! Milvus vector store over its v2 REST API
+//!
+//! Collections are created with the quick setup: a string primary key,
+//! cosine metric and dynamic fields, with the payload stored in a dynamic
+//! `payload` JSON field. Milvus reports most failures in the body with a
+//! non-zero `code` rather than through the HTTP status.
+
+use async_trait::async_trait;
+
+use super::{require_filter, send_json, VectorFilter, VectorMatch, VectorRecord, VectorStore};
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::account_manager::VectorDbConfig;
+
+/// Longest record id the primary key accepts
+const MAX_ID_LENGTH: usize = 256;
+
+pub struct MilvusStore {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl MilvusStore {
+    pub fn new(config: &VectorDbConfig, client: reqwest::Client) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone().filter(|k| !k.is_empty()),
+            client,
+        }
+    }
+
+    async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let request = self.client.post(format!("{}/v2/vectordb{}", self.base_url, path)).json(&body);
+        let request = match self.api_key {
+            Some(ref key) => request.bearer_auth(key),
+            None => request,
+        };
+
+        let response = send_json(request).await?;
+        match response["code"].as_i64() {
+            Some(0) | None => Ok(response),
+            Some(code) => Err(SynapseError::Provider(ProviderError::RequestFailed(format!(
+                "Milvus error {}: {}",
+                code,
+                response["message"].as_str().unwrap_or_default()
+            )))),
+        }
+    }
+
+    async fn has_collection(&self, collection: &str) -> Result<bool> {
+        let response = self.post("/collections/has", serde_json::json!({"collectionName": collection})).await?;
+        Ok(response["data"]["has"].as_bool().unwrap_or(false))
+    }
+}
+
+/// Milvus boolean expression for an exact-match filter on payload fields
+fn milvus_filter(filter: &VectorFilter) -> String {
+    filter
+        .iter()
+        .map(|(key, value)| format!("payload[{}] == {}", serde_json::Value::from(key.as_str()), value))
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+#[async_trait]
+impl VectorStore for MilvusStore {
+    fn name(&self) -> &str {
+        "milvus"
+    }
+
+    async fn ensure_collection(&self, collection: &str, dimensions: usize) -> Result<()> {
+        if !self.has_collection(collection).await? {
+            self.post("/collections/create", serde_json::json!({
+                "collectionName": collection,
+                "dimension": dimensions,
+                "metricType": "COSINE",
+                "idType": "VarChar",
+                "primaryFieldName": "id",
+                "vectorFieldName": "vector",
+                "params": {"max_length": MAX_ID_LENGTH, "enableDynamicField": true},
+            })).await?;
+        }
+        Ok(())
+    }
+
+    async fn drop_collection(&self, collection: &str) -> Result<()> {
+        if self.has_collection(collection).await? {
+            self.post("/collections/drop", serde_json::json!({"collectionName": collection})).await?;
+        }
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let data: Vec<_> = records
+            .into_iter()
+            .map(|r| serde_json::json!({"id": r.id, "vector": r.vector, "payload": r.payload}))
+            .collect();
+        self.post("/entities/upsert", serde_json::json!({"collectionName": collection, "data": data})).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let filter = format!("id in {}", serde_json::to_string(ids).unwrap_or_default());
+        self.post("/entities/delete", serde_json::json!({"collectionName": collection, "filter": filter})).await?;
+        Ok(())
+    }
+
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()> {
+        require_filter(filter)?;
+        self.post("/entities/delete", serde_json::json!({
+            "collectionName": collection,
+            "filter": milvus_filter(filter),
+        })).await?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let mut body = serde_json::json!({
+            "collectionName": collection,
+            "data": [vector],
+            "annsField": "vector",
+            "limit": top_k,
+            "outputFields": ["payload"],
+        });
+        if !filter.is_empty() {
+            body["filter"] = serde_json::Value::String(milvus_filter(filter));
+        }
+        let response = self.post("/entities/search", body).await?;
+
+        Ok(response["data"]
+            .as_array()
+            .map(|results| {
+                results
+                    .iter()
+                    .map(|r| VectorMatch {
+                        id: match r["id"] {
+                            serde_json::Value::String(ref id) => id.clone(),
+                            ref other => other.to_string(),
+                        },
+                        // Cosine similarity with the COSINE metric
+                        score: r["distance"].as_f64().unwrap_or(0.0) as f32,
+                        payload: r["payload"].clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_milvus_filter() {
+        let mut filter = VectorFilter::new();
+        filter.insert("scope".to_string(), serde_json::json!("app \"1\""));
+        assert_eq!(milvus_filter(&filter), r#"payload["scope"] == "app \"1\"""#);
+    }
+}
@@ -0,0 +1,197 @@
+//! Vector store clients
+//!
+//! Backs the semantic cache, knowledge collections and agent retrieval with
+//! nearest-neighbour lookups. Stores are created from `VectorDb` provider
+//! accounts; without an account the Postgres store is used when a database
+//! is connected, and the in-memory store otherwise.
+
+mod chroma;
+mod memory;
+mod milvus;
+mod pinecone;
+mod postgres;
+mod qdrant;
+mod weaviate;
+
+pub use chroma::ChromaStore;
+pub use memory::MemoryVectorStore;
+pub use milvus::MilvusStore;
+pub use pinecone::PineconeStore;
+pub use postgres::PostgresVectorStore;
+pub use qdrant::QdrantStore;
+pub use weaviate::WeaviateStore;
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::account_manager::VectorDbConfig;
+
+/// Exact-match payload filter: every key must equal its value
+pub type VectorFilter = serde_json::Map<String, serde_json::Value>;
+
+/// A vector with its id and payload
+#[derive(Debug, Clone)]
+pub struct VectorRecord {
+    pub id: String,
+    pub vector: Vec<f32>,
+    pub payload: serde_json::Value,
+}
+
+/// A query result, best first
+#[derive(Debug, Clone)]
+pub struct VectorMatch {
+    pub id: String,
+    /// Cosine similarity, 1.0 for identical directions
+    pub score: f32,
+    pub payload: serde_json::Value,
+}
+
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Backend name for logs and stats
+    fn name(&self) -> &str;
+
+    /// Create the collection for `dimensions`-sized vectors if missing
+    async fn ensure_collection(&self, collection: &str, dimensions: usize) -> Result<()>;
+
+    /// Remove a collection and all of its records; missing collections are
+    /// not an error
+    async fn drop_collection(&self, collection: &str) -> Result<()>;
+
+    /// Insert or replace records by id
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()>;
+
+    /// Remove records by id
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()>;
+
+    /// Remove every record matching `filter`, which must not be empty
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()>;
+
+    /// Nearest neighbours of `vector` among records matching `filter`
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>>;
+}
+
+/// Client for a `VectorDb` provider account
+pub fn create_store(
+    provider_id: &str,
+    config: &VectorDbConfig,
+    client: reqwest::Client,
+) -> Result<Arc<dyn VectorStore>> {
+    match provider_id {
+        "qdrant" => Ok(Arc::new(QdrantStore::new(config, client))),
+        "chroma" => Ok(Arc::new(ChromaStore::new(config, client))),
+        "weaviate" => Ok(Arc::new(WeaviateStore::new(config, client))),
+        "milvus" => Ok(Arc::new(MilvusStore::new(config, client))),
+        "pinecone" => Ok(Arc::new(PineconeStore::new(config, client))),
+        other => Err(SynapseError::Provider(ProviderError::Unsupported(format!(
+            "no vector store client for '{}'",
+            other
+        )))),
+    }
+}
+
+/// Payload as flat metadata for stores that only accept scalar values
+///
+/// Nulls are dropped and arrays and objects are stored as JSON strings, so
+/// only top-level scalar fields can be filtered on.
+pub(crate) fn flat_metadata(payload: &serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    let Some(fields) = payload.as_object() else {
+        return serde_json::Map::new();
+    };
+    fields
+        .iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| {
+            let value = match value {
+                serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+                    serde_json::Value::String(value.to_string())
+                }
+                scalar => scalar.clone(),
+            };
+            (key.clone(), value)
+        })
+        .collect()
+}
+
+/// Error for an empty filter passed to `delete_matching`
+pub(crate) fn require_filter(filter: &VectorFilter) -> Result<()> {
+    if filter.is_empty() {
+        return Err(SynapseError::Validation("A filter is required to delete records".to_string()));
+    }
+    Ok(())
+}
+
+/// Send a request and parse its JSON body, `Null` when the body is empty
+pub(crate) async fn send_json(request: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+    let response = crate::providers::streaming::check_status(response).await?;
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+    if body.is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    serde_json::from_slice(&body).map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))
+}
+
+/// Cosine similarity; 0.0 when either vector is zero or lengths differ
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+
+    #[test]
+    fn test_create_store() {
+        let config = VectorDbConfig {
+            url: "http://localhost:6333".to_string(),
+            api_key: None,
+            collection_name: None,
+        };
+        assert_eq!(create_store("qdrant", &config, reqwest::Client::new()).unwrap().name(), "qdrant");
+        for provider in ["chroma", "weaviate", "milvus", "pinecone"] {
+            assert_eq!(create_store(provider, &config, reqwest::Client::new()).unwrap().name(), provider);
+        }
+        assert!(create_store("faiss", &config, reqwest::Client::new()).is_err());
+    }
+
+    #[test]
+    fn test_flat_metadata() {
+        let payload = serde_json::json!({"title": "Guide", "chunk": 2, "heading": null, "tags": ["a"]});
+        let metadata = flat_metadata(&payload);
+        assert_eq!(metadata["title"], "Guide");
+        assert_eq!(metadata["chunk"], 2);
+        assert!(!metadata.contains_key("heading"));
+        assert_eq!(metadata["tags"], "[\"a\"]");
+    }
+}
@@ -0,0 +1,136 @@
+//! Pinecone vector store over its data plane REST API
+//!
+//! The account URL is the index host; collections are namespaces within the
+//! index, which Pinecone creates on first write. The index itself, with its
+//! dimension and cosine metric, is created in the Pinecone console.
+//! Payloads are stored as flat metadata (see [`flat_metadata`]).
+
+use async_trait::async_trait;
+
+use super::{flat_metadata, require_filter, send_json, VectorFilter, VectorMatch, VectorRecord, VectorStore};
+use crate::error::Result;
+use crate::providers::account_manager::VectorDbConfig;
+
+pub struct PineconeStore {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl PineconeStore {
+    pub fn new(config: &VectorDbConfig, client: reqwest::Client) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone().filter(|k| !k.is_empty()),
+            client,
+        }
+    }
+
+    async fn post(&self, path: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let request = self.client.post(format!("{}{}", self.base_url, path)).json(&body);
+        let request = match self.api_key {
+            Some(ref key) => request.header("Api-Key", key),
+            None => request,
+        };
+        send_json(request).await
+    }
+}
+
+/// Pinecone metadata filter for an exact-match filter
+fn pinecone_filter(filter: &VectorFilter) -> serde_json::Value {
+    filter
+        .iter()
+        .map(|(key, value)| (key.clone(), serde_json::json!({"$eq": value})))
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+#[async_trait]
+impl VectorStore for PineconeStore {
+    fn name(&self) -> &str {
+        "pinecone"
+    }
+
+    async fn ensure_collection(&self, _collection: &str, _dimensions: usize) -> Result<()> {
+        Ok(())
+    }
+
+    async fn drop_collection(&self, collection: &str) -> Result<()> {
+        self.post("/vectors/delete", serde_json::json!({"deleteAll": true, "namespace": collection})).await?;
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let vectors: Vec<_> = records
+            .into_iter()
+            .map(|r| serde_json::json!({"id": r.id, "values": r.vector, "metadata": flat_metadata(&r.payload)}))
+            .collect();
+        self.post("/vectors/upsert", serde_json::json!({"vectors": vectors, "namespace": collection})).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.post("/vectors/delete", serde_json::json!({"ids": ids, "namespace": collection})).await?;
+        Ok(())
+    }
+
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()> {
+        require_filter(filter)?;
+        self.post("/vectors/delete", serde_json::json!({
+            "filter": pinecone_filter(filter),
+            "namespace": collection,
+        })).await?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let mut body = serde_json::json!({
+            "vector": vector,
+            "topK": top_k,
+            "namespace": collection,
+            "includeMetadata": true,
+        });
+        if !filter.is_empty() {
+            body["filter"] = pinecone_filter(filter);
+        }
+        let response = self.post("/query", body).await?;
+
+        Ok(response["matches"]
+            .as_array()
+            .map(|results| {
+                results
+                    .iter()
+                    .map(|r| VectorMatch {
+                        id: r["id"].as_str().unwrap_or_default().to_string(),
+                        score: r["score"].as_f64().unwrap_or(0.0) as f32,
+                        payload: r["metadata"].clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinecone_filter() {
+        let mut filter = VectorFilter::new();
+        filter.insert("scope".to_string(), serde_json::json!("app-1"));
+        assert_eq!(pinecone_filter(&filter), serde_json::json!({"scope": {"$eq": "app-1"}}));
+    }
+}
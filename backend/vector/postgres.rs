@@ -0,0 +1,112 @@
+//! Vector store in the hub's own database
+//!
+//! Records live in the `vector_records` table with `REAL[]` vectors and a
+//! JSONB payload. Filters run in Postgres (`payload @> filter`, GIN
+//! indexed) and cosine ranking runs over the matching rows here, which
+//! needs no extension and suits collections up to some tens of thousands
+//! of records; larger ones belong in a dedicated vector database.
+
+use async_trait::async_trait;
+
+use super::{cosine_similarity, require_filter, VectorFilter, VectorMatch, VectorRecord, VectorStore};
+use crate::db::DbPool;
+use crate::error::Result;
+
+pub struct PostgresVectorStore {
+    pool: DbPool,
+}
+
+impl PostgresVectorStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl VectorStore for PostgresVectorStore {
+    fn name(&self) -> &str {
+        "postgres"
+    }
+
+    async fn ensure_collection(&self, _collection: &str, _dimensions: usize) -> Result<()> {
+        Ok(())
+    }
+
+    async fn drop_collection(&self, collection: &str) -> Result<()> {
+        sqlx::query("DELETE FROM vector_records WHERE collection = $1")
+            .bind(collection)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for record in records {
+            sqlx::query(
+                r#"
+                INSERT INTO vector_records (collection, id, embedding, payload, updated_at)
+                VALUES ($1, $2, $3, $4, NOW())
+                ON CONFLICT (collection, id) DO UPDATE SET
+                    embedding = EXCLUDED.embedding, payload = EXCLUDED.payload, updated_at = NOW()
+                "#
+            )
+            .bind(collection)
+            .bind(&record.id)
+            .bind(&record.vector)
+            .bind(&record.payload)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()> {
+        sqlx::query("DELETE FROM vector_records WHERE collection = $1 AND id = ANY($2)")
+            .bind(collection)
+            .bind(ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()> {
+        require_filter(filter)?;
+        sqlx::query("DELETE FROM vector_records WHERE collection = $1 AND payload @> $2")
+            .bind(collection)
+            .bind(serde_json::Value::Object(filter.clone()))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let rows: Vec<(String, Vec<f32>, serde_json::Value)> = sqlx::query_as(
+            "SELECT id, embedding, payload FROM vector_records WHERE collection = $1 AND payload @> $2"
+        )
+        .bind(collection)
+        .bind(serde_json::Value::Object(filter.clone()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut matches: Vec<VectorMatch> = rows
+            .into_iter()
+            .map(|(id, embedding, payload)| VectorMatch {
+                score: cosine_similarity(vector, &embedding),
+                id,
+                payload,
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(top_k);
+        Ok(matches)
+    }
+}
@@ -0,0 +1,203 @@
+//! Qdrant vector store over its REST API
+
+use async_trait::async_trait;
+
+use super::{require_filter, send_json, VectorFilter, VectorMatch, VectorRecord, VectorStore};
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::account_manager::VectorDbConfig;
+
+pub struct QdrantStore {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl QdrantStore {
+    pub fn new(config: &VectorDbConfig, client: reqwest::Client) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone().filter(|k| !k.is_empty()),
+            client,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path));
+        match self.api_key {
+            Some(ref key) => request.header("api-key", key),
+            None => request,
+        }
+    }
+
+}
+
+/// Qdrant `must` clause for an exact-match filter
+fn qdrant_filter(filter: &VectorFilter) -> serde_json::Value {
+    let must: Vec<_> = filter
+        .iter()
+        .map(|(key, value)| serde_json::json!({"key": key, "match": {"value": value}}))
+        .collect();
+    serde_json::json!({"must": must})
+}
+
+#[async_trait]
+impl VectorStore for QdrantStore {
+    fn name(&self) -> &str {
+        "qdrant"
+    }
+
+    async fn ensure_collection(&self, collection: &str, dimensions: usize) -> Result<()> {
+        let path = format!("/collections/{}", collection);
+        let exists = self
+            .request(reqwest::Method::GET, &path)
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?
+            .status()
+            .is_success();
+
+        if !exists {
+            send_json(self.request(reqwest::Method::PUT, &path).json(&serde_json::json!({
+                "vectors": {"size": dimensions, "distance": "Cosine"}
+            }))).await?;
+        }
+        Ok(())
+    }
+
+    async fn drop_collection(&self, collection: &str) -> Result<()> {
+        let response = self
+            .request(reqwest::Method::DELETE, &format!("/collections/{}", collection))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            crate::providers::streaming::check_status(response).await?;
+        }
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()> {
+        let points: Vec<_> = records
+            .into_iter()
+            .map(|r| serde_json::json!({"id": r.id, "vector": r.vector, "payload": r.payload}))
+            .collect();
+
+        let path = format!("/collections/{}/points?wait=true", collection);
+        send_json(self.request(reqwest::Method::PUT, &path).json(&serde_json::json!({"points": points})))
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let path = format!("/collections/{}/points/delete?wait=true", collection);
+        send_json(self.request(reqwest::Method::POST, &path).json(&serde_json::json!({"points": ids}))).await?;
+        Ok(())
+    }
+
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()> {
+        require_filter(filter)?;
+        let path = format!("/collections/{}/points/delete?wait=true", collection);
+        send_json(self.request(reqwest::Method::POST, &path).json(&serde_json::json!({"filter": qdrant_filter(filter)})))
+            .await?;
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let mut body = serde_json::json!({
+            "vector": vector,
+            "limit": top_k,
+            "with_payload": true,
+        });
+        if !filter.is_empty() {
+            body["filter"] = qdrant_filter(filter);
+        }
+
+        let path = format!("/collections/{}/points/search", collection);
+        let response = send_json(self.request(reqwest::Method::POST, &path).json(&body)).await?;
+
+        Ok(response["result"]
+            .as_array()
+            .map(|results| {
+                results
+                    .iter()
+                    .map(|r| VectorMatch {
+                        id: match r["id"] {
+                            serde_json::Value::String(ref id) => id.clone(),
+                            ref other => other.to_string(),
+                        },
+                        score: r["score"].as_f64().unwrap_or(0.0) as f32,
+                        payload: r["payload"].clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_qdrant_filter() {
+        let mut filter = VectorFilter::new();
+        filter.insert("scope".to_string(), serde_json::json!("app-1"));
+        let clause = qdrant_filter(&filter);
+        assert_eq!(clause["must"][0]["key"], "scope");
+        assert_eq!(clause["must"][0]["match"]["value"], "app-1");
+    }
+
+    #[tokio::test]
+    async fn test_collection_management() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/collections/docs"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/collections/docs"))
+            .and(body_json(serde_json::json!({"vectors": {"size": 3, "distance": "Cosine"}})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"result": true})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/collections/docs/points/delete"))
+            .and(body_json(serde_json::json!({
+                "filter": {"must": [{"key": "document_id", "match": {"value": "d1"}}]}
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"result": {}})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/collections/docs"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let config = VectorDbConfig { url: server.uri(), api_key: None, collection_name: None };
+        let store = QdrantStore::new(&config, reqwest::Client::new());
+        store.ensure_collection("docs", 3).await.unwrap();
+
+        let mut filter = VectorFilter::new();
+        filter.insert("document_id".to_string(), serde_json::json!("d1"));
+        store.delete_matching("docs", &filter).await.unwrap();
+        assert!(store.delete_matching("docs", &VectorFilter::new()).await.is_err());
+
+        // Dropping a missing collection is not an error
+        store.drop_collection("docs").await.unwrap();
+    }
+}
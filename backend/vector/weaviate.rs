@@ -0,0 +1,262 @@
+//! Weaviate vector store over its REST and GraphQL APIs
+//!
+//! Each collection is a class without a vectorizer. The payload is kept as
+//! a JSON string property for round-tripping, and its top-level scalar
+//! fields are copied into properties so filters can match them. Weaviate
+//! object ids must be UUIDs.
+
+use async_trait::async_trait;
+
+use super::{flat_metadata, require_filter, send_json, VectorFilter, VectorMatch, VectorRecord, VectorStore};
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::account_manager::VectorDbConfig;
+
+/// Property holding the full payload
+const PAYLOAD_PROPERTY: &str = "payloadJson";
+
+pub struct WeaviateStore {
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WeaviateStore {
+    pub fn new(config: &VectorDbConfig, client: reqwest::Client) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone().filter(|k| !k.is_empty()),
+            client,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path));
+        match self.api_key {
+            Some(ref key) => request.bearer_auth(key),
+            None => request,
+        }
+    }
+
+    async fn batch_delete(&self, class: &str, filter: serde_json::Value) -> Result<()> {
+        send_json(self.request(reqwest::Method::DELETE, "/v1/batch/objects").json(&serde_json::json!({
+            "match": {"class": class, "where": filter},
+        }))).await?;
+        Ok(())
+    }
+}
+
+/// Weaviate class name for a collection: capitalized, other characters
+/// than ASCII letters and digits replaced by `_`
+fn class_name(collection: &str) -> String {
+    let name: String = collection
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    match name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() => {
+            first.to_ascii_uppercase().to_string() + &name[first.len_utf8()..]
+        }
+        _ => format!("C{}", name),
+    }
+}
+
+/// Typed operand of a `where` filter, e.g. `valueText`
+fn value_operand(value: &serde_json::Value) -> (&'static str, serde_json::Value) {
+    match value {
+        serde_json::Value::Bool(_) => ("valueBoolean", value.clone()),
+        serde_json::Value::Number(n) if n.is_i64() || n.is_u64() => ("valueInt", value.clone()),
+        serde_json::Value::Number(_) => ("valueNumber", value.clone()),
+        serde_json::Value::String(_) => ("valueText", value.clone()),
+        other => ("valueText", serde_json::Value::String(other.to_string())),
+    }
+}
+
+/// REST `where` filter for an exact-match filter
+fn weaviate_where(filter: &VectorFilter) -> serde_json::Value {
+    let operands: Vec<_> = filter
+        .iter()
+        .map(|(key, value)| {
+            let (operand, value) = value_operand(value);
+            serde_json::json!({"path": [key], "operator": "Equal", operand: value})
+        })
+        .collect();
+    match operands.len() {
+        1 => operands.into_iter().next().unwrap_or_default(),
+        _ => serde_json::json!({"operator": "And", "operands": operands}),
+    }
+}
+
+/// A `where` filter as a GraphQL input literal: keys and the operator enum
+/// are unquoted
+fn graphql_literal(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Object(fields) => {
+            let fields: Vec<_> = fields
+                .iter()
+                .map(|(key, value)| match (key.as_str(), value) {
+                    ("operator", serde_json::Value::String(op)) => format!("operator: {}", op),
+                    _ => format!("{}: {}", key, graphql_literal(value)),
+                })
+                .collect();
+            format!("{{{}}}", fields.join(", "))
+        }
+        serde_json::Value::Array(items) => {
+            format!("[{}]", items.iter().map(graphql_literal).collect::<Vec<_>>().join(", "))
+        }
+        scalar => scalar.to_string(),
+    }
+}
+
+#[async_trait]
+impl VectorStore for WeaviateStore {
+    fn name(&self) -> &str {
+        "weaviate"
+    }
+
+    async fn ensure_collection(&self, collection: &str, _dimensions: usize) -> Result<()> {
+        let class = class_name(collection);
+        let exists = self
+            .request(reqwest::Method::GET, &format!("/v1/schema/{}", class))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?
+            .status()
+            .is_success();
+
+        if !exists {
+            send_json(self.request(reqwest::Method::POST, "/v1/schema").json(&serde_json::json!({
+                "class": class,
+                "vectorizer": "none",
+                "vectorIndexConfig": {"distance": "cosine"},
+                "properties": [{"name": PAYLOAD_PROPERTY, "dataType": ["text"], "indexFilterable": false}],
+            }))).await?;
+        }
+        Ok(())
+    }
+
+    async fn drop_collection(&self, collection: &str) -> Result<()> {
+        let response = self
+            .request(reqwest::Method::DELETE, &format!("/v1/schema/{}", class_name(collection)))
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+        if response.status() != reqwest::StatusCode::NOT_FOUND {
+            crate::providers::streaming::check_status(response).await?;
+        }
+        Ok(())
+    }
+
+    async fn upsert(&self, collection: &str, records: Vec<VectorRecord>) -> Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let class = class_name(collection);
+        let objects: Vec<_> = records
+            .into_iter()
+            .map(|r| {
+                let mut properties = flat_metadata(&r.payload);
+                properties.insert(PAYLOAD_PROPERTY.to_string(), serde_json::Value::String(r.payload.to_string()));
+                serde_json::json!({"class": class, "id": r.id, "vector": r.vector, "properties": properties})
+            })
+            .collect();
+
+        let response = send_json(
+            self.request(reqwest::Method::POST, "/v1/batch/objects").json(&serde_json::json!({"objects": objects})),
+        ).await?;
+        // Batch requests succeed as a whole and report per-object errors
+        let failed = response
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find_map(|object| object["result"]["errors"]["error"][0]["message"].as_str());
+        if let Some(message) = failed {
+            return Err(SynapseError::Provider(ProviderError::RequestFailed(message.to_string())));
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, collection: &str, ids: &[String]) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        self.batch_delete(
+            &class_name(collection),
+            serde_json::json!({"path": ["id"], "operator": "ContainsAny", "valueTextArray": ids}),
+        ).await
+    }
+
+    async fn delete_matching(&self, collection: &str, filter: &VectorFilter) -> Result<()> {
+        require_filter(filter)?;
+        self.batch_delete(&class_name(collection), weaviate_where(filter)).await
+    }
+
+    async fn query(
+        &self,
+        collection: &str,
+        vector: &[f32],
+        top_k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<VectorMatch>> {
+        let class = class_name(collection);
+        let vector = serde_json::to_string(vector).unwrap_or_default();
+        let filter = if filter.is_empty() {
+            String::new()
+        } else {
+            format!(", where: {}", graphql_literal(&weaviate_where(filter)))
+        };
+        let query = format!(
+            "{{ Get {{ {}(nearVector: {{vector: {}}}, limit: {}{}) {{ {} _additional {{ id distance }} }} }} }}",
+            class, vector, top_k, filter, PAYLOAD_PROPERTY
+        );
+
+        let response = send_json(self.request(reqwest::Method::POST, "/v1/graphql").json(&serde_json::json!({"query": query})))
+            .await?;
+        if let Some(message) = response["errors"][0]["message"].as_str() {
+            // Querying a class that was never created is an empty result
+            if message.contains("Cannot query field") {
+                return Ok(Vec::new());
+            }
+            return Err(SynapseError::Provider(ProviderError::RequestFailed(message.to_string())));
+        }
+
+        Ok(response["data"]["Get"][&class]
+            .as_array()
+            .map(|results| {
+                results
+                    .iter()
+                    .map(|r| VectorMatch {
+                        id: r["_additional"]["id"].as_str().unwrap_or_default().to_string(),
+                        // Cosine distance
+                        score: 1.0 - r["_additional"]["distance"].as_f64().unwrap_or(1.0) as f32,
+                        payload: r[PAYLOAD_PROPERTY]
+                            .as_str()
+                            .and_then(|json| serde_json::from_str(json).ok())
+                            .unwrap_or_default(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_class_name() {
+        assert_eq!(class_name("semantic"), "Semantic");
+        assert_eq!(class_name("3f2a-b1"), "C3f2a_b1");
+    }
+
+    #[test]
+    fn test_graphql_where() {
+        let mut filter = VectorFilter::new();
+        filter.insert("scope".to_string(), serde_json::json!("app-1"));
+        filter.insert("chunk".to_string(), serde_json::json!(2));
+        assert_eq!(
+            graphql_literal(&weaviate_where(&filter)),
+            r#"{operands: [{operator: Equal, path: ["chunk"], valueInt: 2}, {operator: Equal, path: ["scope"], valueText: "app-1"}], operator: And}"#
+        );
+    }
+}
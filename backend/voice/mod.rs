@@ -0,0 +1,234 @@
+//! Speech gateway
+//!
+//! Text-to-speech ([`tts`]) runs through the providers, accounts and voices
+//! in `tts_providers`, `tts_accounts` and `tts_voices`; speech-to-text
+//! ([`stt`]) through `stt_providers`, `stt_accounts` and `stt_models`. A
+//! request goes to one account of the chosen provider: the default account
+//! first, then by priority, skipping accounts whose `quota_config` has no
+//! room left. Each request is recorded with its cost in `voice_usage`.
+
+pub mod stt;
+pub mod tts;
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::db::{VoiceAccountRow, VoiceProviderRow};
+use crate::providers::account_manager::QuotaPeriod;
+
+/// Speech services, each with its own provider and account tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoiceKind {
+    /// Text-to-speech, metered in characters
+    Tts,
+    /// Speech-to-text, metered in seconds of audio
+    Stt,
+}
+
+impl VoiceKind {
+    /// `usage_type` in `voice_usage`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VoiceKind::Tts => "tts",
+            VoiceKind::Stt => "stt",
+        }
+    }
+
+    pub fn providers_table(&self) -> &'static str {
+        match self {
+            VoiceKind::Tts => "tts_providers",
+            VoiceKind::Stt => "stt_providers",
+        }
+    }
+
+    pub fn accounts_table(&self) -> &'static str {
+        match self {
+            VoiceKind::Tts => "tts_accounts",
+            VoiceKind::Stt => "stt_accounts",
+        }
+    }
+
+    /// Whether accounts of a provider need an API key; a local whisper.cpp
+    /// server runs without one
+    pub fn needs_api_key(&self, provider_type: &str) -> bool {
+        !(*self == VoiceKind::Stt && provider_type == "whisper_cpp")
+    }
+}
+
+/// One quota window of a voice account, in the unit its service is metered
+/// in, e.g. `{"day": {"limit": 1000000, "used": 0, "reset_at": null}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoiceQuota {
+    pub limit: u64,
+    #[serde(default)]
+    pub used: u64,
+    /// End of the current window; a new one starts with the next request
+    #[serde(default)]
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+/// An account's `quota_config`; no windows means no limit
+pub type VoiceQuotas = HashMap<QuotaPeriod, VoiceQuota>;
+
+impl VoiceQuota {
+    fn refresh(&mut self, period: QuotaPeriod, now: DateTime<Utc>) {
+        if self.reset_at.is_none_or(|at| at <= now) {
+            self.used = 0;
+            self.reset_at = Some(now + period.duration());
+        }
+    }
+}
+
+/// Parse a `quota_config`
+pub fn parse_quotas(config: &JsonValue) -> std::result::Result<VoiceQuotas, serde_json::Error> {
+    match config {
+        JsonValue::Null => Ok(VoiceQuotas::new()),
+        config => serde_json::from_value(config.clone()),
+    }
+}
+
+/// The quotas with `amount` taken from every window, or `None` if a window
+/// lacks room for it
+pub fn reserve(quotas: &VoiceQuotas, amount: u64, now: DateTime<Utc>) -> Option<VoiceQuotas> {
+    let mut quotas = quotas.clone();
+    for (period, quota) in quotas.iter_mut() {
+        quota.refresh(*period, now);
+        if quota.used.saturating_add(amount) > quota.limit {
+            return None;
+        }
+        quota.used += amount;
+    }
+    Some(quotas)
+}
+
+/// Correct a reservation by `delta`: negative to give back what a failed
+/// request did not use, positive for usage beyond the estimate
+pub fn adjust(quotas: &mut VoiceQuotas, delta: i64, now: DateTime<Utc>) {
+    for (period, quota) in quotas.iter_mut() {
+        quota.refresh(*period, now);
+        quota.used = quota.used.saturating_add_signed(delta);
+    }
+}
+
+/// Cost in USD of `amount` billed at `price` per `per` units
+pub fn cost(amount: u64, price: f64, per: f64) -> f64 {
+    amount as f64 * price / per
+}
+
+/// A provider account ready to call
+#[derive(Debug, Clone)]
+pub struct VoiceEndpoint {
+    /// `provider_type` of the provider, e.g. `openai`
+    pub provider_type: String,
+    pub base_url: String,
+    pub api_key: String,
+}
+
+impl VoiceEndpoint {
+    /// The account's endpoint, or the provider's base URL with `{region}`
+    /// filled in from the account
+    pub fn new(provider: &VoiceProviderRow, account: &VoiceAccountRow) -> Self {
+        let base_url = account.endpoint.clone()
+            .filter(|endpoint| !endpoint.trim().is_empty())
+            .or_else(|| provider.api_base_url.clone())
+            .unwrap_or_default();
+        let base_url = match account.region {
+            Some(ref region) => base_url.replace("{region}", region),
+            None => base_url,
+        };
+        Self {
+            provider_type: provider.provider_type.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: account.api_key_encrypted.clone(),
+        }
+    }
+}
+
+/// API view of a voice account, without its key
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceAccount {
+    pub id: String,
+    pub provider_id: String,
+    pub name: String,
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub quota_config: JsonValue,
+    pub enabled: bool,
+    pub is_default: bool,
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<VoiceAccountRow> for VoiceAccount {
+    fn from(row: VoiceAccountRow) -> Self {
+        Self {
+            id: row.id,
+            provider_id: row.provider_id,
+            name: row.name,
+            endpoint: row.endpoint,
+            region: row.region,
+            quota_config: row.quota_config,
+            enabled: row.enabled,
+            is_default: row.is_default,
+            priority: row.priority,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceProvider {
+    pub id: String,
+    pub name: String,
+    pub provider_type: String,
+    pub description: Option<String>,
+    pub supports_streaming: bool,
+    pub supports_diarization: bool,
+    /// USD per million characters (TTS) or per hour of audio (STT)
+    pub price: f64,
+    pub enabled: bool,
+    pub is_default: bool,
+    /// Whether the hub can call this provider
+    pub supported: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_reserve() {
+        let now = Utc::now();
+        let quotas = parse_quotas(&serde_json::json!({
+            "day": {"limit": 1000, "used": 900, "reset_at": now + Duration::hours(2)},
+            "month": {"limit": 5000},
+        }))
+        .unwrap();
+
+        let reserved = reserve(&quotas, 100, now).unwrap();
+        assert_eq!(reserved[&QuotaPeriod::Day].used, 1000);
+        assert_eq!(reserved[&QuotaPeriod::Month].used, 100);
+        assert_eq!(reserved[&QuotaPeriod::Month].reset_at, Some(now + Duration::days(30)));
+        assert!(reserve(&reserved, 1, now).is_none());
+
+        // A new day starts from zero
+        let tomorrow = now + Duration::hours(3);
+        let reserved = reserve(&reserved, 600, tomorrow).unwrap();
+        assert_eq!(reserved[&QuotaPeriod::Day].used, 600);
+        assert_eq!(reserved[&QuotaPeriod::Month].used, 700);
+
+        let mut quotas = reserved;
+        adjust(&mut quotas, -650, tomorrow);
+        assert_eq!(quotas[&QuotaPeriod::Day].used, 0);
+        assert_eq!(quotas[&QuotaPeriod::Month].used, 50);
+
+        assert!(reserve(&VoiceQuotas::new(), u64::MAX, now).is_some());
+        assert!(parse_quotas(&serde_json::json!({"week": {"limit": 1}})).is_err());
+    }
+}
@@ -0,0 +1,792 @@
+//! Speech-to-text adapters for OpenAI Whisper, Deepgram, AssemblyAI and a
+//! local whisper.cpp server
+//!
+//! Requests follow OpenAI's multipart `POST /audio/transcriptions` and
+//! `/audio/translations`. Every adapter returns a [`Transcript`] with timed
+//! segments, which is then rendered in the format the caller asked for.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::VoiceEndpoint;
+use crate::db::SttModelRow;
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::streaming;
+
+/// Largest upload accepted, matching OpenAI's limit
+pub const MAX_AUDIO_BYTES: usize = 25 * 1024 * 1024;
+
+/// `provider_type`s with an adapter
+pub const STT_PROVIDERS: &[&str] = &["openai", "deepgram", "assemblyai", "whisper_cpp"];
+
+/// STT is priced per hour of audio
+pub const PRICE_UNIT: f64 = 3600.0;
+
+/// Bitrate assumed when estimating the length of compressed audio
+const ESTIMATE_BITS_PER_SECOND: u64 = 64_000;
+
+const ASSEMBLYAI_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest to wait for an AssemblyAI transcript
+const ASSEMBLYAI_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    Transcribe,
+    /// Transcribe into English
+    Translate,
+}
+
+impl Task {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Task::Transcribe => "transcribe",
+            Task::Translate => "translate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptFormat {
+    #[default]
+    Json,
+    Text,
+    VerboseJson,
+    Srt,
+    Vtt,
+}
+
+impl TranscriptFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        serde_json::from_value(serde_json::Value::String(value.to_string()))
+            .map_err(|_| SynapseError::Validation(format!(
+                "response_format must be json, text, verbose_json, srt or vtt, not {}",
+                value
+            )))
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Json | TranscriptFormat::VerboseJson => "application/json",
+            TranscriptFormat::Text | TranscriptFormat::Srt => "text/plain; charset=utf-8",
+            TranscriptFormat::Vtt => "text/vtt; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AudioFile {
+    pub name: String,
+    pub content_type: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+/// `POST /v1/audio/transcriptions` or `/translations` request, parsed from
+/// its multipart form
+#[derive(Debug, Clone, Default)]
+pub struct TranscriptionRequest {
+    pub file: AudioFile,
+    /// Provider model, e.g. `whisper-1` or `nova-3`; picks the provider when
+    /// it is in the model catalog
+    pub model: Option<String>,
+    /// ISO-639-1 language of the audio; detected when absent
+    pub language: Option<String>,
+    pub prompt: Option<String>,
+    pub response_format: TranscriptFormat,
+    pub temperature: Option<f64>,
+    /// Label segments by speaker
+    pub diarize: bool,
+    /// Provider id or type; by default the provider of `model`, else the
+    /// default provider
+    pub provider: Option<String>,
+    pub user: Option<String>,
+}
+
+impl TranscriptionRequest {
+    /// Set a text field of the form; unknown fields are ignored
+    pub fn set_field(&mut self, name: &str, value: String) -> Result<()> {
+        let value = value.trim().to_string();
+        match name {
+            "model" => self.model = Some(value).filter(|v| !v.is_empty()),
+            "language" => self.language = Some(value).filter(|v| !v.is_empty()),
+            "prompt" => self.prompt = Some(value).filter(|v| !v.is_empty()),
+            "response_format" => self.response_format = TranscriptFormat::parse(&value)?,
+            "temperature" => {
+                let temperature = value.parse::<f64>()
+                    .map_err(|_| SynapseError::Validation(format!("temperature must be a number, not {}", value)))?;
+                self.temperature = Some(temperature);
+            }
+            "diarize" => self.diarize = matches!(value.as_str(), "true" | "1"),
+            "provider" => self.provider = Some(value).filter(|v| !v.is_empty()),
+            "user" | "user_id" => self.user = Some(value).filter(|v| !v.is_empty()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.file.bytes.is_empty() {
+            return Err(SynapseError::Validation("file is required".to_string()));
+        }
+        if self.file.bytes.len() > MAX_AUDIO_BYTES {
+            return Err(SynapseError::Validation(format!("file is larger than {} MB", MAX_AUDIO_BYTES / 1024 / 1024)));
+        }
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=1.0).contains(&temperature) {
+                return Err(SynapseError::Validation("temperature must be between 0 and 1".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Length of the audio in whole seconds, exact for WAV and estimated from
+/// the size for compressed formats
+pub fn estimate_seconds(bytes: &[u8]) -> u64 {
+    let seconds = wav_seconds(bytes).unwrap_or(bytes.len() as f64 * 8.0 / ESTIMATE_BITS_PER_SECOND as f64);
+    (seconds.ceil() as u64).max(1)
+}
+
+fn wav_seconds(bytes: &[u8]) -> Option<f64> {
+    if bytes.get(..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let u32_at = |offset: usize| bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let mut offset = 12;
+    let mut byte_rate = None;
+    while let (Some(id), Some(size)) = (bytes.get(offset..offset + 4), u32_at(offset + 4)) {
+        let body = offset + 8;
+        match id {
+            b"fmt " => byte_rate = u32_at(body + 8).filter(|rate| *rate > 0),
+            b"data" => {
+                // Streamed WAVs leave the size unset
+                let size = (size as usize).min(bytes.len() - body);
+                return Some(size as f64 / byte_rate? as f64);
+            }
+            _ => {}
+        }
+        offset = body + size as usize + size as usize % 2;
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Segment {
+    pub id: usize,
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+}
+
+/// A provider's transcript in a common shape
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub text: String,
+    pub language: Option<String>,
+    /// Seconds of audio, when the provider reports it
+    pub duration: Option<f64>,
+    pub segments: Vec<Segment>,
+}
+
+impl Transcript {
+    /// The transcript in `format`
+    pub fn render(&self, format: TranscriptFormat, task: Task) -> String {
+        match format {
+            TranscriptFormat::Json => serde_json::json!({"text": self.text}).to_string(),
+            TranscriptFormat::Text => self.text.clone(),
+            TranscriptFormat::VerboseJson => serde_json::json!({
+                "task": task.as_str(),
+                "language": self.language,
+                "duration": self.duration,
+                "text": self.text,
+                "segments": self.segments,
+            })
+            .to_string(),
+            TranscriptFormat::Srt => self.cues()
+                .iter()
+                .enumerate()
+                .map(|(i, segment)| format!(
+                    "{}\n{} --> {}\n{}\n\n",
+                    i + 1,
+                    timestamp(segment.start, ','),
+                    timestamp(segment.end, ','),
+                    cue_text(segment),
+                ))
+                .collect(),
+            TranscriptFormat::Vtt => std::iter::once("WEBVTT\n\n".to_string())
+                .chain(self.cues().iter().map(|segment| format!(
+                    "{} --> {}\n{}\n\n",
+                    timestamp(segment.start, '.'),
+                    timestamp(segment.end, '.'),
+                    cue_text(segment),
+                )))
+                .collect(),
+        }
+    }
+
+    /// Subtitle cues: the segments, or the whole text as one cue
+    fn cues(&self) -> Vec<Segment> {
+        if !self.segments.is_empty() || self.text.is_empty() {
+            return self.segments.clone();
+        }
+        vec![Segment { id: 0, start: 0.0, end: self.duration.unwrap_or(0.0), text: self.text.clone(), speaker: None }]
+    }
+}
+
+fn cue_text(segment: &Segment) -> String {
+    let text = segment.text.trim();
+    match segment.speaker {
+        Some(ref speaker) => format!("[{}] {}", speaker, text),
+        None => text.to_string(),
+    }
+}
+
+/// `HH:MM:SS,mmm` for SRT or `HH:MM:SS.mmm` for WebVTT
+fn timestamp(seconds: f64, separator: char) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        separator,
+        millis % 1000
+    )
+}
+
+/// A model in the catalog
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SttModel {
+    pub id: String,
+    pub provider_id: String,
+    /// Id to pass as `model` in transcription requests
+    pub model_id: String,
+    pub name: String,
+    pub languages: serde_json::Value,
+    pub word_error_rate: Option<f64>,
+    pub latency_ms: Option<i32>,
+    pub supports_streaming: bool,
+    pub supports_diarization: bool,
+    pub max_audio_length_seconds: Option<i32>,
+    pub quality_tier: String,
+}
+
+impl From<SttModelRow> for SttModel {
+    fn from(row: SttModelRow) -> Self {
+        Self {
+            id: row.id,
+            provider_id: row.provider_id,
+            model_id: row.model_id,
+            name: row.name,
+            languages: row.languages,
+            word_error_rate: row.word_error_rate,
+            latency_ms: row.latency_ms,
+            supports_streaming: row.supports_streaming,
+            supports_diarization: row.supports_diarization,
+            max_audio_length_seconds: row.max_audio_length_seconds,
+            quality_tier: row.quality_tier,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SttAdapter {
+    OpenAI,
+    Deepgram,
+    AssemblyAI,
+    /// The `server` example of whisper.cpp, for local transcription
+    WhisperCpp,
+}
+
+impl SttAdapter {
+    pub fn for_type(provider_type: &str) -> Option<Self> {
+        match provider_type {
+            "openai" => Some(SttAdapter::OpenAI),
+            "deepgram" => Some(SttAdapter::Deepgram),
+            "assemblyai" => Some(SttAdapter::AssemblyAI),
+            "whisper_cpp" => Some(SttAdapter::WhisperCpp),
+            _ => None,
+        }
+    }
+
+    /// Model used when the request names none
+    pub fn default_model(&self) -> Option<&'static str> {
+        match self {
+            SttAdapter::OpenAI => Some("whisper-1"),
+            SttAdapter::Deepgram => Some("nova-3"),
+            SttAdapter::AssemblyAI => Some("best"),
+            // The server transcribes with the model it was started with
+            SttAdapter::WhisperCpp => None,
+        }
+    }
+
+    pub fn supports(&self, task: Task) -> bool {
+        task == Task::Transcribe || matches!(self, SttAdapter::OpenAI | SttAdapter::WhisperCpp)
+    }
+
+    pub fn supports_diarization(&self) -> bool {
+        matches!(self, SttAdapter::Deepgram | SttAdapter::AssemblyAI)
+    }
+
+    pub async fn transcribe(
+        &self,
+        client: &reqwest::Client,
+        endpoint: &VoiceEndpoint,
+        task: Task,
+        model: Option<&str>,
+        request: &TranscriptionRequest,
+    ) -> Result<Transcript> {
+        if !self.supports(task) {
+            return Err(SynapseError::Provider(ProviderError::Unsupported(format!("{} through {}", task.as_str(), endpoint.provider_type))));
+        }
+        if request.diarize && !self.supports_diarization() {
+            return Err(SynapseError::Provider(ProviderError::Unsupported(format!("diarization through {}", endpoint.provider_type))));
+        }
+        match self {
+            SttAdapter::OpenAI => openai(client, endpoint, task, model.unwrap_or("whisper-1"), request).await,
+            SttAdapter::Deepgram => deepgram(client, endpoint, model, request).await,
+            SttAdapter::AssemblyAI => assemblyai(client, endpoint, model, request).await,
+            SttAdapter::WhisperCpp => whisper_cpp(client, endpoint, task, request).await,
+        }
+    }
+}
+
+/// Whether a model in the catalog can serve the request
+pub fn check_model(model: &SttModelRow, request: &TranscriptionRequest) -> Result<()> {
+    if request.diarize && !model.supports_diarization {
+        return Err(SynapseError::Provider(ProviderError::Unsupported(format!("diarization with {}", model.model_id))));
+    }
+    Ok(())
+}
+
+async fn send_json(request: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+    streaming::check_status(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))
+}
+
+fn file_part(file: &AudioFile) -> Result<reqwest::multipart::Part> {
+    let part = reqwest::multipart::Part::bytes(file.bytes.clone()).file_name(file.name.clone());
+    match file.content_type {
+        Some(ref content_type) => part.mime_str(content_type)
+            .map_err(|_| SynapseError::Validation(format!("invalid file content type {}", content_type))),
+        None => Ok(part),
+    }
+}
+
+/// OpenAI's `verbose_json`, which whisper.cpp's server also produces
+fn whisper_transcript(body: &serde_json::Value) -> Transcript {
+    Transcript {
+        text: body["text"].as_str().unwrap_or_default().trim().to_string(),
+        language: body["language"].as_str().map(str::to_string),
+        duration: body["duration"].as_f64(),
+        segments: body["segments"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(id, segment)| Segment {
+                id,
+                start: segment["start"].as_f64().unwrap_or_default(),
+                end: segment["end"].as_f64().unwrap_or_default(),
+                text: segment["text"].as_str().unwrap_or_default().trim().to_string(),
+                speaker: None,
+            })
+            .collect(),
+    }
+}
+
+async fn openai(client: &reqwest::Client, endpoint: &VoiceEndpoint, task: Task, model: &str, request: &TranscriptionRequest) -> Result<Transcript> {
+    // Only the whisper models return segments and the audio's duration
+    let format = if model.starts_with("whisper") { "verbose_json" } else { "json" };
+    let mut form = reqwest::multipart::Form::new()
+        .part("file", file_part(&request.file)?)
+        .text("model", model.to_string())
+        .text("response_format", format);
+    if let (Task::Transcribe, Some(language)) = (task, &request.language) {
+        form = form.text("language", language.clone());
+    }
+    if let Some(ref prompt) = request.prompt {
+        form = form.text("prompt", prompt.clone());
+    }
+    if let Some(temperature) = request.temperature {
+        form = form.text("temperature", temperature.to_string());
+    }
+    let path = match task {
+        Task::Transcribe => "transcriptions",
+        Task::Translate => "translations",
+    };
+    let body = send_json(
+        client
+            .post(format!("{}/audio/{}", endpoint.base_url, path))
+            .bearer_auth(&endpoint.api_key)
+            .multipart(form),
+    )
+    .await?;
+    Ok(whisper_transcript(&body))
+}
+
+async fn whisper_cpp(client: &reqwest::Client, endpoint: &VoiceEndpoint, task: Task, request: &TranscriptionRequest) -> Result<Transcript> {
+    let mut form = reqwest::multipart::Form::new()
+        .part("file", file_part(&request.file)?)
+        .text("response_format", "verbose_json")
+        .text("language", request.language.clone().unwrap_or_else(|| "auto".to_string()))
+        .text("translate", (task == Task::Translate).to_string());
+    if let Some(ref prompt) = request.prompt {
+        form = form.text("prompt", prompt.clone());
+    }
+    if let Some(temperature) = request.temperature {
+        form = form.text("temperature", temperature.to_string());
+    }
+    let mut builder = client.post(format!("{}/inference", endpoint.base_url)).multipart(form);
+    // A local server usually runs without a key
+    if !endpoint.api_key.is_empty() {
+        builder = builder.bearer_auth(&endpoint.api_key);
+    }
+    Ok(whisper_transcript(&send_json(builder).await?))
+}
+
+async fn deepgram(client: &reqwest::Client, endpoint: &VoiceEndpoint, model: Option<&str>, request: &TranscriptionRequest) -> Result<Transcript> {
+    let mut query = vec![
+        ("model", model.unwrap_or("nova-3").to_string()),
+        ("smart_format", "true".to_string()),
+        ("utterances", "true".to_string()),
+    ];
+    match request.language {
+        Some(ref language) => query.push(("language", language.clone())),
+        None => query.push(("detect_language", "true".to_string())),
+    }
+    if request.diarize {
+        query.push(("diarize", "true".to_string()));
+    }
+    let body = send_json(
+        client
+            .post(format!("{}/listen", endpoint.base_url))
+            .query(&query)
+            .header(reqwest::header::AUTHORIZATION, format!("Token {}", endpoint.api_key))
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                request.file.content_type.as_deref().unwrap_or("application/octet-stream"),
+            )
+            .body(request.file.bytes.clone()),
+    )
+    .await?;
+
+    let channel = &body["results"]["channels"][0];
+    Ok(Transcript {
+        text: channel["alternatives"][0]["transcript"].as_str().unwrap_or_default().to_string(),
+        language: channel["detected_language"].as_str().or(request.language.as_deref()).map(str::to_string),
+        duration: body["metadata"]["duration"].as_f64(),
+        segments: body["results"]["utterances"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(id, utterance)| Segment {
+                id,
+                start: utterance["start"].as_f64().unwrap_or_default(),
+                end: utterance["end"].as_f64().unwrap_or_default(),
+                text: utterance["transcript"].as_str().unwrap_or_default().to_string(),
+                speaker: request.diarize
+                    .then(|| utterance["speaker"].as_u64().map(|speaker| speaker.to_string()))
+                    .flatten(),
+            })
+            .collect(),
+    })
+}
+
+/// AssemblyAI transcribes asynchronously: upload the audio, submit a job,
+/// then poll it until it finishes
+async fn assemblyai(client: &reqwest::Client, endpoint: &VoiceEndpoint, model: Option<&str>, request: &TranscriptionRequest) -> Result<Transcript> {
+    let key = endpoint.api_key.as_str();
+    let upload = send_json(
+        client
+            .post(format!("{}/upload", endpoint.base_url))
+            .header(reqwest::header::AUTHORIZATION, key)
+            .body(request.file.bytes.clone()),
+    )
+    .await?;
+    let audio_url = upload["upload_url"].as_str()
+        .ok_or_else(|| SynapseError::Provider(ProviderError::InvalidResponse("upload returned no upload_url".to_string())))?;
+
+    let mut job = serde_json::json!({
+        "audio_url": audio_url,
+        "speech_model": model.unwrap_or("best"),
+        "speaker_labels": request.diarize,
+    });
+    match request.language {
+        Some(ref language) => job["language_code"] = serde_json::json!(language),
+        None => job["language_detection"] = serde_json::json!(true),
+    }
+    let submitted = send_json(client.post(format!("{}/transcript", endpoint.base_url)).header(reqwest::header::AUTHORIZATION, key).json(&job)).await?;
+    let id = submitted["id"].as_str()
+        .ok_or_else(|| SynapseError::Provider(ProviderError::InvalidResponse("transcript job has no id".to_string())))?;
+
+    let url = format!("{}/transcript/{}", endpoint.base_url, id);
+    let deadline = tokio::time::Instant::now() + ASSEMBLYAI_TIMEOUT;
+    let body = loop {
+        let body = send_json(client.get(&url).header(reqwest::header::AUTHORIZATION, key)).await?;
+        match body["status"].as_str() {
+            Some("completed") => break body,
+            Some("error") => {
+                return Err(SynapseError::Provider(ProviderError::RequestFailed(
+                    body["error"].as_str().unwrap_or("transcription failed").to_string(),
+                )));
+            }
+            _ if tokio::time::Instant::now() >= deadline => return Err(SynapseError::Provider(ProviderError::Timeout)),
+            _ => tokio::time::sleep(ASSEMBLYAI_POLL_INTERVAL).await,
+        }
+    };
+
+    // Speaker turns come with the transcript; sentences are fetched apart
+    let segments = if request.diarize {
+        body["utterances"].clone()
+    } else {
+        send_json(client.get(format!("{}/sentences", url)).header(reqwest::header::AUTHORIZATION, key)).await?["sentences"].take()
+    };
+    Ok(Transcript {
+        text: body["text"].as_str().unwrap_or_default().to_string(),
+        language: body["language_code"].as_str().map(str::to_string),
+        duration: body["audio_duration"].as_f64(),
+        segments: segments
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(id, segment)| Segment {
+                id,
+                // AssemblyAI times are in milliseconds
+                start: segment["start"].as_f64().unwrap_or_default() / 1000.0,
+                end: segment["end"].as_f64().unwrap_or_default() / 1000.0,
+                text: segment["text"].as_str().unwrap_or_default().to_string(),
+                speaker: request.diarize.then(|| segment["speaker"].as_str().map(str::to_string)).flatten(),
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn endpoint(provider_type: &str, base_url: String) -> VoiceEndpoint {
+        VoiceEndpoint {
+            provider_type: provider_type.to_string(),
+            base_url,
+            api_key: "key".to_string(),
+        }
+    }
+
+    fn request() -> TranscriptionRequest {
+        TranscriptionRequest {
+            file: AudioFile {
+                name: "call.mp3".to_string(),
+                content_type: Some("audio/mpeg".to_string()),
+                bytes: b"audio".to_vec(),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// A 16 kHz, 16-bit mono WAV of `seconds` of silence
+    fn wav(seconds: f64) -> Vec<u8> {
+        let data = vec![0u8; (32_000.0 * seconds) as usize];
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&[1, 0, 1, 0]);
+        wav.extend_from_slice(&16_000u32.to_le_bytes());
+        wav.extend_from_slice(&32_000u32.to_le_bytes());
+        wav.extend_from_slice(&[2, 0, 16, 0]);
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        wav.extend_from_slice(&data);
+        wav
+    }
+
+    #[test]
+    fn test_estimate_seconds() {
+        assert_eq!(estimate_seconds(&wav(2.5)), 3);
+        assert_eq!(estimate_seconds(&wav(0.0)), 1);
+        // 80 KB of compressed audio at 64 kbit/s
+        assert_eq!(estimate_seconds(&vec![0u8; 80_000]), 10);
+    }
+
+    #[test]
+    fn test_request_fields() {
+        let mut request = request();
+        request.set_field("model", "nova-3".to_string()).unwrap();
+        request.set_field("response_format", "verbose_json".to_string()).unwrap();
+        request.set_field("user_id", "u1".to_string()).unwrap();
+        request.set_field("timestamp_granularities[]", "word".to_string()).unwrap();
+        assert_eq!(request.model.as_deref(), Some("nova-3"));
+        assert_eq!(request.response_format, TranscriptFormat::VerboseJson);
+        assert_eq!(request.user.as_deref(), Some("u1"));
+        assert!(request.set_field("response_format", "docx".to_string()).is_err());
+        assert!(request.set_field("temperature", "hot".to_string()).is_err());
+        assert!(request.validate().is_ok());
+        assert!(TranscriptionRequest::default().validate().is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let transcript = Transcript {
+            text: "Hello there. Refunds take five days.".to_string(),
+            language: Some("en".to_string()),
+            duration: Some(3725.5),
+            segments: vec![
+                Segment { id: 0, start: 0.0, end: 1.25, text: " Hello there.".to_string(), speaker: None },
+                Segment { id: 1, start: 3723.0, end: 3725.5, text: "Refunds take five days.".to_string(), speaker: Some("B".to_string()) },
+            ],
+        };
+        assert_eq!(transcript.render(TranscriptFormat::Json, Task::Transcribe), r#"{"text":"Hello there. Refunds take five days."}"#);
+        assert_eq!(
+            transcript.render(TranscriptFormat::Srt, Task::Transcribe),
+            "1\n00:00:00,000 --> 00:00:01,250\nHello there.\n\n2\n01:02:03,000 --> 01:02:05,500\n[B] Refunds take five days.\n\n"
+        );
+        assert!(transcript
+            .render(TranscriptFormat::Vtt, Task::Transcribe)
+            .starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:01.250\nHello there.\n\n"));
+        let verbose: serde_json::Value = serde_json::from_str(&transcript.render(TranscriptFormat::VerboseJson, Task::Translate)).unwrap();
+        assert_eq!(verbose["task"], "translate");
+        assert_eq!(verbose["segments"][1]["speaker"], "B");
+        assert!(verbose["segments"][0].get("speaker").is_none());
+
+        let plain = Transcript { text: "Hi".to_string(), duration: Some(2.0), ..Default::default() };
+        assert_eq!(plain.render(TranscriptFormat::Srt, Task::Transcribe), "1\n00:00:00,000 --> 00:00:02,000\nHi\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_openai_and_whisper_cpp() {
+        let server = MockServer::start().await;
+        let verbose = serde_json::json!({
+            "task": "translate", "language": "french", "duration": 4.2, "text": " Hello.",
+            "segments": [{"id": 0, "start": 0.0, "end": 4.2, "text": " Hello."}],
+        });
+        Mock::given(method("POST"))
+            .and(path("/v1/audio/translations"))
+            .and(header("authorization", "Bearer key"))
+            .and(body_string_contains("name=\"model\"\r\n\r\nwhisper-1"))
+            .and(body_string_contains("filename=\"call.mp3\""))
+            .respond_with(ResponseTemplate::new(200).set_body_json(verbose.clone()))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/inference"))
+            .and(body_string_contains("name=\"translate\"\r\n\r\nfalse"))
+            .and(body_string_contains("name=\"language\"\r\n\r\nauto"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(verbose))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let openai = endpoint("openai", format!("{}/v1", server.uri()));
+        let transcript = SttAdapter::OpenAI.transcribe(&client, &openai, Task::Translate, Some("whisper-1"), &request()).await.unwrap();
+        assert_eq!(transcript.text, "Hello.");
+        assert_eq!(transcript.duration, Some(4.2));
+        assert_eq!(transcript.segments[0].end, 4.2);
+
+        let local = endpoint("whisper_cpp", server.uri());
+        let transcript = SttAdapter::WhisperCpp.transcribe(&client, &local, Task::Transcribe, None, &request()).await.unwrap();
+        assert_eq!(transcript.language.as_deref(), Some("french"));
+
+        let deepgram = endpoint("deepgram", server.uri());
+        let err = SttAdapter::Deepgram.transcribe(&client, &deepgram, Task::Translate, None, &request()).await.unwrap_err();
+        assert!(matches!(err, SynapseError::Provider(ProviderError::Unsupported(_))));
+    }
+
+    #[tokio::test]
+    async fn test_deepgram() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/listen"))
+            .and(query_param("model", "nova-3"))
+            .and(query_param("detect_language", "true"))
+            .and(query_param("diarize", "true"))
+            .and(header("authorization", "Token key"))
+            .and(header("content-type", "audio/mpeg"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "metadata": {"duration": 7.5},
+                "results": {
+                    "channels": [{"detected_language": "en", "alternatives": [{"transcript": "Hi. Hello."}]}],
+                    "utterances": [
+                        {"start": 0.1, "end": 0.6, "transcript": "Hi.", "speaker": 0},
+                        {"start": 1.0, "end": 1.8, "transcript": "Hello.", "speaker": 1},
+                    ],
+                },
+            })))
+            .mount(&server)
+            .await;
+
+        let request = TranscriptionRequest { diarize: true, ..request() };
+        let endpoint = endpoint("deepgram", format!("{}/v1", server.uri()));
+        let transcript = SttAdapter::Deepgram
+            .transcribe(&reqwest::Client::new(), &endpoint, Task::Transcribe, None, &request)
+            .await
+            .unwrap();
+        assert_eq!(transcript.text, "Hi. Hello.");
+        assert_eq!(transcript.language.as_deref(), Some("en"));
+        assert_eq!(transcript.duration, Some(7.5));
+        assert_eq!(transcript.segments[1].speaker.as_deref(), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_assemblyai() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v2/upload"))
+            .and(header("authorization", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"upload_url": "https://cdn.example.com/a1"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v2/transcript"))
+            .and(body_partial_json(serde_json::json!({
+                "audio_url": "https://cdn.example.com/a1",
+                "speech_model": "nano",
+                "language_code": "en",
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"id": "t1", "status": "queued"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/transcript/t1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "id": "t1", "status": "completed", "text": "Hi. Bye.", "audio_duration": 12, "language_code": "en",
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v2/transcript/t1/sentences"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "sentences": [{"text": "Hi.", "start": 250, "end": 900}, {"text": "Bye.", "start": 11000, "end": 11800}],
+            })))
+            .mount(&server)
+            .await;
+
+        let request = TranscriptionRequest { language: Some("en".to_string()), ..request() };
+        let endpoint = endpoint("assemblyai", format!("{}/v2", server.uri()));
+        let transcript = SttAdapter::AssemblyAI
+            .transcribe(&reqwest::Client::new(), &endpoint, Task::Transcribe, Some("nano"), &request)
+            .await
+            .unwrap();
+        assert_eq!(transcript.duration, Some(12.0));
+        assert_eq!(transcript.segments.len(), 2);
+        assert_eq!(transcript.segments[0].start, 0.25);
+        assert_eq!(transcript.segments[1].end, 11.8);
+    }
+}
@@ -0,0 +1,529 @@
+//! Text-to-speech adapters for OpenAI, ElevenLabs and Azure Speech
+//!
+//! Requests follow OpenAI's `POST /audio/speech` shape. Each adapter
+//! translates it to its provider's API and returns the provider's response
+//! unread, so the audio can be streamed to the client as it arrives.
+
+use serde::{Deserialize, Serialize};
+
+use super::VoiceEndpoint;
+use crate::db::{NewTtsVoice, TtsVoiceRow};
+use crate::error::{ProviderError, Result, SynapseError};
+use crate::providers::streaming;
+
+/// Longest input accepted, matching OpenAI's limit
+pub const MAX_INPUT_CHARS: usize = 4096;
+
+/// `provider_type`s with an adapter
+pub const TTS_PROVIDERS: &[&str] = &["openai", "elevenlabs", "azure"];
+
+/// TTS is priced per million characters
+pub const PRICE_UNIT: f64 = 1_000_000.0;
+
+const OPENAI_DEFAULT_MODEL: &str = "tts-1";
+const ELEVENLABS_DEFAULT_MODEL: &str = "eleven_multilingual_v2";
+
+/// OpenAI's built-in voices; it has no endpoint listing them
+const OPENAI_VOICES: &[&str] = &["alloy", "ash", "coral", "echo", "fable", "onyx", "nova", "sage", "shimmer"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    #[default]
+    Mp3,
+    Opus,
+    Aac,
+    Flac,
+    Wav,
+    Pcm,
+}
+
+impl AudioFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Aac => "aac",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Wav => "wav",
+            AudioFormat::Pcm => "pcm",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "audio/mpeg",
+            AudioFormat::Opus => "audio/ogg",
+            AudioFormat::Aac => "audio/aac",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Pcm => "audio/pcm",
+        }
+    }
+}
+
+/// `POST /v1/audio/speech` request
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpeechRequest {
+    /// Provider model, e.g. `tts-1` or `eleven_turbo_v2_5`; each provider
+    /// has a default
+    #[serde(default)]
+    pub model: Option<String>,
+    pub input: String,
+    /// Provider voice id or catalog id from `GET /v1/audio/voices`
+    pub voice: String,
+    #[serde(default)]
+    pub response_format: AudioFormat,
+    /// 0.25 to 4.0, default 1.0
+    #[serde(default)]
+    pub speed: Option<f64>,
+    /// Provider id or type; by default the provider of `voice`, else the
+    /// default provider
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default, alias = "user_id")]
+    pub user: Option<String>,
+}
+
+impl SpeechRequest {
+    pub fn validate(&self) -> Result<()> {
+        let characters = self.input.chars().count();
+        if self.input.trim().is_empty() {
+            return Err(SynapseError::Validation("input must not be empty".to_string()));
+        }
+        if characters > MAX_INPUT_CHARS {
+            return Err(SynapseError::Validation(format!(
+                "input is {} characters, the limit is {}",
+                characters, MAX_INPUT_CHARS
+            )));
+        }
+        if self.voice.trim().is_empty() {
+            return Err(SynapseError::Validation("voice must not be empty".to_string()));
+        }
+        if let Some(speed) = self.speed {
+            if !(0.25..=4.0).contains(&speed) {
+                return Err(SynapseError::Validation("speed must be between 0.25 and 4.0".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Billed characters
+    pub fn characters(&self) -> u64 {
+        self.input.chars().count() as u64
+    }
+}
+
+/// A voice in the catalog
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Voice {
+    pub id: String,
+    pub provider_id: String,
+    /// Id to pass as `voice` in speech requests
+    pub voice_id: String,
+    pub name: String,
+    pub language: String,
+    pub gender: Option<String>,
+    pub age: Option<String>,
+    pub style: Option<String>,
+    pub description: Option<String>,
+    pub preview_url: Option<String>,
+    pub quality_tier: String,
+    pub usage_count: i64,
+    pub is_custom: bool,
+}
+
+impl From<TtsVoiceRow> for Voice {
+    fn from(row: TtsVoiceRow) -> Self {
+        Self {
+            id: row.id,
+            provider_id: row.provider_id,
+            voice_id: row.voice_id,
+            name: row.name,
+            language: row.language,
+            gender: row.gender,
+            age: row.age,
+            style: row.style,
+            description: row.description,
+            preview_url: row.preview_url,
+            quality_tier: row.quality_tier,
+            usage_count: row.usage_count,
+            is_custom: row.is_custom,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TtsAdapter {
+    OpenAI,
+    ElevenLabs,
+    Azure,
+}
+
+impl TtsAdapter {
+    pub fn for_type(provider_type: &str) -> Option<Self> {
+        match provider_type {
+            "openai" => Some(TtsAdapter::OpenAI),
+            "elevenlabs" => Some(TtsAdapter::ElevenLabs),
+            "azure" => Some(TtsAdapter::Azure),
+            _ => None,
+        }
+    }
+
+    /// Model used when the request names none
+    pub fn default_model(&self) -> Option<&'static str> {
+        match self {
+            TtsAdapter::OpenAI => Some(OPENAI_DEFAULT_MODEL),
+            TtsAdapter::ElevenLabs => Some(ELEVENLABS_DEFAULT_MODEL),
+            TtsAdapter::Azure => None,
+        }
+    }
+
+    /// Start synthesis, returning the response whose body is the audio
+    pub async fn synthesize(
+        &self,
+        client: &reqwest::Client,
+        endpoint: &VoiceEndpoint,
+        voice: &str,
+        request: &SpeechRequest,
+    ) -> Result<reqwest::Response> {
+        let model = request.model.as_deref().or(self.default_model());
+        let builder = match self {
+            TtsAdapter::OpenAI => client
+                .post(format!("{}/audio/speech", endpoint.base_url))
+                .bearer_auth(&endpoint.api_key)
+                .json(&serde_json::json!({
+                    "model": model,
+                    "input": request.input,
+                    "voice": voice,
+                    "response_format": request.response_format,
+                    "speed": request.speed.unwrap_or(1.0),
+                })),
+            TtsAdapter::ElevenLabs => {
+                let mut body = serde_json::json!({"text": request.input, "model_id": model});
+                if let Some(speed) = request.speed {
+                    // ElevenLabs accepts 0.7 to 1.2
+                    body["voice_settings"] = serde_json::json!({"speed": speed.clamp(0.7, 1.2)});
+                }
+                client
+                    .post(format!("{}/text-to-speech/{}/stream", endpoint.base_url, voice))
+                    .query(&[("output_format", elevenlabs_format(request.response_format)?)])
+                    .header("xi-api-key", &endpoint.api_key)
+                    .json(&body)
+            }
+            TtsAdapter::Azure => client
+                .post(format!("{}/cognitiveservices/v1", azure_base_url(endpoint)?))
+                .header("Ocp-Apim-Subscription-Key", &endpoint.api_key)
+                .header("X-Microsoft-OutputFormat", azure_format(request.response_format)?)
+                .header(reqwest::header::CONTENT_TYPE, "application/ssml+xml")
+                .header(reqwest::header::USER_AGENT, "barq-hub")
+                .body(ssml(voice, &request.input, request.speed)),
+        };
+
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+        streaming::check_status(response).await
+    }
+
+    /// The provider's voice catalog
+    pub async fn voices(&self, client: &reqwest::Client, endpoint: &VoiceEndpoint) -> Result<Vec<NewTtsVoice>> {
+        let request = match self {
+            TtsAdapter::OpenAI => {
+                return Ok(OPENAI_VOICES
+                    .iter()
+                    .map(|voice| NewTtsVoice {
+                        voice_id: voice.to_string(),
+                        name: capitalize(voice),
+                        language: "en-US".to_string(),
+                        quality_tier: "neural".to_string(),
+                        ..Default::default()
+                    })
+                    .collect());
+            }
+            TtsAdapter::ElevenLabs => client
+                .get(format!("{}/voices", endpoint.base_url))
+                .header("xi-api-key", &endpoint.api_key),
+            TtsAdapter::Azure => client
+                .get(format!("{}/cognitiveservices/voices/list", azure_base_url(endpoint)?))
+                .header("Ocp-Apim-Subscription-Key", &endpoint.api_key),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::Network(e.to_string())))?;
+        let body: serde_json::Value = streaming::check_status(response)
+            .await?
+            .json()
+            .await
+            .map_err(|e| SynapseError::Provider(ProviderError::InvalidResponse(e.to_string())))?;
+
+        let voices = match self {
+            TtsAdapter::ElevenLabs => body["voices"].as_array(),
+            _ => body.as_array(),
+        }
+        .ok_or_else(|| SynapseError::Provider(ProviderError::InvalidResponse("voice list is not an array".to_string())))?;
+
+        Ok(voices
+            .iter()
+            .filter_map(|voice| match self {
+                TtsAdapter::ElevenLabs => elevenlabs_voice(voice),
+                _ => azure_voice(voice),
+            })
+            .collect())
+    }
+}
+
+fn elevenlabs_format(format: AudioFormat) -> Result<&'static str> {
+    match format {
+        AudioFormat::Mp3 => Ok("mp3_44100_128"),
+        AudioFormat::Opus => Ok("opus_48000_64"),
+        AudioFormat::Pcm => Ok("pcm_24000"),
+        format => Err(unsupported_format("ElevenLabs", format)),
+    }
+}
+
+fn azure_format(format: AudioFormat) -> Result<&'static str> {
+    match format {
+        AudioFormat::Mp3 => Ok("audio-24khz-48kbitrate-mono-mp3"),
+        AudioFormat::Opus => Ok("ogg-24khz-16bit-mono-opus"),
+        AudioFormat::Wav => Ok("riff-24khz-16bit-mono-pcm"),
+        AudioFormat::Pcm => Ok("raw-24khz-16bit-mono-pcm"),
+        format => Err(unsupported_format("Azure Speech", format)),
+    }
+}
+
+fn unsupported_format(provider: &str, format: AudioFormat) -> SynapseError {
+    SynapseError::Provider(ProviderError::Unsupported(format!("{} cannot produce {} audio", provider, format.as_str())))
+}
+
+/// Azure URLs are per region, so the account must fill in `{region}`
+fn azure_base_url(endpoint: &VoiceEndpoint) -> Result<&str> {
+    if endpoint.base_url.contains("{region}") {
+        return Err(SynapseError::Validation("Azure Speech accounts need a region or an endpoint".to_string()));
+    }
+    Ok(&endpoint.base_url)
+}
+
+/// SSML for one Azure voice; the language comes from the voice name,
+/// e.g. `en-US` from `en-US-JennyNeural`
+fn ssml(voice: &str, text: &str, speed: Option<f64>) -> String {
+    let language = voice.splitn(3, '-').take(2).collect::<Vec<_>>().join("-");
+    let rate = speed.map(|speed| format!("{:+.0}%", (speed - 1.0) * 100.0)).unwrap_or_else(|| "+0%".to_string());
+    format!(
+        "<speak version='1.0' xmlns='http://www.w3.org/2001/10/synthesis' xml:lang='{}'><voice name='{}'><prosody rate='{}'>{}</prosody></voice></speak>",
+        xml_escape(&language),
+        xml_escape(voice),
+        rate,
+        xml_escape(text),
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\'', "&apos;")
+        .replace('"', "&quot;")
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// `tts_voices.language` holds at most 10 characters
+fn language(value: Option<&str>, fallback: &str) -> String {
+    value.filter(|value| !value.is_empty()).unwrap_or(fallback).chars().take(10).collect()
+}
+
+fn elevenlabs_voice(voice: &serde_json::Value) -> Option<NewTtsVoice> {
+    let labels = &voice["labels"];
+    let label = |key: &str| labels[key].as_str().filter(|value| !value.is_empty()).map(str::to_string);
+    Some(NewTtsVoice {
+        voice_id: voice["voice_id"].as_str()?.to_string(),
+        name: voice["name"].as_str()?.to_string(),
+        language: language(labels["language"].as_str(), "en"),
+        gender: label("gender"),
+        age: label("age"),
+        description: voice["description"].as_str().or(labels["description"].as_str()).map(str::to_string),
+        preview_url: voice["preview_url"].as_str().map(str::to_string),
+        quality_tier: "premium".to_string(),
+        is_custom: voice["category"].as_str().is_some_and(|category| category != "premade"),
+    })
+}
+
+fn azure_voice(voice: &serde_json::Value) -> Option<NewTtsVoice> {
+    Some(NewTtsVoice {
+        voice_id: voice["ShortName"].as_str()?.to_string(),
+        name: voice["DisplayName"].as_str().or(voice["LocalName"].as_str())?.to_string(),
+        language: language(voice["Locale"].as_str(), "en-US"),
+        gender: voice["Gender"].as_str().map(str::to_lowercase),
+        quality_tier: match voice["VoiceType"].as_str() {
+            Some("Neural") => "neural".to_string(),
+            _ => "standard".to_string(),
+        },
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn endpoint(provider_type: &str, base_url: String) -> VoiceEndpoint {
+        VoiceEndpoint {
+            provider_type: provider_type.to_string(),
+            base_url,
+            api_key: "key".to_string(),
+        }
+    }
+
+    fn request(format: AudioFormat, speed: Option<f64>) -> SpeechRequest {
+        SpeechRequest {
+            model: None,
+            input: "Refunds take <5> days & more".to_string(),
+            voice: "ignored".to_string(),
+            response_format: format,
+            speed,
+            provider: None,
+            user: None,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(request(AudioFormat::Mp3, Some(1.5)).validate().is_ok());
+        assert!(request(AudioFormat::Mp3, Some(5.0)).validate().is_err());
+        let mut long = request(AudioFormat::Mp3, None);
+        long.input = "é".repeat(MAX_INPUT_CHARS);
+        assert!(long.validate().is_ok());
+        assert_eq!(long.characters(), MAX_INPUT_CHARS as u64);
+        long.input.push('a');
+        assert!(long.validate().is_err());
+
+        let parsed: SpeechRequest = serde_json::from_value(serde_json::json!({
+            "input": "hi", "voice": "alloy", "response_format": "wav", "user_id": "u1",
+        }))
+        .unwrap();
+        assert_eq!(parsed.response_format, AudioFormat::Wav);
+        assert_eq!(parsed.user.as_deref(), Some("u1"));
+        assert!(serde_json::from_value::<SpeechRequest>(serde_json::json!({
+            "input": "hi", "voice": "alloy", "response_format": "ogg",
+        }))
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_synthesize() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/audio/speech"))
+            .and(header("authorization", "Bearer key"))
+            .and(body_partial_json(serde_json::json!({"model": "tts-1", "voice": "nova", "response_format": "opus"})))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"openai-audio".to_vec()))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/el/text-to-speech/v123/stream"))
+            .and(query_param("output_format", "pcm_24000"))
+            .and(header("xi-api-key", "key"))
+            .and(body_partial_json(serde_json::json!({
+                "model_id": "eleven_multilingual_v2",
+                "voice_settings": {"speed": 1.2},
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"elevenlabs-audio".to_vec()))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/cognitiveservices/v1"))
+            .and(header("ocp-apim-subscription-key", "key"))
+            .and(header("x-microsoft-outputformat", "riff-24khz-16bit-mono-pcm"))
+            .and(body_string_contains("xml:lang='en-GB'><voice name='en-GB-SoniaNeural'><prosody rate='-50%'>Refunds take &lt;5&gt; days &amp; more<"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"azure-audio".to_vec()))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let openai = endpoint("openai", format!("{}/v1", server.uri()));
+        let response = TtsAdapter::OpenAI.synthesize(&client, &openai, "nova", &request(AudioFormat::Opus, None)).await.unwrap();
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"openai-audio");
+
+        let elevenlabs = endpoint("elevenlabs", format!("{}/el", server.uri()));
+        let response = TtsAdapter::ElevenLabs
+            .synthesize(&client, &elevenlabs, "v123", &request(AudioFormat::Pcm, Some(2.0)))
+            .await
+            .unwrap();
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"elevenlabs-audio");
+        let err = TtsAdapter::ElevenLabs
+            .synthesize(&client, &elevenlabs, "v123", &request(AudioFormat::Flac, None))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SynapseError::Provider(ProviderError::Unsupported(_))));
+
+        let azure = endpoint("azure", server.uri());
+        let response = TtsAdapter::Azure
+            .synthesize(&client, &azure, "en-GB-SoniaNeural", &request(AudioFormat::Wav, Some(0.5)))
+            .await
+            .unwrap();
+        assert_eq!(response.bytes().await.unwrap().as_ref(), b"azure-audio");
+        let unresolved = endpoint("azure", "https://{region}.tts.speech.microsoft.com".to_string());
+        assert!(TtsAdapter::Azure.synthesize(&client, &unresolved, "en-GB-SoniaNeural", &request(AudioFormat::Mp3, None)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_voices() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/el/voices"))
+            .and(header("xi-api-key", "key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "voices": [
+                    {
+                        "voice_id": "21m00", "name": "Rachel", "category": "premade",
+                        "labels": {"gender": "female", "age": "young", "accent": "american"},
+                        "preview_url": "https://example.com/rachel.mp3",
+                    },
+                    {"voice_id": "c10n", "name": "My Clone", "category": "cloned", "labels": {"language": "ar"}},
+                    {"name": "missing id"},
+                ],
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/cognitiveservices/voices/list"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ShortName": "en-US-JennyNeural", "DisplayName": "Jenny", "Locale": "en-US", "Gender": "Female", "VoiceType": "Neural"},
+            ])))
+            .mount(&server)
+            .await;
+
+        let client = reqwest::Client::new();
+        let voices = TtsAdapter::ElevenLabs.voices(&client, &endpoint("elevenlabs", format!("{}/el", server.uri()))).await.unwrap();
+        assert_eq!(voices.len(), 2);
+        assert_eq!(voices[0].voice_id, "21m00");
+        assert_eq!(voices[0].language, "en");
+        assert_eq!(voices[0].gender.as_deref(), Some("female"));
+        assert!(!voices[0].is_custom);
+        assert_eq!(voices[1].language, "ar");
+        assert!(voices[1].is_custom);
+
+        let voices = TtsAdapter::Azure.voices(&client, &endpoint("azure", server.uri())).await.unwrap();
+        assert_eq!(voices, vec![NewTtsVoice {
+            voice_id: "en-US-JennyNeural".to_string(),
+            name: "Jenny".to_string(),
+            language: "en-US".to_string(),
+            gender: Some("female".to_string()),
+            quality_tier: "neural".to_string(),
+            ..Default::default()
+        }]);
+
+        let voices = TtsAdapter::OpenAI.voices(&client, &endpoint("openai", server.uri())).await.unwrap();
+        assert!(voices.iter().any(|voice| voice.voice_id == "alloy" && voice.name == "Alloy"));
+    }
+}
@@ -0,0 +1,757 @@
+//! Workflow executor
+//!
+//! Runs a definition in waves: every node whose predecessors have all
+//! finished runs, concurrently with the rest of its wave, so the branches
+//! after a fan-out proceed in parallel. A node runs when at least one
+//! incoming edge was taken; otherwise it is skipped, which in turn skips
+//! nodes only reachable through it. The first failing node fails the run
+//! once its retries are spent.
+//!
+//! A run can start from checkpoints of an earlier, interrupted run, and
+//! stops between waves when the recorder says the execution should no
+//! longer proceed. Approval nodes are not run: the executor records them as
+//! waiting, carries on with whatever does not depend on them and ends the
+//! run as waiting; a later run starts from the decision, checkpointed as
+//! the node's output.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::future::{join_all, BoxFuture};
+use serde_json::Value as JsonValue;
+
+use super::template::{evaluate, render, render_value};
+use super::{Node, NodeKind, WorkflowDefinition, DEFAULT_LOOP_ITEMS};
+use crate::error::{Result, SynapseError};
+use crate::knowledge::search::{SearchOptions, SearchResult};
+use crate::{ChatRequest, ChatResponse, Message};
+
+/// Timeout of HTTP nodes without their own `timeoutSecs`
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The hub services nodes call
+#[async_trait]
+pub trait WorkflowServices: Send + Sync {
+    /// Route a chat completion, recording its cost
+    async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse>;
+
+    async fn search_knowledge(
+        &self,
+        collection_id: &str,
+        query: &str,
+        options: &SearchOptions,
+        user_id: Option<String>,
+    ) -> Result<Vec<SearchResult>>;
+
+    fn http_client(&self) -> reqwest::Client;
+}
+
+/// Persists node states as an execution progresses
+#[async_trait]
+pub trait ExecutionRecorder: Send + Sync {
+    /// Record a node starting with its resolved input; returns the record id
+    async fn node_started(&self, node_id: &str, input: &JsonValue) -> Result<String>;
+
+    /// Record a started node's output or error
+    async fn node_finished(&self, record_id: &str, output: std::result::Result<&JsonValue, &str>) -> Result<()>;
+
+    async fn node_skipped(&self, node_id: &str) -> Result<()>;
+
+    /// Record an approval node waiting for a decision, with its resolved
+    /// input and how long to wait
+    async fn node_waiting(&self, node_id: &str, input: &JsonValue, timeout_secs: Option<u64>) -> Result<()>;
+
+    /// Whether to start another wave; false once the execution is paused or
+    /// cancelled
+    async fn proceed(&self) -> bool;
+}
+
+/// A node finished by an earlier run of the same execution
+#[derive(Debug, Clone, PartialEq)]
+pub enum Checkpoint {
+    Completed(JsonValue),
+    Skipped,
+    /// An approval node not decided yet
+    Waiting,
+}
+
+/// The node that stopped an execution
+#[derive(Debug)]
+pub struct NodeFailure {
+    pub node_id: String,
+    pub error: SynapseError,
+}
+
+impl std::fmt::Display for NodeFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Node {} failed: {}", self.node_id, self.error)
+    }
+}
+
+#[derive(Debug)]
+pub enum RunOutcome {
+    /// Outputs of the nodes nothing depends on, by node id
+    Completed(JsonValue),
+    Failed(NodeFailure),
+    /// Stopped between waves; finished nodes are checkpointed
+    Interrupted,
+    /// Nothing left to run until approval nodes are decided
+    Waiting {
+        /// Every approval node waiting
+        nodes: Vec<String>,
+        /// Those this run started waiting for, whose approvers are yet to
+        /// be told
+        requested: Vec<String>,
+    },
+}
+
+enum NodeState {
+    Pending,
+    Done(Option<String>),
+    Skipped,
+    Waiting,
+}
+
+pub struct Executor<'a> {
+    pub services: &'a dyn WorkflowServices,
+    pub recorder: &'a dyn ExecutionRecorder,
+    /// Charged for LLM and knowledge search costs
+    pub user_id: Option<String>,
+}
+
+impl Executor<'_> {
+    /// Run a validated definition, skipping the nodes in `checkpoints`
+    pub async fn run(
+        &self,
+        definition: &WorkflowDefinition,
+        variables: JsonValue,
+        checkpoints: &HashMap<String, Checkpoint>,
+    ) -> RunOutcome {
+        let mut context = serde_json::json!({"input": variables, "nodes": {}});
+        let mut states: HashMap<&str, NodeState> = HashMap::new();
+        for node in &definition.nodes {
+            let state = match checkpoints.get(&node.id) {
+                Some(Checkpoint::Completed(output)) => {
+                    context["nodes"][node.id.as_str()] = output.clone();
+                    NodeState::Done(node.kind.branch(output))
+                }
+                Some(Checkpoint::Skipped) => NodeState::Skipped,
+                Some(Checkpoint::Waiting) => NodeState::Waiting,
+                None => NodeState::Pending,
+            };
+            states.insert(&node.id, state);
+        }
+
+        let mut requested = Vec::new();
+        loop {
+            let mut wave = Vec::new();
+            let mut approvals = Vec::new();
+            let mut progressed = false;
+            for node in &definition.nodes {
+                if !matches!(states[node.id.as_str()], NodeState::Pending) {
+                    continue;
+                }
+                let incoming: Vec<_> = definition.edges.iter().filter(|e| e.to == node.id).collect();
+                if incoming.iter().any(|e| matches!(states[e.from.as_str()], NodeState::Pending | NodeState::Waiting)) {
+                    continue;
+                }
+                let taken = incoming.is_empty() || incoming.iter().any(|e| match states[e.from.as_str()] {
+                    // Only a rejected approval node takes the "rejected"
+                    // branch; its plain edges lead on from an approval
+                    NodeState::Done(ref branch) => match e.branch {
+                        Some(_) => e.branch == *branch,
+                        None => branch.as_deref() != Some("rejected"),
+                    },
+                    _ => false,
+                });
+                if taken && matches!(node.kind, NodeKind::Approval(_)) {
+                    approvals.push(node);
+                } else if taken {
+                    wave.push(node);
+                } else {
+                    if let Err(error) = self.recorder.node_skipped(&node.id).await {
+                        return RunOutcome::Failed(NodeFailure { node_id: node.id.clone(), error });
+                    }
+                    states.insert(&node.id, NodeState::Skipped);
+                    progressed = true;
+                }
+            }
+            if wave.is_empty() && approvals.is_empty() {
+                if progressed {
+                    continue;
+                }
+                break;
+            }
+            if !self.recorder.proceed().await {
+                return RunOutcome::Interrupted;
+            }
+
+            for node in approvals {
+                let waiting = match resolve(&node.kind, &context, &[]) {
+                    Ok(input) => self.recorder.node_waiting(&node.id, &input, node.timeout_secs).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(error) = waiting {
+                    return RunOutcome::Failed(NodeFailure { node_id: node.id.clone(), error });
+                }
+                states.insert(&node.id, NodeState::Waiting);
+                requested.push(node.id.clone());
+            }
+            if wave.is_empty() {
+                continue;
+            }
+
+            let steps = join_all(wave.iter().map(|node| self.run_node(node, definition, &context))).await;
+            let mut failure = None;
+            for (node, step) in wave.into_iter().zip(steps) {
+                match step {
+                    Ok(output) => {
+                        states.insert(&node.id, NodeState::Done(node.kind.branch(&output)));
+                        context["nodes"][node.id.as_str()] = output;
+                    }
+                    Err(error) => {
+                        failure.get_or_insert(NodeFailure { node_id: node.id.clone(), error });
+                    }
+                }
+            }
+            if let Some(failure) = failure {
+                return RunOutcome::Failed(failure);
+            }
+        }
+
+        let waiting: Vec<String> = definition.nodes
+            .iter()
+            .filter(|n| matches!(states[n.id.as_str()], NodeState::Waiting))
+            .map(|n| n.id.clone())
+            .collect();
+        if !waiting.is_empty() {
+            return RunOutcome::Waiting { nodes: waiting, requested };
+        }
+
+        let results: serde_json::Map<String, JsonValue> = definition.nodes
+            .iter()
+            .filter(|n| !definition.edges.iter().any(|e| e.from == n.id))
+            .filter_map(|n| context["nodes"].get(&n.id).map(|output| (n.id.clone(), output.clone())))
+            .collect();
+        RunOutcome::Completed(JsonValue::Object(results))
+    }
+
+    /// Run a node, retrying per its policy, and record each attempt
+    async fn run_node(&self, node: &Node, definition: &WorkflowDefinition, context: &JsonValue) -> Result<JsonValue> {
+        let predecessors: Vec<&str> = definition.edges.iter().filter(|e| e.to == node.id).map(|e| e.from.as_str()).collect();
+        let max_attempts = node.retry.as_ref().map_or(1, |r| r.max_attempts);
+        let mut attempt = 1;
+        loop {
+            let result = self.attempt(node, context, &predecessors).await;
+            match result {
+                Err(ref e) if attempt < max_attempts && !matches!(e, SynapseError::Validation(_)) => {
+                    let backoff = node.retry.as_ref().map(|r| r.backoff(attempt)).unwrap_or_default();
+                    tracing::debug!(node = %node.id, attempt, error = %e, "Retrying workflow node in {:?}", backoff);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn attempt(&self, node: &Node, context: &JsonValue, predecessors: &[&str]) -> Result<JsonValue> {
+        let input = resolve(&node.kind, context, predecessors);
+        let record = self.recorder.node_started(&node.id, input.as_ref().unwrap_or(&JsonValue::Null)).await?;
+
+        let output = match input {
+            Ok(input) => match node.timeout_secs {
+                Some(secs) => tokio::time::timeout(Duration::from_secs(secs), self.perform(&node.kind, input, context))
+                    .await
+                    .unwrap_or_else(|_| Err(SynapseError::Internal(format!("Timed out after {}s", secs)))),
+                None => self.perform(&node.kind, input, context).await,
+            },
+            Err(e) => Err(e),
+        };
+
+        match output {
+            Ok(ref output) => self.recorder.node_finished(&record, Ok(output)).await?,
+            Err(ref e) => self.recorder.node_finished(&record, Err(&e.to_string())).await?,
+        }
+        output
+    }
+
+    fn perform<'a>(&'a self, kind: &'a NodeKind, input: JsonValue, context: &'a JsonValue) -> BoxFuture<'a, Result<JsonValue>> {
+        Box::pin(async move {
+            let output = match kind {
+                NodeKind::Llm(node) => {
+                    let mut messages = Vec::new();
+                    if let Some(system) = input["system"].as_str() {
+                        messages.push(Message::system(system));
+                    }
+                    messages.push(Message::user(input["prompt"].as_str().unwrap_or_default()));
+                    let defaults = ChatRequest::default();
+                    let request = ChatRequest {
+                        model: node.model.clone(),
+                        provider: node.provider.clone(),
+                        messages,
+                        temperature: node.temperature.unwrap_or(defaults.temperature),
+                        max_tokens: node.max_tokens.unwrap_or(defaults.max_tokens),
+                        user_id: self.user_id.clone(),
+                        ..defaults
+                    };
+                    let response = self.services.chat(&request).await?;
+                    let text = response.choices.first().map(|c| c.message.content.text()).unwrap_or_default();
+                    let mut output = serde_json::json!({
+                        "text": text,
+                        "provider": response.provider,
+                        "model": response.model,
+                        "usage": response.usage,
+                        "cost": response.cost,
+                    });
+                    if node.json {
+                        output["json"] = parse_json_reply(&text)?;
+                    }
+                    output
+                }
+                NodeKind::Template(_) | NodeKind::Transform(_) | NodeKind::Join => input,
+                NodeKind::Parallel => serde_json::json!({}),
+                NodeKind::Approval(_) => {
+                    return Err(SynapseError::Validation("Approval nodes wait for a decision instead of running".to_string()));
+                }
+                NodeKind::Condition(node) => serde_json::json!({"result": evaluate(&node.expression, context)?}),
+                NodeKind::Loop(node) => {
+                    let items = input["items"].as_array().cloned().unwrap_or_default();
+                    let mut outputs = Vec::with_capacity(items.len());
+                    for (index, item) in items.into_iter().enumerate() {
+                        let mut scope = context.clone();
+                        scope["item"] = item;
+                        scope["index"] = index.into();
+                        let body_input = resolve(&node.body, &scope, &[])?;
+                        outputs.push(self.perform(&node.body, body_input, &scope).await?);
+                    }
+                    serde_json::json!({"items": outputs})
+                }
+                NodeKind::Http(_) => self.http(&input).await?,
+                NodeKind::KnowledgeSearch(node) => {
+                    let results = self.services.search_knowledge(
+                        input["collectionId"].as_str().unwrap_or_default(),
+                        input["query"].as_str().unwrap_or_default(),
+                        &node.search,
+                        self.user_id.clone(),
+                    ).await?;
+                    let text = results.iter().map(|r| r.content.as_str()).collect::<Vec<_>>().join("\n\n");
+                    serde_json::json!({"results": results, "text": text})
+                }
+            };
+            Ok(output)
+        })
+    }
+
+    async fn http(&self, input: &JsonValue) -> Result<JsonValue> {
+        let method = input["method"].as_str().unwrap_or("GET");
+        let url = input["url"].as_str().unwrap_or_default();
+        let failed = |message: String| SynapseError::Internal(format!("{} {}: {}", method, url, message));
+
+        let method = reqwest::Method::from_bytes(method.as_bytes()).map_err(|e| failed(e.to_string()))?;
+        let mut request = self.services.http_client().request(method, url).timeout(HTTP_TIMEOUT);
+        for (name, value) in input["headers"].as_object().into_iter().flatten() {
+            request = request.header(name.as_str(), value.as_str().unwrap_or_default());
+        }
+        if !input["body"].is_null() {
+            request = request.json(&input["body"]);
+        }
+
+        let response = request.send().await.map_err(|e| failed(e.to_string()))?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| failed(e.to_string()))?;
+        if !status.is_success() {
+            return Err(failed(format!("returned {}: {}", status, text.chars().take(500).collect::<String>())));
+        }
+        let body = serde_json::from_str(&text).unwrap_or(JsonValue::String(text));
+        Ok(serde_json::json!({"status": status.as_u16(), "body": body}))
+    }
+}
+
+/// A node's settings with templates rendered against the context
+fn resolve(kind: &NodeKind, context: &JsonValue, predecessors: &[&str]) -> Result<JsonValue> {
+    let input = match kind {
+        NodeKind::Llm(node) => serde_json::json!({
+            "provider": node.provider,
+            "model": node.model,
+            "system": node.system.as_deref().map(|s| render(s, context)),
+            "prompt": render(&node.prompt, context),
+        }),
+        NodeKind::Template(node) => serde_json::json!({"text": render(&node.template, context)}),
+        NodeKind::Condition(node) => serde_json::json!({"expression": node.expression}),
+        NodeKind::Loop(node) => {
+            let items = render_value(&JsonValue::String(node.items.clone()), context);
+            let JsonValue::Array(items) = items else {
+                return Err(SynapseError::Validation(format!("Loop items must be an array, got {}", items)));
+            };
+            let limit = node.max_items.unwrap_or(DEFAULT_LOOP_ITEMS);
+            if items.len() > limit {
+                return Err(SynapseError::Validation(format!("Loop has {} items, more than its limit of {}", items.len(), limit)));
+            }
+            serde_json::json!({"items": items})
+        }
+        NodeKind::Http(node) => serde_json::json!({
+            "method": node.method.to_uppercase(),
+            "url": render(&node.url, context),
+            "headers": node.headers.iter().map(|(k, v)| (k.clone(), render(v, context))).collect::<HashMap<_, _>>(),
+            "body": node.body.as_ref().map(|b| render_value(b, context)),
+        }),
+        NodeKind::Transform(node) => render_value(&node.output, context),
+        NodeKind::KnowledgeSearch(node) => serde_json::json!({
+            "collectionId": render(&node.collection_id, context),
+            "query": render(&node.query, context),
+        }),
+        NodeKind::Approval(node) => serde_json::json!({
+            "message": render(&node.message, context),
+            "permission": node.permission,
+            "notify": node.notify,
+            "onTimeout": node.on_timeout,
+        }),
+        NodeKind::Parallel => serde_json::json!({}),
+        NodeKind::Join => JsonValue::Object(
+            predecessors
+                .iter()
+                .filter_map(|id| context["nodes"].get(*id).map(|output| (id.to_string(), output.clone())))
+                .collect(),
+        ),
+    };
+    Ok(input)
+}
+
+/// Parse a model reply as JSON, tolerating a Markdown code fence
+fn parse_json_reply(text: &str) -> Result<JsonValue> {
+    let trimmed = text.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|t| t.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim())
+        .map_err(|e| SynapseError::Validation(format!("Reply is not valid JSON: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    /// Echoes prompts, counting chat calls
+    #[derive(Default)]
+    struct FakeServices(AtomicUsize);
+
+    #[async_trait]
+    impl WorkflowServices for FakeServices {
+        async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let prompt = request.messages.last().unwrap().content.text();
+            Ok(ChatResponse {
+                id: "r1".to_string(),
+                provider: "fake".to_string(),
+                model: request.model.clone(),
+                choices: vec![crate::Choice {
+                    index: 0,
+                    message: Message::assistant(format!("echo: {}", prompt)),
+                    finish_reason: "stop".to_string(),
+                    logprobs: None,
+                }],
+                usage: Default::default(),
+                created: chrono::Utc::now(),
+                latency_ms: 1,
+                cost: 0.01,
+                system_fingerprint: None,
+            })
+        }
+
+        async fn search_knowledge(&self, _: &str, _: &str, _: &SearchOptions, _: Option<String>) -> Result<Vec<SearchResult>> {
+            Err(SynapseError::NotFound("Collection".to_string()))
+        }
+
+        fn http_client(&self) -> reqwest::Client {
+            reqwest::Client::new()
+        }
+    }
+
+    /// Node ids with each recorded status, in order; stops the run after
+    /// `waves` waves when set
+    #[derive(Default)]
+    struct Log {
+        entries: Mutex<Vec<(String, String)>>,
+        waves: Mutex<Option<usize>>,
+    }
+
+    #[async_trait]
+    impl ExecutionRecorder for Log {
+        async fn node_started(&self, node_id: &str, _: &JsonValue) -> Result<String> {
+            Ok(node_id.to_string())
+        }
+
+        async fn node_finished(&self, record_id: &str, output: std::result::Result<&JsonValue, &str>) -> Result<()> {
+            let status = if output.is_ok() { "completed" } else { "failed" };
+            self.entries.lock().unwrap().push((record_id.to_string(), status.to_string()));
+            Ok(())
+        }
+
+        async fn node_skipped(&self, node_id: &str) -> Result<()> {
+            self.entries.lock().unwrap().push((node_id.to_string(), "skipped".to_string()));
+            Ok(())
+        }
+
+        async fn node_waiting(&self, node_id: &str, input: &JsonValue, _: Option<u64>) -> Result<()> {
+            let status = format!("waiting: {}", input["message"].as_str().unwrap_or_default());
+            self.entries.lock().unwrap().push((node_id.to_string(), status));
+            Ok(())
+        }
+
+        async fn proceed(&self) -> bool {
+            match *self.waves.lock().unwrap() {
+                Some(0) => false,
+                Some(ref mut waves) => {
+                    *waves -= 1;
+                    true
+                }
+                None => true,
+            }
+        }
+    }
+
+    impl Log {
+        fn status(&self, node_id: &str) -> Option<String> {
+            self.statuses(node_id).pop()
+        }
+
+        fn statuses(&self, node_id: &str) -> Vec<String> {
+            self.entries.lock().unwrap().iter().filter(|(id, _)| id == node_id).map(|(_, status)| status.clone()).collect()
+        }
+    }
+
+    fn completed(outcome: RunOutcome) -> JsonValue {
+        match outcome {
+            RunOutcome::Completed(results) => results,
+            other => panic!("expected a completed run, got {:?}", other),
+        }
+    }
+
+    fn definition(nodes: JsonValue, edges: JsonValue) -> WorkflowDefinition {
+        let definition = WorkflowDefinition::from_json(nodes, edges).unwrap();
+        definition.validate().unwrap();
+        definition
+    }
+
+    #[tokio::test]
+    async fn test_branches_and_join() {
+        let definition = definition(
+            serde_json::json!([
+                {"id": "check", "type": "condition", "expression": "input.amount > 100"},
+                {"id": "review", "type": "llm", "model": "m", "prompt": "Review {{input.amount}}"},
+                {"id": "approve", "type": "template", "template": "auto-approved"},
+                {"id": "notify", "type": "template", "template": "notify about {{input.amount}}"},
+                {"id": "fanout", "type": "parallel"},
+                {"id": "done", "type": "join"},
+            ]),
+            serde_json::json!([
+                {"from": "check", "to": "review", "branch": "true"},
+                {"from": "check", "to": "approve", "branch": "false"},
+                {"from": "approve", "to": "notify"},
+                {"from": "review", "to": "fanout"},
+                {"from": "notify", "to": "done"},
+                {"from": "fanout", "to": "done"},
+            ]),
+        );
+        let log = Log::default();
+        let services = FakeServices::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+
+        let results = completed(executor.run(&definition, serde_json::json!({"amount": 250}), &HashMap::new()).await);
+        assert_eq!(log.status("approve").as_deref(), Some("skipped"));
+        assert_eq!(log.status("notify").as_deref(), Some("skipped"));
+        assert_eq!(results["done"]["fanout"], serde_json::json!({}));
+        assert!(results["done"].get("notify").is_none());
+
+        let log = Log::default();
+        let services = FakeServices::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+        let results = completed(executor.run(&definition, serde_json::json!({"amount": 50}), &HashMap::new()).await);
+        assert_eq!(log.status("review").as_deref(), Some("skipped"));
+        assert_eq!(log.status("fanout").as_deref(), Some("skipped"));
+        assert_eq!(results["done"]["notify"]["text"], "notify about 50");
+    }
+
+    #[tokio::test]
+    async fn test_variables_and_loop() {
+        let definition = definition(
+            serde_json::json!([
+                {"id": "each", "type": "loop", "items": "{{input.names}}",
+                    "body": {"type": "llm", "model": "m", "prompt": "Greet {{item}} ({{index}})"}},
+                {"id": "shape", "type": "transform", "output": {"first": "{{nodes.each.items.0.text}}", "all": "{{nodes.each.items}}"}},
+            ]),
+            serde_json::json!([{"from": "each", "to": "shape"}]),
+        );
+        let log = Log::default();
+        let services = FakeServices::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+
+        let results = completed(executor.run(&definition, serde_json::json!({"names": ["Ada", "Lin"]}), &HashMap::new()).await);
+        assert_eq!(results["shape"]["first"], "echo: Greet Ada (0)");
+        assert_eq!(results["shape"]["all"][1]["text"], "echo: Greet Lin (1)");
+        assert_eq!(results.as_object().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failure_stops_run() {
+        let definition = definition(
+            serde_json::json!([
+                {"id": "search", "type": "knowledge_search", "collectionId": "kb", "query": "{{input.q}}"},
+                {"id": "answer", "type": "llm", "model": "m", "prompt": "{{nodes.search.text}}"},
+            ]),
+            serde_json::json!([{"from": "search", "to": "answer"}]),
+        );
+        let log = Log::default();
+        let services = FakeServices::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+
+        let RunOutcome::Failed(failure) = executor.run(&definition, serde_json::json!({"q": "refunds"}), &HashMap::new()).await else {
+            panic!("expected the search node to fail");
+        };
+        assert_eq!(failure.node_id, "search");
+        assert_eq!(log.status("search").as_deref(), Some("failed"));
+        assert_eq!(log.status("answer"), None);
+    }
+
+    #[tokio::test]
+    async fn test_http_node() {
+        use wiremock::matchers::{body_json, header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/tickets"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/tickets"))
+            .and(header("x-team", "support"))
+            .and(body_json(serde_json::json!({"subject": "Refund", "priority": 2})))
+            .respond_with(ResponseTemplate::new(201).set_body_json(serde_json::json!({"id": "T-1"})))
+            .mount(&server)
+            .await;
+
+        let definition = definition(
+            serde_json::json!([{
+                "id": "ticket", "type": "http", "method": "post", "url": format!("{}/tickets", server.uri()),
+                "headers": {"x-team": "{{input.team}}"},
+                "body": {"subject": "{{input.subject}}", "priority": "{{input.priority}}"},
+                "retry": {"maxAttempts": 3, "backoffMs": 1},
+            }]),
+            serde_json::json!([]),
+        );
+        let log = Log::default();
+        let services = FakeServices::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+
+        let variables = serde_json::json!({"team": "support", "subject": "Refund", "priority": 2});
+        let results = completed(executor.run(&definition, variables, &HashMap::new()).await);
+        assert_eq!(results["ticket"], serde_json::json!({"status": 201, "body": {"id": "T-1"}}));
+        assert_eq!(log.statuses("ticket"), ["failed", "failed", "completed"]);
+        assert_eq!(parse_json_reply("```json\n{\"a\": 1}\n```").unwrap(), serde_json::json!({"a": 1}));
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_checkpoint() {
+        let definition = definition(
+            serde_json::json!([
+                {"id": "check", "type": "condition", "expression": "input.draft"},
+                {"id": "draft", "type": "llm", "model": "m", "prompt": "Draft for {{input.name}}"},
+                {"id": "polish", "type": "llm", "model": "m", "prompt": "Polish: {{nodes.draft.text}}"},
+            ]),
+            serde_json::json!([
+                {"from": "check", "to": "draft", "branch": "true"},
+                {"from": "draft", "to": "polish"},
+            ]),
+        );
+        let variables = serde_json::json!({"draft": true, "name": "Ada"});
+        let services = FakeServices::default();
+
+        // Paused after two waves: check and draft are done
+        let log = Log { waves: Mutex::new(Some(2)), ..Default::default() };
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+        assert!(matches!(executor.run(&definition, variables.clone(), &HashMap::new()).await, RunOutcome::Interrupted));
+        assert_eq!(log.status("draft").as_deref(), Some("completed"));
+        assert_eq!(log.status("polish"), None);
+
+        let checkpoints = HashMap::from([
+            ("check".to_string(), Checkpoint::Completed(serde_json::json!({"result": true}))),
+            ("draft".to_string(), Checkpoint::Completed(serde_json::json!({"text": "echo: Draft for Ada"}))),
+        ]);
+        let log = Log::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+        let results = completed(executor.run(&definition, variables, &checkpoints).await);
+        assert_eq!(results["polish"]["text"], "echo: Polish: echo: Draft for Ada");
+        assert_eq!(log.status("draft"), None);
+        assert_eq!(services.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_approval() {
+        let definition = definition(
+            serde_json::json!([
+                {"id": "draft", "type": "template", "template": "Refund {{input.amount}}"},
+                {"id": "signoff", "type": "approval", "message": "Approve: {{nodes.draft.text}}"},
+                {"id": "pay", "type": "template", "template": "paid"},
+                {"id": "apologise", "type": "template", "template": "sorry"},
+                {"id": "log", "type": "template", "template": "logged {{input.amount}}"},
+            ]),
+            serde_json::json!([
+                {"from": "draft", "to": "signoff"},
+                {"from": "signoff", "to": "pay"},
+                {"from": "signoff", "to": "apologise", "branch": "rejected"},
+                {"from": "draft", "to": "log"},
+            ]),
+        );
+        let variables = serde_json::json!({"amount": 120});
+        let services = FakeServices::default();
+
+        // Work that does not depend on the approval finishes first
+        let log = Log::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+        let RunOutcome::Waiting { nodes, requested } = executor.run(&definition, variables.clone(), &HashMap::new()).await else {
+            panic!("expected the run to wait for approval");
+        };
+        assert_eq!(nodes, ["signoff"]);
+        assert_eq!(requested, ["signoff"]);
+        assert_eq!(log.status("signoff").as_deref(), Some("waiting: Approve: Refund 120"));
+        assert_eq!(log.status("log").as_deref(), Some("completed"));
+        assert_eq!(log.status("pay"), None);
+
+        // Resumed before a decision, it waits again without a new request
+        let mut checkpoints = HashMap::from([
+            ("draft".to_string(), Checkpoint::Completed(serde_json::json!({"text": "Refund 120"}))),
+            ("log".to_string(), Checkpoint::Completed(serde_json::json!({"text": "logged 120"}))),
+            ("signoff".to_string(), Checkpoint::Waiting),
+        ]);
+        let log = Log::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+        let RunOutcome::Waiting { requested, .. } = executor.run(&definition, variables.clone(), &checkpoints).await else {
+            panic!("expected the run to keep waiting");
+        };
+        assert!(requested.is_empty());
+        assert!(log.entries.lock().unwrap().is_empty());
+
+        checkpoints.insert("signoff".to_string(), Checkpoint::Completed(serde_json::json!({"decision": "approved"})));
+        let log = Log::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+        let results = completed(executor.run(&definition, variables.clone(), &checkpoints).await);
+        assert_eq!(results["pay"]["text"], "paid");
+        assert_eq!(log.status("apologise").as_deref(), Some("skipped"));
+
+        checkpoints.insert("signoff".to_string(), Checkpoint::Completed(serde_json::json!({"decision": "rejected"})));
+        let log = Log::default();
+        let executor = Executor { services: &services, recorder: &log, user_id: None };
+        let results = completed(executor.run(&definition, variables, &checkpoints).await);
+        assert_eq!(results["apologise"]["text"], "sorry");
+        assert_eq!(log.status("pay").as_deref(), Some("skipped"));
+    }
+}
@@ -0,0 +1,682 @@
+//! Workflows
+//!
+//! A workflow is a directed acyclic graph of [`Node`]s joined by [`Edge`]s,
+//! stored as the `nodes` and `edges` JSON of a `workflows` row. Every saved
+//! definition is kept as a numbered version, and an execution runs one
+//! version with a set of input variables. [`engine`] runs the graph and
+//! records each node's input, output and status in `node_executions`;
+//! [`template`] resolves the `{{path}}` variables nodes use to read the
+//! execution input and the outputs of earlier nodes.
+//!
+//! Executions are durable: a [`runner`] on any replica leases a pending
+//! execution, and the node rows double as checkpoints, so an execution
+//! interrupted by a crash, a pause or a lost lease resumes after its last
+//! finished nodes. An approval node parks its execution, without a lease,
+//! until a user with the node's permission approves or rejects it or its
+//! deadline passes.
+
+pub mod engine;
+pub mod runner;
+pub mod template;
+
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::db::{NodeExecutionRow, WorkflowExecutionRow, WorkflowRepository, WorkflowRow, WorkflowVersionRow};
+use crate::error::{Result, SynapseError};
+use crate::knowledge::search::SearchOptions;
+use engine::{Checkpoint, ExecutionRecorder};
+
+/// Workflow statuses; archived workflows cannot be run
+pub const WORKFLOW_STATUSES: &[&str] = &["draft", "active", "archived"];
+
+/// Items a loop node processes unless it sets `maxItems`
+pub const DEFAULT_LOOP_ITEMS: usize = 100;
+
+/// Longest wait between attempts of a retried node
+pub const MAX_RETRY_BACKOFF_MS: u64 = 5 * 60 * 1000;
+
+/// Execution statuses
+///
+/// `pending` executions wait for a worker; `running` ones hold a lease.
+/// `waiting` executions have nothing left to run until an approval node is
+/// decided. `paused` executions stop after their running nodes finish and
+/// continue from there once resumed; `cancelled`, `completed` and `failed`
+/// are final, though a failed execution can be resumed to retry its failed
+/// nodes.
+pub const EXECUTION_STATUSES: &[&str] = &["pending", "running", "waiting", "paused", "cancelled", "completed", "failed"];
+
+/// Permission needed to decide an approval node that names none
+pub const DEFAULT_APPROVAL_PERMISSION: &str = "workflows:approve";
+
+/// A step of a workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Node {
+    /// Unique within the workflow; outputs are read as `nodes.<id>`
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Fail the node if it runs longer than this; for an approval node,
+    /// how long to wait for a decision before its `onTimeout` applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+}
+
+/// How often a failing node is attempted and how long to wait in between
+///
+/// The wait doubles after every attempt, up to [`MAX_RETRY_BACKOFF_MS`].
+/// Validation errors, such as a bad template or an LLM reply that is not
+/// JSON, are not retried.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// Attempts including the first, 1 to 10
+    pub max_attempts: u32,
+    #[serde(default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+}
+
+fn default_backoff_ms() -> u64 {
+    1000
+}
+
+impl RetryPolicy {
+    /// Wait before attempt `attempt + 1`
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let ms = self.backoff_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(20));
+        std::time::Duration::from_millis(ms.min(MAX_RETRY_BACKOFF_MS))
+    }
+}
+
+/// What a node does, tagged by `type`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeKind {
+    /// Chat completion routed through the hub; outputs `text` (and `json`)
+    Llm(LlmNode),
+    /// Render a prompt template; outputs `text`
+    Template(TemplateNode),
+    /// Evaluate an expression; outputs `result` and takes the `"true"` or
+    /// `"false"` branch
+    Condition(ConditionNode),
+    /// Run a node once per element of an array; outputs `items`
+    Loop(LoopNode),
+    /// Call an HTTP endpoint; outputs `status` and `body`
+    Http(HttpNode),
+    /// Build a JSON value from templates; outputs the value
+    Transform(TransformNode),
+    /// Search a knowledge collection; outputs `results` and `text`
+    KnowledgeSearch(KnowledgeSearchNode),
+    /// Wait for a user to approve or reject; outputs the `decision` and
+    /// takes the `"approved"` or `"rejected"` branch
+    Approval(ApprovalNode),
+    /// Fan out: every outgoing edge runs concurrently
+    Parallel,
+    /// Wait for every incoming branch; outputs their outputs by node id
+    Join,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LlmNode {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    pub prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Parse the reply as JSON into the `json` output
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateNode {
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionNode {
+    /// See [`template::evaluate`]
+    pub expression: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopNode {
+    /// Template resolving to an array, e.g. `{{nodes.search.results}}`
+    pub items: String,
+    /// Run per element with `item` and `index` set
+    pub body: Box<NodeKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpNode {
+    #[serde(default = "default_method")]
+    pub method: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// JSON body; strings are templates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<JsonValue>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformNode {
+    /// JSON value whose strings are templates
+    pub output: JsonValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnowledgeSearchNode {
+    pub collection_id: String,
+    pub query: String,
+    #[serde(flatten)]
+    pub search: SearchOptions,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalNode {
+    /// Template shown to approvers
+    pub message: String,
+    /// Permission a user needs to decide
+    #[serde(default = "default_approval_permission")]
+    pub permission: String,
+    #[serde(default)]
+    pub notify: ApprovalNotify,
+    #[serde(default)]
+    pub on_timeout: TimeoutAction,
+}
+
+fn default_approval_permission() -> String {
+    DEFAULT_APPROVAL_PERMISSION.to_string()
+}
+
+/// Who is told that an approval node is waiting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalNotify {
+    /// Sent an email through the SMTP settings
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emails: Vec<String>,
+    /// Sent a `workflow.approval_requested` event as a JSON POST
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+}
+
+/// What happens to an approval node nobody decides in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutAction {
+    /// Decide as rejected
+    #[default]
+    Reject,
+    /// Decide as approved
+    Approve,
+    /// Fail the node and the execution
+    Fail,
+}
+
+/// A dependency: `to` runs after `from`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    /// Only followed when `from` takes this branch, e.g. `"true"` after a
+    /// condition. Edges without one leaving an approval node are only
+    /// followed once it is approved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowDefinition {
+    pub nodes: Vec<Node>,
+    #[serde(default)]
+    pub edges: Vec<Edge>,
+}
+
+impl NodeKind {
+    /// The branch a node took, derived from its output so that resumed
+    /// executions follow the same edges
+    pub fn branch(&self, output: &JsonValue) -> Option<String> {
+        match self {
+            NodeKind::Condition(_) => output["result"].as_bool().map(|result| result.to_string()),
+            NodeKind::Approval(_) => output["decision"].as_str().map(str::to_string),
+            _ => None,
+        }
+    }
+
+    /// Branches edges leaving this node may name
+    fn branches(&self) -> &'static [&'static str] {
+        match self {
+            NodeKind::Condition(_) => &["true", "false"],
+            NodeKind::Approval(_) => &["approved", "rejected"],
+            _ => &[],
+        }
+    }
+
+    fn validate(&self, id: &str) -> Result<()> {
+        let invalid = |message: &str| Err(SynapseError::Validation(format!("Node {}: {}", id, message)));
+        match self {
+            NodeKind::Llm(node) if node.model.trim().is_empty() => invalid("model is required"),
+            NodeKind::Llm(node) if node.prompt.trim().is_empty() => invalid("prompt is required"),
+            NodeKind::Condition(node) if node.expression.trim().is_empty() => invalid("expression is required"),
+            NodeKind::Http(node) if node.url.trim().is_empty() => invalid("url is required"),
+            NodeKind::Http(node) if reqwest::Method::from_bytes(node.method.to_uppercase().as_bytes()).is_err() => {
+                invalid("method is invalid")
+            }
+            NodeKind::KnowledgeSearch(node) if node.collection_id.trim().is_empty() => invalid("collectionId is required"),
+            NodeKind::KnowledgeSearch(node) => node.search.validate(),
+            NodeKind::Approval(node) if node.message.trim().is_empty() => invalid("message is required"),
+            NodeKind::Approval(node) if node.permission.trim().is_empty() => invalid("permission must not be empty"),
+            NodeKind::Approval(node) => {
+                if let Some(email) = node.notify.emails.iter().find(|e| !e.contains('@') || e.contains(char::is_whitespace)) {
+                    return invalid(&format!("{} is not an email address", email));
+                }
+                match node.notify.webhook {
+                    Some(ref url) if !url.starts_with("http://") && !url.starts_with("https://") => {
+                        invalid("notify.webhook must be an http(s) URL")
+                    }
+                    _ => Ok(()),
+                }
+            }
+            NodeKind::Loop(node) => match *node.body {
+                NodeKind::Loop(_) | NodeKind::Parallel | NodeKind::Join | NodeKind::Approval(_) => {
+                    invalid("a loop body must be a single step")
+                }
+                ref body => body.validate(id),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl WorkflowDefinition {
+    pub fn from_json(nodes: JsonValue, edges: JsonValue) -> Result<Self> {
+        Ok(Self {
+            nodes: serde_json::from_value(nodes)
+                .map_err(|e| SynapseError::Validation(format!("Invalid nodes: {}", e)))?,
+            edges: serde_json::from_value(edges)
+                .map_err(|e| SynapseError::Validation(format!("Invalid edges: {}", e)))?,
+        })
+    }
+
+    pub fn node(&self, id: &str) -> Option<&Node> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    /// Check node ids, node settings and edges, and that the graph has no
+    /// cycles
+    pub fn validate(&self) -> Result<()> {
+        if self.nodes.is_empty() {
+            return Err(SynapseError::Validation("A workflow needs at least one node".to_string()));
+        }
+        let mut ids = HashSet::new();
+        for node in &self.nodes {
+            if node.id.trim().is_empty() {
+                return Err(SynapseError::Validation("Node ids must not be empty".to_string()));
+            }
+            if !ids.insert(node.id.as_str()) {
+                return Err(SynapseError::Validation(format!("Duplicate node id {}", node.id)));
+            }
+            node.kind.validate(&node.id)?;
+            if let Some(ref retry) = node.retry {
+                if !(1..=10).contains(&retry.max_attempts) {
+                    return Err(SynapseError::Validation(format!("Node {}: retry.maxAttempts must be 1 to 10", node.id)));
+                }
+            }
+        }
+
+        for edge in &self.edges {
+            for end in [&edge.from, &edge.to] {
+                if !ids.contains(end.as_str()) {
+                    return Err(SynapseError::Validation(format!("Edge refers to unknown node {}", end)));
+                }
+            }
+            if edge.from == edge.to {
+                return Err(SynapseError::Validation(format!("Node {} has an edge to itself", edge.from)));
+            }
+            if let Some(ref branch) = edge.branch {
+                let branches = self.node(&edge.from).map_or(&[][..], |n| n.kind.branches());
+                if !branches.contains(&branch.as_str()) {
+                    return Err(SynapseError::Validation(format!(
+                        "Edge {} -> {}: only condition nodes (\"true\" or \"false\") and approval nodes \
+                         (\"approved\" or \"rejected\") have branches",
+                        edge.from, edge.to
+                    )));
+                }
+            }
+        }
+
+        // Kahn's algorithm: nodes left over sit on a cycle
+        let mut incoming: HashMap<&str, usize> = self.nodes.iter().map(|n| (n.id.as_str(), 0)).collect();
+        for edge in &self.edges {
+            *incoming.entry(edge.to.as_str()).or_default() += 1;
+        }
+        let mut ready: Vec<&str> = incoming.iter().filter(|(_, &n)| n == 0).map(|(&id, _)| id).collect();
+        let mut visited = 0;
+        while let Some(id) = ready.pop() {
+            visited += 1;
+            for edge in self.edges.iter().filter(|e| e.from == id) {
+                let count = incoming.entry(edge.to.as_str()).or_default();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(&edge.to);
+                }
+            }
+        }
+        if visited < self.nodes.len() {
+            return Err(SynapseError::Validation("Workflow edges form a cycle".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// API view of a workflow with its latest definition
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workflow {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub nodes: JsonValue,
+    pub edges: JsonValue,
+    pub status: String,
+    pub version: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+impl From<WorkflowRow> for Workflow {
+    fn from(row: WorkflowRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            nodes: row.nodes,
+            edges: row.edges,
+            status: row.status,
+            version: row.version,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            created_by: row.created_by,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowVersion {
+    pub version: i32,
+    pub nodes: JsonValue,
+    pub edges: JsonValue,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+impl From<WorkflowVersionRow> for WorkflowVersion {
+    fn from(row: WorkflowVersionRow) -> Self {
+        Self {
+            version: row.version,
+            nodes: row.nodes,
+            edges: row.edges,
+            created_at: row.created_at,
+            created_by: row.created_by,
+        }
+    }
+}
+
+/// State of one node in an execution
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeExecution {
+    pub node_id: String,
+    /// `running`, `waiting`, `completed`, `failed`, `skipped` or
+    /// `cancelled`
+    pub status: String,
+    pub input: JsonValue,
+    pub output: JsonValue,
+    pub error: Option<String>,
+    pub attempts: i32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When a waiting approval node times out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<NodeExecutionRow> for NodeExecution {
+    fn from(row: NodeExecutionRow) -> Self {
+        Self {
+            node_id: row.node_id,
+            status: row.status,
+            input: row.input,
+            output: row.output,
+            error: row.error,
+            attempts: row.attempts,
+            started_at: row.started_at,
+            completed_at: row.completed_at,
+            expires_at: row.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Execution {
+    pub id: String,
+    pub workflow_id: String,
+    pub workflow_version: i32,
+    /// One of [`EXECUTION_STATUSES`]
+    pub status: String,
+    pub variables: JsonValue,
+    /// Outputs of the nodes nothing else depends on, by node id
+    pub results: JsonValue,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub started_by: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeExecution>,
+}
+
+impl From<WorkflowExecutionRow> for Execution {
+    fn from(row: WorkflowExecutionRow) -> Self {
+        Self {
+            id: row.id,
+            workflow_id: row.workflow_id,
+            workflow_version: row.workflow_version,
+            status: row.status,
+            variables: row.variables,
+            results: row.results,
+            error: row.error,
+            started_at: row.started_at,
+            completed_at: row.completed_at,
+            started_by: row.started_by,
+            nodes: Vec::new(),
+        }
+    }
+}
+
+/// Checkpoints to resume from: finished nodes of an earlier run and
+/// approval nodes still waiting for a decision
+pub fn checkpoints(rows: &[NodeExecutionRow]) -> HashMap<String, Checkpoint> {
+    rows.iter()
+        .filter_map(|row| {
+            let checkpoint = match row.status.as_str() {
+                "completed" => Checkpoint::Completed(row.output.clone()),
+                "skipped" => Checkpoint::Skipped,
+                "waiting" => Checkpoint::Waiting,
+                _ => return None,
+            };
+            Some((row.node_id.clone(), checkpoint))
+        })
+        .collect()
+}
+
+/// The definition an execution runs
+pub async fn execution_definition(repo: &WorkflowRepository, execution: &WorkflowExecutionRow) -> Result<WorkflowDefinition> {
+    let version = repo.find_version(&execution.workflow_id, execution.workflow_version).await?
+        .ok_or_else(|| SynapseError::NotFound(format!(
+            "Workflow {} version {}", execution.workflow_id, execution.workflow_version
+        )))?;
+    WorkflowDefinition::from_json(version.nodes, version.edges)
+}
+
+/// Checkpoints an execution's nodes in `node_executions`
+pub struct NodeLog<'a> {
+    pub repo: &'a WorkflowRepository,
+    pub execution_id: &'a str,
+}
+
+#[async_trait]
+impl ExecutionRecorder for NodeLog<'_> {
+    async fn node_started(&self, node_id: &str, input: &JsonValue) -> Result<String> {
+        let id = uuid::Uuid::new_v4().to_string();
+        Ok(self.repo.start_node(&id, self.execution_id, node_id, input).await?)
+    }
+
+    async fn node_finished(&self, record_id: &str, output: std::result::Result<&JsonValue, &str>) -> Result<()> {
+        match output {
+            Ok(output) => self.repo.finish_node(record_id, "completed", output, None).await?,
+            Err(error) => self.repo.finish_node(record_id, "failed", &JsonValue::Null, Some(error)).await?,
+        }
+        Ok(())
+    }
+
+    async fn node_skipped(&self, node_id: &str) -> Result<()> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.repo.skip_node(&id, self.execution_id, node_id).await?;
+        Ok(())
+    }
+
+    async fn node_waiting(&self, node_id: &str, input: &JsonValue, timeout_secs: Option<u64>) -> Result<()> {
+        let id = uuid::Uuid::new_v4().to_string();
+        self.repo.wait_node(&id, self.execution_id, node_id, input, timeout_secs).await?;
+        Ok(())
+    }
+
+    async fn proceed(&self) -> bool {
+        match self.repo.find_execution(self.execution_id).await {
+            Ok(Some(execution)) => execution.status == "running",
+            Ok(None) => false,
+            // Keep going; the lease decides whether this run may finish
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(edges: JsonValue) -> WorkflowDefinition {
+        WorkflowDefinition::from_json(
+            serde_json::json!([
+                {"id": "check", "type": "condition", "expression": "input.vip"},
+                {"id": "greet", "type": "template", "template": "Hello {{input.name}}"},
+                {"id": "fetch", "type": "http", "url": "https://example.com", "timeoutSecs": 5, "retry": {"maxAttempts": 3}},
+                {"id": "each", "type": "loop", "items": "{{input.names}}", "body": {"type": "template", "template": "{{item}}"}},
+                {"id": "done", "type": "join"},
+                {"id": "signoff", "type": "approval", "message": "Send {{input.name}}?", "timeoutSecs": 3600,
+                    "notify": {"emails": ["ops@example.com"]}, "onTimeout": "fail"},
+            ]),
+            edges,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let definition = definition(serde_json::json!([]));
+        assert!(matches!(definition.nodes[0].kind, NodeKind::Condition(_)));
+        let NodeKind::Http(ref http) = definition.nodes[2].kind else { panic!("expected an http node") };
+        assert_eq!(http.method, "GET");
+        assert_eq!(definition.nodes[2].timeout_secs, Some(5));
+        let retry = definition.nodes[2].retry.as_ref().unwrap();
+        assert_eq!(retry.backoff_ms, 1000);
+        assert_eq!(retry.backoff(1).as_millis(), 1000);
+        assert_eq!(retry.backoff(3).as_millis(), 4000);
+        assert_eq!(retry.backoff(30).as_millis() as u64, MAX_RETRY_BACKOFF_MS);
+        let NodeKind::Loop(ref each) = definition.nodes[3].kind else { panic!("expected a loop node") };
+        assert!(matches!(*each.body, NodeKind::Template(_)));
+
+        let json = serde_json::to_value(&definition.nodes[4]).unwrap();
+        assert_eq!(json, serde_json::json!({"id": "done", "type": "join"}));
+        let NodeKind::Approval(ref signoff) = definition.nodes[5].kind else { panic!("expected an approval node") };
+        assert_eq!(signoff.permission, DEFAULT_APPROVAL_PERMISSION);
+        assert_eq!(signoff.on_timeout, TimeoutAction::Fail);
+        assert_eq!(signoff.notify.emails, ["ops@example.com"]);
+        let branch = definition.nodes[5].kind.branch(&serde_json::json!({"decision": "rejected"}));
+        assert_eq!(branch.as_deref(), Some("rejected"));
+        assert!(WorkflowDefinition::from_json(serde_json::json!([{"id": "x", "type": "script"}]), JsonValue::Null).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = definition(serde_json::json!([
+            {"from": "check", "to": "greet", "branch": "true"},
+            {"from": "check", "to": "fetch", "branch": "false"},
+            {"from": "greet", "to": "done"},
+            {"from": "fetch", "to": "done"},
+        ]));
+        assert!(valid.validate().is_ok());
+
+        let cycle = definition(serde_json::json!([
+            {"from": "greet", "to": "fetch"},
+            {"from": "fetch", "to": "each"},
+            {"from": "each", "to": "greet"},
+        ]));
+        assert!(cycle.validate().unwrap_err().to_string().contains("cycle"));
+
+        let unknown = definition(serde_json::json!([{"from": "greet", "to": "nowhere"}]));
+        assert!(unknown.validate().is_err());
+
+        let branch = definition(serde_json::json!([{"from": "greet", "to": "done", "branch": "true"}]));
+        assert!(branch.validate().is_err());
+        let approved = definition(serde_json::json!([
+            {"from": "signoff", "to": "fetch", "branch": "approved"},
+            {"from": "signoff", "to": "greet", "branch": "rejected"},
+        ]));
+        assert!(approved.validate().is_ok());
+        let mismatched = definition(serde_json::json!([{"from": "signoff", "to": "fetch", "branch": "true"}]));
+        assert!(mismatched.validate().is_err());
+
+        let mut webhook = definition(serde_json::json!([]));
+        let NodeKind::Approval(ref mut signoff) = webhook.nodes[5].kind else { unreachable!() };
+        signoff.notify.webhook = Some("ftp://example.com".to_string());
+        assert!(webhook.validate().unwrap_err().to_string().contains("webhook"));
+
+        let mut retries = definition(serde_json::json!([]));
+        retries.nodes[2].retry = Some(RetryPolicy { max_attempts: 0, backoff_ms: 10 });
+        assert!(retries.validate().is_err());
+
+        let mut duplicate = definition(serde_json::json!([]));
+        duplicate.nodes[1].id = "check".to_string();
+        assert!(duplicate.validate().unwrap_err().to_string().contains("Duplicate"));
+    }
+}
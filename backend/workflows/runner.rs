@@ -0,0 +1,888 @@
+//! Durable execution
+//!
+//! [`WorkflowRunner`] claims queued executions, leasing each so that only
+//! one replica runs it, and runs them from their checkpoints. It also
+//! announces approval nodes to their approvers, records decisions on them
+//! and applies the `onTimeout` of those past their deadline.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value as JsonValue;
+use tokio::sync::{Notify, Semaphore};
+
+use super::engine::{ExecutionRecorder, Executor, RunOutcome, WorkflowServices};
+use super::{checkpoints, execution_definition, ApprovalNotify, NodeLog, TimeoutAction, WorkflowDefinition};
+use crate::db::{
+    AuditRepository, DbPool, NodeExecutionRow, WorkflowExecutionRow, WorkflowRepository, WorkflowVersionRow,
+};
+use crate::error::{Result, SynapseError};
+use crate::governance::AuditService;
+use crate::telemetry::PropagateTrace;
+
+/// Lease a replica holds on a running workflow execution, renewed every
+/// third of its length
+const WORKFLOW_LEASE_SECS: i64 = 60;
+
+/// Workflow executions one replica runs at a time
+const MAX_WORKFLOW_RUNS: usize = 8;
+
+/// How often the runner looks for executions without being woken
+const WORKFLOW_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Timeout of the webhook announcing an approval request
+const APPROVAL_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The execution records a run leases, checkpoints and settles; see
+/// [`WorkflowRepository`] for what each call does
+#[async_trait]
+pub trait ExecutionStore: Send + Sync {
+    async fn claim_execution(&self, owner: &str, lease_secs: i64) -> Result<Option<WorkflowExecutionRow>>;
+
+    async fn renew_lease(&self, id: &str, owner: &str, lease_secs: i64) -> Result<Option<String>>;
+
+    async fn release_execution(&self, id: &str, owner: &str) -> Result<()>;
+
+    async fn find_execution(&self, id: &str) -> Result<Option<WorkflowExecutionRow>>;
+
+    async fn find_version(&self, workflow_id: &str, version: i32) -> Result<Option<WorkflowVersionRow>>;
+
+    async fn finish_execution(
+        &self,
+        id: &str,
+        owner: &str,
+        status: &str,
+        results: Option<&JsonValue>,
+        error: Option<&str>,
+    ) -> Result<bool>;
+
+    async fn wait_execution(&self, id: &str, owner: &str) -> Result<bool>;
+
+    async fn transition_execution(&self, id: &str, from: &[&str], to: &str) -> Result<Option<WorkflowExecutionRow>>;
+
+    async fn list_node_executions(&self, execution_id: &str) -> Result<Vec<NodeExecutionRow>>;
+
+    async fn cancel_running_nodes(&self, execution_id: &str) -> Result<u64>;
+
+    /// Checkpoints the nodes of an execution as it runs
+    fn recorder<'a>(&'a self, execution_id: &'a str) -> Box<dyn ExecutionRecorder + 'a>;
+}
+
+#[async_trait]
+impl ExecutionStore for WorkflowRepository {
+    async fn claim_execution(&self, owner: &str, lease_secs: i64) -> Result<Option<WorkflowExecutionRow>> {
+        Ok(WorkflowRepository::claim_execution(self, owner, lease_secs).await?)
+    }
+
+    async fn renew_lease(&self, id: &str, owner: &str, lease_secs: i64) -> Result<Option<String>> {
+        Ok(WorkflowRepository::renew_lease(self, id, owner, lease_secs).await?)
+    }
+
+    async fn release_execution(&self, id: &str, owner: &str) -> Result<()> {
+        Ok(WorkflowRepository::release_execution(self, id, owner).await?)
+    }
+
+    async fn find_execution(&self, id: &str) -> Result<Option<WorkflowExecutionRow>> {
+        Ok(WorkflowRepository::find_execution(self, id).await?)
+    }
+
+    async fn find_version(&self, workflow_id: &str, version: i32) -> Result<Option<WorkflowVersionRow>> {
+        Ok(WorkflowRepository::find_version(self, workflow_id, version).await?)
+    }
+
+    async fn finish_execution(
+        &self,
+        id: &str,
+        owner: &str,
+        status: &str,
+        results: Option<&JsonValue>,
+        error: Option<&str>,
+    ) -> Result<bool> {
+        Ok(WorkflowRepository::finish_execution(self, id, owner, status, results, error).await?)
+    }
+
+    async fn wait_execution(&self, id: &str, owner: &str) -> Result<bool> {
+        Ok(WorkflowRepository::wait_execution(self, id, owner).await?)
+    }
+
+    async fn transition_execution(&self, id: &str, from: &[&str], to: &str) -> Result<Option<WorkflowExecutionRow>> {
+        Ok(WorkflowRepository::transition_execution(self, id, from, to).await?)
+    }
+
+    async fn list_node_executions(&self, execution_id: &str) -> Result<Vec<NodeExecutionRow>> {
+        Ok(WorkflowRepository::list_node_executions(self, execution_id).await?)
+    }
+
+    async fn cancel_running_nodes(&self, execution_id: &str) -> Result<u64> {
+        Ok(WorkflowRepository::cancel_running_nodes(self, execution_id).await?)
+    }
+
+    fn recorder<'a>(&'a self, execution_id: &'a str) -> Box<dyn ExecutionRecorder + 'a> {
+        Box::new(NodeLog { repo: self, execution_id })
+    }
+}
+
+/// Claims and runs workflow executions and decides their approval nodes
+pub struct WorkflowRunner {
+    pool: DbPool,
+    /// Where executions are leased and checkpointed
+    store: Arc<dyn ExecutionStore>,
+    /// Identifies this replica as the holder of execution leases
+    owner: String,
+    http_client: reqwest::Client,
+    audit_service: Arc<AuditService>,
+    /// Wakes the runner when an execution is queued or a slot frees
+    wakeup: Notify,
+}
+
+impl WorkflowRunner {
+    pub fn new(pool: DbPool, owner: String, http_client: reqwest::Client, audit_service: Arc<AuditService>) -> Self {
+        let store = Arc::new(WorkflowRepository::new(pool.clone()));
+        Self { pool, store, owner, http_client, audit_service, wakeup: Notify::new() }
+    }
+
+    /// Have the runner look for queued executions now
+    pub fn wake(&self) {
+        self.wakeup.notify_one();
+    }
+
+    /// Claim and run queued executions until the process exits
+    ///
+    /// Executions left running by a replica that stopped are claimed again
+    /// once the lease lapses and resume from their checkpoints. Approval
+    /// nodes past their deadline are decided on the same schedule.
+    pub async fn run(self: Arc<Self>, services: Arc<dyn WorkflowServices>) {
+        let repo = WorkflowRepository::new(self.pool.clone());
+        let slots = Arc::new(Semaphore::new(MAX_WORKFLOW_RUNS));
+
+        loop {
+            if let Err(e) = self.expire_approvals(&repo).await {
+                tracing::warn!(error = %e, "Failed to expire workflow approvals");
+            }
+            self.start_runs(&services, &slots).await;
+
+            tokio::select! {
+                _ = self.wakeup.notified() => {}
+                _ = tokio::time::sleep(WORKFLOW_POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    /// Claim executions into the free slots, running each on its own task
+    async fn start_runs(self: &Arc<Self>, services: &Arc<dyn WorkflowServices>, slots: &Arc<Semaphore>) {
+        while let Ok(slot) = slots.clone().try_acquire_owned() {
+            let execution = match self.store.claim_execution(&self.owner, WORKFLOW_LEASE_SECS).await {
+                Ok(Some(execution)) => execution,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!(error = %e, "Failed to claim workflow execution");
+                    break;
+                }
+            };
+            let runner = self.clone();
+            let services = services.clone();
+            tokio::spawn(async move {
+                let execution_id = execution.id.clone();
+                if let Err(e) = runner.run_execution(services.as_ref(), execution).await {
+                    tracing::warn!(execution = %execution_id, error = %e, "Workflow execution failed");
+                }
+                drop(slot);
+                runner.wake();
+            });
+        }
+    }
+
+    /// Run a claimed execution from its checkpoints while renewing its lease
+    ///
+    /// Losing the lease or a cancellation drops the run at once; a pause
+    /// stops it once the running nodes finish. The outcome is recorded on
+    /// the execution; the error is that of the failed node.
+    async fn run_execution(&self, services: &dyn WorkflowServices, execution: WorkflowExecutionRow) -> Result<()> {
+        let store = self.store.as_ref();
+        let id = execution.id.as_str();
+        let owner = self.owner.as_str();
+
+        let definition = match store.find_version(&execution.workflow_id, execution.workflow_version).await? {
+            Some(version) => WorkflowDefinition::from_json(version.nodes, version.edges),
+            None => Err(SynapseError::NotFound(format!(
+                "Workflow {} version {}", execution.workflow_id, execution.workflow_version
+            ))),
+        };
+        let definition = match definition {
+            Ok(definition) => definition,
+            Err(e) => {
+                store.finish_execution(id, owner, "failed", None, Some(&e.to_string())).await?;
+                return Err(e);
+            }
+        };
+        let checkpoints = checkpoints(&store.list_node_executions(id).await?);
+        if !checkpoints.is_empty() {
+            tracing::info!(execution = %id, nodes = checkpoints.len(), "Resuming workflow execution");
+        }
+
+        let recorder = store.recorder(id);
+        let executor = Executor { services, recorder: recorder.as_ref(), user_id: execution.started_by.clone() };
+        let keep_lease = async {
+            let mut interval = tokio::time::interval(Duration::from_secs(WORKFLOW_LEASE_SECS as u64 / 3));
+            loop {
+                interval.tick().await;
+                match store.renew_lease(id, owner, WORKFLOW_LEASE_SECS).await {
+                    Ok(Some(status)) if status != "cancelled" => {}
+                    Ok(_) => return,
+                    Err(e) => tracing::warn!(execution = %id, error = %e, "Failed to renew workflow lease"),
+                }
+            }
+        };
+
+        let outcome = tokio::select! {
+            outcome = executor.run(&definition, execution.variables.clone(), &checkpoints) => Some(outcome),
+            _ = keep_lease => None,
+        };
+        let result = match outcome {
+            Some(RunOutcome::Completed(results)) => {
+                if store.finish_execution(id, owner, "completed", Some(&results), None).await? {
+                    tracing::info!(execution = %id, workflow = %execution.workflow_id, "Workflow execution completed");
+                }
+                Ok(())
+            }
+            Some(RunOutcome::Failed(failure)) => {
+                store.finish_execution(id, owner, "failed", None, Some(&failure.to_string())).await?;
+                Err(failure.error)
+            }
+            Some(RunOutcome::Interrupted) => Ok(()),
+            Some(RunOutcome::Waiting { nodes, requested }) => {
+                if store.wait_execution(id, owner).await? {
+                    tracing::info!(execution = %id, nodes = ?nodes, "Workflow execution waiting for approval");
+                    let rows = store.list_node_executions(id).await?;
+                    for row in rows.iter().filter(|row| requested.contains(&row.node_id)) {
+                        self.request_approval(&execution, row).await;
+                    }
+                    // A decision taken while this run was still going found
+                    // the execution running and left it to us
+                    if rows.iter().any(|row| nodes.contains(&row.node_id) && row.status != "waiting") {
+                        self.continue_execution(id).await?;
+                    }
+                }
+                Ok(())
+            }
+            None => {
+                // Nodes dropped mid-run stay `running` until a resumed run
+                // repeats them, unless the execution is over
+                if let Some(row) = store.find_execution(id).await? {
+                    if row.status == "cancelled" {
+                        store.cancel_running_nodes(id).await?;
+                    }
+                }
+                Ok(())
+            }
+        };
+        store.release_execution(id, owner).await?;
+        result
+    }
+
+    /// Queue a waiting execution again, now that an approval node is decided
+    async fn continue_execution(&self, id: &str) -> Result<()> {
+        if self.store.transition_execution(id, &["waiting"], "pending").await?.is_some() {
+            self.wake();
+        }
+        Ok(())
+    }
+
+    /// Tell an approval node's approvers that it waits for them, by email
+    /// and webhook; failures are logged, the node waits regardless
+    async fn request_approval(&self, execution: &WorkflowExecutionRow, node: &NodeExecutionRow) {
+        let notify: ApprovalNotify = serde_json::from_value(node.input["notify"].clone()).unwrap_or_default();
+        let message = node.input["message"].as_str().unwrap_or_default();
+
+        if let Some(ref url) = notify.webhook {
+            let event = serde_json::json!({
+                "event": "workflow.approval_requested",
+                "workflowId": execution.workflow_id,
+                "executionId": execution.id,
+                "nodeId": node.node_id,
+                "message": message,
+                "permission": node.input["permission"],
+                "expiresAt": node.expires_at,
+            });
+            let sent = self.http_client.post(url).propagate_trace().json(&event).timeout(APPROVAL_WEBHOOK_TIMEOUT).send().await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = sent {
+                tracing::warn!(execution = %execution.id, node = %node.node_id, error = %e, "Approval webhook failed");
+            }
+        }
+
+        if !notify.emails.is_empty() {
+            if let Err(e) = self.email_approvers(execution, node, notify.emails, message).await {
+                tracing::warn!(execution = %execution.id, node = %node.node_id, error = %e, "Failed to email approvers");
+            }
+        }
+    }
+
+    async fn email_approvers(
+        &self,
+        execution: &WorkflowExecutionRow,
+        node: &NodeExecutionRow,
+        to: Vec<String>,
+        message: &str,
+    ) -> Result<()> {
+        let settings = crate::mail::load_settings(&self.pool).await?
+            .ok_or_else(|| SynapseError::Config("SMTP settings are not configured".to_string()))?;
+        let workflow = WorkflowRepository::new(self.pool.clone()).find_workflow(&execution.workflow_id).await?
+            .map_or_else(|| execution.workflow_id.clone(), |workflow| workflow.name);
+
+        let mut body = format!(
+            "{}\n\nWorkflow: {}\nExecution: {}\nStep: {}\n",
+            message, workflow, execution.id, node.node_id
+        );
+        if let Some(expires_at) = node.expires_at {
+            body.push_str(&format!("Decide by: {}\n", expires_at.to_rfc2822()));
+        }
+        body.push_str(&format!(
+            "\nApprove or reject with POST /v1/workflows/{}/executions/{}/approvals/{}\n",
+            execution.workflow_id, execution.id, node.node_id
+        ));
+        let email = crate::mail::Email { to, subject: format!("Approval requested: {}", workflow), body };
+        crate::mail::send(&settings, &email).await
+    }
+
+    /// Record a decision on a waiting approval node, then queue the
+    /// execution again, or fail it when a rejection has no `"rejected"`
+    /// branch to follow
+    ///
+    /// `decided_by` is `None` when the node's deadline passed. The decision
+    /// becomes the node's output and is written to the audit log.
+    pub async fn decide_approval(
+        &self,
+        execution: &WorkflowExecutionRow,
+        definition: &WorkflowDefinition,
+        node_id: &str,
+        approved: bool,
+        decided_by: Option<&str>,
+        comment: Option<&str>,
+    ) -> Result<NodeExecutionRow> {
+        let repo = WorkflowRepository::new(self.pool.clone());
+
+        let decision = if approved { "approved" } else { "rejected" };
+        let output = serde_json::json!({
+            "decision": decision,
+            "decidedBy": decided_by,
+            "timedOut": decided_by.is_none(),
+            "comment": comment,
+            "decidedAt": chrono::Utc::now(),
+        });
+        let rejected_branch = definition.edges.iter().any(|e| e.from == node_id && e.branch.as_deref() == Some("rejected"));
+        let error = (!approved && !rejected_branch).then(|| match decided_by {
+            Some(user) => format!("Rejected by {}", user),
+            None => "Rejected as no decision was made in time".to_string(),
+        });
+        let status = if error.is_some() { "failed" } else { "completed" };
+
+        let row = repo.decide_node(&execution.id, node_id, status, &output, error.as_deref()).await?
+            .ok_or_else(|| SynapseError::Validation(format!("Node {} is not waiting for a decision", node_id)))?;
+        self.audit_approval(execution, node_id, if approved { "approve" } else { "reject" }, decided_by, &output).await;
+        tracing::info!(execution = %execution.id, node = %node_id, decision, "Workflow approval decided");
+
+        match error {
+            Some(error) => {
+                repo.fail_execution(&execution.id, &format!("Node {} failed: {}", node_id, error)).await?;
+            }
+            None => self.continue_execution(&execution.id).await?,
+        }
+        Ok(row)
+    }
+
+    /// Apply the `onTimeout` of approval nodes past their deadline
+    async fn expire_approvals(&self, repo: &WorkflowRepository) -> Result<()> {
+        for node in repo.list_expired_approvals().await? {
+            let Some(execution) = repo.find_execution(&node.execution_id).await? else {
+                continue;
+            };
+            let action: TimeoutAction = serde_json::from_value(node.input["onTimeout"].clone()).unwrap_or_default();
+            let expired = match action {
+                TimeoutAction::Approve | TimeoutAction::Reject => match execution_definition(repo, &execution).await {
+                    Ok(definition) => self
+                        .decide_approval(&execution, &definition, &node.node_id, action == TimeoutAction::Approve, None, None)
+                        .await
+                        .map(|_| ()),
+                    Err(e) => Err(e),
+                },
+                TimeoutAction::Fail => self.fail_expired_approval(repo, &execution, &node).await,
+            };
+            if let Err(e) = expired {
+                tracing::warn!(execution = %execution.id, node = %node.node_id, error = %e, "Failed to expire workflow approval");
+            }
+        }
+        Ok(())
+    }
+
+    async fn fail_expired_approval(
+        &self,
+        repo: &WorkflowRepository,
+        execution: &WorkflowExecutionRow,
+        node: &NodeExecutionRow,
+    ) -> Result<()> {
+        let output = serde_json::json!({"decision": null, "timedOut": true, "decidedAt": chrono::Utc::now()});
+        let error = "No decision was made in time";
+        // Someone decided in the meantime
+        if repo.decide_node(&execution.id, &node.node_id, "failed", &output, Some(error)).await?.is_none() {
+            return Ok(());
+        }
+        self.audit_approval(execution, &node.node_id, "expire", None, &output).await;
+        repo.fail_execution(&execution.id, &format!("Node {} failed: {}", node.node_id, error)).await?;
+        tracing::info!(execution = %execution.id, node = %node.node_id, "Workflow approval expired");
+        Ok(())
+    }
+
+    /// Write an approval decision to the audit log, in memory and in
+    /// `audit_logs`
+    async fn audit_approval(
+        &self,
+        execution: &WorkflowExecutionRow,
+        node_id: &str,
+        action: &str,
+        decided_by: Option<&str>,
+        output: &serde_json::Value,
+    ) {
+        let details = serde_json::json!({
+            "execution_id": execution.id,
+            "node_id": node_id,
+            "comment": output["comment"],
+            "timed_out": decided_by.is_none(),
+        });
+        self.audit_service
+            .log_workflow_decision(decided_by.unwrap_or("system"), action, &execution.workflow_id, details.clone())
+            .await;
+        let id = uuid::Uuid::new_v4().to_string();
+        let logged = AuditRepository::new(self.pool.clone())
+            .create(&id, decided_by, action, Some("workflow"), Some(&execution.workflow_id), &details, None, None)
+            .await;
+        if let Err(e) = logged {
+            tracing::warn!(execution = %execution.id, error = %e, "Failed to write approval to the audit log");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+    use std::sync::Mutex;
+    use crate::knowledge::search::{SearchOptions, SearchResult};
+    use crate::{ChatRequest, ChatResponse, Message};
+
+    /// Executions and node records in memory, leased against a clock the
+    /// test moves
+    struct MemoryStore {
+        now: Mutex<DateTime<Utc>>,
+        version: WorkflowVersionRow,
+        executions: Mutex<Vec<WorkflowExecutionRow>>,
+        nodes: Mutex<Vec<NodeExecutionRow>>,
+    }
+
+    impl MemoryStore {
+        fn new(nodes: JsonValue, edges: JsonValue, executions: Vec<WorkflowExecutionRow>) -> Self {
+            let now = Utc::now();
+            let version = WorkflowVersionRow {
+                workflow_id: "wf".to_string(),
+                version: 1,
+                nodes,
+                edges,
+                created_at: now,
+                created_by: None,
+            };
+            Self { now: Mutex::new(now), version, executions: Mutex::new(executions), nodes: Mutex::new(Vec::new()) }
+        }
+
+        fn advance(&self, secs: i64) {
+            *self.now.lock().unwrap() += chrono::Duration::seconds(secs);
+        }
+
+        fn execution(&self, id: &str) -> WorkflowExecutionRow {
+            self.executions.lock().unwrap().iter().find(|e| e.id == id).unwrap().clone()
+        }
+
+        fn update<T>(&self, id: &str, f: impl FnOnce(&mut WorkflowExecutionRow) -> T) -> Option<T> {
+            self.executions.lock().unwrap().iter_mut().find(|e| e.id == id).map(f)
+        }
+
+        fn node(&self, execution_id: &str, node_id: &str) -> Option<NodeExecutionRow> {
+            self.nodes.lock().unwrap().iter().find(|n| n.execution_id == execution_id && n.node_id == node_id).cloned()
+        }
+
+        /// Insert or restart an execution's record of a node
+        fn upsert_node(&self, execution_id: &str, node_id: &str, status: &str, input: &JsonValue) -> String {
+            let mut nodes = self.nodes.lock().unwrap();
+            let now = *self.now.lock().unwrap();
+            match nodes.iter_mut().find(|n| n.execution_id == execution_id && n.node_id == node_id) {
+                Some(node) => {
+                    node.status = status.to_string();
+                    node.input = input.clone();
+                    node.attempts += 1;
+                    node.started_at = Some(now);
+                    node.id.clone()
+                }
+                None => {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    nodes.push(NodeExecutionRow {
+                        id: id.clone(),
+                        execution_id: execution_id.to_string(),
+                        node_id: node_id.to_string(),
+                        status: status.to_string(),
+                        input: input.clone(),
+                        output: serde_json::json!({}),
+                        error: None,
+                        attempts: 1,
+                        started_at: Some(now),
+                        completed_at: None,
+                        expires_at: None,
+                    });
+                    id
+                }
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ExecutionStore for MemoryStore {
+        async fn claim_execution(&self, owner: &str, lease_secs: i64) -> Result<Option<WorkflowExecutionRow>> {
+            let now = *self.now.lock().unwrap();
+            let mut executions = self.executions.lock().unwrap();
+            let claimable = executions
+                .iter_mut()
+                .filter(|e| e.status == "pending" || e.status == "running")
+                .filter(|e| e.lease_expires_at.is_none_or(|expires_at| expires_at < now))
+                .min_by_key(|e| e.started_at);
+            Ok(claimable.map(|execution| {
+                execution.status = "running".to_string();
+                execution.lease_owner = Some(owner.to_string());
+                execution.lease_expires_at = Some(now + chrono::Duration::seconds(lease_secs));
+                execution.clone()
+            }))
+        }
+
+        async fn renew_lease(&self, id: &str, owner: &str, lease_secs: i64) -> Result<Option<String>> {
+            let now = *self.now.lock().unwrap();
+            Ok(self.update(id, |e| {
+                (e.lease_owner.as_deref() == Some(owner)).then(|| {
+                    e.lease_expires_at = Some(now + chrono::Duration::seconds(lease_secs));
+                    e.status.clone()
+                })
+            }).flatten())
+        }
+
+        async fn release_execution(&self, id: &str, owner: &str) -> Result<()> {
+            self.update(id, |e| {
+                if e.lease_owner.as_deref() == Some(owner) {
+                    e.lease_owner = None;
+                    e.lease_expires_at = None;
+                }
+            });
+            Ok(())
+        }
+
+        async fn find_execution(&self, id: &str) -> Result<Option<WorkflowExecutionRow>> {
+            Ok(self.executions.lock().unwrap().iter().find(|e| e.id == id).cloned())
+        }
+
+        async fn find_version(&self, workflow_id: &str, version: i32) -> Result<Option<WorkflowVersionRow>> {
+            Ok((workflow_id == self.version.workflow_id && version == self.version.version).then(|| self.version.clone()))
+        }
+
+        async fn finish_execution(
+            &self,
+            id: &str,
+            owner: &str,
+            status: &str,
+            results: Option<&JsonValue>,
+            error: Option<&str>,
+        ) -> Result<bool> {
+            let now = *self.now.lock().unwrap();
+            Ok(self.update(id, |e| {
+                if e.lease_owner.as_deref() != Some(owner) || e.status != "running" {
+                    return false;
+                }
+                e.status = status.to_string();
+                if let Some(results) = results {
+                    e.results = results.clone();
+                }
+                e.error = error.map(str::to_string);
+                e.completed_at = Some(now);
+                e.lease_owner = None;
+                e.lease_expires_at = None;
+                true
+            }).unwrap_or(false))
+        }
+
+        async fn wait_execution(&self, id: &str, owner: &str) -> Result<bool> {
+            Ok(self.update(id, |e| {
+                if e.lease_owner.as_deref() != Some(owner) || e.status != "running" {
+                    return false;
+                }
+                e.status = "waiting".to_string();
+                e.lease_owner = None;
+                e.lease_expires_at = None;
+                true
+            }).unwrap_or(false))
+        }
+
+        async fn transition_execution(&self, id: &str, from: &[&str], to: &str) -> Result<Option<WorkflowExecutionRow>> {
+            let now = *self.now.lock().unwrap();
+            Ok(self.update(id, |e| {
+                from.contains(&e.status.as_str()).then(|| {
+                    e.status = to.to_string();
+                    if to == "pending" {
+                        e.error = None;
+                    }
+                    e.completed_at = (to == "cancelled").then_some(now);
+                    e.clone()
+                })
+            }).flatten())
+        }
+
+        async fn list_node_executions(&self, execution_id: &str) -> Result<Vec<NodeExecutionRow>> {
+            let mut nodes: Vec<_> = self.nodes.lock().unwrap().iter().filter(|n| n.execution_id == execution_id).cloned().collect();
+            nodes.sort_by(|a, b| (a.started_at, &a.node_id).cmp(&(b.started_at, &b.node_id)));
+            Ok(nodes)
+        }
+
+        async fn cancel_running_nodes(&self, execution_id: &str) -> Result<u64> {
+            let mut cancelled = 0;
+            for node in self.nodes.lock().unwrap().iter_mut() {
+                if node.execution_id == execution_id && (node.status == "running" || node.status == "waiting") {
+                    node.status = "cancelled".to_string();
+                    cancelled += 1;
+                }
+            }
+            Ok(cancelled)
+        }
+
+        fn recorder<'a>(&'a self, execution_id: &'a str) -> Box<dyn ExecutionRecorder + 'a> {
+            Box::new(MemoryLog { store: self, execution_id })
+        }
+    }
+
+    struct MemoryLog<'a> {
+        store: &'a MemoryStore,
+        execution_id: &'a str,
+    }
+
+    #[async_trait]
+    impl ExecutionRecorder for MemoryLog<'_> {
+        async fn node_started(&self, node_id: &str, input: &JsonValue) -> Result<String> {
+            Ok(self.store.upsert_node(self.execution_id, node_id, "running", input))
+        }
+
+        async fn node_finished(&self, record_id: &str, output: std::result::Result<&JsonValue, &str>) -> Result<()> {
+            let mut nodes = self.store.nodes.lock().unwrap();
+            let node = nodes.iter_mut().find(|n| n.id == record_id).unwrap();
+            match output {
+                Ok(output) => {
+                    node.status = "completed".to_string();
+                    node.output = output.clone();
+                }
+                Err(error) => {
+                    node.status = "failed".to_string();
+                    node.error = Some(error.to_string());
+                }
+            }
+            Ok(())
+        }
+
+        async fn node_skipped(&self, node_id: &str) -> Result<()> {
+            self.store.upsert_node(self.execution_id, node_id, "skipped", &serde_json::json!({}));
+            Ok(())
+        }
+
+        async fn node_waiting(&self, node_id: &str, input: &JsonValue, _: Option<u64>) -> Result<()> {
+            self.store.upsert_node(self.execution_id, node_id, "waiting", input);
+            Ok(())
+        }
+
+        async fn proceed(&self) -> bool {
+            self.store.execution(self.execution_id).status == "running"
+        }
+    }
+
+    /// Echoes prompts; a prompt of "Hold" never gets a reply
+    #[derive(Default)]
+    struct Echo {
+        prompts: Mutex<Vec<String>>,
+        holding: Notify,
+    }
+
+    #[async_trait]
+    impl WorkflowServices for Echo {
+        async fn chat(&self, request: &ChatRequest) -> Result<ChatResponse> {
+            let prompt = request.messages.last().unwrap().content.text();
+            self.prompts.lock().unwrap().push(prompt.clone());
+            if prompt == "Hold" {
+                self.holding.notify_one();
+                std::future::pending::<()>().await;
+            }
+            Ok(ChatResponse {
+                id: "r1".to_string(),
+                provider: "fake".to_string(),
+                model: request.model.clone(),
+                choices: vec![crate::Choice {
+                    index: 0,
+                    message: Message::assistant(format!("echo: {}", prompt)),
+                    finish_reason: "stop".to_string(),
+                    logprobs: None,
+                }],
+                usage: Default::default(),
+                created: Utc::now(),
+                latency_ms: 1,
+                cost: 0.0,
+                system_fingerprint: None,
+            })
+        }
+
+        async fn search_knowledge(&self, _: &str, _: &str, _: &SearchOptions, _: Option<String>) -> Result<Vec<SearchResult>> {
+            Err(SynapseError::NotFound("Collection".to_string()))
+        }
+
+        fn http_client(&self) -> reqwest::Client {
+            reqwest::Client::new()
+        }
+    }
+
+    fn runner(store: Arc<MemoryStore>) -> Arc<WorkflowRunner> {
+        let pool = sqlx::postgres::PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        Arc::new(WorkflowRunner {
+            pool,
+            store,
+            owner: "replica-b".to_string(),
+            http_client: reqwest::Client::new(),
+            audit_service: Arc::new(AuditService::new()),
+            wakeup: Notify::new(),
+        })
+    }
+
+    fn execution(id: &str, status: &str, lease: Option<(&str, DateTime<Utc>)>, started_at: DateTime<Utc>) -> WorkflowExecutionRow {
+        WorkflowExecutionRow {
+            id: id.to_string(),
+            workflow_id: "wf".to_string(),
+            workflow_version: 1,
+            status: status.to_string(),
+            variables: serde_json::json!({"name": "Ada"}),
+            results: serde_json::json!({}),
+            error: None,
+            started_at,
+            completed_at: None,
+            started_by: None,
+            lease_owner: lease.map(|(owner, _)| owner.to_string()),
+            lease_expires_at: lease.map(|(_, expires_at)| expires_at),
+        }
+    }
+
+    fn draft_and_polish() -> (JsonValue, JsonValue) {
+        (
+            serde_json::json!([
+                {"id": "draft", "type": "llm", "model": "m", "prompt": "Draft for {{input.name}}"},
+                {"id": "polish", "type": "llm", "model": "m", "prompt": "Polish: {{nodes.draft.text}}"},
+            ]),
+            serde_json::json!([{"from": "draft", "to": "polish"}]),
+        )
+    }
+
+    /// Let the spawned runs go until `id` is no longer leased
+    async fn released(store: &MemoryStore, id: &str) -> WorkflowExecutionRow {
+        for _ in 0..100 {
+            let execution = store.execution(id);
+            if execution.lease_owner.is_none() {
+                return execution;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("execution {} is still leased", id);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_claim_skips_live_lease() {
+        let (nodes, edges) = draft_and_polish();
+        let now = Utc::now();
+        let store = Arc::new(MemoryStore::new(nodes, edges, vec![
+            execution("leased", "running", Some(("replica-a", now + chrono::Duration::seconds(30))), now - chrono::Duration::minutes(5)),
+            execution("queued", "pending", None, now),
+        ]));
+        let runner = runner(store.clone());
+        let services: Arc<dyn WorkflowServices> = Arc::new(Echo::default());
+
+        runner.start_runs(&services, &Arc::new(Semaphore::new(MAX_WORKFLOW_RUNS))).await;
+        let queued = released(&store, "queued").await;
+        assert_eq!(queued.status, "completed");
+        assert_eq!(queued.results["polish"]["text"], "echo: Polish: echo: Draft for Ada");
+
+        // The older execution is still replica-a's to run
+        let leased = store.execution("leased");
+        assert_eq!(leased.status, "running");
+        assert_eq!(leased.lease_owner.as_deref(), Some("replica-a"));
+        assert!(store.node("leased", "draft").is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_expired_lease_resumes_after_checkpoint() {
+        let (nodes, edges) = draft_and_polish();
+        let now = Utc::now();
+        let store = Arc::new(MemoryStore::new(nodes, edges, vec![
+            execution("crashed", "running", Some(("replica-a", now + chrono::Duration::seconds(30))), now),
+        ]));
+        // replica-a finished draft, then stopped while running polish
+        let record = store.upsert_node("crashed", "draft", "running", &serde_json::json!({}));
+        MemoryLog { store: &store, execution_id: "crashed" }
+            .node_finished(&record, Ok(&serde_json::json!({"text": "echo: Draft for Ada"})))
+            .await
+            .unwrap();
+        store.upsert_node("crashed", "polish", "running", &serde_json::json!({}));
+        let runner = runner(store.clone());
+        let echo = Arc::new(Echo::default());
+        let services: Arc<dyn WorkflowServices> = echo.clone();
+        let slots = Arc::new(Semaphore::new(MAX_WORKFLOW_RUNS));
+
+        runner.start_runs(&services, &slots).await;
+        assert_eq!(store.execution("crashed").lease_owner.as_deref(), Some("replica-a"));
+
+        store.advance(60);
+        runner.start_runs(&services, &slots).await;
+        let execution = released(&store, "crashed").await;
+        assert_eq!(execution.status, "completed");
+        assert_eq!(execution.results["polish"]["text"], "echo: Polish: echo: Draft for Ada");
+        assert_eq!(*echo.prompts.lock().unwrap(), vec!["Polish: echo: Draft for Ada".to_string()]);
+        assert_eq!(store.node("crashed", "draft").unwrap().attempts, 1);
+        assert_eq!(store.node("crashed", "polish").unwrap().attempts, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lost_lease_stops_run() {
+        let now = Utc::now();
+        let store = Arc::new(MemoryStore::new(
+            serde_json::json!([
+                {"id": "hold", "type": "llm", "model": "m", "prompt": "Hold"},
+                {"id": "after", "type": "template", "template": "done"},
+            ]),
+            serde_json::json!([{"from": "hold", "to": "after"}]),
+            vec![execution("slow", "pending", None, now)],
+        ));
+        let runner = runner(store.clone());
+        let echo = Arc::new(Echo::default());
+        let execution = store.claim_execution(&runner.owner, WORKFLOW_LEASE_SECS).await.unwrap().unwrap();
+
+        let run = tokio::spawn({
+            let runner = runner.clone();
+            let echo = echo.clone();
+            async move { runner.run_execution(echo.as_ref(), execution).await }
+        });
+        echo.holding.notified().await;
+        // The lease lapsed and replica-c took the execution over
+        store.update("slow", |e| e.lease_owner = Some("replica-c".to_string()));
+
+        let stopped = tokio::time::timeout(Duration::from_secs(WORKFLOW_LEASE_SECS as u64), run).await;
+        stopped.expect("run kept going without its lease").unwrap().unwrap();
+        let execution = store.execution("slow");
+        assert_eq!(execution.status, "running");
+        assert_eq!(execution.lease_owner.as_deref(), Some("replica-c"));
+        assert_eq!(store.node("slow", "hold").unwrap().status, "running");
+        assert!(store.node("slow", "after").is_none());
+    }
+}
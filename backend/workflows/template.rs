@@ -0,0 +1,162 @@
+//! Variable templates and conditions
+//!
+//! Templates reference the execution context with `{{path}}`, where a path
+//! is dot-separated keys and array indices: `{{input.question}}`,
+//! `{{nodes.classify.text}}`, `{{nodes.search.results.0.content}}`. Inside
+//! a loop, `item` and `index` refer to the current element. A string that
+//! is exactly one placeholder is replaced by the referenced JSON value;
+//! otherwise values are interpolated as text.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde_json::Value as JsonValue;
+
+use crate::error::{Result, SynapseError};
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{\s*([^{}]+?)\s*\}\}").unwrap());
+
+/// Value at a dot-separated path, `Null` when missing
+pub fn lookup<'a>(context: &'a JsonValue, path: &str) -> &'a JsonValue {
+    path.split('.').filter(|key| !key.is_empty()).fold(context, |value, key| match value {
+        JsonValue::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)).unwrap_or(&JsonValue::Null),
+        _ => value.get(key).unwrap_or(&JsonValue::Null),
+    })
+}
+
+fn as_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        JsonValue::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Interpolate placeholders into text
+pub fn render(template: &str, context: &JsonValue) -> String {
+    PLACEHOLDER
+        .replace_all(template, |caps: &regex::Captures| as_text(lookup(context, &caps[1])))
+        .into_owned()
+}
+
+/// Render every string in a JSON value, keeping the type of values
+/// referenced by a lone placeholder
+pub fn render_value(value: &JsonValue, context: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::String(template) => {
+            let trimmed = template.trim();
+            if let Some(caps) = PLACEHOLDER.captures(trimmed) {
+                if caps.get(0).is_some_and(|m| m.as_str().len() == trimmed.len()) {
+                    return lookup(context, &caps[1]).clone();
+                }
+            }
+            JsonValue::String(render(template, context))
+        }
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(|v| render_value(v, context)).collect()),
+        JsonValue::Object(fields) => JsonValue::Object(
+            fields.iter().map(|(key, v)| (key.clone(), render_value(v, context))).collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Whether a value counts as true: not null, false, 0, "" or empty
+pub fn truthy(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Null => false,
+        JsonValue::Bool(b) => *b,
+        JsonValue::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        JsonValue::String(s) => !s.is_empty() && s != "false",
+        JsonValue::Array(items) => !items.is_empty(),
+        JsonValue::Object(fields) => !fields.is_empty(),
+    }
+}
+
+const OPERATORS: &[&str] = &["==", "!=", ">=", "<=", ">", "<", " contains "];
+
+/// An operand: a JSON literal (`"refund"`, `3`, `true`) or a context path
+fn operand(text: &str, context: &JsonValue) -> JsonValue {
+    let text = text.trim();
+    let text = text.strip_prefix("{{").and_then(|t| t.strip_suffix("}}")).map_or(text, str::trim);
+    serde_json::from_str(text).unwrap_or_else(|_| lookup(context, text).clone())
+}
+
+/// Evaluate a condition: `left OP right` with `==`, `!=`, `>`, `>=`, `<`,
+/// `<=` or `contains`, or a single operand tested for truthiness
+pub fn evaluate(expression: &str, context: &JsonValue) -> Result<bool> {
+    let expression = expression.trim();
+    if expression.is_empty() {
+        return Err(SynapseError::Validation("Condition expression is empty".to_string()));
+    }
+
+    let Some((op, at)) = OPERATORS.iter().filter_map(|op| expression.find(op).map(|at| (*op, at))).min_by_key(|&(_, at)| at)
+    else {
+        return Ok(truthy(&operand(expression, context)));
+    };
+    let left = operand(&expression[..at], context);
+    let right = operand(&expression[at + op.len()..], context);
+
+    let numbers = left.as_f64().zip(right.as_f64());
+    let result = match op.trim() {
+        "==" => numbers.map_or(left == right, |(l, r)| l == r),
+        "!=" => numbers.map_or(left != right, |(l, r)| l != r),
+        "contains" => match (&left, &right) {
+            (JsonValue::String(l), r) => l.contains(&as_text(r)),
+            (JsonValue::Array(items), r) => items.contains(r),
+            (JsonValue::Object(fields), JsonValue::String(key)) => fields.contains_key(key),
+            _ => false,
+        },
+        op => {
+            let (l, r) = numbers.ok_or_else(|| {
+                SynapseError::Validation(format!("'{}' compares numbers, got {} and {}", op, left, right))
+            })?;
+            match op {
+                ">" => l > r,
+                ">=" => l >= r,
+                "<" => l < r,
+                _ => l <= r,
+            }
+        }
+    };
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> JsonValue {
+        serde_json::json!({
+            "input": {"question": "Where is my refund?", "amount": 120},
+            "nodes": {
+                "classify": {"text": "refund"},
+                "search": {"results": [{"content": "Refunds take 5 days."}]},
+            },
+        })
+    }
+
+    #[test]
+    fn test_render() {
+        let context = context();
+        assert_eq!(
+            render("Q: {{input.question}} ({{ nodes.classify.text }}) {{missing}}", &context),
+            "Q: Where is my refund? (refund) "
+        );
+        assert_eq!(render("{{nodes.search.results.0.content}}", &context), "Refunds take 5 days.");
+
+        let value = render_value(&serde_json::json!({"amount": "{{input.amount}}", "label": "n={{input.amount}}"}), &context);
+        assert_eq!(value, serde_json::json!({"amount": 120, "label": "n=120"}));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let context = context();
+        assert!(evaluate(r#"nodes.classify.text == "refund""#, &context).unwrap());
+        assert!(evaluate("{{input.amount}} > 100", &context).unwrap());
+        assert!(!evaluate("input.amount <= 100", &context).unwrap());
+        assert!(evaluate(r#"input.question contains "refund""#, &context).unwrap());
+        assert!(evaluate("nodes.search.results", &context).unwrap());
+        assert!(!evaluate("nodes.missing", &context).unwrap());
+        assert!(evaluate("input.question > 3", &context).is_err());
+    }
+}
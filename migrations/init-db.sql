@@ -60,7 +60,15 @@ CREATE TABLE IF NOT EXISTS applications (
     last_used TIMESTAMP WITH TIME ZONE,
     expires_at TIMESTAMP WITH TIME ZONE,
     requests_today BIGINT DEFAULT 0,
-    requests_reset_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    requests_reset_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    -- Response cache TTL in seconds; 0 leaves caching to each request
+    cache_ttl_seconds INTEGER DEFAULT 0,
+    -- Minimum similarity for semantic cache hits; 0 disables them
+    semantic_cache_threshold REAL DEFAULT 0,
+    -- Opt-in request/response logging, see request_logs
+    request_logging BOOLEAN DEFAULT false,
+    log_retention_days INTEGER DEFAULT 30,
+    log_redaction BOOLEAN DEFAULT true
 );
 
 -- ============================================================================
@@ -142,6 +150,30 @@ CREATE TABLE IF NOT EXISTS agents (
     created_by VARCHAR(100) REFERENCES users(id) ON DELETE SET NULL
 );
 
+-- Server-side conversation history for agent chat
+CREATE TABLE IF NOT EXISTS agent_threads (
+    id VARCHAR(100) PRIMARY KEY,
+    agent_id VARCHAR(100) NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    user_id VARCHAR(255) NOT NULL, -- owner; only they can read or extend it
+    title VARCHAR(255),
+    summary TEXT, -- rolling summary of messages before summarized_count
+    summarized_count INTEGER DEFAULT 0,
+    message_count INTEGER DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS agent_thread_messages (
+    id VARCHAR(100) PRIMARY KEY,
+    thread_id VARCHAR(100) NOT NULL REFERENCES agent_threads(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    role VARCHAR(20) NOT NULL,
+    message JSONB NOT NULL,
+    token_count INTEGER DEFAULT 0, -- estimated
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (thread_id, position)
+);
+
 -- ============================================================================
 -- WORKFLOWS
 -- ============================================================================
@@ -159,18 +191,34 @@ CREATE TABLE IF NOT EXISTS workflows (
     created_by VARCHAR(100) REFERENCES users(id) ON DELETE SET NULL
 );
 
+-- Every saved definition; workflows holds the latest
+CREATE TABLE IF NOT EXISTS workflow_versions (
+    workflow_id VARCHAR(100) REFERENCES workflows(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    nodes JSONB NOT NULL DEFAULT '[]',
+    edges JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    created_by VARCHAR(100) REFERENCES users(id) ON DELETE SET NULL,
+    PRIMARY KEY (workflow_id, version)
+);
+
 CREATE TABLE IF NOT EXISTS workflow_executions (
     id VARCHAR(100) PRIMARY KEY,
     workflow_id VARCHAR(100) REFERENCES workflows(id) ON DELETE CASCADE,
+    workflow_version INTEGER NOT NULL DEFAULT 1,
     status VARCHAR(50) DEFAULT 'pending',
     variables JSONB DEFAULT '{}',
     results JSONB DEFAULT '{}',
     error TEXT,
     started_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
     completed_at TIMESTAMP WITH TIME ZONE,
-    started_by VARCHAR(100) REFERENCES users(id) ON DELETE SET NULL
+    started_by VARCHAR(100) REFERENCES users(id) ON DELETE SET NULL,
+    -- Replica running the execution; others may claim it once the lease expires
+    lease_owner VARCHAR(100),
+    lease_expires_at TIMESTAMP WITH TIME ZONE
 );
 
+-- One row per node and execution, the checkpoint a resumed execution starts from
 CREATE TABLE IF NOT EXISTS node_executions (
     id VARCHAR(100) PRIMARY KEY,
     execution_id VARCHAR(100) REFERENCES workflow_executions(id) ON DELETE CASCADE,
@@ -179,8 +227,12 @@ CREATE TABLE IF NOT EXISTS node_executions (
     input JSONB DEFAULT '{}',
     output JSONB DEFAULT '{}',
     error TEXT,
+    attempts INTEGER DEFAULT 0,
     started_at TIMESTAMP WITH TIME ZONE,
-    completed_at TIMESTAMP WITH TIME ZONE
+    completed_at TIMESTAMP WITH TIME ZONE,
+    -- Deadline of an approval node waiting for a decision
+    expires_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (execution_id, node_id)
 );
 
 -- ============================================================================
@@ -194,6 +246,11 @@ CREATE TABLE IF NOT EXISTS knowledge_collections (
     agent_id VARCHAR(100) REFERENCES agents(id) ON DELETE CASCADE,
     document_count INTEGER DEFAULT 0,
     chunk_count INTEGER DEFAULT 0,
+    -- Ingestion settings; vectors live in the collection of the same id
+    embedding_model VARCHAR(255),
+    embedding_provider VARCHAR(100),
+    vector_account_id VARCHAR(100), -- VectorDb provider account, in-process store when NULL
+    chunking JSONB DEFAULT '{}',
     metadata JSONB DEFAULT '{}',
     created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
     updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
@@ -207,9 +264,17 @@ CREATE TABLE IF NOT EXISTS knowledge_documents (
     content_type VARCHAR(100) DEFAULT 'text/plain',
     file_size BIGINT DEFAULT 0,
     chunk_count INTEGER DEFAULT 0,
+    -- Ingestion job: 'pending', 'processing', 'completed' or 'failed'
+    status VARCHAR(20) DEFAULT 'pending',
+    chunks_embedded INTEGER DEFAULT 0,
+    error TEXT,
     metadata JSONB DEFAULT '{}',
     created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
-    created_by VARCHAR(100) REFERENCES users(id) ON DELETE SET NULL
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    created_by VARCHAR(100) REFERENCES users(id) ON DELETE SET NULL,
+    -- Replica ingesting the document; others may claim it once the lease expires
+    lease_owner VARCHAR(100),
+    lease_expires_at TIMESTAMP WITH TIME ZONE
 );
 
 CREATE TABLE IF NOT EXISTS knowledge_chunks (
@@ -222,6 +287,16 @@ CREATE TABLE IF NOT EXISTS knowledge_chunks (
     created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
 );
 
+-- Vectors for collections without a VectorDb account
+CREATE TABLE IF NOT EXISTS vector_records (
+    collection VARCHAR(255) NOT NULL,
+    id VARCHAR(255) NOT NULL,
+    embedding REAL[] NOT NULL,
+    payload JSONB DEFAULT '{}',
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (collection, id)
+);
+
 -- ============================================================================
 -- COST TRACKING & BILLING
 -- ============================================================================
@@ -241,6 +316,31 @@ CREATE TABLE IF NOT EXISTS cost_entries (
     created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
 );
 
+-- Full request/response records for applications that opt in
+CREATE TABLE IF NOT EXISTS request_logs (
+    id VARCHAR(100) PRIMARY KEY,
+    application_id VARCHAR(100) NOT NULL REFERENCES applications(id) ON DELETE CASCADE,
+    user_id VARCHAR(255),
+    request_id VARCHAR(255) NOT NULL,
+    model VARCHAR(255) NOT NULL,
+    provider VARCHAR(100),
+    account VARCHAR(255), -- provider or account that answered
+    status VARCHAR(20) NOT NULL, -- 'success', 'error'
+    error TEXT,
+    request JSONB NOT NULL,
+    response JSONB,
+    attempts JSONB DEFAULT '[]', -- every provider tried, with failures
+    prompt_tokens INTEGER DEFAULT 0,
+    completion_tokens INTEGER DEFAULT 0,
+    cost DOUBLE PRECISION DEFAULT 0,
+    latency_ms BIGINT DEFAULT 0,
+    cache_status VARCHAR(20),
+    streamed BOOLEAN DEFAULT false,
+    redacted BOOLEAN DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
 CREATE TABLE IF NOT EXISTS budgets (
     id VARCHAR(100) PRIMARY KEY,
     entity_type VARCHAR(50) NOT NULL, -- 'user', 'agent', 'application'
@@ -347,7 +447,8 @@ CREATE TABLE IF NOT EXISTS tts_voices (
     is_custom BOOLEAN DEFAULT false,
     enabled BOOLEAN DEFAULT true,
     metadata JSONB DEFAULT '{}',
-    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (provider_id, voice_id)
 );
 
 -- ============================================================================
@@ -357,7 +458,7 @@ CREATE TABLE IF NOT EXISTS tts_voices (
 CREATE TABLE IF NOT EXISTS stt_providers (
     id VARCHAR(100) PRIMARY KEY,
     name VARCHAR(255) NOT NULL,
-    provider_type VARCHAR(50) NOT NULL, -- deepgram, openai, assemblyai, google, minimax
+    provider_type VARCHAR(50) NOT NULL, -- deepgram, openai, assemblyai, google, minimax, whisper_cpp
     description TEXT,
     api_base_url TEXT,
     
@@ -492,6 +593,52 @@ CREATE TABLE IF NOT EXISTS webrtc_sessions (
     created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
 );
 
+-- ============================================================================
+-- UPGRADES
+-- ============================================================================
+-- Columns added after a table was first created; CREATE TABLE IF NOT EXISTS
+-- leaves existing tables as they are
+
+-- Response cache and request logging settings
+ALTER TABLE applications ADD COLUMN IF NOT EXISTS cache_ttl_seconds INTEGER DEFAULT 0;
+ALTER TABLE applications ADD COLUMN IF NOT EXISTS semantic_cache_threshold REAL DEFAULT 0;
+ALTER TABLE applications ADD COLUMN IF NOT EXISTS request_logging BOOLEAN DEFAULT false;
+ALTER TABLE applications ADD COLUMN IF NOT EXISTS log_retention_days INTEGER DEFAULT 30;
+ALTER TABLE applications ADD COLUMN IF NOT EXISTS log_redaction BOOLEAN DEFAULT true;
+
+-- Knowledge ingestion
+ALTER TABLE knowledge_collections ADD COLUMN IF NOT EXISTS embedding_model VARCHAR(255);
+ALTER TABLE knowledge_collections ADD COLUMN IF NOT EXISTS embedding_provider VARCHAR(100);
+ALTER TABLE knowledge_collections ADD COLUMN IF NOT EXISTS vector_account_id VARCHAR(100);
+ALTER TABLE knowledge_collections ADD COLUMN IF NOT EXISTS chunking JSONB DEFAULT '{}';
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS status VARCHAR(20) DEFAULT 'pending';
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS chunks_embedded INTEGER DEFAULT 0;
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS error TEXT;
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW();
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS lease_owner VARCHAR(100);
+ALTER TABLE knowledge_documents ADD COLUMN IF NOT EXISTS lease_expires_at TIMESTAMP WITH TIME ZONE;
+
+-- Durable workflow executions and approvals
+ALTER TABLE workflow_executions ADD COLUMN IF NOT EXISTS workflow_version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE workflow_executions ADD COLUMN IF NOT EXISTS lease_owner VARCHAR(100);
+ALTER TABLE workflow_executions ADD COLUMN IF NOT EXISTS lease_expires_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE node_executions ADD COLUMN IF NOT EXISTS attempts INTEGER DEFAULT 0;
+ALTER TABLE node_executions ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP WITH TIME ZONE;
+
+-- Unique constraints have no IF NOT EXISTS; these use the names Postgres
+-- gives the inline ones above
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'node_executions_execution_id_node_id_key') THEN
+        ALTER TABLE node_executions
+            ADD CONSTRAINT node_executions_execution_id_node_id_key UNIQUE (execution_id, node_id);
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'tts_voices_provider_id_voice_id_key') THEN
+        ALTER TABLE tts_voices
+            ADD CONSTRAINT tts_voices_provider_id_voice_id_key UNIQUE (provider_id, voice_id);
+    END IF;
+END $$;
+
 -- ============================================================================
 -- INDEXES
 -- ============================================================================
@@ -508,15 +655,22 @@ CREATE INDEX IF NOT EXISTS idx_provider_accounts_provider ON provider_accounts(p
 CREATE INDEX IF NOT EXISTS idx_provider_accounts_default ON provider_accounts(is_default);
 CREATE INDEX IF NOT EXISTS idx_agents_status ON agents(status);
 CREATE INDEX IF NOT EXISTS idx_agents_created_by ON agents(created_by);
+CREATE INDEX IF NOT EXISTS idx_agent_threads_owner ON agent_threads(agent_id, user_id, updated_at DESC);
 CREATE INDEX IF NOT EXISTS idx_workflows_status ON workflows(status);
 CREATE INDEX IF NOT EXISTS idx_workflow_executions_workflow ON workflow_executions(workflow_id);
 CREATE INDEX IF NOT EXISTS idx_workflow_executions_status ON workflow_executions(status);
+CREATE INDEX IF NOT EXISTS idx_node_executions_waiting ON node_executions(expires_at) WHERE status = 'waiting';
 CREATE INDEX IF NOT EXISTS idx_knowledge_collections_agent ON knowledge_collections(agent_id);
 CREATE INDEX IF NOT EXISTS idx_knowledge_documents_collection ON knowledge_documents(collection_id);
+CREATE INDEX IF NOT EXISTS idx_knowledge_documents_status ON knowledge_documents(status);
 CREATE INDEX IF NOT EXISTS idx_knowledge_chunks_document ON knowledge_chunks(document_id);
+CREATE INDEX IF NOT EXISTS idx_vector_records_payload ON vector_records USING GIN (payload);
 CREATE INDEX IF NOT EXISTS idx_cost_entries_user ON cost_entries(user_id);
 CREATE INDEX IF NOT EXISTS idx_cost_entries_created ON cost_entries(created_at);
 CREATE INDEX IF NOT EXISTS idx_cost_entries_provider ON cost_entries(provider);
+CREATE INDEX IF NOT EXISTS idx_request_logs_app_created ON request_logs(application_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_request_logs_created ON request_logs(created_at);
+CREATE INDEX IF NOT EXISTS idx_request_logs_expires ON request_logs(expires_at);
 CREATE INDEX IF NOT EXISTS idx_budgets_entity ON budgets(entity_type, entity_id);
 CREATE INDEX IF NOT EXISTS idx_audit_logs_user ON audit_logs(user_id);
 CREATE INDEX IF NOT EXISTS idx_audit_logs_action ON audit_logs(action);
@@ -536,6 +690,23 @@ CREATE INDEX IF NOT EXISTS idx_voice_usage_created ON voice_usage(created_at);
 CREATE INDEX IF NOT EXISTS idx_webrtc_sessions_user ON webrtc_sessions(user_id);
 CREATE INDEX IF NOT EXISTS idx_webrtc_sessions_status ON webrtc_sessions(status);
 
+-- ============================================================================
+-- NOTIFICATIONS
+-- ============================================================================
+
+-- Tell every replica to rebuild its router when provider accounts change
+CREATE OR REPLACE FUNCTION notify_provider_accounts_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('provider_accounts_changed', TG_OP);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS provider_accounts_changed ON provider_accounts;
+CREATE TRIGGER provider_accounts_changed
+    AFTER INSERT OR UPDATE OR DELETE ON provider_accounts
+    FOR EACH STATEMENT EXECUTE FUNCTION notify_provider_accounts_changed();
+
 -- ============================================================================
 -- DEFAULT DATA
 -- ============================================================================
@@ -642,7 +813,14 @@ INSERT INTO stt_providers (id, name, provider_type, description, api_base_url, s
      'https://api.minimax.chat/v1',
      true, true, false,
      '["zh", "ar", "en", "es", "fr", "de"]',
-     10.0, 200, 0.15, true, false)
+     10.0, 200, 0.15, true, false),
+    
+    ('stt-whisper-cpp', 'whisper.cpp (local)', 'whisper_cpp',
+     'Self-hosted whisper.cpp server, audio stays on the network',
+     'http://localhost:8080',
+     false, false, false,
+     '["en", "es", "fr", "de", "it", "pt", "ru", "zh", "ja", "ko", "ar", "hi", "nl"]',
+     NULL, NULL, 0, true, false)
 ON CONFLICT (id) DO NOTHING;
 
 -- ============================================================================